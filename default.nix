@@ -181,6 +181,31 @@ packages
   # Good for debugging
   inherit pkgs;
 
+  # A derivation that only builds if nixpkgs-vet finds no problems in the source tree.
+  # With `base`, the tree is compared against it, like for a PR.
+  mkCheck =
+    {
+      src,
+      base ? null,
+      args ? [ ],
+    }:
+    pkgs.runCommand "nixpkgs-vet-check"
+      {
+        nativeBuildInputs = [
+          packages.build
+          defaultNixPackage
+        ];
+      }
+      ''
+        ${initNix}
+        nixpkgs-vet ${
+          lib.escapeShellArgs (
+            lib.optionals (base != null) [ "--base=${base}" ] ++ args ++ [ "${src}" ]
+          )
+        }
+        touch $out
+      '';
+
   # Built by CI
   ci = pkgs.linkFarm "ci" packages;
 
@@ -0,0 +1,58 @@
+{
+  description = "Checks Nixpkgs for many sorts of defects, like the structure of pkgs/by-name";
+
+  # The inputs are pinned with npins in ./npins, like for default.nix
+  inputs = { };
+
+  outputs =
+    { self }:
+    let
+      systems = [
+        "x86_64-linux"
+        "aarch64-linux"
+        "x86_64-darwin"
+        "aarch64-darwin"
+      ];
+      forAllSystems =
+        f:
+        builtins.listToAttrs (
+          map (system: {
+            name = system;
+            value = f (import ./. { inherit system; });
+          }) systems
+        );
+    in
+    {
+      packages = forAllSystems (outputs: {
+        default = outputs.build;
+        nixpkgs-vet = outputs.build;
+      });
+
+      apps = forAllSystems (
+        outputs:
+        let
+          app = {
+            type = "app";
+            program = outputs.pkgs.lib.getExe outputs.build;
+          };
+        in
+        {
+          default = app;
+          nixpkgs-vet = app;
+        }
+      );
+
+      lib = {
+        # A derivation for `checks.<system>` that only builds if nixpkgs-vet finds no problems in
+        # `src`, compared against `base` if given, with additional command line `args`
+        mkCheck =
+          {
+            system,
+            src,
+            base ? null,
+            args ? [ ],
+          }:
+          (import ./. { inherit system; }).mkCheck { inherit src base args; };
+      };
+    };
+}
@@ -6,6 +6,11 @@
   nixVersions,
   lixPackageSets,
   clippy,
+  curl,
+  gitMinimal,
+  gnutar,
+  gzip,
+  xz,
   makeWrapper,
   versionCheckHook,
 
@@ -44,6 +49,14 @@ rustPlatform.buildRustPackage {
     makeWrapper
   ];
 
+  # Some features and their tests use curl, git and tar
+  nativeCheckInputs = [
+    curl
+    gitMinimal
+    gnutar
+    gzip
+  ];
+
   doInstallCheck = true;
   nativeInstallCheckInputs = [ versionCheckHook ];
 
@@ -73,7 +86,16 @@ rustPlatform.buildRustPackage {
   '';
   postInstall = ''
     wrapProgram $out/bin/nixpkgs-vet \
-      --set NIXPKGS_VET_NIX_PACKAGE ${lib.getBin nix}
+      --set NIXPKGS_VET_NIX_PACKAGE ${lib.getBin nix} \
+      --suffix PATH : ${
+        lib.makeBinPath [
+          curl
+          gitMinimal
+          gnutar
+          gzip
+          xz
+        ]
+      }
   '';
 
   # silence a warning when building
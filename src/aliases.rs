@@ -0,0 +1,153 @@
+//! Checks of `pkgs/top-level/aliases.nix` against the packages in `pkgs/by-name`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use rnix::{SyntaxKind, ast};
+use rowan::ast::AstNode;
+
+use crate::location::Location;
+use crate::nix_file::NixFileStore;
+use crate::problem::npv_113;
+use crate::ratchet::{self, RatchetState};
+use crate::scope::Scope;
+use crate::structure;
+use crate::validation::{self, Validation::Success};
+
+/// The file defining the aliases of Nixpkgs, relative to Nixpkgs.
+pub const ALIASES_PATH: &str = "pkgs/top-level/aliases.nix";
+
+/// Matches the comment at the end of the line of an alias with the date it was added, like
+/// `# Added 2024-01-31`, which tells when it can be removed.
+static DATE_COMMENT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^#\s*[Aa]dded \d{4}-\d{2}-\d{2}\b").expect("valid regex"));
+
+/// Checks that no package in `pkgs/by-name` also has an alias with the same name, since the alias
+/// would shadow the package.
+///
+/// Only conflicts whose package or alias definition is within the given scope are reported.
+pub fn check_aliases(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    package_names: &[String],
+    scope: &Scope,
+) -> validation::Result<()> {
+    let _span = tracing::info_span!("aliases").entered();
+    let path = nixpkgs_path.join(ALIASES_PATH);
+    if !path.exists() {
+        return Ok(Success(()));
+    }
+    let package_names: BTreeSet<&str> = package_names.iter().map(String::as_str).collect();
+
+    let aliases = nix_file_store.get(&path)?.top_level_attributes();
+    Ok(aliases
+        .into_iter()
+        .filter_map(|(name, line, column)| {
+            let in_scope =
+                scope.contains_package(&name) || scope.contains_line(ALIASES_PATH.as_ref(), line);
+            (package_names.contains(name.as_str()) && in_scope).then(|| {
+                npv_113::ByNameAliasConflict::new(name, Location::new(ALIASES_PATH, line, column))
+                    .into()
+            })
+        })
+        .collect())
+}
+
+/// Returns the ratchet value of each alias in scope that refers to a package in `pkgs/by-name`,
+/// like `foo = self.bar;` or `foo = bar;`.
+pub fn alias_ratchets(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    scope: &Scope,
+) -> anyhow::Result<BTreeMap<String, ratchet::Alias>> {
+    let path = nixpkgs_path.join(ALIASES_PATH);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let nix_file = nix_file_store.get(&path)?;
+
+    let mut aliases = BTreeMap::new();
+    for binding in nix_file.top_level_bindings() {
+        let (Some(attrpath), Some(value)) = (binding.attrpath(), binding.value()) else {
+            continue;
+        };
+        let mut attrs = attrpath.attrs();
+        let (Some(ast::Attr::Ident(name)), None) = (attrs.next(), attrs.next()) else {
+            continue;
+        };
+        let Some(target) = alias_target(&value) else {
+            continue;
+        };
+        let is_by_name = structure::relative_file_for_package(&target)
+            .to_path(nixpkgs_path)
+            .is_file();
+        let offset = usize::from(binding.syntax().text_range().start());
+        let line = nix_file.line_index.line(offset);
+        if !is_by_name || !scope.contains_line(ALIASES_PATH.as_ref(), line) {
+            continue;
+        }
+
+        // The comment that follows the binding on the same line
+        let comment = std::iter::successors(binding.syntax().next_sibling_or_token(), |element| {
+            element.next_sibling_or_token()
+        })
+        .take_while(|element| {
+            element.kind() != SyntaxKind::TOKEN_WHITESPACE || !element.to_string().contains('\n')
+        })
+        .find(|element| element.kind() == SyntaxKind::TOKEN_COMMENT);
+        let dated =
+            if comment.is_some_and(|comment| DATE_COMMENT_REGEX.is_match(&comment.to_string())) {
+                RatchetState::Tight
+            } else {
+                RatchetState::Loose((
+                    target,
+                    Location::new(ALIASES_PATH, line, nix_file.line_index.column(offset)),
+                ))
+            };
+        aliases.insert(name.syntax().text().to_string(), ratchet::Alias { dated });
+    }
+    Ok(aliases)
+}
+
+/// Returns the aliases of Nixpkgs with the attribute each refers to, if it's a plain reference like
+/// `foo = bar;` or `foo = self.bar;`, see `--check no-aliases`.
+pub fn alias_targets(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+) -> anyhow::Result<BTreeMap<String, Option<String>>> {
+    let path = nixpkgs_path.join(ALIASES_PATH);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(nix_file_store
+        .get(&path)?
+        .top_level_bindings()
+        .filter_map(|binding| {
+            let mut attrs = binding.attrpath()?.attrs();
+            let (Some(ast::Attr::Ident(name)), None) = (attrs.next(), attrs.next()) else {
+                return None;
+            };
+            let target = binding.value().and_then(|value| alias_target(&value));
+            Some((name.syntax().text().to_string(), target))
+        })
+        .collect())
+}
+
+/// The name of the attribute an alias refers to, if its value is a plain reference to one.
+fn alias_target(value: &ast::Expr) -> Option<String> {
+    match value {
+        ast::Expr::Ident(ident) => Some(ident.syntax().text().to_string()),
+        ast::Expr::Select(select) if select.default_expr().is_none() => {
+            match select
+                .attrpath()
+                .and_then(|attrpath| attrpath.attrs().last())
+            {
+                Some(ast::Attr::Ident(ident)) => Some(ident.syntax().text().to_string()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
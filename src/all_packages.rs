@@ -0,0 +1,145 @@
+//! Checks of the style of `pkgs/top-level/all-packages.nix`.
+
+use std::path::Path;
+
+use rnix::{SyntaxKind, ast};
+use rowan::NodeOrToken;
+use rowan::ast::AstNode;
+
+use crate::location::Location;
+use crate::nix_file::{NixFile, NixFileStore};
+use crate::problem::{npv_146, npv_147};
+use crate::scope::Scope;
+use crate::validation::{self, Validation::Success};
+
+/// The file in which most top-level attributes not in `pkgs/by-name` are defined, relative to
+/// Nixpkgs.
+pub const ALL_PACKAGES_PATH: &str = "pkgs/top-level/all-packages.nix";
+
+/// A top-level attribute defined in `pkgs/top-level/all-packages.nix`.
+struct Entry {
+    /// The attribute path as written, like `foo` or `xorg.bar`.
+    name: String,
+    line: usize,
+    column: usize,
+    end_line: usize,
+}
+
+/// Checks that the changed entries of `pkgs/top-level/all-packages.nix` are sorted alphabetically
+/// within their section, and that each is on a line of its own.
+///
+/// A section is a block of entries without blank lines or comments between them. Since the file
+/// as a whole isn't consistently sorted, only changed entries are checked, and nothing is checked
+/// if there's no diff to tell the changed entries.
+pub fn check_all_packages(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    scope: &Scope,
+) -> validation::Result<()> {
+    let _span = tracing::info_span!("all-packages").entered();
+    let path = nixpkgs_path.join(ALL_PACKAGES_PATH);
+    if scope.is_everything() || !path.exists() {
+        return Ok(Success(()));
+    }
+    let nix_file = nix_file_store.get(&path)?;
+    let in_scope = |entry: &Entry| {
+        (entry.line..=entry.end_line)
+            .any(|line| scope.contains_line(ALL_PACKAGES_PATH.as_ref(), line))
+    };
+    let location = |entry: &Entry| Location::new(ALL_PACKAGES_PATH, entry.line, entry.column);
+
+    let mut problems = vec![];
+    for section in sections(nix_file) {
+        for pair in section.windows(2) {
+            let [previous, entry] = pair else {
+                continue;
+            };
+            // The entry that was changed is the misplaced one
+            if previous.name.to_lowercase() > entry.name.to_lowercase() {
+                if in_scope(entry) {
+                    problems.push(
+                        npv_146::TopLevelEntryUnsorted::new(
+                            &entry.name,
+                            &previous.name,
+                            true,
+                            location(entry),
+                        )
+                        .into(),
+                    );
+                } else if in_scope(previous) {
+                    problems.push(
+                        npv_146::TopLevelEntryUnsorted::new(
+                            &previous.name,
+                            &entry.name,
+                            false,
+                            location(previous),
+                        )
+                        .into(),
+                    );
+                }
+            }
+            if entry.line == previous.end_line && (in_scope(entry) || in_scope(previous)) {
+                problems.push(
+                    npv_147::TopLevelEntriesOnSameLine::new(
+                        &entry.name,
+                        &previous.name,
+                        location(entry),
+                    )
+                    .into(),
+                );
+            }
+        }
+    }
+    Ok(problems.into_iter().collect())
+}
+
+/// Returns the sections of the entries of the outermost attribute sets of the file.
+fn sections(nix_file: &NixFile) -> Vec<Vec<Entry>> {
+    let mut sections = vec![];
+    let top_level_attr_sets = nix_file
+        .syntax_root
+        .syntax()
+        .descendants()
+        .filter_map(ast::AttrSet::cast)
+        .filter(|attr_set| {
+            !attr_set
+                .syntax()
+                .ancestors()
+                .any(|ancestor| ast::AttrpathValue::can_cast(ancestor.kind()))
+        });
+    for attr_set in top_level_attr_sets {
+        let mut section = vec![];
+        for child in attr_set.syntax().children_with_tokens() {
+            let starts_section = match &child {
+                NodeOrToken::Token(token) => {
+                    token.kind() == SyntaxKind::TOKEN_COMMENT
+                        || (token.kind() == SyntaxKind::TOKEN_WHITESPACE
+                            && token.text().matches('\n').count() >= 2)
+                }
+                NodeOrToken::Node(_) => false,
+            };
+            if starts_section && !section.is_empty() {
+                sections.push(std::mem::take(&mut section));
+            }
+            let NodeOrToken::Node(node) = child else {
+                continue;
+            };
+            let Some(attrpath) = ast::AttrpathValue::cast(node).and_then(|entry| entry.attrpath())
+            else {
+                continue;
+            };
+            let range = attrpath.syntax().parent().expect("an entry").text_range();
+            let start = usize::from(range.start());
+            section.push(Entry {
+                name: attrpath.syntax().text().to_string(),
+                line: nix_file.line_index.line(start),
+                column: nix_file.line_index.column(start),
+                end_line: nix_file.line_index.line(usize::from(range.end()) - 1),
+            });
+        }
+        if !section.is_empty() {
+            sections.push(section);
+        }
+    }
+    sections
+}
@@ -0,0 +1,103 @@
+//! Unpacking of Nixpkgs source archives like channel tarballs, using the `tar` binary.
+
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::Context;
+use tempfile::TempDir;
+
+use crate::status::Fatal;
+use crate::structure;
+
+/// File name suffixes of the archives that can be unpacked.
+const ARCHIVE_SUFFIXES: &[&str] = &[
+    ".tar", ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tar.zst",
+];
+
+/// Whether the path is a source archive that needs to be unpacked before it can be checked.
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| ARCHIVE_SUFFIXES.iter().any(|s| name.ends_with(s)))
+}
+
+/// A source archive unpacked into a temporary directory, removed again when dropped.
+pub struct Unpacked {
+    // Only kept to remove the directory when dropped
+    _dir: TempDir,
+    root: PathBuf,
+}
+
+impl Unpacked {
+    /// Unpacks the archive into a new temporary directory.
+    pub fn new(archive: &Path) -> anyhow::Result<Unpacked> {
+        let dir = tempfile::Builder::new()
+            .prefix("nixpkgs-vet-unpacked")
+            .tempdir()
+            .context("Failed to create a directory to unpack an archive into")?;
+
+        let mut command = process::Command::new("tar");
+        command.arg("-xf").arg(archive).arg("-C").arg(dir.path());
+        tracing::debug!(?command, "Unpacking archive");
+        let output = command.output().with_context(|| {
+            Fatal::Misconfiguration(format!("Failed to run {command:?}, is tar installed?"))
+        })?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Could not unpack {}:\n{}",
+                archive.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        // Archives of Nixpkgs like channel tarballs usually contain a single top-level directory
+        // like `nixos-24.05.1234.abcdef`, which is the actual root of Nixpkgs.
+        let entries = structure::read_dir_sorted(dir.path())?;
+        let root = match &entries[..] {
+            [entry] if entry.file_type()?.is_dir() => entry.path(),
+            _ => dir.path().to_owned(),
+        };
+        Ok(Unpacked { _dir: dir, root })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::fs;
+
+    #[test]
+    fn unpacks_single_directory() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let source = temp_dir.path().join("nixos-24.05.1234.abcdef");
+        fs::create_dir(&source)?;
+        fs::write(source.join("default.nix"), "{ }")?;
+
+        let archive = temp_dir.path().join("nixexprs.tar.gz");
+        let status = process::Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(temp_dir.path())
+            .arg("nixos-24.05.1234.abcdef")
+            .status()?;
+        assert!(status.success());
+
+        assert!(is_archive(&archive));
+        assert!(!is_archive(&source));
+
+        let unpacked = Unpacked::new(&archive)?;
+        assert_eq!(
+            fs::read_to_string(unpacked.path().join("default.nix"))?,
+            "{ }"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+//! Attribution of problems to the commits that last touched their location, see `--blame`.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::git;
+use crate::location::ProblemLocation;
+
+/// The last commit that touched the location of a problem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blame {
+    pub commit: String,
+    pub author: String,
+    pub summary: String,
+}
+
+/// The all-zero hash `git blame` uses for lines that aren't committed yet.
+const UNCOMMITTED: &str = "0000000000000000000000000000000000000000";
+
+impl Blame {
+    /// Finds the last commit that touched a location in a Nixpkgs git repository.
+    ///
+    /// If the location has a line, that line is blamed. Otherwise the last commit that touched
+    /// the path at all is used. Returns `None` if the location isn't committed yet.
+    pub fn find(repo: &Path, location: &ProblemLocation) -> anyhow::Result<Option<Blame>> {
+        let path = location.path.as_str();
+        let blame = match location.line {
+            Some(line) => {
+                let range = format!("{line},{line}");
+                let output = git::run(repo, &["blame", "--porcelain", "-L", &range, "--", path])?;
+                Self::parse_porcelain(&output)
+            }
+            None => {
+                let output = git::run(repo, &["log", "-1", "--format=%H%n%an%n%s", "--", path])?;
+                let mut lines = output.lines();
+                match (lines.next(), lines.next(), lines.next()) {
+                    (Some(commit), Some(author), summary) => Some(Blame {
+                        commit: commit.to_owned(),
+                        author: author.to_owned(),
+                        summary: summary.unwrap_or_default().to_owned(),
+                    }),
+                    // The path was never committed
+                    _ => None,
+                }
+            }
+        };
+        Ok(blame.filter(|blame| blame.commit != UNCOMMITTED))
+    }
+
+    /// Parses the output of `git blame --porcelain` for a single line.
+    fn parse_porcelain(output: &str) -> Option<Blame> {
+        let mut lines = output.lines();
+        let commit = lines.next()?.split(' ').next()?.to_owned();
+        let mut author = String::new();
+        let mut summary = String::new();
+        for line in lines {
+            if let Some(value) = line.strip_prefix("author ") {
+                value.clone_into(&mut author);
+            } else if let Some(value) = line.strip_prefix("summary ") {
+                value.clone_into(&mut summary);
+            } else if line.starts_with('\t') {
+                // The line contents come last
+                break;
+            }
+        }
+        Some(Blame {
+            commit,
+            author,
+            summary,
+        })
+    }
+}
+
+impl fmt::Display for Blame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            commit,
+            author,
+            summary,
+        } = self;
+        let short_commit = commit.get(..12).unwrap_or(commit);
+        write!(f, "Last changed by {author} in {short_commit} ({summary})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn parses_porcelain() {
+        let output = indoc! {"
+            0123456789abcdef0123456789abcdef01234567 3 3 1
+            author Jane Doe
+            author-mail <jane@example.com>
+            author-time 1700000000
+            author-tz +0000
+            committer Jane Doe
+            summary foo: init at 1.0
+            filename pkgs/by-name/fo/foo/package.nix
+            \t{ someDrv }: someDrv
+        "};
+        assert_eq!(
+            Blame::parse_porcelain(output),
+            Some(Blame {
+                commit: "0123456789abcdef0123456789abcdef01234567".to_owned(),
+                author: "Jane Doe".to_owned(),
+                summary: "foo: init at 1.0".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn blames_lines_and_paths() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let repo = temp_dir.path();
+        git::run(repo, &["init", "--quiet"])?;
+        fs::write(repo.join("file.nix"), "a\nb\n")?;
+        git::run(repo, &["add", "file.nix"])?;
+        git::run(
+            repo,
+            &[
+                "-c",
+                "user.name=Jane Doe",
+                "-c",
+                "user.email=jane@example.com",
+                "commit",
+                "--quiet",
+                "--message",
+                "init",
+            ],
+        )?;
+        fs::write(repo.join("file.nix"), "a\nc\n")?;
+
+        let committed =
+            Blame::find(repo, &ProblemLocation::new("file.nix", Some(1)))?.expect("committed line");
+        assert_eq!(committed.author, "Jane Doe");
+        assert_eq!(committed.summary, "init");
+
+        assert_eq!(
+            Blame::find(repo, &ProblemLocation::new("file.nix", Some(2)))?,
+            None
+        );
+        assert_eq!(
+            Blame::find(repo, &ProblemLocation::new("file.nix", None))?,
+            Some(committed)
+        );
+        Ok(())
+    }
+}
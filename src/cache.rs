@@ -0,0 +1,169 @@
+//! Shares the results of the Nix evaluations between runs, like on ephemeral CI runners, through
+//! a directory or an HTTP object store, see `--cache`.
+//!
+//! The results are stored under the keys of `checkpoint`, which address everything the
+//! evaluation depends on, so a result is only used by runs that would evaluate the same. The
+//! cache is only an optimisation, so a run doesn't fail if it can't be reached.
+//!
+//! The Nix files are parsed again by every run, since that only takes a fraction of the time of
+//! evaluating Nixpkgs, and the syntax trees of rnix can't be stored.
+
+use std::fs;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process;
+use std::sync::OnceLock;
+
+use anyhow::Context as _;
+use clap::ValueEnum;
+
+use crate::status::Fatal;
+
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+/// Where `--cache` is, see `--cache-backend`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CacheBackend {
+    /// A directory, like one that CI restores before the run and saves after it.
+    #[default]
+    Dir,
+    /// The base URL of an HTTP object store, whose objects are read with `GET` and written with
+    /// `PUT`. Credentials are taken from `~/.netrc` if it has them.
+    Http,
+}
+
+enum Cache {
+    Dir(PathBuf),
+    Http(String),
+}
+
+/// Uses the cache at the location for the rest of the program. Needs to be called before any
+/// evaluation, and only once.
+pub fn init(backend: CacheBackend, location: &str) -> anyhow::Result<()> {
+    let cache = match backend {
+        CacheBackend::Dir => {
+            fs::create_dir_all(location)
+                .with_context(|| format!("Could not create the cache directory {location}"))?;
+            Cache::Dir(PathBuf::from(location))
+        }
+        CacheBackend::Http => Cache::Http(location.trim_end_matches('/').to_owned()),
+    };
+    if CACHE.set(cache).is_err() {
+        panic!("the cache is only initialised once");
+    }
+    Ok(())
+}
+
+/// Whether there's a `--cache`.
+pub fn enabled() -> bool {
+    CACHE.get().is_some()
+}
+
+/// The object of the key, if the cache has it.
+pub fn get(key: &str) -> Option<Vec<u8>> {
+    let name = object_name(key);
+    let result = match CACHE.get()? {
+        Cache::Dir(dir) => fs::read(dir.join(&name)).map_err(anyhow::Error::from),
+        Cache::Http(url) => curl(&format!("{url}/{name}"), None),
+    };
+    match result {
+        Ok(object) => {
+            tracing::debug!(key, "Using the cached evaluation");
+            Some(object)
+        }
+        Err(error) => {
+            tracing::debug!(key, "The evaluation isn't cached: {error:#}");
+            None
+        }
+    }
+}
+
+/// Stores the object under the key, only warning if it can't be stored.
+pub fn put(key: &str, object: &[u8]) {
+    let name = object_name(key);
+    let result = match CACHE.get() {
+        None => return,
+        Some(Cache::Dir(dir)) => {
+            // Writing to a temporary file first, such that concurrent runs never read a partial
+            // object
+            let temporary_path = dir.join(format!("{name}.{}.tmp", process::id()));
+            fs::write(&temporary_path, object)
+                .and_then(|()| fs::rename(&temporary_path, dir.join(&name)))
+                .map_err(anyhow::Error::from)
+        }
+        Some(Cache::Http(url)) => curl(&format!("{url}/{name}"), Some(object)).map(drop),
+    };
+    if let Err(error) = result {
+        tracing::warn!(
+            key,
+            "Could not store the evaluation in the cache: {error:#}"
+        );
+    }
+}
+
+/// The name of the object of the key. Other versions of nixpkgs-vet may evaluate differently, so
+/// they don't share objects.
+fn object_name(key: &str) -> String {
+    format!("nixpkgs-vet-{}-{key}.json", env!("CARGO_PKG_VERSION"))
+}
+
+/// Gets the URL with `curl`, or puts the body to it.
+fn curl(url: &str, body: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+    let mut command = process::Command::new("curl");
+    command
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--netrc-optional",
+        ])
+        .arg(url);
+    if body.is_some() {
+        command.args(["--upload-file", "-"]);
+    }
+    let mut child = command
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            Fatal::Misconfiguration("Failed to run curl, is it installed?".to_owned())
+        })?;
+    {
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        if let Some(body) = body {
+            stdin.write_all(body)?;
+        }
+    }
+    let result = child.wait_with_output()?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "Request to {url} failed:\n{}",
+            String::from_utf8_lossy(&result.stderr)
+        );
+    }
+    Ok(result.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn stores_objects_over_http() -> anyhow::Result<()> {
+        // curl also supports local files, which emulate the object store here
+        let temp_dir = testing::tempdir()?;
+        let url = format!("file://{}", temp_dir.path().display());
+        let object = format!("{url}/{}", object_name("tree-0123"));
+        assert!(curl(&object, None).is_err());
+        curl(&object, Some(b"{}"))?;
+        assert_eq!(curl(&object, None)?, b"{}");
+        assert_eq!(
+            fs::read(temp_dir.path().join(object_name("tree-0123")))?,
+            b"{}"
+        );
+        Ok(())
+    }
+}
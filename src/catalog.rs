@@ -0,0 +1,170 @@
+//! Translations of the problem messages, see `--lang`.
+//!
+//! A catalog is a JSON file mapping problem codes to message templates:
+//!
+//! ```json
+//! { "NPV-140": "- {package_name}: Dieses Paketverzeichnis ist kein Verzeichnis." }
+//! ```
+//!
+//! Placeholders like `{package_name}` are replaced by the field of the problem with that name,
+//! where fields of fields can be accessed like `{location.line}`. `{{` and `}}` stand for literal
+//! braces. Problems without a template in the catalog keep their English message.
+//!
+//! The catalog for language `<lang>` is read from `<lang>.json` in the directory given by the
+//! `NIXPKGS_VET_MESSAGES_DIR` environment variable, defaulting to `share/nixpkgs-vet/messages`
+//! next to the `bin` directory of nixpkgs-vet, where distributions can install their catalogs.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use serde_json::Value;
+
+use crate::problem::Problem;
+use crate::status::Fatal;
+
+/// The language of the messages built into nixpkgs-vet, which doesn't need a catalog.
+pub const BUILTIN_LANG: &str = "en";
+
+/// The catalog used for all problem messages, if any.
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Message templates keyed by problem code.
+pub struct Catalog {
+    messages: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    /// Parses the JSON of a catalog.
+    pub fn parse(text: &str) -> anyhow::Result<Catalog> {
+        Ok(Catalog {
+            messages: serde_json::from_str(text)?,
+        })
+    }
+
+    /// Reads the catalog for the given language from the messages directory.
+    pub fn read(lang: &str) -> anyhow::Result<Catalog> {
+        if lang.is_empty()
+            || !lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow::anyhow!(Fatal::Usage(format!(
+                "Invalid language {lang:?}, expected something like `de` or `pt_BR`"
+            ))));
+        }
+
+        let dir = match env::var_os("NIXPKGS_VET_MESSAGES_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => env::current_exe()?
+                .parent()
+                .and_then(|bin| bin.parent())
+                .context("Could not determine the installation directory of nixpkgs-vet")?
+                .join("share/nixpkgs-vet/messages"),
+        };
+        let path = dir.join(format!("{lang}.json"));
+        let text = fs::read_to_string(&path).with_context(|| {
+            Fatal::Usage(format!(
+                "There are no messages for language {lang:?}, expected them in {}",
+                path.display()
+            ))
+        })?;
+        Catalog::parse(&text).with_context(|| format!("Invalid message catalog {}", path.display()))
+    }
+
+    /// The message for the problem according to the catalog, if it has a template for it.
+    pub fn translate(&self, problem: &Problem) -> Option<String> {
+        let template = self.messages.get(problem.code())?;
+        let fields = serde_json::to_value(problem).expect("problems can always be serialized");
+        Some(render(template, &fields))
+    }
+}
+
+/// Uses the catalog for all problem messages from now on. Can only be called once.
+pub fn set(catalog: Catalog) {
+    if CATALOG.set(catalog).is_err() {
+        panic!("the message catalog is only set once");
+    }
+}
+
+/// The message for the problem according to the catalog in use, if any.
+pub fn translate(problem: &Problem) -> Option<String> {
+    CATALOG.get()?.translate(problem)
+}
+
+/// Fills the fields into the placeholders of the template. Unknown placeholders are kept as is.
+fn render(template: &str, fields: &Value) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+            result.push_str(&rest[..1]);
+            rest = after;
+            continue;
+        }
+        let placeholder = rest
+            .strip_prefix('{')
+            .and_then(|after| after.split_once('}'))
+            .and_then(|(name, after)| Some((lookup(fields, name)?, after)));
+        match placeholder {
+            Some((value, after)) => {
+                result.push_str(&value);
+                rest = after;
+            }
+            None => {
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The field with the given dotted name, as it should appear in messages.
+fn lookup(fields: &Value, name: &str) -> Option<String> {
+    let value = name
+        .split('.')
+        .try_fold(fields, |value, key| value.get(key))?;
+    Some(match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{npv_120, npv_123};
+
+    #[test]
+    fn translates_problems() -> anyhow::Result<()> {
+        let catalog = Catalog::parse(
+            r#"{ "NPV-123": "- {relative_package_dir}: {subpath}:{line} enthält {{ {text} }} {unknown}" }"#,
+        )?;
+
+        let problem = npv_123::NixFileContainsPathOutsideDirectory::new(
+            "pkgs/by-name/fo/foo",
+            "package.nix",
+            3,
+            5,
+            "../.",
+        )
+        .into();
+        assert_eq!(
+            catalog.translate(&problem).as_deref(),
+            Some("- pkgs/by-name/fo/foo: package.nix:3 enthält { ../. } {unknown}")
+        );
+
+        let problem =
+            npv_120::NixEvalError::new(npv_120::EvalFailureKind::Other, None, "error").into();
+        assert_eq!(catalog.translate(&problem), None);
+        Ok(())
+    }
+}
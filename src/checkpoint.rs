@@ -0,0 +1,198 @@
+//! Persists the results of the Nix evaluations as they complete, such that a run that was
+//! interrupted, e.g. by a CI timeout, can resume from them, see `--resume`.
+//!
+//! The evaluation of each shard (see `--jobs`) is stored under a key of everything it depends on,
+//! so stored results are only used if evaluating again would give the same result. Since Nixpkgs
+//! is identified by its git tree or its Nix store path, nothing is stored for Nixpkgs that's
+//! neither a git checkout without changes nor in the Nix store, like with `--diff` or `pr`.
+//!
+//! The evaluations are also shared with other runs through `--cache` under the same keys.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::process::ExitStatusExt as _;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::Context;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{cache, git, store};
+
+static CHECKPOINT: OnceLock<Checkpoint> = OnceLock::new();
+
+/// The state file given by `--resume`, with its current contents.
+struct Checkpoint {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    /// The version of nixpkgs-vet that wrote the state, which is discarded by other versions.
+    version: String,
+    /// The results of the completed evaluations by their key.
+    evaluations: BTreeMap<String, Evaluation>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Evaluation {
+    /// The Nixpkgs that was evaluated. The same tree may be checked out elsewhere when resuming,
+    /// like in a new temporary directory for `--base-ref`.
+    nixpkgs_path: PathBuf,
+    /// The standard output of the successful evaluation.
+    stdout: String,
+    /// The standard error of the successful evaluation, with the warnings emitted by it.
+    #[serde(default)]
+    stderr: String,
+}
+
+/// Reads the state file, or starts with an empty state if it doesn't exist yet or was written by
+/// another version. Needs to be called before any evaluation, and only once.
+pub fn init(path: &Path) -> anyhow::Result<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    let state = match fs::read_to_string(path) {
+        Ok(contents) => {
+            let state: State = serde_json::from_str(&contents)
+                .with_context(|| format!("Could not parse the state file {}", path.display()))?;
+            if state.version == version {
+                tracing::info!(
+                    evaluations = state.evaluations.len(),
+                    "Resuming from {}",
+                    path.display()
+                );
+                state
+            } else {
+                State::default()
+            }
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => State::default(),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("Could not read the state file {}", path.display()));
+        }
+    };
+    let checkpoint = Checkpoint {
+        path: path.to_owned(),
+        state: Mutex::new(State {
+            version: version.to_owned(),
+            ..state
+        }),
+    };
+    if CHECKPOINT.set(checkpoint).is_err() {
+        panic!("the state file is only initialised once");
+    }
+    Ok(())
+}
+
+/// The key of an evaluation of the Nixpkgs with the given inputs, like the files passed to Nix.
+/// Returns `None` if there's neither `--resume` nor `--cache`, or if the Nixpkgs can't be
+/// identified.
+///
+/// The key is a SHA-256 hash of the identity of the Nixpkgs and the inputs, such that runners
+/// sharing a cache agree on it with any build of nixpkgs-vet, and different evaluations don't.
+pub fn key(nixpkgs_path: &Path, inputs: &[&[u8]]) -> Option<String> {
+    if CHECKPOINT.get().is_none() && !cache::enabled() {
+        return None;
+    }
+    let Some(tree) = git::clean_tree(nixpkgs_path).or_else(|| store::store_path_name(nixpkgs_path))
+    else {
+        tracing::debug!(
+            nixpkgs = %nixpkgs_path.display(),
+            "Not storing the evaluation, since the Nixpkgs is neither a git checkout without \
+             changes nor a Nix store path"
+        );
+        return None;
+    };
+    // Each part is prefixed by its length, such that the boundaries between them are part of the
+    // hash
+    let mut hasher = Sha256::new();
+    for part in [tree.as_bytes()].into_iter().chain(inputs.iter().copied()) {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    let hash = hasher.finalize();
+    Some(format!("{hash:x}"))
+}
+
+/// The output of the completed evaluation with the given key, as if the Nixpkgs was in the given
+/// path.
+pub fn get(key: &str, nixpkgs_path: &Path) -> Option<process::Output> {
+    let stored = CHECKPOINT.get().and_then(|checkpoint| {
+        let state = checkpoint.state.lock().expect("not poisoned");
+        let evaluation = state.evaluations.get(key)?;
+        tracing::debug!(key, "Using the stored evaluation");
+        Some(evaluation.clone())
+    });
+    let evaluation = stored.or_else(|| serde_json::from_slice(&cache::get(key)?).ok())?;
+    // The locations of attributes are absolute paths
+    let stdout = match (evaluation.nixpkgs_path.to_str(), nixpkgs_path.to_str()) {
+        (Some(from), Some(to)) if from != to => relocate(&evaluation.stdout, from, to),
+        _ => evaluation.stdout.clone(),
+    };
+    Some(process::Output {
+        status: process::ExitStatus::from_raw(0),
+        stdout: stdout.into_bytes(),
+        stderr: evaluation.stderr.clone().into_bytes(),
+    })
+}
+
+/// Replaces the paths within the Nixpkgs at `from` in the JSON output of an evaluation with the
+/// ones within `to`. Only whole paths are replaced, not other paths that start with the same
+/// characters, like `/tmp/nixpkgs-2` for `/tmp/nixpkgs`.
+fn relocate(stdout: &str, from: &str, to: &str) -> String {
+    let pattern = Regex::new(&format!(r#"{}([/"])"#, regex::escape(from))).expect("valid regex");
+    pattern
+        .replace_all(stdout, |captures: &Captures| {
+            format!("{to}{}", &captures[1])
+        })
+        .into_owned()
+}
+
+/// Stores the output of a completed evaluation with the given key, writing the state file right
+/// away such that it survives an interruption, and putting it in the cache.
+pub fn save(key: &str, nixpkgs_path: &Path, output: &process::Output) -> anyhow::Result<()> {
+    let evaluation = Evaluation {
+        nixpkgs_path: nixpkgs_path.to_owned(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    };
+    if cache::enabled() {
+        cache::put(key, &serde_json::to_vec(&evaluation)?);
+    }
+    let Some(checkpoint) = CHECKPOINT.get() else {
+        return Ok(());
+    };
+    let mut state = checkpoint.state.lock().expect("not poisoned");
+    state.evaluations.insert(key.to_owned(), evaluation);
+    // Writing to a temporary file first, such that an interruption can't leave a partial file
+    let temporary_path = checkpoint.path.with_extension("tmp");
+    fs::write(&temporary_path, serde_json::to_vec(&*state)?)
+        .and_then(|()| fs::rename(&temporary_path, &checkpoint.path))
+        .with_context(|| {
+            format!(
+                "Could not write the state file {}",
+                checkpoint.path.display()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocates_whole_paths() {
+        assert_eq!(
+            relocate(
+                r#"{"file":"/tmp/nixpkgs/pkgs/foo.nix","root":"/tmp/nixpkgs","other":"/tmp/nixpkgs-vet-worktree/pkgs/foo.nix"}"#,
+                "/tmp/nixpkgs",
+                "/build/nixpkgs",
+            ),
+            r#"{"file":"/build/nixpkgs/pkgs/foo.nix","root":"/build/nixpkgs","other":"/tmp/nixpkgs-vet-worktree/pkgs/foo.nix"}"#,
+        );
+    }
+}
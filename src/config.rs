@@ -0,0 +1,691 @@
+//! Configuration of the checks, such that Nixpkgs forks can enforce their own conventions, see
+//! `--config`.
+//!
+//! The configuration is a TOML file like this, where all settings are optional:
+//!
+//! ```toml
+//! # The regex the names of package directories in pkgs/by-name need to match in full
+//! package-name-pattern = "[a-z0-9-]+"
+//!
+//! # Whether new packages in pkgs/by-name need a `passthru.updateScript`, and existing ones can't
+//! # lose theirs
+//! require-update-script = true
+//!
+//! # The `type` attributes of attribute sets that are allowed in pkgs/by-name although they're not
+//! # derivations
+//! non-derivation-types = ["nixos-test"]
+//!
+//! # Whether to run the checks that need to evaluate Nixpkgs with Nix, see `--no-eval`
+//! eval = false
+//!
+//! # How often to retry Nix evaluations that fail in a way that looks transient, like when killed
+//! # by the out-of-memory killer
+//! eval-retries = 2
+//!
+//! # The systems to evaluate Nixpkgs for, reporting the problems that only occur on the other
+//! # systems in addition to the ones of the first, see `--systems`
+//! systems = ["x86_64-linux", "aarch64-darwin"]
+//!
+//! # How many seconds each check may take at most, not counting the time it waits for Nix
+//! check-timeout = 600
+//!
+//! # The kind of repository, see `--profile`
+//! profile = "overlay"
+//!
+//! # What to do about symlinks pointing to paths that don't exist, see `BrokenSymlinks`
+//! broken-symlinks = "warning"
+//!
+//! # Whether to only report the problems that the base Nixpkgs doesn't have if it has any, see
+//! # `--only-new-problems`
+//! only-new-problems = true
+//!
+//! # Checks that aren't run by default, see `--check`
+//! checks = ["nixos-modules", "lib-tests", "maintainers", "doc-references", "release-jobs", "no-aliases", "cross", "meta-gating", "update-scripts"]
+//!
+//! # The regex finding references to packages in the manual in `doc`, with the attribute path in
+//! # the first group
+//! doc-reference-pattern = "`pkgs\\.([a-zA-Z0-9_.-]+)`"
+//!
+//! # The attributes of `pkgsCross` to evaluate the changed packages for, see `--check cross`
+//! cross-targets = ["aarch64-multiplatform", "mingwW64"]
+//!
+//! # The nixfmt binary to format the Nix files changed by `--fix` with, like the formatting check
+//! # of CI, unless they weren't formatted before
+//! nixfmt = "/run/current-system/sw/bin/nixfmt"
+//!
+//! # Package sets with a pkgs/by-name-like directory of their own, which are checked the same way
+//! [[package-sets]]
+//! attribute = "vimPlugins"
+//! path = "pkgs/applications/editors/vim/plugins/by-name"
+//!
+//! # Conventions of language ecosystems for the attributes passed to their builders, which new
+//! # files need to follow
+//! [[conventions]]
+//! name = "python-pyproject"
+//! path = "pkgs/development/python-modules"
+//! builder = "buildPythonPackage"
+//! required-attributes = ["pyproject"]
+//! forbidden-attributes = ["format"]
+//! reason = "The build system should be declared with `pyproject = true;` and `build-system`."
+//!
+//! # Settings for the paths matching any of the globs, and the files and directories within them,
+//! # which take precedence over the ones above. Later sections take precedence over earlier ones.
+//! [[overrides]]
+//! paths = ["pkgs/by-name/no/node-*"]
+//! # Checks whose problems about the paths aren't reported
+//! allow = ["NPV-141"]
+//! package-name-pattern = "[a-z0-9_.-]+"
+//! require-update-script = false
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use regex::Regex;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::{Deserialize, Deserializer, de};
+
+use crate::problem::Problem;
+use crate::status::Fatal;
+
+/// The default of `package-name-pattern`, the convention of Nixpkgs.
+pub const DEFAULT_PACKAGE_NAME_PATTERN: &str = "[a-zA-Z0-9_-]+";
+
+/// The default of `systems`, which most of Nixpkgs' CI runs on.
+pub const DEFAULT_SYSTEM: &str = "x86_64-linux";
+
+/// The default of `cross-targets`, the attributes of `pkgsCross` most packages should evaluate for.
+pub const DEFAULT_CROSS_TARGETS: &[&str] = &["aarch64-multiplatform", "riscv64"];
+
+/// The default of `doc-reference-pattern`, matching literals like `` `pkgs.foo.bar` ``.
+pub const DEFAULT_DOC_REFERENCE_PATTERN: &str =
+    r"`pkgs\.([a-zA-Z_][a-zA-Z0-9_'-]*(?:\.[a-zA-Z_][a-zA-Z0-9_'-]*)*)`";
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// The names of package directories in `pkgs/by-name` need to match this.
+    pub package_name_pattern: Pattern,
+    /// Package sets whose packages are in a `pkgs/by-name`-like directory of their own. They're
+    /// only checked if listed here.
+    pub package_sets: Vec<PackageSet>,
+    /// Whether new packages in `pkgs/by-name` need a `passthru.updateScript`.
+    pub require_update_script: bool,
+    /// The `type` attributes of attribute sets that are allowed in `pkgs/by-name` and package sets
+    /// although they're not derivations.
+    pub non_derivation_types: Vec<String>,
+    /// Whether to run the checks that need to evaluate Nixpkgs with Nix, also see `--no-eval`.
+    pub eval: bool,
+    /// How often to retry a Nix evaluation that failed in a way that looks transient.
+    pub eval_retries: usize,
+    /// The systems to evaluate Nixpkgs for. The ratchets only use the evaluation for the first
+    /// one, also see `--systems`.
+    pub systems: Vec<String>,
+    /// How many seconds each check may take, not counting the time it waits for Nix evaluations,
+    /// see `timeout`.
+    pub check_timeout: Option<u64>,
+    /// The kind of repository that's checked, also see `--profile`.
+    pub profile: Profile,
+    /// What to do about symlinks pointing to paths that don't exist.
+    pub broken_symlinks: BrokenSymlinks,
+    /// Whether to only report the problems the base Nixpkgs doesn't have if it has problems too,
+    /// also see `--only-new-problems`.
+    pub only_new_problems: bool,
+    /// The opt-in checks to run in addition to the default ones, also see `--check`.
+    pub checks: BTreeSet<Check>,
+    /// Finds the references to attributes of `pkgs` in the manual, see `Check::DocReferences`.
+    pub doc_reference_pattern: SearchPattern,
+    /// The attributes of `pkgsCross` to evaluate the changed packages for, see `Check::Cross`.
+    pub cross_targets: Vec<String>,
+    /// Conventions for the attributes passed to the builders of language ecosystems.
+    pub conventions: Vec<Convention>,
+    /// The nixfmt binary to format the Nix files changed by fixes with, see `fix::Fix::changes`.
+    pub nixfmt: Option<PathBuf>,
+    /// Settings for some paths that take precedence over the ones above, see `Config::for_path`.
+    pub overrides: Vec<Override>,
+}
+
+/// Settings for the paths matching any of the globs, and everything within them.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Override {
+    /// Globs of paths relative to Nixpkgs.
+    pub paths: Vec<PathGlob>,
+    /// The codes of the checks whose problems about the paths aren't reported, like `NPV-141`.
+    #[serde(default)]
+    pub allow: BTreeSet<String>,
+    /// Overrides `Config::package_name_pattern`.
+    pub package_name_pattern: Option<Pattern>,
+    /// Overrides `Config::require_update_script`.
+    pub require_update_script: Option<bool>,
+}
+
+impl Override {
+    /// Whether the override applies to the path.
+    fn applies_to(&self, path: &RelativePath) -> bool {
+        self.paths.iter().any(|glob| glob.matches_within(path))
+    }
+}
+
+/// A convention for the attributes passed to a builder like `buildPythonPackage`, see
+/// `ratchet::FollowsConvention`.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Convention {
+    /// The name of the convention, for messages.
+    pub name: String,
+    /// The directory with the files the convention applies to, relative to Nixpkgs.
+    pub path: RelativePathBuf,
+    /// The builder function, which matches both `buildPythonPackage` and
+    /// `python.pkgs.buildPythonPackage` if it's `buildPythonPackage`.
+    pub builder: String,
+    /// The attributes that need to be passed to the builder.
+    #[serde(default)]
+    pub required_attributes: Vec<String>,
+    /// The attributes that must not be passed to the builder.
+    #[serde(default)]
+    pub forbidden_attributes: Vec<String>,
+    /// Why the convention exists, for messages.
+    pub reason: Option<String>,
+}
+
+/// The kind of repository that's checked, which determines how the checks are adapted to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// Nixpkgs itself or a fork of it.
+    #[default]
+    Nixpkgs,
+    /// A third-party repository with its own `pkgs/by-name` that's applied on top of Nixpkgs, like
+    /// an overlay. Its `default.nix` needs to be a function like the one of Nixpkgs, taking
+    /// `overlays`, `config` and `system`. Since there's no `pkgs/top-level`, only the attributes
+    /// in `pkgs/by-name` and the configured package sets are evaluated, and the checks of
+    /// `all-packages.nix` and `aliases.nix` are skipped.
+    Overlay,
+}
+
+/// What to do about symlinks anywhere in Nixpkgs that point to paths that don't exist, see
+/// `symlinks::check_broken_symlinks`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BrokenSymlinks {
+    /// Broken symlinks are problems that fail the check.
+    #[default]
+    Error,
+    /// New broken symlinks are discouraged, but existing ones are allowed, like a ratchet check.
+    Warning,
+    /// Broken symlinks aren't checked.
+    Ignore,
+}
+
+/// A check that only runs if it's enabled with `--check` or in the configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// The modules in `nixos/modules` need to be listed in `module-list.nix`, either directly or
+    /// through the `imports` of a listed module, listed modules need to exist, and all modules
+    /// need to parse.
+    NixosModules,
+    /// New functions in the top-level files of `lib` need to be used by a test in `lib/tests`, and
+    /// existing ones can't lose their tests.
+    LibTests,
+    /// The maintainers in `maintainers/maintainer-list.nix` need to be sorted, have a `github`
+    /// and a unique `githubId`, and valid email addresses. The teams in
+    /// `maintainers/team-list.nix` need to have unique short names and known maintainers as
+    /// members, and the teams in `meta.teams` of packages in `pkgs/by-name` need to exist.
+    Maintainers,
+    /// The attribute paths of `pkgs` referred to by the manual in `doc`, as found by
+    /// `doc-reference-pattern`, need to exist.
+    DocReferences,
+    /// The Hydra jobs in `pkgs/top-level/release.nix` of the changed packages need to evaluate,
+    /// unless they already didn't.
+    ReleaseJobs,
+    /// The changed packages in `pkgs/by-name` need to evaluate for the `cross-targets` of
+    /// `pkgsCross` they're available on, unless they already didn't.
+    Cross,
+    /// The changed packages in `pkgs/by-name` need to be refused without `allowUnfree` or
+    /// `allowBroken` exactly if their `meta` marks them as unfree or broken, unless they already
+    /// weren't.
+    MetaGating,
+    /// The `passthru.updateScript` of the changed packages in `pkgs/by-name` needs to have a shape
+    /// `maintainers/scripts/update.nix` can run, and the files it refers to need to exist.
+    UpdateScripts,
+    /// The changed packages in `pkgs/by-name` need to evaluate with `config.allowAliases = false`,
+    /// so they can't use the aliases of `pkgs/top-level/aliases.nix`.
+    NoAliases,
+}
+
+/// A package set like `pkgs.vimPlugins`, with each package `<name>` defined by
+/// `<path>/<shard>/<name>/package.nix`.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageSet {
+    /// The attribute path of the package set below `pkgs`, like `vimPlugins` or
+    /// `python3Packages`.
+    pub attribute: String,
+    /// The directory with the shards of the package set, relative to Nixpkgs.
+    pub path: RelativePathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            package_name_pattern: Pattern::new(DEFAULT_PACKAGE_NAME_PATTERN)
+                .expect("the default pattern is valid"),
+            package_sets: vec![],
+            require_update_script: false,
+            non_derivation_types: vec![],
+            eval: true,
+            eval_retries: 2,
+            systems: vec![DEFAULT_SYSTEM.to_owned()],
+            check_timeout: None,
+            profile: Profile::default(),
+            broken_symlinks: BrokenSymlinks::default(),
+            only_new_problems: false,
+            checks: BTreeSet::new(),
+            doc_reference_pattern: SearchPattern::new(DEFAULT_DOC_REFERENCE_PATTERN)
+                .expect("the default pattern is valid"),
+            cross_targets: DEFAULT_CROSS_TARGETS
+                .iter()
+                .map(|target| (*target).to_owned())
+                .collect(),
+            conventions: vec![],
+            nixfmt: None,
+            overrides: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration file at the given path, or returns the default configuration if
+    /// there's none.
+    pub fn read(path: Option<&Path>) -> anyhow::Result<Config> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+        let text = fs::read_to_string(path).with_context(|| {
+            Fatal::Misconfiguration(format!(
+                "Could not read the configuration file {}",
+                path.display()
+            ))
+        })?;
+        Config::parse(&text).with_context(|| {
+            Fatal::Misconfiguration(format!("Invalid configuration file {}", path.display()))
+        })
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Config> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// The overrides that apply to the path, with the ones that take precedence last.
+    fn for_path(&self, path: &RelativePath) -> Vec<&Override> {
+        self.overrides
+            .iter()
+            .filter(|over| over.applies_to(path))
+            .collect()
+    }
+
+    /// The pattern the names of the package directories at the path need to match.
+    pub fn package_name_pattern_for(&self, path: &RelativePath) -> &Pattern {
+        self.for_path(path)
+            .into_iter()
+            .rev()
+            .find_map(|over| over.package_name_pattern.as_ref())
+            .unwrap_or(&self.package_name_pattern)
+    }
+
+    /// Whether the package at the path needs a `passthru.updateScript`.
+    pub fn require_update_script_for(&self, path: &RelativePath) -> bool {
+        self.for_path(path)
+            .into_iter()
+            .rev()
+            .find_map(|over| over.require_update_script)
+            .unwrap_or(self.require_update_script)
+    }
+
+    /// Whether an override allows the problem at its location, such that it isn't reported.
+    pub fn allows(&self, problem: &Problem) -> bool {
+        problem.location().is_some_and(|location| {
+            self.for_path(&location.path)
+                .into_iter()
+                .any(|over| over.allow.contains(problem.code()))
+        })
+    }
+}
+
+/// A glob of paths, where `*` matches any characters within a path component, `**` matches any
+/// number of components, and `?` matches a single character.
+#[derive(Clone)]
+pub struct PathGlob {
+    regex: Regex,
+}
+
+impl PathGlob {
+    pub fn new(source: &str) -> anyhow::Result<PathGlob> {
+        let mut regex = String::from("^");
+        let mut rest = source.trim_end_matches('/');
+        while let Some(char) = rest.chars().next() {
+            let (part, len) = if rest.starts_with("**/") {
+                ("(?:.*/)?".to_owned(), 3)
+            } else if rest.starts_with("**") {
+                (".*".to_owned(), 2)
+            } else if char == '*' {
+                ("[^/]*".to_owned(), 1)
+            } else if char == '?' {
+                ("[^/]".to_owned(), 1)
+            } else {
+                (regex::escape(&char.to_string()), char.len_utf8())
+            };
+            regex.push_str(&part);
+            rest = &rest[len..];
+        }
+        regex.push('$');
+        Ok(PathGlob {
+            regex: Regex::new(&regex)?,
+        })
+    }
+
+    /// Whether the glob matches the path or a directory it's in.
+    pub fn matches_within(&self, path: &RelativePath) -> bool {
+        let path = path.normalize();
+        let components = path
+            .components()
+            .map(|component| component.as_str())
+            .collect::<Vec<_>>();
+        (1..=components.len()).any(|count| self.regex.is_match(&components[..count].join("/")))
+    }
+}
+
+impl<'de> Deserialize<'de> for PathGlob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        PathGlob::new(&source).map_err(de::Error::custom)
+    }
+}
+
+/// A regex that needs to match strings in full.
+#[derive(Clone)]
+pub struct Pattern {
+    /// The regex as written by the user, for messages.
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> anyhow::Result<Pattern> {
+        Ok(Pattern {
+            source: source.to_owned(),
+            regex: Regex::new(&format!("^(?:{source})$"))?,
+        })
+    }
+
+    /// Whether the whole string matches.
+    pub fn is_match(&self, string: &str) -> bool {
+        self.regex.is_match(string)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Pattern::new(&source).map_err(de::Error::custom)
+    }
+}
+
+/// A regex that finds matches anywhere in strings, with the part of interest in its first group.
+#[derive(Clone)]
+pub struct SearchPattern {
+    regex: Regex,
+}
+
+impl SearchPattern {
+    pub fn new(source: &str) -> anyhow::Result<SearchPattern> {
+        let regex = Regex::new(source)?;
+        if regex.captures_len() < 2 {
+            anyhow::bail!("The regex {source:?} needs a group for the part of interest");
+        }
+        Ok(SearchPattern { regex })
+    }
+
+    /// The first group of each match.
+    pub fn find_iter<'a>(&'a self, string: &'a str) -> impl Iterator<Item = regex::Match<'a>> {
+        self.regex
+            .captures_iter(string)
+            .filter_map(|captures| captures.get(1))
+    }
+}
+
+impl<'de> Deserialize<'de> for SearchPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        SearchPattern::new(&source).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_package_name_pattern() -> anyhow::Result<()> {
+        let default = Config::parse("")?;
+        assert!(default.package_name_pattern.is_match("Foo_bar-2"));
+        assert!(!default.package_name_pattern.is_match("foo.bar"));
+
+        let config = Config::parse(r#"package-name-pattern = "[a-z0-9-]+""#)?;
+        assert!(config.package_name_pattern.is_match("foo-2"));
+        // The pattern needs to match in full
+        assert!(!config.package_name_pattern.is_match("Foo-2"));
+        assert!(!config.package_name_pattern.is_match("foo_2"));
+
+        assert!(Config::parse(r#"package-name-pattern = "[a-z""#).is_err());
+        assert!(Config::parse(r#"unknown = true"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_package_sets() -> anyhow::Result<()> {
+        assert!(Config::parse("")?.package_sets.is_empty());
+
+        let config = Config::parse(indoc::indoc! {r#"
+            [[package-sets]]
+            attribute = "vimPlugins"
+            path = "pkgs/applications/editors/vim/plugins/by-name"
+        "#})?;
+        let [package_set] = config.package_sets.as_slice() else {
+            panic!("expected one package set");
+        };
+        assert_eq!(package_set.attribute, "vimPlugins");
+        assert_eq!(
+            package_set.path,
+            "pkgs/applications/editors/vim/plugins/by-name"
+        );
+
+        assert!(Config::parse("[[package-sets]]\nattribute = \"vimPlugins\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_non_derivation_types() -> anyhow::Result<()> {
+        assert!(Config::parse("")?.non_derivation_types.is_empty());
+        assert_eq!(
+            Config::parse(r#"non-derivation-types = ["nixos-test"]"#)?.non_derivation_types,
+            ["nixos-test"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_checks() -> anyhow::Result<()> {
+        assert!(Config::parse("")?.checks.is_empty());
+        let config = Config::parse(
+            r#"checks = ["nixos-modules", "lib-tests", "maintainers", "doc-references", "release-jobs", "no-aliases", "cross", "meta-gating", "update-scripts"]"#,
+        )?;
+        assert!(config.checks.contains(&Check::NixosModules));
+        assert!(config.checks.contains(&Check::LibTests));
+        assert!(config.checks.contains(&Check::Maintainers));
+        assert!(config.checks.contains(&Check::DocReferences));
+        assert!(config.checks.contains(&Check::ReleaseJobs));
+        assert!(config.checks.contains(&Check::NoAliases));
+        assert!(config.checks.contains(&Check::Cross));
+        assert!(config.checks.contains(&Check::MetaGating));
+        assert!(config.checks.contains(&Check::UpdateScripts));
+        assert!(Config::parse(r#"checks = ["unknown"]"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_doc_reference_pattern() -> anyhow::Result<()> {
+        let default = Config::parse("")?;
+        let references: Vec<&str> = default
+            .doc_reference_pattern
+            .find_iter("Use `pkgs.foo.bar` or `pkgs.baz`, but not `pkgs.${name}` or pkgs.qux.")
+            .map(|found| found.as_str())
+            .collect();
+        assert_eq!(references, vec!["foo.bar", "baz"]);
+
+        assert!(Config::parse(r#"doc-reference-pattern = "pkgs\\.[a-z]+""#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_conventions() -> anyhow::Result<()> {
+        assert!(Config::parse("")?.conventions.is_empty());
+
+        let config = Config::parse(indoc::indoc! {r#"
+            [[conventions]]
+            name = "python-pyproject"
+            path = "pkgs/development/python-modules"
+            builder = "buildPythonPackage"
+            forbidden-attributes = ["format"]
+        "#})?;
+        let [convention] = config.conventions.as_slice() else {
+            panic!("expected one convention");
+        };
+        assert_eq!(convention.builder, "buildPythonPackage");
+        assert!(convention.required_attributes.is_empty());
+        assert_eq!(convention.forbidden_attributes, vec!["format"]);
+        assert_eq!(convention.reason, None);
+
+        assert!(Config::parse("[[conventions]]\nname = \"foo\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_profile() -> anyhow::Result<()> {
+        assert_eq!(Config::parse("")?.profile, Profile::Nixpkgs);
+        assert_eq!(
+            Config::parse("profile = \"overlay\"")?.profile,
+            Profile::Overlay
+        );
+        assert!(Config::parse("profile = \"flake\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_broken_symlinks() -> anyhow::Result<()> {
+        assert_eq!(Config::parse("")?.broken_symlinks, BrokenSymlinks::Error);
+        assert_eq!(
+            Config::parse("broken-symlinks = \"ignore\"")?.broken_symlinks,
+            BrokenSymlinks::Ignore
+        );
+        assert!(Config::parse("broken-symlinks = \"fatal\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_eval() -> anyhow::Result<()> {
+        assert!(Config::parse("")?.eval);
+        assert!(!Config::parse("eval = false")?.eval);
+        assert_eq!(Config::parse("")?.eval_retries, 2);
+        assert_eq!(Config::parse("eval-retries = 0")?.eval_retries, 0);
+        assert_eq!(Config::parse("")?.systems, [DEFAULT_SYSTEM]);
+        assert_eq!(
+            Config::parse(r#"systems = ["x86_64-linux", "aarch64-darwin"]"#)?.systems,
+            ["x86_64-linux", "aarch64-darwin"]
+        );
+        assert_eq!(Config::parse("")?.cross_targets, DEFAULT_CROSS_TARGETS);
+        assert_eq!(
+            Config::parse(r#"cross-targets = ["mingwW64"]"#)?.cross_targets,
+            ["mingwW64"]
+        );
+        assert_eq!(Config::parse("")?.check_timeout, None);
+        assert_eq!(Config::parse("check-timeout = 60")?.check_timeout, Some(60));
+        assert_eq!(Config::parse("")?.nixfmt, None);
+        assert_eq!(
+            Config::parse(r#"nixfmt = "/bin/nixfmt""#)?.nixfmt,
+            Some(PathBuf::from("/bin/nixfmt"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_overrides() -> anyhow::Result<()> {
+        use crate::problem::{npv_141, npv_143};
+
+        assert!(Config::parse("")?.overrides.is_empty());
+        let config = Config::parse(indoc::indoc! {r#"
+            require-update-script = true
+
+            [[overrides]]
+            paths = ["pkgs/by-name/no/node-*", "pkgs/**/vendored"]
+            allow = ["NPV-143"]
+            package-name-pattern = "[a-z.-]+"
+
+            [[overrides]]
+            paths = ["pkgs/by-name/no/node-old"]
+            require-update-script = false
+            package-name-pattern = "[a-z_-]+"
+        "#})?;
+        let path = RelativePath::new;
+        assert!(config.require_update_script_for(path("pkgs/by-name/no/node-foo")));
+        assert!(!config.require_update_script_for(path("pkgs/by-name/no/node-old")));
+        assert_eq!(
+            config
+                .package_name_pattern_for(path("pkgs/by-name/no/node-foo"))
+                .as_str(),
+            "[a-z.-]+"
+        );
+        // Later overrides take precedence
+        assert_eq!(
+            config
+                .package_name_pattern_for(path("pkgs/by-name/no/node-old"))
+                .as_str(),
+            "[a-z_-]+"
+        );
+        assert_eq!(
+            config
+                .package_name_pattern_for(path("pkgs/by-name/no/nodejs"))
+                .as_str(),
+            DEFAULT_PACKAGE_NAME_PATTERN
+        );
+
+        // Globs match the paths within the matching ones, and `*` doesn't cross directories
+        let missing = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        assert!(config.allows(&missing("pkgs/by-name/no/node-foo")));
+        assert!(config.allows(&missing("pkgs/by-name/no/node-foo/sub")));
+        assert!(config.allows(&missing("pkgs/a/b/vendored/c")));
+        assert!(!config.allows(&missing("pkgs/by-name/no/nodejs")));
+        let invalid: Problem =
+            npv_141::InvalidPackageDirectoryName::new("node-foo", "pkgs/by-name/no/node-foo", "x")
+                .into();
+        assert!(!config.allows(&invalid));
+
+        assert!(Config::parse("[[overrides]]\nallow = [\"NPV-143\"]").is_err());
+        Ok(())
+    }
+}
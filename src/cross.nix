@@ -0,0 +1,47 @@
+# Takes a path to nixpkgs, a path to the json-encoded list of attributes to evaluate, and a path
+# to the json-encoded list of attributes of `pkgsCross` to evaluate them for.
+#
+# Returns for each attribute and target whether the derivation evaluates, or null if the package
+# isn't available on the host platform of the target according to its `meta.platforms`. See
+# ./cross.rs for how this is used.
+{
+  attrsPath,
+  targetsPath,
+  nixpkgsPath,
+  system ? "x86_64-linux",
+}:
+let
+  attrs = builtins.fromJSON (builtins.readFile attrsPath);
+  targets = builtins.fromJSON (builtins.readFile targetsPath);
+
+  pkgs = import nixpkgsPath {
+    # Don't let the user's home directory influence this result.
+    config = { };
+    overlays = [ ];
+    inherit system;
+  };
+
+  evaluates =
+    target: name:
+    let
+      set = pkgs.pkgsCross.${target};
+      result = builtins.tryEval (
+        if !pkgs.lib.meta.availableOn set.stdenv.hostPlatform pkgs.${name} then
+          null
+        else
+          builtins.seq set.${name}.drvPath true
+      );
+    in
+    if result.success then result.value else false;
+in
+builtins.listToAttrs (
+  map (name: {
+    inherit name;
+    value = builtins.listToAttrs (
+      map (target: {
+        name = target;
+        value = evaluates target name;
+      }) targets
+    );
+  }) attrs
+)
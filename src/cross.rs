@@ -0,0 +1,83 @@
+//! Evaluates the changed packages in `pkgs/by-name` for some targets of `pkgsCross`, see
+//! `--check cross`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::eval;
+use crate::ratchet::{self, RatchetState};
+use crate::scope::Scope;
+use crate::validation::{self, Validation::Success};
+
+const CROSS_NIX: &[u8] = include_bytes!("cross.nix");
+
+/// Returns the ratchet value of each of the packages in `pkgs/by-name` that are in scope for each
+/// of the configured `cross-targets`, by `pkgsCross.<target>.<name>`, see `eval::evaluate_json`.
+///
+/// The targets are cross-compiled to from the first of the configured systems, which is the only
+/// one the ratchets use, see `eval::check_values`.
+pub fn check_cross(
+    nixpkgs_path: &Path,
+    packages: &BTreeMap<String, ratchet::Package>,
+    scope: &Scope,
+    config: &Config,
+) -> validation::Result<BTreeMap<String, ratchet::CrossEval>> {
+    let _span = tracing::info_span!("cross").entered();
+    if scope.is_everything() || config.cross_targets.is_empty() {
+        return Ok(Success(BTreeMap::new()));
+    }
+    let attributes: Vec<&str> = packages
+        .iter()
+        // Only packages in `pkgs/by-name` have the hash of their `package.nix`
+        .filter(|(name, package)| {
+            package.package_nix_hash.is_some() && scope.contains_package(name)
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if attributes.is_empty() {
+        return Ok(Success(BTreeMap::new()));
+    }
+
+    let system = config
+        .systems
+        .first()
+        .context("No system to evaluate Nixpkgs for")?;
+    let results: BTreeMap<String, BTreeMap<String, Option<bool>>> = match eval::evaluate_json(
+        "cross",
+        CROSS_NIX,
+        nixpkgs_path,
+        &[
+            ("attrsPath", json!(attributes)),
+            ("targetsPath", json!(config.cross_targets)),
+        ],
+        Some(system),
+        config,
+    )? {
+        Ok(results) => results,
+        // Failures that can't be caught, like of `pkgsCross` itself
+        Err(stderr) => return Ok(eval::eval_error(&stderr).into()),
+    };
+    Ok(Success(
+        results
+            .into_iter()
+            .flat_map(|(name, targets)| {
+                targets.into_iter().map(move |(target, evaluates)| {
+                    let evaluates = match evaluates {
+                        Some(true) => RatchetState::Tight,
+                        Some(false) => RatchetState::Loose((name.clone(), target.clone())),
+                        // Not available for the target
+                        None => RatchetState::NonApplicable,
+                    };
+                    (
+                        format!("pkgsCross.{target}.{name}"),
+                        ratchet::CrossEval { evaluates },
+                    )
+                })
+            })
+            .collect(),
+    ))
+}
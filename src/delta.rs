@@ -0,0 +1,111 @@
+//! Compares the problems of the base and the main Nixpkgs, to report only the ones a PR
+//! introduces when the base branch is already broken, see `only-new-problems`.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::problem::Problem;
+
+/// The problems of the main Nixpkgs that the base Nixpkgs doesn't have, and the problems of the
+/// base Nixpkgs that the main Nixpkgs doesn't have anymore, in their original order.
+///
+/// Problems are the same if they only differ in their lines and columns, since unrelated changes
+/// to a file move the problems in it.
+pub fn compare(base: Vec<Problem>, main: Vec<Problem>) -> (Vec<Problem>, Vec<Problem>) {
+    let mut base_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for problem in &base {
+        *base_counts.entry(identity(problem)).or_default() += 1;
+    }
+    let mut main_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for problem in &main {
+        *main_counts.entry(identity(problem)).or_default() += 1;
+    }
+    (
+        only_in(main, &mut base_counts),
+        only_in(base, &mut main_counts),
+    )
+}
+
+/// The problems that aren't counted in `other`, taking the ones that are counted from it.
+fn only_in(problems: Vec<Problem>, other: &mut BTreeMap<String, usize>) -> Vec<Problem> {
+    problems
+        .into_iter()
+        .filter(|problem| match other.get_mut(&identity(problem)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+/// What tells a problem apart from others, which is its code and its fields except for lines and
+/// columns.
+pub fn identity(problem: &Problem) -> String {
+    let mut fields = serde_json::to_value(problem).expect("problems can be serialised");
+    without_positions(&mut fields);
+    format!("{} {fields}", problem.code())
+}
+
+/// What tells a problem apart from all others, which is its code and all of its fields, to keep
+/// track of problems while changing a status.
+pub fn key(problem: &Problem) -> String {
+    let fields = serde_json::to_value(problem).expect("problems can be serialised");
+    format!("{} {fields}", problem.code())
+}
+
+fn without_positions(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.retain(|name, _| !matches!(name.as_str(), "line" | "column" | "end_line"));
+            fields.values_mut().for_each(without_positions);
+        }
+        Value::Array(values) => values.iter_mut().for_each(without_positions),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_143, npv_146};
+
+    #[test]
+    fn compares_problems() {
+        let missing = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let unsorted = |line: usize| -> Problem {
+            npv_146::TopLevelEntryUnsorted::new(
+                "foo",
+                "bar",
+                true,
+                Location::new("pkgs/top-level/all-packages.nix", line, 3),
+            )
+            .into()
+        };
+        let (introduced, fixed) = compare(
+            vec![missing("pkgs/by-name/fo/foo"), unsorted(10)],
+            vec![
+                unsorted(12),
+                missing("pkgs/by-name/ba/bar"),
+                missing("pkgs/by-name/ba/bar"),
+            ],
+        );
+        assert_eq!(
+            introduced
+                .iter()
+                .map(Problem::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "- pkgs/by-name/ba/bar: Missing required \"package.nix\" file.",
+                "- pkgs/by-name/ba/bar: Missing required \"package.nix\" file."
+            ]
+        );
+        assert_eq!(
+            fixed.iter().map(Problem::to_string).collect::<Vec<_>>(),
+            ["- pkgs/by-name/fo/foo: Missing required \"package.nix\" file."]
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! Parsing of unified diffs, used to restrict the checks to the parts of Nixpkgs a PR touches,
+//! and rendering them, used to preview fixes with `--fix --dry-run`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read as _};
+use std::ops::RangeInclusive;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::Path;
+use std::process;
+
+use anyhow::Context;
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::scope::Scope;
+use crate::status::Fatal;
+
+/// The changes a unified diff makes to a single file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    /// The path of the file before the change, or `None` if the file was created.
+    pub old_path: Option<RelativePathBuf>,
+    /// The path of the file after the change, or `None` if the file was deleted.
+    pub new_path: Option<RelativePathBuf>,
+    /// The 1-indexed lines of the old file that were removed.
+    pub removed_lines: BTreeSet<usize>,
+    /// The 1-indexed lines of the new file that were added.
+    pub added_lines: BTreeSet<usize>,
+    /// The ranges of 1-indexed lines of the new file covered by hunks, including context lines.
+    pub hunks: Vec<RangeInclusive<usize>>,
+}
+
+impl FileDiff {
+    /// Whether a line of the new file is shown in the diff, e.g. as context.
+    pub fn shows_line(&self, line: usize) -> bool {
+        self.hunks.iter().any(|hunk| hunk.contains(&line))
+    }
+}
+
+/// A parsed unified diff, as produced by `git diff` or `diff -u`.
+pub struct Diff {
+    /// The unparsed diff, needed for applying it.
+    text: String,
+    pub files: Vec<FileDiff>,
+}
+
+impl Diff {
+    /// Reads a diff from a file, or from stdin if the path is `-`.
+    pub fn read(path: &Path) -> anyhow::Result<Diff> {
+        let text = if path == Path::new("-") {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .context("Could not read the diff from stdin")?;
+            text
+        } else {
+            fs::read_to_string(path)
+                .with_context(|| format!("Could not read the diff {}", path.display()))?
+        };
+        Diff::parse(text)
+    }
+
+    /// Parses the text of a unified diff.
+    pub fn parse(text: String) -> anyhow::Result<Diff> {
+        let mut files: Vec<FileDiff> = vec![];
+        let mut lines = text.lines().enumerate().peekable();
+
+        while let Some((index, line)) = lines.next() {
+            if let Some(rest) = line.strip_prefix("diff --git ") {
+                // Git diffs always start with this header, which is the only place mentioning
+                // the paths of pure renames and binary files.
+                let (old, new) = rest.split_once(' ').with_context(|| {
+                    format!("Malformed diff header on line {}: {line}", index + 1)
+                })?;
+                files.push(FileDiff {
+                    old_path: Some(strip_diff_prefix(old)),
+                    new_path: Some(strip_diff_prefix(new)),
+                    ..FileDiff::default()
+                });
+            } else if let Some(old) = line.strip_prefix("--- ") {
+                // Non-git diffs don't have a `diff --git` header, in which case this starts a new
+                // file. For git diffs, this refines the paths from the header.
+                let Some((_, new_line)) = lines.next() else {
+                    anyhow::bail!("Diff ends after the \"---\" line {}", index + 1)
+                };
+                let Some(new) = new_line.strip_prefix("+++ ") else {
+                    anyhow::bail!(
+                        "Expected a \"+++\" line after line {}, but got: {new_line}",
+                        index + 1
+                    )
+                };
+                let file = match files.last_mut() {
+                    Some(file) if file.removed_lines.is_empty() && file.added_lines.is_empty() => {
+                        file
+                    }
+                    _ => {
+                        files.push(FileDiff::default());
+                        files.last_mut().expect("just pushed")
+                    }
+                };
+                file.old_path = diff_path(old);
+                file.new_path = diff_path(new);
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                if let Some(file) = files.last_mut() {
+                    file.old_path = Some(RelativePathBuf::from(path));
+                }
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                if let Some(file) = files.last_mut() {
+                    file.new_path = Some(RelativePathBuf::from(path));
+                }
+            } else if line.starts_with("@@ ") {
+                let Some(file) = files.last_mut() else {
+                    anyhow::bail!("Hunk on line {} doesn't belong to any file", index + 1)
+                };
+                let Hunk {
+                    mut old_line,
+                    mut old_remaining,
+                    mut new_line,
+                    mut new_remaining,
+                } = Hunk::parse(line)
+                    .with_context(|| format!("Malformed hunk header on line {}", index + 1))?;
+                if new_remaining > 0 {
+                    file.hunks.push(new_line..=new_line + new_remaining - 1);
+                }
+
+                // Consume exactly as many lines as the header announces, otherwise removed lines
+                // starting with `--` could be mistaken for the start of the next file.
+                while old_remaining > 0 || new_remaining > 0 {
+                    let Some((_, hunk_line)) = lines.next() else {
+                        anyhow::bail!("Diff ends in the middle of the hunk on line {}", index + 1)
+                    };
+                    match hunk_line.chars().next() {
+                        Some('+') => {
+                            file.added_lines.insert(new_line);
+                            new_line += 1;
+                            new_remaining = new_remaining.saturating_sub(1);
+                        }
+                        Some('-') => {
+                            file.removed_lines.insert(old_line);
+                            old_line += 1;
+                            old_remaining = old_remaining.saturating_sub(1);
+                        }
+                        // "\ No newline at end of file"
+                        Some('\\') => {}
+                        // Context lines, some tools strip the trailing space from empty ones.
+                        _ => {
+                            old_line += 1;
+                            new_line += 1;
+                            old_remaining = old_remaining.saturating_sub(1);
+                            new_remaining = new_remaining.saturating_sub(1);
+                        }
+                    }
+                }
+                // A trailing "\ No newline at end of file" belongs to the hunk as well.
+                lines.next_if(|(_, l)| l.starts_with('\\'));
+            }
+            // Everything else (index lines, mode changes, commit messages) is irrelevant.
+        }
+
+        Ok(Diff { text, files })
+    }
+
+    /// The scopes of the checks for the Nixpkgs before and after the diff is applied.
+    pub fn scopes(&self) -> (Scope, Scope) {
+        (self.base_scope(), self.main_scope())
+    }
+
+    /// The scope of the checks for the Nixpkgs before the diff is applied.
+    pub fn base_scope(&self) -> Scope {
+        Scope::changed(self.files.iter().filter_map(|file| {
+            let path = file.old_path.clone()?;
+            Some((path, file.removed_lines.clone()))
+        }))
+    }
+
+    /// The scope of the checks for the Nixpkgs after the diff is applied.
+    pub fn main_scope(&self) -> Scope {
+        Scope::changed(self.files.iter().filter_map(|file| {
+            let path = file.new_path.clone()?;
+            Some((path, file.added_lines.clone()))
+        }))
+    }
+
+    /// Reconstructs the Nixpkgs before the diff was applied, given the Nixpkgs after it was
+    /// applied. The result is written into `target`, which should be an empty directory.
+    ///
+    /// This requires `git` to be available, since it's used to apply the diff in reverse.
+    pub fn reconstruct_base(&self, main_nixpkgs: &Path, target: &Path) -> anyhow::Result<()> {
+        copy_tree(main_nixpkgs, target).with_context(|| {
+            format!(
+                "Could not copy {} to {}",
+                main_nixpkgs.display(),
+                target.display()
+            )
+        })?;
+
+        tracing::debug!(target = %target.display(), "Applying the diff in reverse");
+        let mut child = process::Command::new("git")
+            .args(["apply", "--reverse", "--whitespace=nowarn", "-"])
+            .current_dir(target)
+            .stdin(process::Stdio::piped())
+            .stderr(process::Stdio::piped())
+            .spawn()
+            .with_context(|| {
+                Fatal::Misconfiguration("Failed to run `git apply`, is git installed?".to_owned())
+            })?;
+
+        {
+            use std::io::Write as _;
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            stdin.write_all(self.text.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to apply the diff in reverse to {}:\n{}",
+                main_nixpkgs.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The number of unchanged lines shown around the changed ones, like `git diff` does.
+const CONTEXT_LINES: usize = 3;
+
+/// Renders a git-style diff of the changes to a file, which `Diff::parse` and `git apply` accept.
+/// A file at a different path afterwards is shown as a rename, and nothing is rendered for a file
+/// that doesn't change at all.
+pub fn render(old_path: &RelativePath, new_path: &RelativePath, old: &str, new: &str) -> String {
+    let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+    let edits = diff_lines(&old_lines, &new_lines);
+    let changed = edits.iter().any(|edit| !matches!(edit, Edit::Same(..)));
+    if old_path == new_path && !changed {
+        return String::new();
+    }
+
+    let mut text = format!("diff --git a/{old_path} b/{new_path}\n");
+    if old_path != new_path {
+        let similarity = if changed {
+            ""
+        } else {
+            "similarity index 100%\n"
+        };
+        text.push_str(&format!(
+            "{similarity}rename from {old_path}\nrename to {new_path}\n"
+        ));
+    }
+    if !changed {
+        return text;
+    }
+    text.push_str(&format!("--- a/{old_path}\n+++ b/{new_path}\n"));
+
+    // The 0-indexed lines of the old and new file each edit is at
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for edit in &edits {
+        positions.push((old_line, new_line));
+        match edit {
+            Edit::Same(..) => (old_line, new_line) = (old_line + 1, new_line + 1),
+            Edit::Removed(..) => old_line += 1,
+            Edit::Added(..) => new_line += 1,
+        }
+    }
+
+    // Hunks are the changed edits with their context, merged if their context overlaps
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Same(..)) {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Added(..)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Removed(..)))
+            .count();
+        let (old_start, new_start) = positions[start];
+        text.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for edit in hunk {
+            let (prefix, line) = match edit {
+                Edit::Same(line) => (' ', line),
+                Edit::Removed(line) => ('-', line),
+                Edit::Added(line) => ('+', line),
+            };
+            text.push(prefix);
+            text.push_str(line);
+            if !line.ends_with('\n') {
+                text.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    text
+}
+
+/// The range of a hunk header for the 0-indexed start line and the number of lines, where empty
+/// ranges start at the line before and the count is left out if it's 1.
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{count}", start + 1),
+    }
+}
+
+/// A line of a diff.
+#[derive(Debug, PartialEq, Eq)]
+enum Edit<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The shortest edits that turn the old lines into the new ones, with Myers' algorithm. It takes
+/// time proportional to the number of lines times the number of edits, which is fast for fixes
+/// that only change a few lines of large files like `pkgs/top-level/all-packages.nix`.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    // The furthest old line reached on each diagonal `k = x - y`, stored for every number of
+    // edits `d` to backtrack from, only for the diagonals `-d - 1..=d + 1` that can be reached
+    let mut v = vec![0; 2 * (n + m) as usize + 3];
+    let offset = n + m + 1;
+    let mut trace = Vec::new();
+    'search: for d in 0..=n + m {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let get = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = get(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Same(old[x as usize - 1]));
+            (x, y) = (x - 1, y - 1);
+        }
+        if d > 0 {
+            if x == previous_x {
+                edits.push(Edit::Added(new[y as usize - 1]));
+            } else {
+                edits.push(Edit::Removed(old[x as usize - 1]));
+            }
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    edits.reverse();
+    edits
+}
+
+/// The header of a hunk: `@@ -<old>[,<count>] +<new>[,<count>] @@`.
+struct Hunk {
+    old_line: usize,
+    old_remaining: usize,
+    new_line: usize,
+    new_remaining: usize,
+}
+
+impl Hunk {
+    fn parse(line: &str) -> anyhow::Result<Hunk> {
+        let mut parts = line.split(' ').skip(1);
+        let mut range = |prefix: char| -> anyhow::Result<(usize, usize)> {
+            let part = parts.next().context("Missing range")?;
+            let range = part
+                .strip_prefix(prefix)
+                .with_context(|| format!("Range {part} doesn't start with {prefix}"))?;
+            // The count is optional and defaults to 1.
+            Ok(match range.split_once(',') {
+                Some((start, count)) => (start.parse()?, count.parse()?),
+                None => (range.parse()?, 1),
+            })
+        };
+        let (old_start, old_count) = range('-')?;
+        let (new_start, new_count) = range('+')?;
+        // Empty ranges start at the line before, but then no line will take that number anyways.
+        Ok(Hunk {
+            old_line: old_start.max(1),
+            old_remaining: old_count,
+            new_line: new_start.max(1),
+            new_remaining: new_count,
+        })
+    }
+}
+
+/// Turns a path from a `---`/`+++` line into a Nixpkgs-relative path.
+fn diff_path(path: &str) -> Option<RelativePathBuf> {
+    // Some tools append a timestamp separated by a tab.
+    let path = path.split('\t').next().unwrap_or(path);
+    if path == "/dev/null" {
+        None
+    } else {
+        Some(strip_diff_prefix(path))
+    }
+}
+
+/// Strips the `a/` and `b/` prefixes used by git.
+fn strip_diff_prefix(path: &str) -> RelativePathBuf {
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    RelativePathBuf::from(path)
+}
+
+/// Recursively copies a directory, preserving symlinks, but skipping `.git`.
+fn copy_tree(source: &Path, target: &Path) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in source.read_dir()? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target_path = target.join(entry.file_name());
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, target_path)?;
+        } else if file_type.is_dir() {
+            if entry.file_name() != ".git" {
+                copy_tree(&entry.path(), &target_path)?;
+            }
+        } else {
+            fs::copy(entry.path(), &target_path)?;
+            // Files from the Nix store are read-only, but `git apply` needs to change them
+            let mut permissions = fs::metadata(&target_path)?.permissions();
+            permissions.set_mode(permissions.mode() | 0o200);
+            fs::set_permissions(&target_path, permissions)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_diffs() -> anyhow::Result<()> {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl";
+        let new = "a\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm";
+        let text = render(
+            RelativePath::new("file"),
+            RelativePath::new("file"),
+            old,
+            new,
+        );
+        assert_eq!(
+            text,
+            indoc! {r"
+                diff --git a/file b/file
+                --- a/file
+                +++ b/file
+                @@ -1,5 +1,4 @@
+                 a
+                -b
+                 c
+                 d
+                 e
+                @@ -9,4 +8,5 @@
+                 i
+                 j
+                 k
+                -l
+                \ No newline at end of file
+                +l
+                +m
+                \ No newline at end of file
+            "}
+        );
+        let diff = Diff::parse(text)?;
+        assert_eq!(diff.files[0].removed_lines, BTreeSet::from([2, 12]));
+        assert_eq!(diff.files[0].added_lines, BTreeSet::from([11, 12]));
+
+        // Renames without changes only have a header, and unchanged files nothing
+        let moved = |old, new| render(RelativePath::new(old), RelativePath::new(new), "a\n", "a\n");
+        assert_eq!(
+            moved(
+                "pkgs/by-name/aa/foo/package.nix",
+                "pkgs/by-name/fo/foo/package.nix"
+            ),
+            indoc! {"
+                diff --git a/pkgs/by-name/aa/foo/package.nix b/pkgs/by-name/fo/foo/package.nix
+                similarity index 100%
+                rename from pkgs/by-name/aa/foo/package.nix
+                rename to pkgs/by-name/fo/foo/package.nix
+            "}
+        );
+        assert_eq!(moved("file", "file"), "");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_git_diff() -> anyhow::Result<()> {
+        let diff = Diff::parse(
+            indoc! {"
+                diff --git a/pkgs/top-level/all-packages.nix b/pkgs/top-level/all-packages.nix
+                index 1111111..2222222 100644
+                --- a/pkgs/top-level/all-packages.nix
+                +++ b/pkgs/top-level/all-packages.nix
+                @@ -10,3 +10,3 @@ self: super: {
+                   a = 1;
+                -  foo = callPackage ../foo { };
+                +  foo = callPackage ../by-name/fo/foo/package.nix { };
+                   b = 2;
+                @@ -20,2 +20,3 @@
+                   c = 3;
+                +  d = 4;
+                 }
+                diff --git a/pkgs/foo/default.nix b/pkgs/by-name/fo/foo/package.nix
+                similarity index 100%
+                rename from pkgs/foo/default.nix
+                rename to pkgs/by-name/fo/foo/package.nix
+                diff --git a/pkgs/by-name/ba/bar/package.nix b/pkgs/by-name/ba/bar/package.nix
+                new file mode 100644
+                --- /dev/null
+                +++ b/pkgs/by-name/ba/bar/package.nix
+                @@ -0,0 +1,2 @@
+                +{ someDrv }:
+                +someDrv
+            "}
+            .to_owned(),
+        )?;
+
+        assert_eq!(
+            diff.files,
+            vec![
+                FileDiff {
+                    old_path: Some("pkgs/top-level/all-packages.nix".into()),
+                    new_path: Some("pkgs/top-level/all-packages.nix".into()),
+                    removed_lines: BTreeSet::from([11]),
+                    added_lines: BTreeSet::from([11, 21]),
+                    hunks: vec![10..=12, 20..=22],
+                },
+                FileDiff {
+                    old_path: Some("pkgs/foo/default.nix".into()),
+                    new_path: Some("pkgs/by-name/fo/foo/package.nix".into()),
+                    removed_lines: BTreeSet::new(),
+                    added_lines: BTreeSet::new(),
+                    hunks: vec![],
+                },
+                FileDiff {
+                    old_path: None,
+                    new_path: Some("pkgs/by-name/ba/bar/package.nix".into()),
+                    removed_lines: BTreeSet::new(),
+                    added_lines: BTreeSet::from([1, 2]),
+                    hunks: vec![1..=2],
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_plain_diff() -> anyhow::Result<()> {
+        let diff = Diff::parse(
+            indoc! {"
+                --- pkgs/by-name/fo/foo/package.nix\t2024-01-01 00:00:00
+                +++ pkgs/by-name/fo/foo/package.nix\t2024-01-02 00:00:00
+                @@ -1 +1 @@
+                -{ someDrv }: someDrv
+                +{ someOtherDrv }: someOtherDrv
+                --- pkgs/by-name/ba/bar/package.nix
+                +++ pkgs/by-name/ba/bar/package.nix
+                @@ -3,0 +4 @@
+                +# comment
+            "}
+            .to_owned(),
+        )?;
+
+        assert_eq!(
+            diff.files,
+            vec![
+                FileDiff {
+                    old_path: Some("pkgs/by-name/fo/foo/package.nix".into()),
+                    new_path: Some("pkgs/by-name/fo/foo/package.nix".into()),
+                    removed_lines: BTreeSet::from([1]),
+                    added_lines: BTreeSet::from([1]),
+                    hunks: vec![1..=1],
+                },
+                FileDiff {
+                    old_path: Some("pkgs/by-name/ba/bar/package.nix".into()),
+                    new_path: Some("pkgs/by-name/ba/bar/package.nix".into()),
+                    removed_lines: BTreeSet::new(),
+                    added_lines: BTreeSet::from([4]),
+                    hunks: vec![4..=4],
+                },
+            ]
+        );
+        Ok(())
+    }
+}
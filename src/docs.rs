@@ -0,0 +1,71 @@
+//! Finds the references to packages in the manual in `doc`, see `--check doc-references`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::config::SearchPattern;
+use crate::location::LineIndex;
+use crate::structure::read_dir_sorted;
+
+/// The directory with the sources of the manual, relative to Nixpkgs.
+pub const DOC_SUBPATH: &str = "doc";
+
+/// A reference to an attribute of `pkgs` in the manual, like `` `pkgs.foo` ``.
+pub struct DocReference {
+    /// The file of the manual, relative to Nixpkgs.
+    pub file: RelativePathBuf,
+    /// The attribute path below `pkgs`, like `["foo"]`.
+    pub attribute_path: Vec<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Returns the references found by the pattern in the Markdown files of the manual.
+pub fn doc_references(
+    nixpkgs_path: &Path,
+    pattern: &SearchPattern,
+) -> anyhow::Result<Vec<DocReference>> {
+    let doc_dir = nixpkgs_path.join(DOC_SUBPATH);
+    if !doc_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    collect_markdown_files(nixpkgs_path, RelativePath::new(DOC_SUBPATH), &mut files)?;
+
+    let mut references = vec![];
+    for file in files {
+        let path = file.to_path(nixpkgs_path);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read file {}", path.display()))?;
+        let line_index = LineIndex::new(&contents);
+        references.extend(pattern.find_iter(&contents).map(|found| DocReference {
+            file: file.clone(),
+            attribute_path: found.as_str().split('.').map(str::to_owned).collect(),
+            line: line_index.line(found.start()),
+            column: line_index.column(found.start()),
+        }));
+    }
+    Ok(references)
+}
+
+/// Recursively collects the Markdown files within the given directory relative to Nixpkgs, without
+/// following symlinks.
+fn collect_markdown_files(
+    nixpkgs_path: &Path,
+    dir: &RelativePath,
+    files: &mut Vec<RelativePathBuf>,
+) -> anyhow::Result<()> {
+    for entry in read_dir_sorted(&dir.to_path(nixpkgs_path))? {
+        let subpath = dir.join(entry.file_name().to_string_lossy().as_ref());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_markdown_files(nixpkgs_path, &subpath, files)?;
+        } else if file_type.is_file() && subpath.extension() == Some("md") {
+            files.push(subpath);
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,28 @@
-# Takes a path to nixpkgs and a path to the json-encoded list of `pkgs/by-name` attributes.
+# Takes a path to nixpkgs, a path to the json-encoded list of `pkgs/by-name` attributes, a path
+# to the json-encoded list of package sets configured with `package-sets`, each with the names of
+# its packages, and a path to the json-encoded list of attribute paths referenced by `passthru.tests`
+# or the manual.
+# With `byNameOnly`, attributes outside of `pkgs/by-name` aren't evaluated, see `--profile overlay`.
+# The attributes are evaluated for `system`, see `systems` of the configuration.
+# Optionally, only every `shardCount`th attribute starting at `shard` is returned, such that
+# separate processes can evaluate the attributes in parallel.
 #
 # Returns a value containing information on all Nixpkgs attributes which is decoded on the Rust
 # side. See ./eval.rs for the meaning of the returned values.
-{ attrsPath, nixpkgsPath }:
+{
+  attrsPath,
+  packageSetsPath,
+  referencesPath,
+  nixpkgsPath,
+  byNameOnly ? false,
+  system ? "x86_64-linux",
+  shard ? 0,
+  shardCount ? 1,
+}:
 let
   attrs = builtins.fromJSON (builtins.readFile attrsPath);
+  packageSets = builtins.fromJSON (builtins.readFile packageSetsPath);
+  references = builtins.fromJSON (builtins.readFile referencesPath);
 
   # We need to check whether attributes are defined manually e.g. in `all-packages.nix`,
   # automatically by the `pkgs/by-name` overlay, or neither. The only way to do so is to override
@@ -46,16 +64,17 @@ let
     # Don't let the user's home directory influence this result.
     config = { };
     overlays = [ overlay ];
-    # We check evaluation and `callPackage` only for x86_64-linux.  Not ideal, but hard to fix.
-    system = "x86_64-linux";
+    # Evaluating for other systems is done by separate evaluations with another `system`
+    inherit system;
   };
 
   # See AttributeInfo in ./eval.rs for the meaning of this.
-  attrInfo = name: value: {
-    location = builtins.unsafeGetAttrPos name pkgs;
+  attrInfo = attrInfoIn pkgs;
+  attrInfoIn = set: name: value: {
+    location = builtins.unsafeGetAttrPos name set;
     attribute_variant =
       if !builtins.isAttrs value then
-        { NonAttributeSet = null; }
+        { NonAttributeSet.value_type = builtins.typeOf value; }
       else
         {
           AttributeSet = {
@@ -69,6 +88,35 @@ let
         };
   };
 
+  # The `pname` of a derivation, if it has one. This and `has_update_script` are only needed for
+  # `pkgs/by-name`, where evaluation failures aren't allowed anyways.
+  pnameOf =
+    value:
+    if builtins.isAttrs value && value ? pname && builtins.isString value.pname then
+      value.pname
+    else
+      null;
+
+  # The `name`, `version` or `type` of a value, if they're strings. Unlike `pname`, they're only
+  # used to check the derivation name and to tell what non-derivations are, so failures to
+  # evaluate them are ignored here.
+  stringAttrOf =
+    attr: value:
+    let
+      result = builtins.tryEval (
+        if builtins.isAttrs value && value ? ${attr} && builtins.isString value.${attr} then
+          value.${attr}
+        else
+          null
+      );
+    in
+    if result.success then result.value else null;
+
+  # Whether a derivation has a `passthru.updateScript`, which `mkDerivation` also makes available
+  # as `updateScript`.
+  hasUpdateScript =
+    value: builtins.isAttrs value && (value ? updateScript || value ? passthru.updateScript);
+
   # Information on all attributes that are in `pkgs/by-name`.
   byNameAttrs = builtins.listToAttrs (
     map (name: {
@@ -78,30 +126,90 @@ let
           { Missing = null; }
         else
           # Evaluation failures are not allowed, so don't try to catch them.
-          { Existing = attrInfo name pkgs.${name}; };
+          {
+            Existing = attrInfo name pkgs.${name} // {
+              pname = pnameOf pkgs.${name};
+              name = stringAttrOf "name" pkgs.${name};
+              version = stringAttrOf "version" pkgs.${name};
+              type = stringAttrOf "type" pkgs.${name};
+              has_update_script = hasUpdateScript pkgs.${name};
+            };
+          };
     }) attrs
   );
 
   # Information on all attributes that exist but are not in `pkgs/by-name`.
   # We need this to enforce `pkgs/by-name` for new packages.
-  nonByNameAttrs = builtins.mapAttrs (
-    name: value:
-    let
-      # Packages outside `pkgs/by-name` often fail evaluation, so we need to handle that.
-      output = attrInfo name value;
-      result = builtins.tryEval (builtins.deepSeq output null);
-    in
-    {
-      NonByName = if result.success then { EvalSuccess = output; } else { EvalFailure = null; };
-    }
-  ) (builtins.removeAttrs pkgs attrs);
+  # With `byNameOnly`, these are the attributes of the Nixpkgs the repository is applied on instead.
+  nonByNameAttrs =
+    if byNameOnly then
+      { }
+    else
+      builtins.mapAttrs (
+        name: value:
+        let
+          # Packages outside `pkgs/by-name` often fail evaluation, so we need to handle that.
+          output = attrInfo name value;
+          result = builtins.tryEval (builtins.deepSeq output null);
+        in
+        {
+          NonByName = if result.success then { EvalSuccess = output; } else { EvalFailure = null; };
+        }
+      ) (builtins.removeAttrs pkgs attrs);
+
+  # Information on the attributes of the configured package sets, named by their attribute path
+  # like `vimPlugins.foo`, which can't clash with the names of other attributes.
+  packageSetAttrs = builtins.listToAttrs (
+    builtins.concatMap (
+      packageSet:
+      let
+        set = pkgs.lib.attrByPath packageSet.attribute null pkgs;
+      in
+      map (name: {
+        name = builtins.concatStringsSep "." (packageSet.attribute ++ [ name ]);
+        value.PackageSet =
+          if !builtins.isAttrs set || !set ? ${name} then
+            { Missing = null; }
+          else
+            # Like for `pkgs/by-name`, evaluation failures are not allowed.
+            {
+              Existing = attrInfoIn set name set.${name} // {
+                pname = pnameOf set.${name};
+                type = stringAttrOf "type" set.${name};
+              };
+            };
+      }) packageSet.names
+    ) packageSets
+  );
 
   # All attributes
-  attributes = byNameAttrs // nonByNameAttrs;
+  attributes = byNameAttrs // nonByNameAttrs // packageSetAttrs;
+
+  # The names of the attributes in this shard
+  names =
+    let
+      allNames = builtins.attrNames attributes;
+      count = builtins.div (builtins.length allNames - shard + shardCount - 1) shardCount;
+    in
+    builtins.genList (i: builtins.elemAt allNames (shard + i * shardCount)) count;
+  # Whether each referenced attribute path exists. If that can't be determined because evaluation
+  # fails, it's assumed to exist, since finding such failures isn't the job of this check.
+  referencesExist = map (
+    path:
+    let
+      result = builtins.tryEval (pkgs.lib.hasAttrByPath path pkgs);
+    in
+    !result.success || result.value
+  ) references;
 in
-# We output them in the form [ [ <name> <value> ] ]` such that the Rust side doesn't need to sort
-# them again to get deterministic behavior. This is good for testing.
-map (name: [
-  name
-  attributes.${name}
-]) (builtins.attrNames attributes)
+{
+  # We output them in the form [ [ <name> <value> ] ]` such that the Rust side only needs to merge
+  # the sorted shards to get deterministic behavior. This is good for testing.
+  attributes = map (name: [
+    name
+    attributes.${name}
+  ]) names;
+
+  # These are only needed once
+  references_exist = if shard == 0 then referencesExist else [ ];
+}
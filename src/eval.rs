@@ -1,24 +1,62 @@
 use std::collections::BTreeMap;
+use std::os::unix::process::ExitStatusExt as _;
 use std::path::{Path, PathBuf};
-use std::{env, fs, process};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use std::{env, fs, panic, process, thread};
 
 use anyhow::Context;
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use sha2::{Digest as _, Sha256};
 
 use crate::NixFileStore;
+use crate::config::{Check, Config, PackageSet, Profile};
 use crate::nix_file::CallPackageArgumentInfo;
+use crate::problem::npv_101::ValueShape;
+use crate::problem::npv_120::EvalFailureKind;
 use crate::problem::{
-    npv_100, npv_101, npv_102, npv_103, npv_104, npv_105, npv_106, npv_107, npv_108, npv_120,
+    npv_100, npv_101, npv_102, npv_103, npv_104, npv_105, npv_106, npv_107, npv_108, npv_114,
+    npv_115, npv_117, npv_120, npv_152, npv_157,
 };
-use crate::ratchet::RatchetState::{Loose, Tight};
+use crate::progress::Progress;
+use crate::ratchet::RatchetState::{Loose, NonApplicable, Tight};
+use crate::scope::Scope;
+use crate::status::Fatal;
 use crate::structure::{self, BASE_SUBPATH};
 use crate::validation::ResultIteratorExt as _;
-use crate::validation::{self, Validation::Success};
-use crate::{location, ratchet};
+use crate::validation::{
+    self, Validation::Failure, Validation::Success, Validation::SuccessWithWarnings,
+};
+use crate::{checkpoint, delta, docs, location, ratchet};
+use crate::{interrupt, jobs, timeout};
 
 const EVAL_NIX: &[u8] = include_bytes!("eval.nix");
 
+/// The oldest version of Nix that can evaluate Nixpkgs, see `lib/minver.nix` of Nixpkgs.
+pub const MIN_NIX_VERSION: &str = "2.3.17";
+
+/// The ratchet values found by evaluating Nixpkgs.
+#[derive(Default)]
+pub struct Evaluation {
+    /// The ratchet values of the packages by their name.
+    pub packages: BTreeMap<String, ratchet::Package>,
+    /// The ratchet values of the warnings emitted by the evaluation.
+    pub warnings: BTreeMap<String, ratchet::EvalWarning>,
+}
+
+/// The structure returned by `./eval.nix` for each shard.
+#[derive(Deserialize)]
+struct EvalResult {
+    /// The attributes of the shard, sorted by name.
+    attributes: Vec<(String, Attribute)>,
+    /// Whether each attribute path referenced by `passthru.tests` or the manual exists, in the
+    /// same order as they're passed, but only for the first shard.
+    references_exist: Vec<bool>,
+}
+
 /// Attribute set of this structure is returned by `./eval.nix`
 #[derive(Deserialize)]
 enum Attribute {
@@ -26,6 +64,9 @@ enum Attribute {
     ByName(ByNameAttribute),
     /// An attribute not defined via `pkgs/by-name`.
     NonByName(NonByNameAttribute),
+    /// An attribute of a package set configured with `package-sets`, named by its attribute path
+    /// like `vimPlugins.foo`.
+    PackageSet(ByNameAttribute),
 }
 
 #[derive(Deserialize)]
@@ -47,6 +88,23 @@ struct AttributeInfo {
     /// The location of the attribute as returned by `builtins.unsafeGetAttrPos`.
     location: Option<Location>,
     attribute_variant: AttributeVariant,
+    /// The `pname` of the attribute, only determined for attributes in `pkgs/by-name`.
+    #[serde(default)]
+    pname: Option<String>,
+    /// The `name` of the derivation, only determined for attributes in `pkgs/by-name`.
+    #[serde(default)]
+    name: Option<String>,
+    /// The `version` of the derivation, only determined for attributes in `pkgs/by-name`.
+    #[serde(default)]
+    version: Option<String>,
+    /// Whether the attribute has an `updateScript`, only determined for attributes in
+    /// `pkgs/by-name`.
+    #[serde(default)]
+    has_update_script: bool,
+    /// The `type` attribute of the value, which tells what non-derivations are, only determined
+    /// for attributes in `pkgs/by-name` and package sets.
+    #[serde(default, rename = "type")]
+    type_attribute: Option<String>,
 }
 
 /// The structure returned by a successful `builtins.unsafeGetAttrPos`.
@@ -77,7 +135,10 @@ impl Location {
 pub enum AttributeVariant {
     /// The attribute is not an attribute set, so we're limited in the amount of information we can
     /// get from it. Since all derivations are attribute sets, it's obviously not a derivation.
-    NonAttributeSet,
+    NonAttributeSet {
+        /// The type of the value as returned by `builtins.typeOf`.
+        value_type: String,
+    },
     AttributeSet {
         /// Whether the attribute is a derivation (`lib.isDerivation`)
         is_derivation: bool,
@@ -99,6 +160,341 @@ pub enum DefinitionVariant {
     },
 }
 
+/// Parts of the error messages of Nix that are likely to go away when trying again, like when
+/// another process holds a lock on the Nix store.
+const TRANSIENT_ERRORS: &[&str] = &[
+    "error: opening lock file",
+    "database is locked",
+    "Resource temporarily unavailable",
+];
+
+/// How many Nix evaluations failed in a transient way and were retried, see `retried_count`.
+static RETRIED: AtomicUsize = AtomicUsize::new(0);
+
+/// How many Nix evaluations had to be retried so far, since they failed in a way that looked
+/// transient.
+pub fn retried_count() -> usize {
+    RETRIED.load(Ordering::Relaxed)
+}
+
+/// Runs a Nix command as a job, see `--jobs`. If it fails in a way that looks transient, like
+/// being killed by the out-of-memory killer or contention on the Nix store, it's retried up to
+/// `retries` times, waiting twice as long before each retry, starting with a second.
+pub fn run_nix(command: &mut process::Command, retries: usize) -> anyhow::Result<process::Output> {
+    let mut attempt = 0;
+    loop {
+        interrupt::check()?;
+        let output = timeout::excluding(|| {
+            let _slot = jobs::Slot::acquire();
+            interrupt::check()?;
+            tracing::debug!(?command, "Running Nix");
+            interrupt::output(command).with_context(|| {
+                Fatal::Misconfiguration(format!("Failed to run command {command:?}"))
+            })
+        })?;
+        // The process was probably killed because of the interruption
+        interrupt::check()?;
+        if output.status.success() || attempt >= retries || !is_transient_failure(&output) {
+            return Ok(output);
+        }
+        attempt += 1;
+        RETRIED.fetch_add(1, Ordering::Relaxed);
+        let backoff = Duration::from_secs(1 << (attempt - 1).min(6));
+        tracing::warn!(
+            "Nix evaluation failed in a way that looks transient, retrying in {}s ({attempt}/{retries})",
+            backoff.as_secs()
+        );
+        timeout::excluding(|| thread::sleep(backoff));
+    }
+}
+
+/// Whether a failed Nix command is likely to succeed when run again.
+fn is_transient_failure(output: &process::Output) -> bool {
+    // Most likely the out-of-memory killer, which may not strike again with fewer other jobs
+    output.status.signal() == Some(9)
+        || TRANSIENT_ERRORS
+            .iter()
+            .any(|error| String::from_utf8_lossy(&output.stderr).contains(error))
+}
+
+/// How many frames of a Nix trace are kept at its end, which are the closest to the error, see
+/// `trim_trace`.
+const TRACE_FRAMES: usize = 10;
+
+/// The problem of a failed Nix evaluation, with what caused it, the package it's about and a
+/// trimmed trace.
+pub fn eval_error(stderr: &[u8]) -> npv_120::NixEvalError {
+    let stderr = String::from_utf8_lossy(stderr);
+    npv_120::NixEvalError::new(
+        classify(&stderr),
+        failed_package(&stderr),
+        trim_trace(&stderr),
+    )
+}
+
+/// The package in `pkgs/by-name` whose file is the last one in the trace of the error output,
+/// which is the closest one to the error.
+fn failed_package(stderr: &str) -> Option<String> {
+    stderr.lines().rev().find_map(|line| {
+        let start = line.find(&format!("{BASE_SUBPATH}/"))?;
+        // Like `at /nix/store/...-source/pkgs/by-name/fo/foo/package.nix:3:5:`
+        let path = line[start..].split(':').next()?;
+        structure::package_for_path(RelativePath::new(path))
+    })
+}
+
+/// The warnings in the error output of a Nix evaluation, like the ones of `lib.warn`, which newer
+/// versions of Nix emit as `evaluation warning: ...` and older ones as `trace: warning: ...`.
+fn eval_warnings(stderr: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("evaluation warning: ")
+                .or_else(|| line.strip_prefix("trace: warning: "))
+        })
+        .map(|warning| warning.trim().to_owned())
+        .collect()
+}
+
+/// Tells why a Nix evaluation failed from its error output.
+fn classify(stderr: &str) -> EvalFailureKind {
+    // The checks of `meta` use `throw`, so they're recognized first
+    if [
+        "is marked as broken",
+        "has an unfree license",
+        "is marked as insecure",
+        "is not available on the requested hostPlatform",
+    ]
+    .iter()
+    .any(|message| stderr.contains(message))
+    {
+        EvalFailureKind::UnfreeOrBroken
+    } else if stderr.contains("called without required argument") {
+        EvalFailureKind::MissingArgument
+    } else if stderr.contains("was not found in the Nix search path") {
+        EvalFailureKind::SearchPathLookup
+    } else if stderr.contains("infinite recursion encountered") {
+        EvalFailureKind::InfiniteRecursion
+    } else if stderr.contains("assertion") && stderr.contains("failed") {
+        EvalFailureKind::Assertion
+    } else if stderr.contains("while calling the 'throw' builtin") {
+        EvalFailureKind::Throw
+    } else {
+        EvalFailureKind::Other
+    }
+}
+
+/// Leaves out all but the last `TRACE_FRAMES` frames of the trace in the error output of Nix,
+/// which start with `…` and are followed by their location and code.
+fn trim_trace(stderr: &str) -> String {
+    let is_frame = |line: &str| line.trim_start().starts_with('…');
+    let frames = stderr.lines().filter(|line| is_frame(line)).count();
+    let Some(omitted) = frames
+        .checked_sub(TRACE_FRAMES)
+        .filter(|omitted| *omitted > 0)
+    else {
+        return stderr.to_owned();
+    };
+    let mut trimmed = String::new();
+    let mut frame = 0;
+    for line in stderr.split_inclusive('\n') {
+        if is_frame(line) {
+            frame += 1;
+            if frame == 1 {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                trimmed.push_str(&format!("{indent}… ({omitted} more frames)\n"));
+            }
+        } else if frame > 0 && line.trim_start().starts_with("error:") {
+            // The final error message after the frames
+            frame = frames + 1;
+        }
+        if frame == 0 || frame > omitted {
+            trimmed.push_str(line);
+        }
+    }
+    trimmed
+}
+
+/// Checks that the Nix given by `NIXPKGS_VET_NIX_PACKAGE` can be run and isn't too old, such that
+/// a missing or outdated Nix gives a clear error up front instead of failing the evaluation.
+pub fn check_nix_version() -> anyhow::Result<()> {
+    let problem = match nix_version() {
+        Ok(version) if version_at_least(&version, MIN_NIX_VERSION) => return Ok(()),
+        Ok(version) => format!("the Nix given by NIXPKGS_VET_NIX_PACKAGE has version {version}"),
+        Err(error) => format!("{error:#}"),
+    };
+    Err(anyhow::anyhow!(Fatal::Misconfiguration(format!(
+        "The evaluation checks need Nix {MIN_NIX_VERSION} or newer, but {problem}. \
+        Please set NIXPKGS_VET_NIX_PACKAGE to the store path of a newer Nix, \
+        or skip the evaluation checks with `--no-eval`."
+    ))))
+}
+
+/// The version of the Nix given by `NIXPKGS_VET_NIX_PACKAGE`, like `2.24.10`.
+fn nix_version() -> anyhow::Result<String> {
+    let nix_package = env::var("NIXPKGS_VET_NIX_PACKAGE")
+        .context("the environment variable NIXPKGS_VET_NIX_PACKAGE isn't set")?;
+    let nix_instantiate = format!("{nix_package}/bin/nix-instantiate");
+    let output = process::Command::new(&nix_instantiate)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("{nix_instantiate} could not be run"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Like `nix-instantiate (Nix) 2.24.10` or `nix-instantiate (Lix, like Nix) 2.91.1`
+    match stdout.split_whitespace().last() {
+        Some(version) if output.status.success() => Ok(version.to_owned()),
+        _ => anyhow::bail!("`{nix_instantiate} --version` failed: {stdout}"),
+    }
+}
+
+/// Whether the version is at least the minimum version, comparing the numeric components, such
+/// that `2.25.0pre20241010_5f841fe` counts as `2.25.0`.
+fn version_at_least(version: &str, minimum: &str) -> bool {
+    let components = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map_while(|component| {
+                let digits: String = component.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().ok()
+            })
+            .collect()
+    };
+    components(version) >= components(minimum)
+}
+
+/// Returns a `nix-instantiate --eval --json --strict` command in restrict-eval mode, which can
+/// access the work directory and the Nixpkgs, passed to the Nix file as `nixpkgsPath`. The
+/// caller still needs to add the other arguments and the Nix file to evaluate.
+pub fn nix_instantiate(
+    work_dir_path: &Path,
+    nixpkgs_path: &Path,
+) -> anyhow::Result<process::Command> {
+    // Pinning Nix in this way makes the tool more reproducible
+    let nix_package = env::var("NIXPKGS_VET_NIX_PACKAGE").with_context(|| {
+        Fatal::Misconfiguration(
+            "Could not get environment variable NIXPKGS_VET_NIX_PACKAGE".to_owned(),
+        )
+    })?;
+
+    // With restrict-eval, only paths in NIX_PATH can be accessed. We explicitly specify
+    // them here.
+    let mut command = process::Command::new(format!("{nix_package}/bin/nix-instantiate"));
+    command
+        // Capture stderr so that it can be printed later in case of failure
+        .stderr(process::Stdio::piped())
+        // Clear environment so that nothing from the outside influences this
+        // `nix-instantiate`.
+        .env_clear()
+        .args([
+            "--eval",
+            "--json",
+            "--strict",
+            "--readonly-mode",
+            "--restrict-eval",
+        ])
+        // Don't let the channels of the environment be found by lookups like `<nixpkgs>`, which
+        // would make the result depend on them.
+        .args(["--option", "nix-path", ""])
+        // Add the work directory to the NIX_PATH so that it can be accessed in
+        // restrict-eval mode.
+        .arg("-I")
+        .arg(work_dir_path)
+        // Same for the nixpkgs to test, adding it to the NIX_PATH so it can be accessed
+        // in restrict-eval mode.
+        .args(["--arg", "nixpkgsPath"])
+        .arg(nixpkgs_path)
+        .arg("-I")
+        .arg(nixpkgs_path);
+
+    pass_through_environment_variables_for_nix_eval_in_nix_build(&mut command);
+    mutate_nix_instatiate_arguments_based_on_cfg(work_dir_path, &mut command)?;
+    Ok(command)
+}
+
+/// Evaluates the Nix file of a check that evaluates packages apart from `check_values`, like
+/// `./cross.nix` for `name` being `cross`. Each input is written to a JSON file in the work
+/// directory, whose path is passed to the Nix file by the name of the input, like `attrsPath`, and
+/// the system is passed as `system` if given.
+///
+/// These checks only evaluate the packages in scope, and are skipped if everything is in scope,
+/// since evaluating all packages again would take much longer than `check_values` already does.
+///
+/// Returns the deserialised result, or the error output if the evaluation failed in a way that
+/// the Nix file can't catch.
+pub fn evaluate_json<T: DeserializeOwned>(
+    name: &str,
+    nix_file: &[u8],
+    nixpkgs_path: &Path,
+    inputs: &[(&str, serde_json::Value)],
+    system: Option<&str>,
+    config: &Config,
+) -> anyhow::Result<Result<T, Vec<u8>>> {
+    let work_dir = tempfile::Builder::new()
+        .prefix(&format!("nixpkgs-vet-{name}"))
+        .tempdir()
+        .context("Failed to create a working directory")?;
+    let work_dir_path = work_dir.path().canonicalize()?;
+    let nix_file_path = work_dir_path.join(format!("{name}.nix"));
+    fs::write(&nix_file_path, nix_file)?;
+
+    let mut command = nix_instantiate(&work_dir_path, nixpkgs_path)?;
+    for (argument, input) in inputs {
+        let input_path = work_dir_path.join(format!("{argument}.json"));
+        fs::write(&input_path, serde_json::to_vec(input)?).with_context(|| {
+            format!(
+                "Failed to write {argument} to the work dir {}",
+                work_dir_path.display()
+            )
+        })?;
+        command.args(["--arg", argument]).arg(&input_path);
+    }
+    if let Some(system) = system {
+        command.args(["--argstr", "system", system]);
+    }
+    command.arg(&nix_file_path);
+    let output = run_nix(&mut command, config.eval_retries)?;
+    if !output.status.success() {
+        return Ok(Err(output.stderr));
+    }
+    let result = serde_json::from_slice(&output.stdout).with_context(|| {
+        format!(
+            "Failed to deserialise {}",
+            String::from_utf8_lossy(&output.stdout)
+        )
+    })?;
+    Ok(Ok(result))
+}
+
+/// Runs a check that evaluates Nixpkgs for each of the configured systems. The result is the one
+/// for the first system, and the problems that only occur on the others are added to it, with the
+/// system they occur on.
+pub fn for_each_system<A>(
+    config: &Config,
+    mut check: impl FnMut(&str) -> validation::Result<A>,
+) -> validation::Result<A> {
+    let (system, other_systems) = config
+        .systems
+        .split_first()
+        .context("No system to evaluate Nixpkgs for")?;
+    let mut result = check(system)?;
+    let problems = match &result {
+        Failure(problems) | SuccessWithWarnings(_, problems) => problems.clone(),
+        Success(_) => vec![],
+    };
+    for other_system in other_systems {
+        // Warnings are about the ratchets, which only use the first system
+        if let Failure(other_problems) = check(other_system)? {
+            let (only_on_other_system, _) = delta::compare(problems.clone(), other_problems);
+            result.extend(
+                only_on_other_system
+                    .into_iter()
+                    .map(|problem| problem.with_context(&format!("On {other_system}"))),
+            );
+        }
+    }
+    Ok(result)
+}
+
 /// Pass through variables needed to make Nix evaluation work inside Nix build. See `initNix`.
 /// If these variables don't exist, assume we're not in a Nix sandbox.
 fn pass_through_environment_variables_for_nix_eval_in_nix_build(command: &mut process::Command) {
@@ -141,8 +537,11 @@ fn mutate_nix_instatiate_arguments_based_on_cfg(
     command.arg(format!("test-nixpkgs={}", mock_nixpkgs_path.display()));
 
     // Retrieve the path to the real nixpkgs lib, then wire it up to `import <test-nixpkgs/lib>`.
-    let nixpkgs_lib = env::var("NIXPKGS_VET_NIXPKGS_LIB")
-        .with_context(|| "Could not get environment variable NIXPKGS_VET_NIXPKGS_LIB")?;
+    let nixpkgs_lib = env::var("NIXPKGS_VET_NIXPKGS_LIB").with_context(|| {
+        Fatal::Misconfiguration(
+            "Could not get environment variable NIXPKGS_VET_NIXPKGS_LIB".to_owned(),
+        )
+    })?;
 
     command.arg("-I");
     command.arg(format!("test-nixpkgs/lib={nixpkgs_lib}"));
@@ -155,11 +554,51 @@ fn mutate_nix_instatiate_arguments_based_on_cfg(
 /// achieved on the Nix side.
 ///
 /// The validation result is a map from package names to a package ratchet state.
+///
+/// The attributes of the given package sets and the names of their packages are only checked to
+/// exist and be derivations, which is all that's known about how package sets are defined.
+///
+/// Attributes outside of the given scope are still evaluated, but not checked.
+///
+/// Nixpkgs is evaluated for each of the configured systems, see `for_each_system`. The ratchets
+/// only use the evaluation for the first one.
 pub fn check_values(
     nixpkgs_path: &Path,
     nix_file_store: &mut NixFileStore,
     package_names: &[String],
-) -> validation::Result<BTreeMap<String, ratchet::Package>> {
+    package_sets: &[(&PackageSet, Vec<String>)],
+    scope: &Scope,
+    config: &Config,
+    progress: &Progress,
+) -> validation::Result<Evaluation> {
+    for_each_system(config, |system| {
+        check_values_for_system(
+            nixpkgs_path,
+            nix_file_store,
+            package_names,
+            package_sets,
+            scope,
+            config,
+            progress,
+            system,
+        )
+    })
+}
+
+/// Like `check_values`, but for a single system.
+#[allow(clippy::too_many_arguments)]
+fn check_values_for_system(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    package_names: &[String],
+    package_sets: &[(&PackageSet, Vec<String>)],
+    scope: &Scope,
+    config: &Config,
+    progress: &Progress,
+    system: &str,
+) -> validation::Result<Evaluation> {
+    let _span = tracing::info_span!("evaluation", system).entered();
+
     let work_dir = tempfile::Builder::new()
         .prefix("nixpkgs-vet")
         .tempdir()
@@ -170,104 +609,381 @@ pub fn check_values(
 
     // Write the list of packages we need to check into a temporary JSON file.
     let package_names_path = work_dir_path.join("package-names.json");
-    let package_names_file = fs::File::create(&package_names_path)?;
-    serde_json::to_writer(&package_names_file, &package_names).with_context(|| {
+    let package_names_json = serde_json::to_vec(&package_names)?;
+    fs::write(&package_names_path, &package_names_json).with_context(|| {
         format!(
             "Failed to serialise the package names to the work dir {}",
             work_dir_path.display()
         )
     })?;
 
+    // Same for the package sets, with the names of their packages.
+    let package_sets_path = work_dir_path.join("package-sets.json");
+    let package_sets_json = package_sets
+        .iter()
+        .map(|(package_set, names)| {
+            json!({
+                "attribute": package_set.attribute.split('.').collect::<Vec<_>>(),
+                "names": names,
+            })
+        })
+        .collect::<Vec<_>>();
+    let package_sets_json = serde_json::to_vec(&package_sets_json)?;
+    fs::write(&package_sets_path, &package_sets_json).with_context(|| {
+        format!(
+            "Failed to write the package sets to the work dir {}",
+            work_dir_path.display()
+        )
+    })?;
+    // The package file of each package set attribute, by the name it gets in the evaluation result
+    let package_set_files: BTreeMap<String, RelativePathBuf> = package_sets
+        .iter()
+        .flat_map(|(package_set, names)| {
+            names.iter().map(|name| {
+                (
+                    format!("{}.{name}", package_set.attribute),
+                    package_set
+                        .path
+                        .join(structure::shard_for_package(name))
+                        .join(name)
+                        .join(structure::PACKAGE_NIX_FILENAME),
+                )
+            })
+        })
+        .collect();
+
+    // Same for the attribute paths referenced by `passthru.tests` of the packages in scope.
+    let mut test_references = vec![];
+    for package_name in package_names {
+        if !scope.contains_package(package_name) {
+            continue;
+        }
+        let path = structure::relative_file_for_package(package_name).to_path(nixpkgs_path);
+        let nix_file = nix_file_store.get(&path)?;
+        test_references.extend(
+            nix_file
+                .passthru_test_references()
+                .into_iter()
+                .map(|reference| (package_name.as_str(), reference)),
+        );
+    }
+    // And for the attribute paths referenced by the manual, see `--check doc-references`. These
+    // aren't restricted to the scope, since references usually become stale by changes elsewhere.
+    let doc_references = if config.checks.contains(&Check::DocReferences) {
+        docs::doc_references(nixpkgs_path, &config.doc_reference_pattern)?
+    } else {
+        vec![]
+    };
+    let references_path = work_dir_path.join("references.json");
+    let reference_paths = test_references
+        .iter()
+        .map(|(_, reference)| &reference.attribute_path)
+        .chain(
+            doc_references
+                .iter()
+                .map(|reference| &reference.attribute_path),
+        )
+        .collect::<Vec<_>>();
+    let references_json = serde_json::to_vec(&reference_paths)?;
+    fs::write(&references_path, &references_json).with_context(|| {
+        format!(
+            "Failed to write the references to the work dir {}",
+            work_dir_path.display()
+        )
+    })?;
+
     // Write the Nix file into the work directory.
     let eval_nix_path = work_dir_path.join("eval.nix");
     fs::write(&eval_nix_path, EVAL_NIX)?;
 
-    // Pinning Nix in this way makes the tool more reproducible
-    let nix_package = env::var("NIXPKGS_VET_NIX_PACKAGE")
-        .with_context(|| "Could not get environment variable NIXPKGS_VET_NIX_PACKAGE")?;
+    // Evaluating all attributes is the slowest part, so it's split into shards evaluated by
+    // separate processes, see `--jobs`.
+    let shard_count = jobs::count().get();
+    let by_name_only = config.profile == Profile::Overlay;
+    // Everything the evaluation depends on in addition to the Nixpkgs, see `--resume`
+    let checkpoint_key = checkpoint::key(
+        nixpkgs_path,
+        &[
+            EVAL_NIX,
+            &package_names_json,
+            &package_sets_json,
+            &references_json,
+            &[u8::from(by_name_only)],
+            system.as_bytes(),
+        ],
+    );
+    let commands = (0..shard_count)
+        .map(|shard| {
+            let mut command = nix_instantiate(&work_dir_path, nixpkgs_path)?;
+            command
+                .args(["--arg", "attrsPath"])
+                .arg(&package_names_path)
+                .args(["--arg", "packageSetsPath"])
+                .arg(&package_sets_path)
+                .args(["--arg", "referencesPath"])
+                .arg(&references_path)
+                .args(["--arg", "byNameOnly"])
+                .arg(by_name_only.to_string())
+                .args(["--argstr", "system", system])
+                .args(["--arg", "shard", &shard.to_string()])
+                .args(["--arg", "shardCount", &shard_count.to_string()])
+                .arg(&eval_nix_path);
+            let checkpoint_key = checkpoint_key
+                .as_ref()
+                .map(|key| format!("{key}-{shard}of{shard_count}"));
+            Ok((command, checkpoint_key))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-    // With restrict-eval, only paths in NIX_PATH can be accessed. We explicitly specify them here.
-    let mut command = process::Command::new(format!("{nix_package}/bin/nix-instantiate"));
-    command
-        // Capture stderr so that it can be printed later in case of failure
-        .stderr(process::Stdio::piped())
-        // Clear environment so that nothing from the outside influences this `nix-instantiate`.
-        .env_clear()
-        .args([
-            "--eval",
-            "--json",
-            "--strict",
-            "--readonly-mode",
-            "--restrict-eval",
-        ])
-        // Add the work directory to the NIX_PATH so that it can be accessed in restrict-eval mode.
-        .arg("-I")
-        .arg(&work_dir_path)
-        .args(["--arg", "attrsPath"])
-        .arg(&package_names_path)
-        // Same for the nixpkgs to test, adding it to the NIX_PATH so it can be accessed in
-        // restrict-eval mode.
-        .args(["--arg", "nixpkgsPath"])
-        .arg(nixpkgs_path)
-        .arg("-I")
-        .arg(nixpkgs_path);
+    progress.phase("Evaluating Nixpkgs", None);
+    let span = tracing::Span::current();
+    // The shards are evaluated on other threads, without the timeout of this one
+    let results = timeout::excluding(|| {
+        thread::scope(|threads| {
+            let handles = commands
+                .into_iter()
+                .enumerate()
+                .map(|(shard, (mut command, checkpoint_key))| {
+                    let span = &span;
+                    threads.spawn(move || {
+                        let _span = tracing::info_span!(parent: span, "shard", shard).entered();
+                        let Some(key) = checkpoint_key else {
+                            return run_nix(&mut command, config.eval_retries);
+                        };
+                        if let Some(output) = checkpoint::get(&key, nixpkgs_path) {
+                            return Ok(output);
+                        }
+                        let output = run_nix(&mut command, config.eval_retries)?;
+                        if output.status.success() {
+                            checkpoint::save(&key, nixpkgs_path, &output)?;
+                        }
+                        Ok(output)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+    })?;
 
-    pass_through_environment_variables_for_nix_eval_in_nix_build(&mut command);
-    mutate_nix_instatiate_arguments_based_on_cfg(&work_dir_path, &mut command)?;
+    let mut attributes: Vec<(String, Attribute)> = vec![];
+    let mut references_exist = vec![];
+    let mut warnings = BTreeMap::new();
+    for result in results {
+        if !result.status.success() {
+            // Early return in case evaluation fails
+            return Ok(eval_error(&result.stderr).into());
+        }
+        for warning in eval_warnings(&result.stderr) {
+            warnings.insert(warning, ratchet::EvalWarning { emitted: Loose(()) });
+        }
 
-    command.arg(eval_nix_path);
+        // Parse the resulting JSON value
+        let shard_result: EvalResult =
+            serde_json::from_slice(&result.stdout).with_context(|| {
+                format!(
+                    "Failed to deserialise {}",
+                    String::from_utf8_lossy(&result.stdout)
+                )
+            })?;
+        attributes.extend(shard_result.attributes);
+        references_exist.extend(shard_result.references_exist);
+    }
+    // Each shard is sorted, but the result should be as if there was only one.
+    attributes.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-    let result = command
-        .output()
-        .with_context(|| format!("Failed to run command {command:?}"))?;
+    let pname_result = check_pnames(&attributes, scope);
 
-    if !result.status.success() {
-        // Early return in case evaluation fails
-        return Ok(npv_120::NixEvalError::new(String::from_utf8_lossy(&result.stderr)).into());
-    }
+    let mut references_exist = references_exist.into_iter();
+    let test_reference_result = test_references
+        .into_iter()
+        .zip(references_exist.by_ref())
+        .filter(|(_, exists)| !exists)
+        .map(|((package_name, reference), _)| {
+            npv_115::ByNameTestReferenceMissing::new(
+                package_name,
+                reference.attribute_path.join("."),
+                reference.line,
+                reference.column,
+            )
+            .into()
+        })
+        .collect();
 
-    // Parse the resulting JSON value
-    let attributes: Vec<(String, Attribute)> = serde_json::from_slice(&result.stdout)
-        .with_context(|| {
-            format!(
-                "Failed to deserialise {}",
-                String::from_utf8_lossy(&result.stdout)
+    let doc_reference_result = doc_references
+        .into_iter()
+        .zip(references_exist)
+        .filter(|(_, exists)| !exists)
+        .map(|(reference, _)| {
+            npv_117::DocReferenceMissing::new(
+                reference.file,
+                reference.attribute_path.join("."),
+                reference.line,
+                reference.column,
             )
-        })?;
+            .into()
+        })
+        .collect();
 
-    let check_result = validation::sequence(
-        attributes
-            .into_iter()
-            .map(|(attribute_name, attribute_value)| {
-                let check_result = match attribute_value {
-                    Attribute::NonByName(non_by_name_attribute) => handle_non_by_name_attribute(
-                        nixpkgs_path,
-                        nix_file_store,
-                        &attribute_name,
-                        non_by_name_attribute,
-                    )?,
-                    Attribute::ByName(by_name_attribute) => by_name(
-                        nix_file_store,
-                        nixpkgs_path,
+    progress.phase("Checking the attributes", Some(attributes.len() as u64));
+    let check_result: validation::Validation<Vec<_>> = attributes
+        .into_iter()
+        .map(|(attribute_name, attribute_value)| {
+            progress.inc();
+            let check_result = match attribute_value {
+                Attribute::NonByName(non_by_name_attribute) => handle_non_by_name_attribute(
+                    nixpkgs_path,
+                    nix_file_store,
+                    scope,
+                    &attribute_name,
+                    non_by_name_attribute,
+                )?,
+                Attribute::ByName(by_name_attribute) => by_name(
+                    nix_file_store,
+                    nixpkgs_path,
+                    scope,
+                    config,
+                    &attribute_name,
+                    by_name_attribute,
+                )?,
+                Attribute::PackageSet(package_set_attribute) => {
+                    let relative_package_file =
+                        package_set_files.get(&attribute_name).with_context(|| {
+                            format!("Attribute {attribute_name} is not in any package set")
+                        })?;
+                    // Package sets aren't subject to the ratchets
+                    return Ok(package_set_member(
+                        config,
+                        scope,
                         &attribute_name,
-                        by_name_attribute,
-                    )?,
-                };
-                Ok::<_, anyhow::Error>(check_result.map(|value| (attribute_name.clone(), value)))
-            })
-            .collect_vec()?,
-    );
+                        relative_package_file,
+                        package_set_attribute,
+                    )
+                    .map(|()| None));
+                }
+            };
+            Ok::<_, anyhow::Error>(check_result.map(|value| Some((attribute_name.clone(), value))))
+        })
+        .collect_vec()?
+        .into_iter()
+        .collect();
 
-    Ok(check_result.map(|elems| elems.into_iter().collect()))
+    Ok(pname_result
+        .and_(test_reference_result)
+        .and_(doc_reference_result)
+        .and_(check_result.map(|elems| Evaluation {
+            packages: elems.into_iter().flatten().collect(),
+            warnings,
+        })))
+}
+
+/// Checks that no two attributes in `pkgs/by-name` have the same `pname`, as long as at least one
+/// of them is in scope.
+fn check_pnames(attributes: &[(String, Attribute)], scope: &Scope) -> validation::Validation<()> {
+    let mut packages_by_pname: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (attribute_name, attribute) in attributes {
+        if let Attribute::ByName(ByNameAttribute::Existing(AttributeInfo {
+            pname: Some(pname),
+            ..
+        })) = attribute
+        {
+            packages_by_pname
+                .entry(pname)
+                .or_default()
+                .push(attribute_name.clone());
+        }
+    }
+
+    packages_by_pname
+        .into_iter()
+        .filter(|(_, package_names)| {
+            package_names.len() > 1
+                && package_names
+                    .iter()
+                    .any(|package_name| scope.contains_package(package_name))
+        })
+        .map(|(pname, package_names)| {
+            npv_114::ByNameDuplicatePname::new(pname, package_names).into()
+        })
+        .collect()
+}
+
+/// Checks that the `name` of a derivation is `${pname}-${version}`, or just the `pname` if the
+/// `version` is empty, which is what `mkDerivation` makes it unless it's overridden.
+fn check_derivation_name(
+    attribute_name: &str,
+    pname: &str,
+    name: &str,
+    version: &str,
+) -> validation::Validation<()> {
+    let expected_name = if version.is_empty() {
+        pname.to_owned()
+    } else {
+        format!("{pname}-{version}")
+    };
+    if name == expected_name {
+        Success(())
+    } else {
+        npv_152::ByNameDerivationNameMismatch::new(attribute_name, name, expected_name).into()
+    }
 }
 
 /// Handle the evaluation result for an attribute in `pkgs/by-name`, making it a validation result.
 fn by_name(
     nix_file_store: &mut NixFileStore,
     nixpkgs_path: &Path,
+    scope: &Scope,
+    config: &Config,
     attribute_name: &str,
     by_name_attribute: ByNameAttribute,
 ) -> validation::Result<ratchet::Package> {
+    // A package is in scope if either its package directory or the line of its manual definition
+    // is.
+    let definition_in_scope = match &by_name_attribute {
+        ByNameAttribute::Existing(AttributeInfo {
+            location: Some(location),
+            ..
+        }) => location
+            .clone()
+            .relative(nixpkgs_path)
+            .is_ok_and(|location| scope.contains_line(&location.file, location.line)),
+        _ => false,
+    };
+    let package_nix_hash = package_nix_hash(nixpkgs_path, attribute_name);
+    let relative_package_file = structure::relative_file_for_package(attribute_name);
+    if !definition_in_scope && !scope.contains_package(attribute_name) {
+        return Ok(Success(ratchet::Package {
+            manual_definition: NonApplicable,
+            uses_by_name: Tight,
+            update_script: NonApplicable,
+            package_nix_hash,
+            variant_of: None,
+            definition: None,
+        }));
+    }
+
+    let update_script =
+        match &by_name_attribute {
+            ByNameAttribute::Existing(AttributeInfo {
+                has_update_script, ..
+            }) if config.require_update_script_for(&relative_package_file) => {
+                if *has_update_script { Tight } else { Loose(()) }
+            }
+            _ => NonApplicable,
+        };
+
+    let name_result = match &by_name_attribute {
+        ByNameAttribute::Existing(AttributeInfo {
+            pname: Some(pname),
+            name: Some(name),
+            version: Some(version),
+            ..
+        }) => check_derivation_name(attribute_name, pname, name, version),
+        _ => Success(()),
+    };
+
     // At this point we know that `pkgs/by-name/fo/foo/package.nix` has to exists.  This match
     // decides whether the attribute `foo` is defined accordingly and whether a legacy manual
     // definition could be removed.
@@ -276,21 +992,26 @@ fn by_name(
         ByNameAttribute::Missing => {
             // This indicates a bug in the `pkgs/by-name` overlay, because it's supposed to
             // automatically defined attributes in `pkgs/by-name`
-            npv_100::ByNameUndefinedAttribute::new(attribute_name).into()
+            npv_100::ByNameUndefinedAttribute::new(attribute_name, relative_package_file).into()
         }
         // The attribute exists
         ByNameAttribute::Existing(AttributeInfo {
             // But it's not an attribute set, which limits the amount of information we can get
             // about this attribute (see ./eval.nix)
-            attribute_variant: AttributeVariant::NonAttributeSet,
-            location: _location,
+            attribute_variant: AttributeVariant::NonAttributeSet { value_type },
+            ..
         }) => {
             // The only thing we know is that it's definitely not a derivation, since those are
             // always attribute sets.
             //
             // We can't know whether the attribute is automatically or manually defined for sure,
             // and while we could check the location, the error seems clear enough as is.
-            npv_101::ByNameNonDerivation::new(attribute_name).into()
+            npv_101::ByNameNonDerivation::new(
+                attribute_name,
+                relative_package_file,
+                ValueShape::non_attribute_set(value_type),
+            )
+            .into()
         }
         // The attribute exists
         ByNameAttribute::Existing(AttributeInfo {
@@ -301,13 +1022,22 @@ fn by_name(
                     definition_variant,
                 },
             location,
+            type_attribute,
+            ..
         }) => {
-            // Only derivations are allowed in `pkgs/by-name`.
-            let is_derivation_result = if is_derivation {
-                Success(())
-            } else {
-                npv_101::ByNameNonDerivation::new(attribute_name).into()
-            };
+            // Only derivations are allowed in `pkgs/by-name`, apart from the configured types of
+            // attribute sets.
+            let is_derivation_result =
+                if is_derivation || is_allowed_non_derivation(config, type_attribute.as_deref()) {
+                    Success(())
+                } else {
+                    npv_101::ByNameNonDerivation::new(
+                        attribute_name,
+                        relative_package_file.clone(),
+                        ValueShape::AttributeSet { type_attribute },
+                    )
+                    .into()
+                };
 
             // If the definition looks correct
             let variant_result = match definition_variant {
@@ -380,13 +1110,88 @@ fn by_name(
         // Packages being checked in this function are _always_ already defined in `pkgs/by-name`,
         // so instead of repeating ourselves all the time to define `uses_by_name`, just set it
         // once at the end with a map.
-        manual_definition_result.map(|manual_definition| ratchet::Package {
-            manual_definition,
-            uses_by_name: Tight,
-        }),
+        name_result
+            .and_(manual_definition_result)
+            .map(|manual_definition| ratchet::Package {
+                manual_definition,
+                uses_by_name: Tight,
+                update_script,
+                package_nix_hash,
+                variant_of: None,
+                definition: None,
+            }),
     )
 }
 
+/// Whether an attribute set with the given `type` attribute is allowed although it's not a
+/// derivation, see `non-derivation-types` of the configuration.
+fn is_allowed_non_derivation(config: &Config, type_attribute: Option<&str>) -> bool {
+    type_attribute.is_some_and(|type_attribute| {
+        config
+            .non_derivation_types
+            .iter()
+            .any(|allowed| allowed == type_attribute)
+    })
+}
+
+/// Handle the evaluation result for an attribute of a package set, making it a validation result.
+fn package_set_member(
+    config: &Config,
+    scope: &Scope,
+    attribute_name: &str,
+    relative_package_file: &RelativePathBuf,
+    package_set_attribute: ByNameAttribute,
+) -> validation::Validation<()> {
+    if !scope.contains_path(relative_package_file) {
+        return Success(());
+    }
+    match package_set_attribute {
+        ByNameAttribute::Missing => {
+            npv_100::ByNameUndefinedAttribute::new(attribute_name, relative_package_file).into()
+        }
+        ByNameAttribute::Existing(AttributeInfo {
+            attribute_variant:
+                AttributeVariant::AttributeSet {
+                    is_derivation: true,
+                    ..
+                },
+            ..
+        }) => Success(()),
+        ByNameAttribute::Existing(AttributeInfo {
+            attribute_variant: AttributeVariant::NonAttributeSet { value_type },
+            ..
+        }) => npv_101::ByNameNonDerivation::new(
+            attribute_name,
+            relative_package_file.clone(),
+            ValueShape::non_attribute_set(value_type),
+        )
+        .into(),
+        ByNameAttribute::Existing(AttributeInfo { type_attribute, .. }) => {
+            if is_allowed_non_derivation(config, type_attribute.as_deref()) {
+                Success(())
+            } else {
+                npv_101::ByNameNonDerivation::new(
+                    attribute_name,
+                    relative_package_file.clone(),
+                    ValueShape::AttributeSet { type_attribute },
+                )
+                .into()
+            }
+        }
+    }
+}
+
+/// Hashes the contents of the `package.nix` file of a package in `pkgs/by-name` with SHA-256, such
+/// that files only have the same hash if they have the same contents.
+///
+/// Returns `None` if the file can't be read, which can only happen for packages out of scope,
+/// since the structure of the others has been checked already.
+fn package_nix_hash(nixpkgs_path: &Path, attribute_name: &str) -> Option<[u8; 32]> {
+    let path = structure::relative_file_for_package(attribute_name).to_path(nixpkgs_path);
+    let contents = fs::read(path).ok()?;
+    Some(Sha256::digest(contents).into())
+}
+
 /// Handles the case for packages in `pkgs/by-name` that are manually overridden,
 /// e.g. in `pkgs/top-level/all-packages.nix`.
 fn by_name_override(
@@ -438,6 +1243,16 @@ fn by_name_override(
             npv_107::ByNameOverrideContainsEmptyArgument::new(attribute_name, location, definition)
                 .into(),
         ))
+    } else if syntactic_call_package.default_arg {
+        // Passing the arguments `callPackage` passes anyway is the same as passing none
+        Success(Loose(
+            npv_157::ByNameOverrideContainsDefaultArguments::new(
+                attribute_name,
+                location,
+                definition,
+            )
+            .into(),
+        ))
     } else {
         // This is the state to migrate to.
         Success(Tight)
@@ -449,18 +1264,29 @@ fn by_name_override(
 fn handle_non_by_name_attribute(
     nixpkgs_path: &Path,
     nix_file_store: &mut NixFileStore,
+    scope: &Scope,
     attribute_name: &str,
     non_by_name_attribute: NonByNameAttribute,
 ) -> validation::Result<ratchet::Package> {
     use NonByNameAttribute::EvalSuccess;
-    use ratchet::RatchetState::{Loose, NonApplicable, Tight};
+
+    // Where the attribute is defined, used to detect attributes that were removed
+    let definition = match &non_by_name_attribute {
+        EvalSuccess(AttributeInfo {
+            location: Some(location),
+            ..
+        }) => location.clone().relative(nixpkgs_path).ok(),
+        _ => None,
+    };
 
     // The ratchet state whether this attribute uses `pkgs/by-name`.
     //
     // This is never `Tight`, because we only either:
     // - Know that the attribute _could_ be migrated to `pkgs/by-name`, which is `Loose`
     // - Or we're unsure, in which case we use `NonApplicable`
-    let uses_by_name =
+    //
+    // Also determines whether the attribute is a variant of a package in `pkgs/by-name`.
+    let (uses_by_name, variant_of) =
         // This is a big ol' match on various properties of the attribute
         //
         // First, it needs to succeed evaluation. We can't know whether an attribute could be
@@ -503,6 +1329,7 @@ fn handle_non_by_name_attribute(
             // We need the location of the manual definition, because otherwise we can't figure out
             // whether it's a syntactic `callPackage`.
             location: Some(location),
+            ..
         }) = non_by_name_attribute {
 
         // Parse the Nix file in the location
@@ -538,7 +1365,7 @@ fn handle_non_by_name_attribute(
             | (true, None) => {
                 // In all of these cases, it's not possible to migrate the package to
                 // `pkgs/by-name`.
-                NonApplicable
+                (NonApplicable, None)
             }
 
             // Something like `<attr> = pkgs.callPackage ...`
@@ -558,12 +1385,19 @@ fn handle_non_by_name_attribute(
                         //
                         // See also "package variants" in RFC 140:
                         // https://github.com/NixOS/rfcs/blob/master/rfcs/0140-simple-package-paths.md#package-variants
-                        NonApplicable
+                        let variant_of = structure::package_for_relative_file(rel_path)
+                            .map(|package_name| (package_name, location.file));
+                        (NonApplicable, variant_of)
+                    }
+                    _ if !scope.contains_line(&location.file, location.line) => {
+                        // The definition could be migrated, but it's not in scope, so we don't
+                        // want to enforce anything about it.
+                        (NonApplicable, None)
                     }
                     _ => {
                         // Otherwise, the path is outside `pkgs/by-name`, which means it can be
                         // migrated.
-                        Loose((syntactic_call_package, location.file))
+                        (Loose((syntactic_call_package, location.file)), None)
                     }
                 }
             }
@@ -571,7 +1405,7 @@ fn handle_non_by_name_attribute(
     } else {
         // This catches all the cases not matched by the above `if let`, falling back to not being
         // able to migrate such attributes.
-        NonApplicable
+        (NonApplicable, None)
     };
     Ok(Success(ratchet::Package {
         // Packages being checked in this function _always_ need a manual definition, because
@@ -579,5 +1413,144 @@ fn handle_non_by_name_attribute(
         // ourselves all the time to define `manual_definition`, just set it once at the end here.
         manual_definition: Tight,
         uses_by_name,
+        update_script: NonApplicable,
+        package_nix_hash: None,
+        variant_of,
+        definition,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_problems_matching, assert_success};
+    use indoc::indoc;
+
+    #[test]
+    fn compares_nix_versions() {
+        assert!(version_at_least("2.3.17", MIN_NIX_VERSION));
+        assert!(version_at_least("2.24.10", MIN_NIX_VERSION));
+        assert!(version_at_least(
+            "2.25.0pre20241010_5f841fe",
+            MIN_NIX_VERSION
+        ));
+        assert!(version_at_least("2.91.1", MIN_NIX_VERSION));
+        assert!(!version_at_least("2.3.16", MIN_NIX_VERSION));
+        assert!(!version_at_least("2.3", MIN_NIX_VERSION));
+        assert!(!version_at_least("unknown", MIN_NIX_VERSION));
+    }
+
+    #[test]
+    fn classifies_failures() {
+        assert_eq!(
+            classify("error: function 'anonymous lambda' called without required argument 'foo'"),
+            EvalFailureKind::MissingArgument
+        );
+        assert_eq!(
+            classify("error: assertion '(stdenv.isLinux)' failed"),
+            EvalFailureKind::Assertion
+        );
+        assert_eq!(
+            classify("error: infinite recursion encountered"),
+            EvalFailureKind::InfiniteRecursion
+        );
+        assert_eq!(
+            classify(indoc! {"
+                error:
+                       … while calling the 'throw' builtin
+                       error: Package 'foo-1.0' in /nix/store/...-source/pkgs/by-name/fo/foo/package.nix:12 is marked as broken, refusing to evaluate.
+            "}),
+            EvalFailureKind::UnfreeOrBroken
+        );
+        assert_eq!(
+            classify(indoc! {"
+                error:
+                       … while calling the 'throw' builtin
+                       error: foo has been removed
+            "}),
+            EvalFailureKind::Throw
+        );
+        assert_eq!(
+            classify("error: file 'nixpkgs' was not found in the Nix search path"),
+            EvalFailureKind::SearchPathLookup
+        );
+        assert_eq!(classify("error: syntax error"), EvalFailureKind::Other);
+    }
+
+    #[test]
+    fn finds_failed_packages() {
+        let stderr = indoc! {"
+            error:
+                   … while evaluating the attribute 'bar'
+                     at /nix/store/abc-source/pkgs/by-name/ba/bar/package.nix:4:3:
+                   … while calling the 'import' builtin
+                     at /nix/store/abc-source/pkgs/by-name/fo/foo/package.nix:2:10:
+                   error: file 'nixpkgs' was not found in the Nix search path
+        "};
+        assert_eq!(failed_package(stderr).as_deref(), Some("foo"));
+        assert_eq!(failed_package("error: syntax error"), None);
+    }
+
+    #[test]
+    fn checks_derivation_names() {
+        assert_success(check_derivation_name("foo", "foo", "foo-1.0", "1.0"));
+        assert_success(check_derivation_name("foo", "foo", "foo", ""));
+        assert_problems_matching(
+            check_derivation_name("foo", "foo", "foo-2.0", "1.0"),
+            &[
+                "- pkgs.foo: The derivation name is \"foo-2.0\", but it should be \"foo-1.0\", which is `${pname}-${version}`. Please make sure that pkgs/by-name/fo/foo/package.nix doesn't override `name` inconsistently with `pname` and `version`.",
+            ],
+        );
+    }
+
+    #[test]
+    fn finds_warnings() {
+        let stderr = indoc! {"
+            evaluation warning: foo has been renamed to bar
+            trace: warning: baz is deprecated
+            trace: something else
+            error: not a warning
+        "};
+        assert_eq!(
+            eval_warnings(stderr.as_bytes()),
+            ["foo has been renamed to bar", "baz is deprecated"]
+        );
+    }
+
+    #[test]
+    fn trims_traces() {
+        let frames = (1..=12)
+            .map(|frame| {
+                format!("       … while evaluating {frame}\n         at file.nix:{frame}:1:\n")
+            })
+            .collect::<String>();
+        let stderr = format!("trace: hello\nerror:\n{frames}       error: foo has been removed\n");
+        let trimmed = trim_trace(&stderr);
+        assert!(trimmed.starts_with(
+            "trace: hello\nerror:\n       … (2 more frames)\n       … while evaluating 3\n"
+        ));
+        assert!(!trimmed.contains("evaluating 2\n"));
+        assert!(trimmed.ends_with("at file.nix:12:1:\n       error: foo has been removed\n"));
+        assert_eq!(trim_trace("error: foo"), "error: foo");
+    }
+
+    #[test]
+    fn detects_transient_failures() {
+        let output = |raw_status: i32, stderr: &str| process::Output {
+            status: process::ExitStatus::from_raw(raw_status),
+            stdout: vec![],
+            stderr: stderr.as_bytes().to_vec(),
+        };
+        // Killed by SIGKILL
+        assert!(is_transient_failure(&output(9, "")));
+        // Exited with 1
+        assert!(is_transient_failure(&output(
+            1 << 8,
+            "error: opening lock file '/nix/var/nix/db/big-lock'"
+        )));
+        assert!(!is_transient_failure(&output(
+            1 << 8,
+            "error: attribute 'foo' missing"
+        )));
+    }
+}
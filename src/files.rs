@@ -1,30 +1,79 @@
 use relative_path::RelativePath;
 use relative_path::RelativePathBuf;
 use std::collections::BTreeMap;
+use std::iter;
 use std::path::Path;
 
+use crate::config::Config;
+use crate::location::Location;
 use crate::nix_file::NixFileStore;
+use crate::problem::npv_148;
+use crate::ratchet::{ConventionViolation, RatchetState};
 use crate::validation::ResultIteratorExt;
-use crate::validation::Validation::Success;
+use crate::validation::Validation::{self, Success};
 use crate::{nix_file, ratchet, structure, validation};
 
 /// Runs check on all Nix files, returning a ratchet result for each
 pub fn check_files(
     nixpkgs_path: &Path,
+    config: &Config,
     nix_file_store: &mut NixFileStore,
 ) -> validation::Result<BTreeMap<RelativePathBuf, ratchet::File>> {
-    process_nix_files(nixpkgs_path, nix_file_store, |_nix_file| {
-        // Noop for now, only boilerplate to make it easier to add future file-based checks
-        Ok(Success(ratchet::File {}))
-    })
+    let _span = tracing::info_span!("files").entered();
+    process_nix_files(
+        nixpkgs_path,
+        config,
+        nix_file_store,
+        |relative_path, nix_file| {
+            let conventions = config
+                .conventions
+                .iter()
+                .map(|convention| {
+                    if !relative_path.starts_with(&convention.path) {
+                        return (convention.name.clone(), RatchetState::NonApplicable);
+                    }
+                    let violation = nix_file
+                        .builder_calls(&convention.builder)
+                        .into_iter()
+                        .find_map(|call| {
+                            let missing = convention
+                                .required_attributes
+                                .iter()
+                                .find(|attribute| !call.attributes.contains(*attribute))
+                                .map(|attribute| (attribute, false));
+                            let forbidden = convention
+                                .forbidden_attributes
+                                .iter()
+                                .find(|attribute| call.attributes.contains(*attribute))
+                                .map(|attribute| (attribute, true));
+                            let (attribute, forbidden) = missing.or(forbidden)?;
+                            Some(ConventionViolation {
+                                builder: convention.builder.clone(),
+                                attribute: attribute.clone(),
+                                forbidden,
+                                reason: convention.reason.clone(),
+                                location: Location::new(relative_path, call.line, call.column),
+                            })
+                        });
+                    let state = match violation {
+                        Some(violation) => RatchetState::Loose(violation),
+                        None => RatchetState::Tight,
+                    };
+                    (convention.name.clone(), state)
+                })
+                .collect();
+            Ok(Success(ratchet::File { conventions }))
+        },
+    )
 }
 
 /// Processes all Nix files in a Nixpkgs directory according to a given function `f`, collecting the
 /// results into a mapping from each file to a ratchet value.
 fn process_nix_files(
     nixpkgs_path: &Path,
+    config: &Config,
     nix_file_store: &mut NixFileStore,
-    f: impl Fn(&nix_file::NixFile) -> validation::Result<ratchet::File>,
+    f: impl Fn(&RelativePath, &nix_file::NixFile) -> validation::Result<ratchet::File>,
 ) -> validation::Result<BTreeMap<RelativePathBuf, ratchet::File>> {
     // Get all Nix files
     let files = {
@@ -33,26 +82,50 @@ fn process_nix_files(
         files
     };
 
+    // Package directories that can't be read are already reported by the structure check
+    let by_name_dirs: Vec<&RelativePath> = iter::once(RelativePath::new(structure::BASE_SUBPATH))
+        .chain(
+            config
+                .package_sets
+                .iter()
+                .map(|package_set| package_set.path.as_ref()),
+        )
+        .collect();
+
     let results = files
         .into_iter()
         .map(|path| {
             // Get the (optionally-cached) parsed Nix file
-            let nix_file = nix_file_store.get(&path.to_path(nixpkgs_path))?;
-            let result = f(nix_file)?;
-            let val = result.map(|ratchet| (path, ratchet));
+            let nix_file = match nix_file_store.get(&path.to_path(nixpkgs_path)) {
+                Ok(nix_file) => nix_file,
+                // A file that can't be read shouldn't prevent checking the others
+                Err(error) if structure::is_path_error(&error) => {
+                    return Ok(if by_name_dirs.iter().any(|dir| path.starts_with(dir)) {
+                        Success(None)
+                    } else {
+                        npv_148::PathUnreadable::new(path, error.root_cause().to_string()).into()
+                    });
+                }
+                Err(error) => return Err(error),
+            };
+            let result = f(&path, nix_file)?;
+            let val = result.map(|ratchet| Some((path, ratchet)));
             Ok::<_, anyhow::Error>(val)
         })
         .collect_vec()?;
 
-    Ok(validation::sequence(results).map(|entries| {
-        // Convert the Vec to a BTreeMap
-        entries.into_iter().collect()
-    }))
+    Ok(results
+        .into_iter()
+        .collect::<Validation<Vec<_>>>()
+        .map(|entries| {
+            // Convert the Vec to a BTreeMap
+            entries.into_iter().flatten().collect()
+        }))
 }
 
 /// Recursively collects all Nix files in the relative `dir` within `base`
 /// into the `files` `Vec`.
-fn collect_nix_files(
+pub fn collect_nix_files(
     base: &Path,
     dir: &RelativePath,
     files: &mut Vec<RelativePathBuf>,
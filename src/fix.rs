@@ -0,0 +1,822 @@
+//! Changes to Nixpkgs that resolve a problem without any judgement needed, see `Problem::fix` and
+//! `--fix`.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::Write as _;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::Path;
+use std::process;
+
+use anyhow::Context;
+use relative_path::{RelativePath, RelativePathBuf};
+use rnix::ast;
+use rowan::ast::AstNode as _;
+use serde::Serialize;
+
+use colored::Colorize as _;
+
+use crate::delta;
+use crate::diff;
+use crate::git;
+use crate::problem::Problem;
+use crate::status::{Fatal, Status};
+use crate::structure;
+
+/// A machine-applicable fix for a problem, with paths relative to Nixpkgs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Fix {
+    /// Moves a file or directory. Files tracked by git are moved with `git mv` to keep them
+    /// tracked, and files in a moved directory that refer to it by its path, like update scripts,
+    /// are changed to refer to the new path, as are the path expressions of the Nix files in
+    /// Nixpkgs that refer to within it, like `callPackage ../by-name/aa/foo/package.nix { }`.
+    Move {
+        from: RelativePathBuf,
+        to: RelativePathBuf,
+    },
+    /// Removes the text starting at the 1-indexed line and column of the file, together with the
+    /// lines it's on if nothing else is on them, such that the surrounding lines stay as they are.
+    Remove {
+        file: RelativePathBuf,
+        line: usize,
+        column: usize,
+        text: String,
+    },
+}
+
+/// A change to a file that a fix makes, see `Fix::changes`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileChange {
+    /// The path of the file before the fix.
+    pub from: RelativePathBuf,
+    /// The path of the file after the fix, which differs from `from` if it's moved.
+    pub to: RelativePathBuf,
+    /// The contents before and after the fix, or `None` if the file isn't text, in which case it's
+    /// only moved.
+    pub contents: Option<(String, String)>,
+}
+
+impl FileChange {
+    /// The change as a git-style diff, like `git apply` accepts.
+    pub fn diff(&self) -> String {
+        let (old, new) = self
+            .contents
+            .as_ref()
+            .map_or(("", ""), |(old, new)| (old.as_str(), new.as_str()));
+        diff::render(&self.from, &self.to, old, new)
+    }
+}
+
+impl Fix {
+    /// The changes the fix makes to the files of the Nixpkgs at the given path, without making
+    /// them, to preview them with `--fix --dry-run`.
+    ///
+    /// With a `nixfmt` binary, the changed Nix files are formatted with it, unless they weren't
+    /// formatted before, in which case formatting them would change unrelated parts.
+    pub fn changes(
+        &self,
+        nixpkgs_path: &Path,
+        nixfmt: Option<&Path>,
+    ) -> anyhow::Result<Vec<FileChange>> {
+        let mut changes = self.unformatted_changes(nixpkgs_path)?;
+        if let Some(nixfmt) = nixfmt {
+            for change in &mut changes {
+                if change.to.extension() == Some("nix")
+                    && let Some((old, new)) = &mut change.contents
+                    && old != new
+                    && format(nixfmt, old)? == *old
+                {
+                    *new = format(nixfmt, new)
+                        .with_context(|| format!("Could not format {}", change.to))?;
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    fn unformatted_changes(&self, nixpkgs_path: &Path) -> anyhow::Result<Vec<FileChange>> {
+        match self {
+            Self::Move { from, to } => {
+                if to.to_path(nixpkgs_path).symlink_metadata().is_ok() {
+                    anyhow::bail!("Cannot move {from} to {to}, because {to} already exists");
+                }
+                if from.to_path(nixpkgs_path).symlink_metadata().is_err() {
+                    anyhow::bail!("Cannot move {from} to {to}, because {from} doesn't exist");
+                }
+                let mut changes = Vec::new();
+                moved_files(nixpkgs_path, from, to, RelativePath::new(""), &mut changes)?;
+                path_expression_changes(
+                    nixpkgs_path,
+                    RelativePath::new(""),
+                    from,
+                    to,
+                    &mut changes,
+                )?;
+                Ok(changes)
+            }
+            Self::Remove {
+                file,
+                line,
+                column,
+                text,
+            } => {
+                let contents = fs::read_to_string(file.to_path(nixpkgs_path))
+                    .with_context(|| format!("Could not read {file}"))?;
+                let Some(start) = offset(&contents, *line, *column)
+                    .filter(|start| contents[*start..].starts_with(text.as_str()))
+                else {
+                    anyhow::bail!("{file} changed, {file}:{line} isn't the text to remove anymore");
+                };
+                let (mut start, mut end) = (start, start + text.len());
+                let line_start = contents[..start].rfind('\n').map_or(0, |index| index + 1);
+                let line_end = contents[end..]
+                    .find('\n')
+                    .map_or(contents.len(), |index| end + index + 1);
+                if contents[line_start..start].trim().is_empty()
+                    && contents[end..line_end].trim().is_empty()
+                {
+                    (start, end) = (line_start, line_end);
+                }
+                let removed = format!("{}{}", &contents[..start], &contents[end..]);
+                Ok(vec![FileChange {
+                    from: file.clone(),
+                    to: file.clone(),
+                    contents: Some((contents, removed)),
+                }])
+            }
+        }
+    }
+
+    /// Applies the fix to the Nixpkgs at the given path, see `changes`.
+    pub fn apply(&self, nixpkgs_path: &Path, nixfmt: Option<&Path>) -> anyhow::Result<()> {
+        let changes = self.changes(nixpkgs_path, nixfmt)?;
+        if let Self::Move { from, to } = self {
+            let target = to.to_path(nixpkgs_path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Could not create the directory for {to}"))?;
+            }
+            if is_tracked(nixpkgs_path, from) {
+                git::run(nixpkgs_path, &["mv", "--", from.as_str(), to.as_str()])?;
+            } else {
+                fs::rename(from.to_path(nixpkgs_path), &target)
+                    .with_context(|| format!("Could not move {from} to {to}"))?;
+            }
+        }
+        for change in changes {
+            if let Some((old, new)) = &change.contents
+                && old != new
+            {
+                fs::write(change.to.to_path(nixpkgs_path), new)
+                    .with_context(|| format!("Could not write {}", change.to))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fixes of the problems that have one, with the index of their problem, in the order to apply
+/// them in: the removals from the end of each file first, such that they don't shift the lines of
+/// the other removals from the same file, and then the moves.
+pub fn in_apply_order(problems: &[Problem]) -> Vec<(usize, Fix)> {
+    let mut fixes = problems
+        .iter()
+        .enumerate()
+        .filter_map(|(index, problem)| Some((index, problem.fix()?)))
+        .collect::<Vec<_>>();
+    fixes.sort_by_key(|(index, fix)| match fix {
+        Fix::Remove {
+            file, line, column, ..
+        } => (0, Some(file.clone()), Reverse((*line, *column)), *index),
+        Fix::Move { .. } => (1, None, Reverse((0, 0)), *index),
+    });
+    fixes
+}
+
+/// Applies the fixes of all problems of the status that have one, returning the status without
+/// the problems that were fixed. Problems whose fix fails are kept, with the reason printed.
+///
+/// With `dry_run`, the diffs of the changes are printed instead of making them, and all problems
+/// are kept. Each diff is of a single fix, such that fixes changing the same file conflict.
+pub fn apply_all(
+    status: Status,
+    nixpkgs_path: &Path,
+    dry_run: bool,
+    nixfmt: Option<&Path>,
+) -> anyhow::Result<Status> {
+    let Some(problems) = status.errors() else {
+        return Ok(status);
+    };
+    let mut fixed = BTreeSet::new();
+    for (index, fix) in in_apply_order(problems) {
+        let result = if dry_run {
+            fix.changes(nixpkgs_path, nixfmt).map(|changes| {
+                eprintln!("{}", format!("Would apply: {fix}").green());
+                for change in changes {
+                    print!("{}", change.diff());
+                }
+            })
+        } else {
+            fix.apply(nixpkgs_path, nixfmt)
+                .map(|()| eprintln!("{}", format!("Applied: {fix}").green()))
+        };
+        match result {
+            Ok(()) if dry_run => {}
+            Ok(()) => {
+                fixed.insert(delta::key(&problems[index]));
+            }
+            Err(error) => eprintln!("{}", format!("Could not fix: {error:#}").red()),
+        }
+    }
+    Ok(status.retain(|problem| !fixed.contains(&delta::key(problem))))
+}
+
+/// The fixes of a status with their changes, for bots to apply them, see `--emit-fixes`.
+#[derive(Debug, Serialize)]
+pub struct FixExport {
+    pub fixes: Vec<ExportedFix>,
+    /// The changes of all fixes as one git-style diff, which `git apply` accepts.
+    pub diff: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedFix {
+    /// The code of the check that found the problem, like `NPV-109`.
+    pub code: String,
+    /// What the fix does, like in the human-readable output.
+    pub description: String,
+    pub fix: Fix,
+    /// The changes of the fix to each file, as git-style diffs made against the current files.
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Edit {
+    pub from: RelativePathBuf,
+    pub to: RelativePathBuf,
+    pub diff: String,
+}
+
+impl FixExport {
+    /// Determines the changes of the fixes of all problems of the status that have one, without
+    /// making them. Fixes that can't be made are left out, with the reason printed.
+    ///
+    /// For the combined diff, the fixes are applied to a copy of the files they change, such that
+    /// fixes changing the same file, like removals from `pkgs/top-level/all-packages.nix`, don't
+    /// conflict.
+    pub fn new(
+        status: &Status,
+        nixpkgs_path: &Path,
+        nixfmt: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let problems = status.errors().map(Vec::as_slice).unwrap_or_default();
+        let copy = tempfile::Builder::new()
+            .prefix("nixpkgs-vet-fixes")
+            .tempdir()
+            .context("Could not create a temporary directory for the fixes")?;
+        let mut fixes = Vec::new();
+        // The paths of the changed files before and after all fixes
+        let mut paths: BTreeMap<RelativePathBuf, RelativePathBuf> = BTreeMap::new();
+        for (index, fix) in in_apply_order(problems) {
+            let changes = match fix.changes(nixpkgs_path, nixfmt) {
+                Ok(changes) => changes,
+                Err(error) => {
+                    eprintln!("{}", format!("Could not fix: {error:#}").red());
+                    continue;
+                }
+            };
+            for change in &changes {
+                let original = paths
+                    .iter()
+                    .find(|(_, to)| **to == change.from)
+                    .map_or_else(|| change.from.clone(), |(from, _)| from.clone());
+                let source = original.to_path(nixpkgs_path);
+                let target = change.from.to_path(copy.path());
+                if !target.exists() && source.is_file() {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(&source, &target)
+                        .with_context(|| format!("Could not copy {original}"))?;
+                    // Files from the Nix store are read-only, but the fixes change the copies
+                    let mut permissions = fs::metadata(&target)?.permissions();
+                    permissions.set_mode(permissions.mode() | 0o200);
+                    fs::set_permissions(&target, permissions)?;
+                }
+                paths.insert(original, change.to.clone());
+            }
+            let edits = changes
+                .iter()
+                .map(|change| Edit {
+                    from: change.from.clone(),
+                    to: change.to.clone(),
+                    diff: change.diff(),
+                })
+                .collect();
+            fixes.push((index, fix, edits));
+        }
+
+        for (_, fix, _) in &fixes {
+            fix.apply(copy.path(), nixfmt)
+                .with_context(|| format!("Could not combine the fix: {fix}"))?;
+        }
+        let mut diff = String::new();
+        for (from, to) in &paths {
+            let read = |path: &Path| fs::read(path).map(|bytes| String::from_utf8(bytes).ok());
+            let old = read(&from.to_path(nixpkgs_path))
+                .with_context(|| format!("Could not read {from}"))?;
+            let new = read(&to.to_path(copy.path()))
+                .with_context(|| format!("Could not read the fixed {to}"))?;
+            diff.push_str(&match (old, new) {
+                (Some(old), Some(new)) => diff::render(from, to, &old, &new),
+                // Files that aren't text can only be moved
+                _ => diff::render(from, to, "", ""),
+            });
+        }
+
+        // In the order of the problems, like the other output
+        fixes.sort_by_key(|(index, _, _)| *index);
+        Ok(Self {
+            fixes: fixes
+                .into_iter()
+                .map(|(index, fix, edits)| ExportedFix {
+                    code: problems[index].code().to_owned(),
+                    description: fix.to_string(),
+                    fix,
+                    edits,
+                })
+                .collect(),
+            diff,
+        })
+    }
+
+    /// Writes the fixes as JSON to the file.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("fixes can be serialised");
+        fs::write(path, json + "\n")
+            .with_context(|| format!("Could not write the fixes to {}", path.display()))
+    }
+}
+
+/// Formats the contents of a Nix file with the nixfmt binary, which reads them from stdin.
+fn format(nixfmt: &Path, contents: &str) -> anyhow::Result<String> {
+    let mut child = process::Command::new(nixfmt)
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            Fatal::Misconfiguration(format!("Failed to run nixfmt at {}", nixfmt.display()))
+        })?;
+    {
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        stdin.write_all(contents.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "nixfmt failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    String::from_utf8(output.stdout).context("The output of nixfmt isn't UTF-8")
+}
+
+/// The byte offset of the 1-indexed line and column in the contents, if it's within them.
+fn offset(contents: &str, line: usize, column: usize) -> Option<usize> {
+    let line_start = if line == 1 {
+        0
+    } else {
+        contents.match_indices('\n').nth(line.checked_sub(2)?)?.0 + 1
+    };
+    let offset = line_start + column.checked_sub(1)?;
+    contents.is_char_boundary(offset).then_some(offset)
+}
+
+/// The changes of moving the file or directory, where the references to the old path in the moved
+/// files that are text are replaced with the new one, like `cd` in update scripts, and the
+/// relative path expressions of the moved Nix files are rewritten to still refer to the same files.
+fn moved_files(
+    nixpkgs_path: &Path,
+    from: &RelativePath,
+    to: &RelativePath,
+    within: &RelativePath,
+    changes: &mut Vec<FileChange>,
+) -> anyhow::Result<()> {
+    let (file_from, file_to) = (from.join(within), to.join(within));
+    let path = file_from.to_path(nixpkgs_path);
+    if path.is_dir() {
+        for entry in structure::read_dir_sorted(&path)? {
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                anyhow::bail!(
+                    "Cannot move {}, it's not valid UTF-8",
+                    entry.path().display()
+                );
+            };
+            moved_files(nixpkgs_path, from, to, &within.join(file_name), changes)?;
+        }
+        return Ok(());
+    }
+    let contents = fs::read_to_string(&path).ok().map(|contents| {
+        let mut moved = contents.replace(from.as_str(), to.as_str());
+        if file_to.extension() == Some("nix")
+            && let Some(dir) = file_to.parent()
+            && let Some(rewritten) = rewrite_path_expressions(&moved, dir, from, to)
+        {
+            moved = rewritten;
+        }
+        (contents, moved)
+    });
+    changes.push(FileChange {
+        from: file_from,
+        to: file_to,
+        contents,
+    });
+    Ok(())
+}
+
+/// The changes of rewriting the relative path expressions in the Nix files within the directory of
+/// Nixpkgs that refer to the moved path or to within it, such that they refer to the same file at
+/// its new path. The moved path itself and hidden directories like `.git` are skipped, as are files
+/// that don't mention the moved path's name, which can't refer to it.
+fn path_expression_changes(
+    nixpkgs_path: &Path,
+    dir: &RelativePath,
+    from: &RelativePath,
+    to: &RelativePath,
+    changes: &mut Vec<FileChange>,
+) -> anyhow::Result<()> {
+    let name = from.file_name().unwrap_or_default();
+    for entry in structure::read_dir_sorted(&dir.to_path(nixpkgs_path))? {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        let relative_path = dir.join(file_name);
+        if relative_path == from {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() && !file_name.starts_with('.') {
+            path_expression_changes(nixpkgs_path, &relative_path, from, to, changes)?;
+        } else if file_type.is_file()
+            && file_name.ends_with(".nix")
+            && let Ok(contents) = fs::read_to_string(entry.path())
+            && contents.contains(name)
+            && let Some(updated) = rewrite_path_expressions(&contents, dir, from, to)
+        {
+            changes.push(FileChange {
+                from: relative_path.clone(),
+                to: relative_path,
+                contents: Some((contents, updated)),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The contents of a Nix file in the directory with the path expressions referring to the moved
+/// path rewritten, or `None` if there are none.
+fn rewrite_path_expressions(
+    contents: &str,
+    dir: &RelativePath,
+    from: &RelativePath,
+    to: &RelativePath,
+) -> Option<String> {
+    let root = rnix::Root::parse(contents);
+    let mut replacements = root
+        .syntax()
+        .descendants()
+        .filter_map(ast::Path::cast)
+        .filter_map(|path| {
+            let text = path.syntax().to_string();
+            // Only literal relative paths can be resolved without evaluating
+            if path.parts().count() != 1 || !text.starts_with('.') {
+                return None;
+            }
+            let target = dir.join_normalized(&text);
+            let rest = target.strip_prefix(from).ok()?;
+            let new_target = to.join(rest);
+            let mut new_text = dir.relative(&new_target).into_string();
+            // Keep the style of the path expression, and paths need a slash to be paths
+            if text.starts_with("./") && !new_text.starts_with("./") || !new_text.starts_with('.') {
+                new_text = format!("./{new_text}");
+            }
+            (new_text != text).then(|| (path.syntax().text_range(), new_text))
+        })
+        .collect::<Vec<_>>();
+    if replacements.is_empty() {
+        return None;
+    }
+    let mut updated = contents.to_owned();
+    replacements.reverse();
+    for (range, new_text) in replacements {
+        updated.replace_range(
+            usize::from(range.start())..usize::from(range.end()),
+            &new_text,
+        );
+    }
+    Some(updated)
+}
+
+/// Whether git tracks any file at the given path, which is false if it's not in a git repository
+/// at all.
+fn is_tracked(nixpkgs_path: &Path, path: &RelativePathBuf) -> bool {
+    git::run(nixpkgs_path, &["ls-files", "--", path.as_str()])
+        .is_ok_and(|files| !files.trim().is_empty())
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Move { from, to } => write!(f, "Move {from} to {to}"),
+            Self::Remove {
+                file, line, text, ..
+            } => {
+                let first_line = text.lines().next().unwrap_or_default();
+                let ellipsis = if text.contains('\n') { " …" } else { "" };
+                write!(f, "Remove `{first_line}{ellipsis}` from {file}:{line}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_107, npv_142, npv_143};
+    use crate::testing::tempdir;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn moves_package_directories() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        fs::create_dir_all(nixpkgs.join("pkgs/by-name/aa/FOO"))?;
+        fs::create_dir_all(nixpkgs.join("pkgs/top-level"))?;
+        fs::write(nixpkgs.join("pkgs/by-name/aa/FOO/package.nix"), "{ }")?;
+        fs::write(
+            nixpkgs.join("pkgs/top-level/all-packages.nix"),
+            "{ FOO = callPackage ./../by-name/aa/FOO/package.nix { }; bar = ../by-name/aa/FOOBAR; }",
+        )?;
+        fs::write(
+            nixpkgs.join("pkgs/by-name/aa/FOO/update.sh"),
+            "cd pkgs/by-name/aa/FOO && ./update.py",
+        )?;
+
+        let fix = Fix::Move {
+            from: "pkgs/by-name/aa/FOO".into(),
+            to: "pkgs/by-name/fo/FOO".into(),
+        };
+        fix.apply(nixpkgs, None)?;
+        assert!(nixpkgs.join("pkgs/by-name/fo/FOO/package.nix").is_file());
+        assert!(!nixpkgs.join("pkgs/by-name/aa/FOO").exists());
+        assert_eq!(
+            fs::read_to_string(nixpkgs.join("pkgs/by-name/fo/FOO/update.sh"))?,
+            "cd pkgs/by-name/fo/FOO && ./update.py"
+        );
+        assert_eq!(
+            fs::read_to_string(nixpkgs.join("pkgs/top-level/all-packages.nix"))?,
+            "{ FOO = callPackage ./../by-name/fo/FOO/package.nix { }; bar = ../by-name/aa/FOOBAR; }"
+        );
+
+        // Moving again would need to overwrite the target
+        fs::create_dir_all(nixpkgs.join("pkgs/by-name/aa/FOO"))?;
+        assert!(fix.apply(nixpkgs, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn previews_changes() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        fs::create_dir_all(nixpkgs.join("pkgs/by-name/aa/FOO"))?;
+        fs::create_dir_all(nixpkgs.join("pkgs/top-level"))?;
+        fs::write(nixpkgs.join("pkgs/by-name/aa/FOO/package.nix"), "{ }\n")?;
+        fs::write(
+            nixpkgs.join("pkgs/top-level/all-packages.nix"),
+            "{\n  FOO = callPackage ../by-name/aa/FOO/package.nix { };\n}\n",
+        )?;
+
+        let fix = Fix::Move {
+            from: "pkgs/by-name/aa/FOO".into(),
+            to: "pkgs/by-name/fo/FOO".into(),
+        };
+        let diff = fix
+            .changes(nixpkgs, None)?
+            .iter()
+            .map(FileChange::diff)
+            .collect::<String>();
+        assert_eq!(
+            diff,
+            indoc! {"
+                diff --git a/pkgs/by-name/aa/FOO/package.nix b/pkgs/by-name/fo/FOO/package.nix
+                similarity index 100%
+                rename from pkgs/by-name/aa/FOO/package.nix
+                rename to pkgs/by-name/fo/FOO/package.nix
+                diff --git a/pkgs/top-level/all-packages.nix b/pkgs/top-level/all-packages.nix
+                --- a/pkgs/top-level/all-packages.nix
+                +++ b/pkgs/top-level/all-packages.nix
+                @@ -1,3 +1,3 @@
+                 {
+                -  FOO = callPackage ../by-name/aa/FOO/package.nix { };
+                +  FOO = callPackage ../by-name/fo/FOO/package.nix { };
+                 }
+            "}
+        );
+        // Nothing changed yet
+        assert!(nixpkgs.join("pkgs/by-name/aa/FOO/package.nix").is_file());
+        assert!(!nixpkgs.join("pkgs/by-name/fo").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn removes_definitions() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        let path = nixpkgs.join("all-packages.nix");
+        fs::write(
+            &path,
+            "{\n  bar = 1;\n  foo = callPackage ../by-name/fo/foo/package.nix { };\n  baz = 2; foo2 = 3;\n}\n",
+        )?;
+
+        let remove = |line, column, text: &str| Fix::Remove {
+            file: "all-packages.nix".into(),
+            line,
+            column,
+            text: text.to_owned(),
+        };
+        let fix = remove(3, 3, "foo = callPackage ../by-name/fo/foo/package.nix { };");
+        assert_eq!(
+            fix.to_string(),
+            "Remove `foo = callPackage ../by-name/fo/foo/package.nix { };` from all-packages.nix:3"
+        );
+        fix.apply(nixpkgs, None)?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "{\n  bar = 1;\n  baz = 2; foo2 = 3;\n}\n"
+        );
+
+        // Other definitions on the same line are kept
+        remove(3, 12, "foo2 = 3;").apply(nixpkgs, None)?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "{\n  bar = 1;\n  baz = 2; \n}\n"
+        );
+
+        // The file changed since the problem was found
+        assert!(remove(2, 3, "foo = 1;").apply(nixpkgs, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn applies_fixes_of_introduced_problems() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        fs::create_dir_all(nixpkgs.join("pkgs/top-level"))?;
+        fs::write(
+            nixpkgs.join("pkgs/top-level/all-packages.nix"),
+            "{\n  foo = callPackage ../by-name/fo/foo/package.nix { };\n}\n",
+        )?;
+
+        let missing = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let status = Status::ProblemsChanged {
+            introduced: vec![
+                missing("pkgs/by-name/ba/bar"),
+                npv_107::ByNameOverrideContainsEmptyArgument::new(
+                    "foo",
+                    Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+                    "foo = callPackage ../by-name/fo/foo/package.nix { };",
+                )
+                .into(),
+            ],
+            fixed: vec![
+                missing("pkgs/by-name/ba/baz"),
+                missing("pkgs/by-name/qu/qux"),
+            ],
+        };
+        let Status::ProblemsChanged { introduced, fixed } =
+            apply_all(status, nixpkgs, false, None)?
+        else {
+            panic!("the base branch is still broken");
+        };
+        assert_eq!(
+            introduced
+                .iter()
+                .map(Problem::to_string)
+                .collect::<Vec<_>>(),
+            ["- pkgs/by-name/ba/bar: Missing required \"package.nix\" file."]
+        );
+        assert_eq!(fixed.len(), 2);
+        assert_eq!(
+            fs::read_to_string(nixpkgs.join("pkgs/top-level/all-packages.nix"))?,
+            "{\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exports_fixes() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        fs::create_dir_all(nixpkgs.join("pkgs/by-name/aa/foo"))?;
+        fs::create_dir_all(nixpkgs.join("pkgs/top-level"))?;
+        fs::write(nixpkgs.join("pkgs/by-name/aa/foo/package.nix"), "{ }\n")?;
+        let all_packages = "{\n  bar = callPackage ../by-name/ba/bar/package.nix { };\n  \
+            baz = callPackage ../by-name/ba/baz/package.nix { };\n}\n";
+        fs::write(
+            nixpkgs.join("pkgs/top-level/all-packages.nix"),
+            all_packages,
+        )?;
+
+        let empty_argument = |name: &str, line| -> Problem {
+            npv_107::ByNameOverrideContainsEmptyArgument::new(
+                name,
+                Location::new("pkgs/top-level/all-packages.nix", line, 3),
+                format!("{name} = callPackage ../by-name/ba/{name}/package.nix {{ }};"),
+            )
+            .into()
+        };
+        let status = Status::ProblemsIntroduced(vec![
+            empty_argument("bar", 2),
+            npv_142::PackageInWrongShard::new("foo", "pkgs/by-name/aa/foo", "fo").into(),
+            empty_argument("baz", 3),
+        ]);
+        let export = FixExport::new(&status, nixpkgs, None)?;
+        assert_eq!(
+            export
+                .fixes
+                .iter()
+                .map(|fix| fix.code.as_str())
+                .collect::<Vec<_>>(),
+            ["NPV-107", "NPV-142", "NPV-107"]
+        );
+        // Each fix on its own removes a line of the file, and both do together
+        assert!(export.fixes[2].edits[0].diff.contains("@@ -1,4 +1,3 @@"));
+        assert_eq!(
+            export.diff,
+            indoc! {"
+                diff --git a/pkgs/by-name/aa/foo/package.nix b/pkgs/by-name/fo/foo/package.nix
+                similarity index 100%
+                rename from pkgs/by-name/aa/foo/package.nix
+                rename to pkgs/by-name/fo/foo/package.nix
+                diff --git a/pkgs/top-level/all-packages.nix b/pkgs/top-level/all-packages.nix
+                --- a/pkgs/top-level/all-packages.nix
+                +++ b/pkgs/top-level/all-packages.nix
+                @@ -1,4 +1,2 @@
+                 {
+                -  bar = callPackage ../by-name/ba/bar/package.nix { };
+                -  baz = callPackage ../by-name/ba/baz/package.nix { };
+                 }
+            "}
+        );
+        // Nothing changed
+        assert_eq!(
+            fs::read_to_string(nixpkgs.join("pkgs/top-level/all-packages.nix"))?,
+            all_packages
+        );
+        assert!(nixpkgs.join("pkgs/by-name/aa/foo").is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn formats_changed_files() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        // A formatter that only removes trailing whitespace
+        let nixfmt = nixpkgs.join("nixfmt");
+        fs::write(&nixfmt, "#!/bin/sh\nsed 's/ *$//'\n")?;
+        fs::set_permissions(&nixfmt, fs::Permissions::from_mode(0o755))?;
+
+        let remove = |file: &str| Fix::Remove {
+            file: file.into(),
+            line: 2,
+            column: 12,
+            text: "foo = 3;".to_owned(),
+        };
+        fs::write(nixpkgs.join("formatted.nix"), "{\n  bar = 2; foo = 3;\n}\n")?;
+        remove("formatted.nix").apply(nixpkgs, Some(&nixfmt))?;
+        assert_eq!(
+            fs::read_to_string(nixpkgs.join("formatted.nix"))?,
+            "{\n  bar = 2;\n}\n"
+        );
+
+        // Files that weren't formatted before aren't formatted
+        fs::write(
+            nixpkgs.join("unformatted.nix"),
+            "{ \n  bar = 2; foo = 3;\n}\n",
+        )?;
+        remove("unformatted.nix").apply(nixpkgs, Some(&nixfmt))?;
+        assert_eq!(
+            fs::read_to_string(nixpkgs.join("unformatted.nix"))?,
+            "{ \n  bar = 2; \n}\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+//! Utilities for getting Nixpkgs trees out of a git repository, using the `git` binary.
+
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::Context;
+use tempfile::TempDir;
+
+use crate::status::Fatal;
+
+/// Runs `git` with the given arguments in the given repository, returning its standard output.
+pub fn run(repo: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let mut command = process::Command::new("git");
+    command.arg("-C").arg(repo).args(args);
+    tracing::debug!(?command, "Running git");
+
+    let output = command.output().with_context(|| {
+        Fatal::Misconfiguration(format!("Failed to run {command:?}, is git installed?"))
+    })?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Command {command:?} failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("Output of {command:?} isn't UTF-8"))
+}
+
+/// Resolves a ref like `master` or `HEAD~2` to a commit hash.
+pub fn resolve(repo: &Path, reference: &str) -> anyhow::Result<String> {
+    let commit = run(
+        repo,
+        &["rev-parse", "--verify", &format!("{reference}^{{commit}}")],
+    )
+    .with_context(|| format!("Could not resolve {reference} to a commit"))?;
+    Ok(commit.trim().to_owned())
+}
+
+/// Returns the commit where the history of the two refs diverged, which is the commit that a
+/// branch was forked from.
+pub fn merge_base(repo: &Path, first: &str, second: &str) -> anyhow::Result<String> {
+    let commit = run(repo, &["merge-base", first, second]).with_context(|| {
+        let hint = if is_shallow(repo) {
+            "\nThe repository is a shallow clone, which may not contain the merge base. Fetch \
+             more history with `git fetch --deepen=<depth>` or `git fetch --unshallow`."
+        } else {
+            ""
+        };
+        format!("Could not determine the merge base of {first} and {second}{hint}")
+    })?;
+    Ok(commit.trim().to_owned())
+}
+
+/// Whether the path is in a shallow clone, which doesn't have the full history. Returns `false`
+/// if it's not in a git repository at all.
+pub fn is_shallow(repo: &Path) -> bool {
+    run(repo, &["rev-parse", "--is-shallow-repository"]).is_ok_and(|output| output.trim() == "true")
+}
+
+/// Whether the path is in a sparse checkout, where only some files are checked out. Returns
+/// `false` if it's not in a git repository at all.
+pub fn is_sparse_checkout(repo: &Path) -> bool {
+    run(repo, &["config", "--bool", "core.sparseCheckout"])
+        .is_ok_and(|output| output.trim() == "true")
+}
+
+/// The hash of the tree of the directory in the commit that's checked out, if it's in a git
+/// repository and has no changes compared to that commit, including untracked files.
+pub fn clean_tree(dir: &Path) -> Option<String> {
+    let status = run(dir, &["status", "--porcelain", "--", "."]).ok()?;
+    if !status.is_empty() {
+        return None;
+    }
+    Some(run(dir, &["rev-parse", "HEAD:./"]).ok()?.trim().to_owned())
+}
+
+/// A temporary checkout of a commit, removed again when dropped.
+pub struct Worktree {
+    repo: PathBuf,
+    dir: TempDir,
+}
+
+impl Worktree {
+    /// Checks out the given commit into a new temporary directory.
+    pub fn new(repo: &Path, commit: &str) -> anyhow::Result<Worktree> {
+        let dir = tempfile::Builder::new()
+            .prefix("nixpkgs-vet-worktree")
+            .tempdir()
+            .context("Failed to create a directory for a git worktree")?;
+        let dir_str = dir
+            .path()
+            .to_str()
+            .context("Non-UTF-8 temporary directory")?;
+        run(
+            repo,
+            &["worktree", "add", "--quiet", "--detach", dir_str, commit],
+        )
+        .with_context(|| format!("Could not check out commit {commit}"))?;
+        Ok(Worktree {
+            repo: repo.to_owned(),
+            dir,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        // Errors are ignored here, the worst that can happen is a stale worktree entry, which git
+        // cleans up by itself once the directory is gone.
+        if let Some(dir) = self.dir.path().to_str() {
+            let _ = run(&self.repo, &["worktree", "remove", "--force", dir]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::fs;
+
+    #[test]
+    fn finds_merge_base() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let repo = temp_dir.path();
+        let commit = |message: &str| -> anyhow::Result<String> {
+            fs::write(repo.join("file"), message)?;
+            run(repo, &["add", "file"])?;
+            run(
+                repo,
+                &[
+                    "-c",
+                    "user.name=test",
+                    "-c",
+                    "user.email=test@example.com",
+                    "commit",
+                    "--quiet",
+                    "--message",
+                    message,
+                ],
+            )?;
+            resolve(repo, "HEAD")
+        };
+
+        run(repo, &["init", "--quiet", "--initial-branch=master"])?;
+        let fork_point = commit("fork point")?;
+        run(repo, &["checkout", "--quiet", "-b", "feature"])?;
+        commit("feature")?;
+        run(repo, &["checkout", "--quiet", "master"])?;
+        commit("master")?;
+
+        assert_eq!(merge_base(repo, "master", "feature")?, fork_point);
+
+        let worktree = Worktree::new(repo, &fork_point)?;
+        assert_eq!(
+            fs::read_to_string(worktree.path().join("file"))?,
+            "fork point"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! Fetching the Nixpkgs trees of GitHub PRs via the GitHub API, see `nixpkgs-vet pr`.
+
+use std::env;
+use std::io::Write as _;
+use std::path::Path;
+use std::process;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::archive::Unpacked;
+use crate::status::Fatal;
+
+/// The commits of a PR that need to be compared.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PullRequest {
+    /// The latest commit of the PR branch.
+    pub head: String,
+    /// The commit of the base branch that the PR branch was forked from.
+    pub merge_base: String,
+}
+
+#[derive(Deserialize)]
+struct PullResponse {
+    head: CommitResponse,
+    base: CommitResponse,
+}
+
+#[derive(Deserialize)]
+struct CompareResponse {
+    merge_base_commit: CommitResponse,
+}
+
+#[derive(Deserialize)]
+struct CommitResponse {
+    sha: String,
+}
+
+/// A client for the GitHub API, using the `curl` binary.
+pub struct Client {
+    api_url: String,
+    repo: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// A client for the given repository, e.g. `NixOS/nixpkgs`.
+    ///
+    /// The API URL can be changed with the `GITHUB_API_URL` environment variable, which is also
+    /// set in GitHub Actions. The token for authentication is taken from `GITHUB_TOKEN` or
+    /// `GH_TOKEN` if set, which avoids low rate limits.
+    pub fn new(repo: &str) -> Client {
+        let api_url = env::var("GITHUB_API_URL").unwrap_or("https://api.github.com".to_owned());
+        let token = env::var("GITHUB_TOKEN")
+            .or_else(|_| env::var("GH_TOKEN"))
+            .ok();
+        Client {
+            api_url,
+            repo: repo.to_owned(),
+            token,
+        }
+    }
+
+    /// Requests an API endpoint of the repository, writing the response to the given file if any,
+    /// or returning it otherwise.
+    fn get(&self, endpoint: &str, output: Option<&Path>) -> anyhow::Result<String> {
+        let Self {
+            api_url,
+            repo,
+            token,
+        } = self;
+        let url = format!("{api_url}/repos/{repo}/{endpoint}");
+
+        let mut command = process::Command::new("curl");
+        command
+            .args(["--fail", "--silent", "--show-error", "--location"])
+            // The headers are passed on stdin to not leak the token in the process list
+            .args(["--header", "@-"])
+            .arg(&url);
+        if let Some(output) = output {
+            command.arg("--output").arg(output);
+        }
+
+        tracing::debug!(url, "Requesting the GitHub API");
+        let mut child = command
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped())
+            .spawn()
+            .with_context(|| {
+                Fatal::Misconfiguration("Failed to run curl, is it installed?".to_owned())
+            })?;
+        {
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            writeln!(stdin, "Accept: application/vnd.github+json")?;
+            if let Some(token) = token {
+                writeln!(stdin, "Authorization: Bearer {token}")?;
+            }
+        }
+
+        let result = child.wait_with_output()?;
+        if !result.status.success() {
+            anyhow::bail!(
+                "Request to {url} failed:\n{}",
+                String::from_utf8_lossy(&result.stderr)
+            );
+        }
+        String::from_utf8(result.stdout).with_context(|| format!("Response of {url} isn't UTF-8"))
+    }
+
+    /// Looks up the commits to compare for the PR with the given number.
+    pub fn pull_request(&self, number: u64) -> anyhow::Result<PullRequest> {
+        let pull: PullResponse = serde_json::from_str(&self.get(&format!("pulls/{number}"), None)?)
+            .with_context(|| format!("Could not parse the information of PR #{number}"))?;
+
+        let compare: CompareResponse = serde_json::from_str(&self.get(
+            &format!("compare/{}...{}", pull.base.sha, pull.head.sha),
+            None,
+        )?)
+        .with_context(|| format!("Could not parse the comparison for PR #{number}"))?;
+
+        Ok(PullRequest {
+            head: pull.head.sha,
+            merge_base: compare.merge_base_commit.sha,
+        })
+    }
+
+    /// Downloads and unpacks the tree of the given commit.
+    pub fn download_tree(&self, commit: &str) -> anyhow::Result<Unpacked> {
+        let download_dir = tempfile::Builder::new()
+            .prefix("nixpkgs-vet-download")
+            .tempdir()
+            .context("Failed to create a directory to download into")?;
+        let archive = download_dir.path().join("source.tar.gz");
+        self.get(&format!("tarball/{commit}"), Some(&archive))
+            .with_context(|| format!("Could not download commit {commit}"))?;
+        Unpacked::new(&archive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::fs;
+
+    #[test]
+    fn fetches_pull_request() -> anyhow::Result<()> {
+        // curl also supports local files, which emulate the API here
+        let temp_dir = testing::tempdir()?;
+        let api = temp_dir.path().join("api");
+        let repo = api.join("repos/NixOS/nixpkgs");
+        fs::create_dir_all(repo.join("pulls"))?;
+        fs::create_dir_all(repo.join("compare"))?;
+        fs::create_dir_all(repo.join("tarball"))?;
+        fs::write(
+            repo.join("pulls/123"),
+            r#"{ "head": { "sha": "head" }, "base": { "sha": "base" } }"#,
+        )?;
+        fs::write(
+            repo.join("compare/base...head"),
+            r#"{ "merge_base_commit": { "sha": "fork" } }"#,
+        )?;
+
+        let source = temp_dir.path().join("NixOS-nixpkgs-head");
+        fs::create_dir(&source)?;
+        fs::write(source.join("default.nix"), "{ }")?;
+        let status = process::Command::new("tar")
+            .arg("-czf")
+            .arg(repo.join("tarball/head"))
+            .arg("-C")
+            .arg(temp_dir.path())
+            .arg("NixOS-nixpkgs-head")
+            .status()?;
+        assert!(status.success());
+
+        let client = Client {
+            api_url: format!("file://{}", api.display()),
+            repo: "NixOS/nixpkgs".to_owned(),
+            token: None,
+        };
+        assert_eq!(
+            client.pull_request(123)?,
+            PullRequest {
+                head: "head".to_owned(),
+                merge_base: "fork".to_owned(),
+            }
+        );
+        let tree = client.download_tree("head")?;
+        assert_eq!(fs::read_to_string(tree.path().join("default.nix"))?, "{ }");
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+//! Installing git hooks that check changes before they're committed or pushed, see
+//! `nixpkgs-vet install-hooks`.
+//!
+//! The hooks are POSIX shell scripts. Git doesn't necessarily run them with the `PATH` of the
+//! user's shell, like from editors, so the hooks start with a shim that runs the nixpkgs-vet that
+//! installed them, and only fall back to the one on the `PATH` if it's gone.
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use indoc::formatdoc;
+
+use crate::git;
+use crate::status::Fatal;
+
+/// The line that marks hooks as installed by nixpkgs-vet, which can be replaced without `--force`.
+const MARKER: &str = "# Installed by `nixpkgs-vet install-hooks`";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Hook {
+    /// Checks the staged changes before each commit, without the slower Nix evaluation checks.
+    PreCommit,
+    /// Checks each pushed commit against the commit it was forked from of the base ref.
+    PrePush,
+}
+
+impl Hook {
+    pub const ALL: [Self; 2] = [Self::PreCommit, Self::PrePush];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::PreCommit => "pre-commit",
+            Self::PrePush => "pre-push",
+        }
+    }
+
+    /// The script of the hook, running nixpkgs-vet from the given path if it still exists.
+    fn script(self, executable: &str, base_ref: &str) -> String {
+        let shim = formatdoc! {r#"
+            nixpkgs_vet={}
+            [ -x "$nixpkgs_vet" ] || nixpkgs_vet=nixpkgs-vet
+        "#, shell_quote(executable)};
+        match self {
+            Self::PreCommit => formatdoc! {r#"
+                #!/bin/sh
+                {MARKER}
+                # Checks the changes to be committed, skip it with `git commit --no-verify`.
+                {shim}
+                # Nothing to compare the first commit to
+                git rev-parse --verify --quiet HEAD >/dev/null || exit 0
+                # A commit of the index that's not on any branch, such that changes that aren't
+                # staged aren't checked
+                staged=$(git commit-tree "$(git write-tree)" -p HEAD -m "Staged changes") || exit 1
+                git diff --cached --name-only --no-renames |
+                  "$nixpkgs_vet" --base-ref HEAD --head-ref "$staged" --files-from - \
+                    --only-new-problems --no-eval .
+            "#},
+            Self::PrePush => formatdoc! {r#"
+                #!/bin/sh
+                {MARKER}
+                # Checks the pushed commits against {base_ref}, skip it with `git push --no-verify`.
+                {shim}
+                while read -r local_ref local_sha remote_ref remote_sha; do
+                  case "$local_sha" in
+                  *[!0]*) ;;
+                  # Only zeros when deleting the remote ref
+                  *) continue ;;
+                  esac
+                  "$nixpkgs_vet" --base-ref {} --head-ref "$local_sha" --only-new-problems . \
+                    </dev/null || exit 1
+                done
+            "#, shell_quote(base_ref)},
+        }
+    }
+}
+
+/// Writes the hooks to the hooks directory of the git repository of the Nixpkgs, returning their
+/// paths. Hooks that weren't installed by nixpkgs-vet are only replaced with `force`.
+pub fn install(
+    nixpkgs_path: &Path,
+    hooks: &[Hook],
+    base_ref: &str,
+    force: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    // Respects `core.hooksPath`, and is relative to the repository unless it's configured
+    let hooks_dir = nixpkgs_path.join(
+        git::run(nixpkgs_path, &["rev-parse", "--git-path", "hooks"])
+            .context("Could not find the hooks directory of the git repository")?
+            .trim(),
+    );
+    fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Could not create {}", hooks_dir.display()))?;
+    let executable =
+        env::current_exe().context("Could not find the path of the nixpkgs-vet executable")?;
+    hooks
+        .iter()
+        .map(|hook| {
+            let path = hooks_dir.join(hook.name());
+            if !force
+                && let Ok(existing) = fs::read_to_string(&path)
+                && !existing.contains(MARKER)
+            {
+                anyhow::bail!(Fatal::Usage(format!(
+                    "There already is a {} hook at {}, replace it with `--force`",
+                    hook.name(),
+                    path.display()
+                )));
+            }
+            fs::write(&path, hook.script(&executable.to_string_lossy(), base_ref))
+                .and_then(|()| make_executable(&path))
+                .with_context(|| format!("Could not write {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+/// Quotes the text for a shell, such that it's a single word.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn installs_hooks() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let repo = temp_dir.path();
+        git::run(repo, &["init", "--quiet"])?;
+
+        let paths = install(repo, &Hook::ALL, "origin/master", false)?;
+        assert_eq!(
+            paths,
+            [
+                repo.join(".git/hooks/pre-commit"),
+                repo.join(".git/hooks/pre-push")
+            ]
+        );
+        let pre_push = fs::read_to_string(&paths[1])?;
+        assert!(pre_push.contains(r#""$nixpkgs_vet" --base-ref 'origin/master' --head-ref"#));
+        // The hooks run the nixpkgs-vet that installed them
+        let executable = env::current_exe()?;
+        assert!(pre_push.contains(&format!("nixpkgs_vet='{}'", executable.display())));
+        assert_eq!(fs::metadata(&paths[0])?.permissions().mode() & 0o777, 0o755);
+
+        // Hooks installed before are replaced, but others only with force
+        install(repo, &[Hook::PrePush], "it's", false)?;
+        assert!(fs::read_to_string(&paths[1])?.contains(r"'it'\''s'"));
+        fs::write(&paths[0], "#!/bin/sh\nmake lint\n")?;
+        assert!(install(repo, &[Hook::PreCommit], "master", false).is_err());
+        install(repo, &[Hook::PreCommit], "master", true)?;
+        assert!(fs::read_to_string(&paths[0])?.contains(MARKER));
+        Ok(())
+    }
+
+    #[test]
+    fn checks_staged_changes() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir(&repo)?;
+        git::run(&repo, &["init", "--quiet"])?;
+        let identity = [
+            ("GIT_AUTHOR_NAME", "test"),
+            ("GIT_AUTHOR_EMAIL", "test@example.com"),
+            ("GIT_COMMITTER_NAME", "test"),
+            ("GIT_COMMITTER_EMAIL", "test@example.com"),
+        ];
+        fs::write(repo.join("file"), "committed")?;
+        git::run(&repo, &["add", "file"])?;
+        let status = process::Command::new("git")
+            .args(["commit", "--quiet", "--message", "init"])
+            .current_dir(&repo)
+            .envs(identity)
+            .status()?;
+        assert!(status.success());
+        fs::write(repo.join("file"), "staged")?;
+        git::run(&repo, &["add", "file"])?;
+        fs::write(repo.join("file"), "not staged")?;
+
+        // Records how the hook runs nixpkgs-vet instead
+        let executable = temp_dir.path().join("nixpkgs-vet");
+        fs::write(
+            &executable,
+            "#!/bin/sh\ncat > \"$0.files\"\necho \"$@\" > \"$0.args\"\n",
+        )?;
+        make_executable(&executable)?;
+        let script = Hook::PreCommit.script(&executable.to_string_lossy(), "origin/master");
+        let status = process::Command::new("sh")
+            .args(["-c", &script])
+            .current_dir(&repo)
+            .envs(identity)
+            .status()?;
+        assert!(status.success());
+
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("nixpkgs-vet.files"))?,
+            "file\n"
+        );
+        let args = fs::read_to_string(temp_dir.path().join("nixpkgs-vet.args"))?;
+        let args = args.split_whitespace().collect::<Vec<_>>();
+        let [
+            "--base-ref",
+            "HEAD",
+            "--head-ref",
+            staged,
+            "--files-from",
+            "-",
+            "--only-new-problems",
+            "--no-eval",
+            ".",
+        ] = args[..]
+        else {
+            panic!("unexpected arguments {args:?}");
+        };
+        assert_eq!(
+            git::run(&repo, &["show", &format!("{staged}:file")])?,
+            "staged"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Graceful cancellation of the run when it's interrupted by `SIGINT` or `SIGTERM`, like by
+//! Ctrl-C or a CI timeout.
+//!
+//! Once interrupted, no new checks or Nix evaluations start, the running Nix processes are killed,
+//! and the problems found so far are printed, see `Status::Interrupted`. A second signal exits
+//! right away.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::process::{self, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::{error, fmt, io, thread};
+
+use crate::problem::Problem;
+use crate::status::EXIT_INTERRUPTED;
+use crate::validation::Validation;
+
+/// Whether a signal was received.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The writing end of the pipe the signal handler notifies the thread killing the children with,
+/// since there's little else it's allowed to do.
+static NOTIFY_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// The process IDs of the running Nix processes.
+static CHILDREN: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+thread_local! {
+    /// The problems found by the checks that completed on this thread, see `record`.
+    static FOUND: RefCell<Vec<Problem>> = const { RefCell::new(vec![]) };
+}
+
+/// The error of work that was stopped because the run was interrupted.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Interrupted")
+    }
+}
+
+impl error::Error for Interrupted {}
+
+/// Installs the handlers of `SIGINT` and `SIGTERM`. Needs to be called before any work starts.
+pub fn install() {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two file descriptors of the pipe.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        tracing::warn!(
+            "Could not create a pipe, interrupting won't be graceful: {}",
+            io::Error::last_os_error()
+        );
+        return;
+    }
+    let [read_fd, write_fd] = fds;
+    for fd in fds {
+        // SAFETY: The file descriptor was just created. Nix processes don't need to inherit it.
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+    NOTIFY_FD.store(write_fd, Ordering::SeqCst);
+
+    thread::spawn(move || {
+        let mut byte = 0u8;
+        // SAFETY: `byte` has room for the one byte read. This blocks until the first signal.
+        if unsafe { libc::read(read_fd, (&raw mut byte).cast(), 1) } == 1 {
+            tracing::warn!("Interrupted, stopping the checks and the running Nix evaluations");
+            kill_children();
+        }
+    });
+
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: `handle` only does what's async-signal-safe.
+        unsafe {
+            libc::signal(
+                signal,
+                handle as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            )
+        };
+    }
+}
+
+extern "C" fn handle(_signal: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        // SAFETY: `_exit` is async-signal-safe, unlike `process::exit`.
+        unsafe { libc::_exit(EXIT_INTERRUPTED.into()) };
+    }
+    let fd = NOTIFY_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        // SAFETY: Writing one byte from a valid buffer, which is async-signal-safe.
+        unsafe { libc::write(fd, [0u8].as_ptr().cast(), 1) };
+    }
+}
+
+/// Sends `SIGTERM` to all running Nix processes.
+fn kill_children() {
+    for &pid in CHILDREN.lock().expect("not poisoned").iter() {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            continue;
+        };
+        // SAFETY: Sending a signal has no memory safety requirements.
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+    }
+}
+
+/// Fails if the run was interrupted, such that no new work starts.
+pub fn check() -> anyhow::Result<()> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        Err(Interrupted.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether the error is because the run was interrupted.
+pub fn is_interrupted(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Interrupted>().is_some()
+}
+
+/// Like `Command::output`, but the process is killed if the run is interrupted.
+pub fn output(command: &mut process::Command) -> io::Result<process::Output> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().expect("not poisoned").insert(pid);
+    // The signal may have been handled between checking for it and registering the process
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        kill_children();
+    }
+    let output = child.wait_with_output();
+    CHILDREN.lock().expect("not poisoned").remove(&pid);
+    output
+}
+
+/// Records the problems and warnings of a check that completed on this thread, see `take_found`.
+pub fn record<T>(result: &Validation<T>) {
+    if let Validation::Failure(problems) | Validation::SuccessWithWarnings(_, problems) = result {
+        FOUND.with_borrow_mut(|found| found.extend(problems.iter().cloned()));
+    }
+}
+
+/// The problems recorded on this thread, resetting them for the next check of a Nixpkgs.
+pub fn take_found() -> Vec<Problem> {
+    FOUND.with_borrow_mut(std::mem::take)
+}
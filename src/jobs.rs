@@ -0,0 +1,112 @@
+//! Limits on how much work is done in parallel, see `--jobs`.
+
+use std::num::NonZeroUsize;
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+
+static JOBS: OnceLock<NonZeroUsize> = OnceLock::new();
+
+/// The number of jobs that can run without running out of file descriptors, see `fd_job_limit`.
+static FD_JOBS: OnceLock<NonZeroUsize> = OnceLock::new();
+
+/// File descriptors kept free for everything other than the jobs, like the standard streams, the
+/// Nix files being read and the threads checking the base and main Nixpkgs.
+const RESERVED_FDS: libc::rlim_t = 64;
+
+/// File descriptors a job needs at most, which is a Nix process with pipes for its standard
+/// streams, both ends of which are open while it starts.
+const FDS_PER_JOB: libc::rlim_t = 8;
+
+/// The highest soft limit to raise to, since some systems don't allow an infinite one even if
+/// it's the hard limit, like macOS.
+const MAX_FD_LIMIT: libc::rlim_t = 65536;
+
+/// The number of jobs currently running.
+static RUNNING: Mutex<usize> = Mutex::new(0);
+
+/// Notified whenever a job finishes.
+static FINISHED: Condvar = Condvar::new();
+
+/// Sets the number of jobs that may run at the same time. Needs to be called before any job
+/// starts, and only once.
+pub fn set(jobs: NonZeroUsize) {
+    JOBS.set(jobs).expect("the number of jobs is only set once");
+}
+
+/// The number of jobs that may run at the same time, by default the available parallelism.
+pub fn count() -> NonZeroUsize {
+    *JOBS.get_or_init(|| thread::available_parallelism().unwrap_or(NonZeroUsize::MIN))
+}
+
+/// The permission to run a job, e.g. a Nix evaluation, given back when dropped.
+pub struct Slot(());
+
+impl Slot {
+    /// Waits until fewer than `count()` jobs are running, and few enough that the jobs can't run
+    /// out of file descriptors.
+    pub fn acquire() -> Slot {
+        let limit = count().min(fd_job_limit());
+        let mut running = RUNNING.lock().expect("not poisoned");
+        while *running >= limit.get() {
+            running = FINISHED.wait(running).expect("not poisoned");
+        }
+        *running += 1;
+        Slot(())
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *RUNNING.lock().expect("not poisoned") -= 1;
+        FINISHED.notify_one();
+    }
+}
+
+/// The number of jobs that can run at the same time without exceeding the limit on open file
+/// descriptors, which would make them fail with "Too many open files" on very large trees.
+fn fd_job_limit() -> NonZeroUsize {
+    *FD_JOBS.get_or_init(|| {
+        let limit = raise_fd_limit();
+        let jobs = limit.saturating_sub(RESERVED_FDS) / FDS_PER_JOB;
+        let jobs = NonZeroUsize::new(usize::try_from(jobs).unwrap_or(usize::MAX))
+            .unwrap_or(NonZeroUsize::MIN);
+        if jobs < count() {
+            tracing::debug!(
+                limit,
+                jobs,
+                "Running fewer jobs because of the file descriptor limit"
+            );
+        }
+        jobs
+    })
+}
+
+/// Raises the soft limit on open file descriptors up to the hard limit, returning the new soft
+/// limit, or the old one if it couldn't be raised.
+fn raise_fd_limit() -> libc::rlim_t {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `rlimit` is a valid pointer to write the limits to.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlimit) } != 0 {
+        // Without knowing the limit, assume it's enough, like before
+        return libc::rlim_t::MAX;
+    }
+    let soft = rlimit.rlim_cur;
+    let target = rlimit.rlim_max.min(MAX_FD_LIMIT);
+    if soft >= target {
+        return soft;
+    }
+    let raised = libc::rlimit {
+        rlim_cur: target,
+        rlim_max: rlimit.rlim_max,
+    };
+    // SAFETY: `raised` is a valid pointer to read the limits from.
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+        tracing::debug!(from = soft, to = target, "Raised the file descriptor limit");
+        target
+    } else {
+        soft
+    }
+}
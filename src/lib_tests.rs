@@ -0,0 +1,85 @@
+//! Collects which library functions in `lib` are tested in `lib/tests`, see `--check lib-tests`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use relative_path::RelativePath;
+use rowan::ast::AstNode;
+
+use crate::files;
+use crate::location::Location;
+use crate::nix_file::NixFileStore;
+use crate::ratchet::{self, RatchetState};
+use crate::scope::Scope;
+use crate::structure::read_dir_sorted;
+
+/// The directory with the library, relative to Nixpkgs.
+pub const LIB_SUBPATH: &str = "lib";
+
+/// The directory with the tests of the library, relative to Nixpkgs.
+pub const LIB_TESTS_SUBPATH: &str = "lib/tests";
+
+/// Returns the ratchet value of each function defined in the top-level files of `lib`, like
+/// `strings.concatLines` for `concatLines` in `lib/strings.nix`.
+///
+/// A function counts as tested if its name is used anywhere in the Nix files of `lib/tests`, since
+/// tests can refer to it in many ways, like `lib.strings.concatLines`, or just `concatLines` with
+/// `with lib;`.
+pub fn check_lib_tests(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    scope: &Scope,
+) -> anyhow::Result<BTreeMap<String, ratchet::LibFunction>> {
+    let _span = tracing::info_span!("lib-tests").entered();
+    let lib_dir = nixpkgs_path.join(LIB_SUBPATH);
+    if !lib_dir.is_dir() {
+        return Ok(BTreeMap::new());
+    }
+
+    let mut tested = BTreeSet::new();
+    let tests_dir = nixpkgs_path.join(LIB_TESTS_SUBPATH);
+    if tests_dir.is_dir() {
+        let mut test_files = vec![];
+        files::collect_nix_files(&tests_dir, RelativePath::new(""), &mut test_files)?;
+        for test_file in test_files {
+            let nix_file = nix_file_store.get(&test_file.to_path(&tests_dir))?;
+            tested.extend(
+                nix_file
+                    .syntax_root
+                    .syntax()
+                    .descendants()
+                    .filter_map(rnix::ast::Ident::cast)
+                    .map(|ident| ident.syntax().text().to_string()),
+            );
+        }
+    }
+
+    let mut functions = BTreeMap::new();
+    for entry in read_dir_sorted(&lib_dir)? {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(set_name) = file_name.strip_suffix(".nix") else {
+            continue;
+        };
+        let relative_file = RelativePath::new(LIB_SUBPATH).join(&file_name);
+        // `default.nix` only combines the other files
+        if set_name == "default"
+            || !entry.file_type()?.is_file()
+            || !scope.contains_path(&relative_file)
+        {
+            continue;
+        }
+        let nix_file = nix_file_store.get(&entry.path())?;
+        for (name, line, column) in nix_file.top_level_attributes() {
+            let tested = if tested.contains(&name) {
+                RatchetState::Tight
+            } else {
+                RatchetState::Loose(Location::new(relative_file.clone(), line, column))
+            };
+            functions.insert(
+                format!("{set_name}.{name}"),
+                ratchet::LibFunction { tested },
+            );
+        }
+    }
+    Ok(functions)
+}
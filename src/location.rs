@@ -1,7 +1,8 @@
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
 /// A location that's suitable for error messages.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Location {
     pub file: RelativePathBuf,
     pub line: usize,
@@ -18,6 +19,39 @@ impl Location {
     }
 }
 
+/// The file or directory a problem is about, optionally pointing to a position within it.
+///
+/// Unlike [`Location`], this is not meant for error messages, but for tools processing problems.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProblemLocation {
+    /// The path relative to Nixpkgs.
+    pub path: RelativePathBuf,
+    /// The 1-indexed line within the file, if the problem is about a specific one.
+    pub line: Option<usize>,
+    /// The 1-indexed column within the line, if known.
+    pub column: Option<usize>,
+}
+
+impl ProblemLocation {
+    pub fn new(path: impl Into<RelativePathBuf>, line: Option<usize>) -> Self {
+        Self {
+            path: path.into(),
+            line,
+            column: None,
+        }
+    }
+}
+
+impl From<&Location> for ProblemLocation {
+    fn from(location: &Location) -> Self {
+        Self {
+            path: location.file.clone(),
+            line: Some(location.line),
+            column: Some(location.column),
+        }
+    }
+}
+
 /// A simple utility for calculating the line for a string offset.
 ///
 /// This doesn't do any Unicode handling, though that probably doesn't matter because newlines
@@ -49,6 +83,15 @@ impl LineIndex {
         }
     }
 
+    /// Returns the 1-indexed column for a string index.
+    pub fn column(&self, index: usize) -> usize {
+        match self.line(index) {
+            1 => index + 1,
+            // The index of the position after the newline ending the previous line is column 1
+            line => index - self.newlines[line - 2],
+        }
+    }
+
     /// Returns the string index for a line and column.
     pub fn fromlinecolumn(&self, line: usize, column: usize) -> usize {
         // If it's the 1th line, the column is the index
@@ -88,6 +131,7 @@ mod tests {
 
         for (index, line, column) in pairs {
             assert_eq!(line_index.line(index), line);
+            assert_eq!(line_index.column(index), column);
             assert_eq!(line_index.fromlinecolumn(line, column), index);
         }
     }
@@ -9,29 +9,89 @@
 // #![allow(clippy::use_self)]
 // #![allow(clippy::missing_const_for_fn)]
 
+mod aliases;
+mod all_packages;
+mod archive;
+mod blame;
+mod cache;
+mod catalog;
+mod checkpoint;
+mod config;
+mod cross;
+mod delta;
+mod diff;
+mod docs;
 mod eval;
 mod files;
+mod fix;
+mod git;
+mod github;
+mod hooks;
+mod interrupt;
+mod jobs;
+mod lib_tests;
 mod location;
+mod maintainers;
+mod merge;
+mod meta_gating;
+mod metrics;
+mod migrate;
 mod nix_file;
+mod nixos_modules;
+mod no_aliases;
+mod output;
 mod problem;
+mod progress;
 mod ratchet;
 mod references;
+mod release;
+mod report;
+mod scaffold;
+mod scope;
+mod serve;
+mod stats;
 mod status;
+mod store;
 mod structure;
+mod suppressions;
+mod symlinks;
+#[cfg(test)]
+mod testing;
+mod timeout;
+mod triage;
+mod update_scripts;
 mod validation;
 
 use anyhow::Context as _;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::collections::BTreeMap;
+use std::io::{self, Write as _};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use std::{panic, thread};
+use std::{fs, panic, thread};
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::{
+    Layer as _, filter, layer::SubscriberExt as _, util::SubscriberInitExt as _,
+};
 
+use crate::blame::Blame;
+use crate::catalog::Catalog;
+use crate::config::{Check, Config, Profile};
+use crate::diff::Diff;
 use crate::nix_file::NixFileStore;
-use crate::status::{ColoredStatus, Status};
+use crate::output::{ColorChoice, CsvColumn, Format, GroupBy, OutputStyle, Report, snippet};
+use crate::problem::{Problem, npv_112};
+use crate::progress::Progress;
+use crate::report::{Breadcrumbs, ErrorReport};
+use crate::scope::Scope;
+use crate::status::{ColoredStatus, Fatal, Status};
 use crate::structure::check_structure;
+use crate::suppressions::Suppressions;
 use crate::validation::Validation::Failure;
 use crate::validation::Validation::Success;
+use crate::validation::Validation::SuccessWithWarnings;
 
 /// Program to check the validity of pkgs/by-name
 ///
@@ -41,28 +101,697 @@ use crate::validation::Validation::Success;
 /// Exit code:
 /// - `0`: If the validation is successful
 /// - `1`: If the validation is not successful
-/// - `2`: If an unexpected I/O error occurs
+/// - `2`: If the command line arguments are invalid
+/// - `3`: If an unexpected error occurs, e.g. an I/O error
+/// - `4`: If nixpkgs-vet is set up incorrectly, e.g. `git` or `nix-instantiate` can't be run
+/// - `130`: If interrupted, e.g. with Ctrl-C
 ///
 /// Standard error:
 /// - Informative messages
 /// - Detected problems if validation is not successful
 #[derive(Parser, Debug)]
-#[command(about, version, verbatim_doc_comment)]
+#[command(
+    about,
+    version,
+    verbatim_doc_comment,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the main Nixpkgs to check. For PRs, set this to a checkout of the PR branch.
-    nixpkgs: PathBuf,
+    /// This can also be a store path or a source archive like a channel's `nixexprs.tar.xz`.
+    #[arg(required = true)]
+    nixpkgs: Option<PathBuf>,
 
     /// Path to the base Nixpkgs to run ratchet checks against.
     /// For PRs, set this to a checkout of the PRs base branch.
+    /// Like the main Nixpkgs, this can also be a store path or a source archive.
+    /// If omitted, `--diff` is applied in reverse to a copy of the main Nixpkgs to get the base.
+    #[arg(long, required_unless_present_any = ["diff", "base_ref"], conflicts_with = "base_ref")]
+    base: Option<PathBuf>,
+
+    /// Git ref in the main Nixpkgs repository to run ratchet checks against, e.g. `master`.
+    /// The base Nixpkgs is then the merge base of this ref and the checked ref, which is the
+    /// commit a feature branch was forked from.
+    #[arg(long, value_name = "REF")]
+    base_ref: Option<String>,
+
+    /// Git ref in the main Nixpkgs repository to check. Defaults to checking the working tree.
+    #[arg(long, value_name = "REF", requires = "base_ref")]
+    head_ref: Option<String>,
+
+    /// Path to a unified diff (e.g. from `git diff`) between the base and the main Nixpkgs, or `-`
+    /// to read it from stdin. Only the packages and attributes affected by it are checked.
+    #[arg(long, value_name = "PATCH")]
+    diff: Option<PathBuf>,
+
+    /// Path to a newline-separated list of changed files relative to Nixpkgs, or `-` to read it
+    /// from stdin. Only the packages owning these files and the attributes defined in them are
+    /// checked.
+    #[arg(long, value_name = "FILE", conflicts_with = "diff")]
+    files_from: Option<PathBuf>,
+
+    /// Annotate each problem with the commit and author that last changed its location.
+    /// Requires the main Nixpkgs to be a git repository.
     #[arg(long)]
-    base: PathBuf,
+    blame: bool,
+
+    /// File with problems to not report, see below. With `--interactive`, suppressed problems
+    /// are added to it.
+    ///
+    /// Each line contains a problem code like `NPV-123`, optionally followed by the path the
+    /// problem is about, relative to Nixpkgs. Lines starting with `#` are ignored.
+    #[arg(long, value_name = "FILE", global = true)]
+    suppressions: Option<PathBuf>,
+
+    /// Step through the problems one by one, to open the file of each in `$VISUAL` or `$EDITOR`,
+    /// to apply its fix if there is one, or to suppress or skip it. With `--fix`, step through the
+    /// fixes instead, showing the diff of each to apply it, to skip it, or to edit the diff first.
+    #[arg(long, global = true)]
+    interactive: bool,
+
+    /// Apply the fixes of all problems that have one, like moving a package to its correct shard,
+    /// and only report the problems that remain. Files tracked by git are moved with `git mv`.
+    #[arg(long, global = true)]
+    fix: bool,
+
+    /// With `--fix`, print the changes of the fixes as git-style diffs instead of making them,
+    /// with moved files as renames, to review them or to apply them later with `git apply`.
+    #[arg(long, global = true, requires = "fix", conflicts_with = "interactive")]
+    dry_run: bool,
+
+    /// Write the fixes of all problems that have one as JSON to the file, without making them,
+    /// with the git-style diff of each and a combined one of all, for bots to apply them.
+    #[arg(long, value_name = "FILE", global = true)]
+    emit_fixes: Option<PathBuf>,
+
+    /// Path to a TOML file configuring the checks, e.g. for Nixpkgs forks with other conventions.
+    /// Without it, the conventions of Nixpkgs are enforced.
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+
+    /// Skip the checks that need to evaluate Nixpkgs with Nix, e.g. if Nix isn't available. Only
+    /// the structure and the Nix files are checked then. Overrides `eval` of the configuration.
+    #[arg(long, global = true)]
+    no_eval: bool,
+
+    /// The systems to evaluate Nixpkgs for, comma-separated. Problems that only occur on the
+    /// systems after the first are reported in addition to the ones of the first, with the system
+    /// they occur on. Overrides `systems` of the configuration.
+    #[arg(long, value_delimiter = ',', value_name = "SYSTEM", global = true)]
+    systems: Option<Vec<String>>,
+
+    /// If the base Nixpkgs has problems too, only report the problems it doesn't have, and list
+    /// the ones that are fixed separately. Overrides `only-new-problems` of the configuration.
+    #[arg(long, global = true)]
+    only_new_problems: bool,
+
+    /// The kind of repository to check. With `overlay`, a third-party repository with its own
+    /// `pkgs/by-name` but without `pkgs/top-level` can be checked, see the README. Overrides
+    /// `profile` of the configuration.
+    #[arg(long, value_enum, value_name = "PROFILE", global = true)]
+    profile: Option<Profile>,
+
+    /// Also run a check that isn't run by default. Can be repeated to run multiple ones.
+    #[arg(long, value_enum, value_name = "CHECK", global = true)]
+    check: Vec<Check>,
+
+    /// Also write the report of an internal error, which is printed after it, as JSON to this
+    /// file, or `-` for standard output. Attaching it to a bug report helps to fix the bug.
+    #[arg(long, value_name = "FILE", global = true)]
+    error_report: Option<PathBuf>,
+
+    /// Store the results of the Nix evaluations in this file as they complete, and use the ones
+    /// already stored in it, such that an interrupted run can resume instead of starting from
+    /// scratch. Only used for Nixpkgs in a git checkout without changes or in the Nix store.
+    #[arg(long, value_name = "STATE", global = true)]
+    resume: Option<PathBuf>,
+
+    /// Share the results of the Nix evaluations with other runs through this cache, a directory
+    /// or the base URL of an HTTP object store depending on `--cache-backend`, such that
+    /// ephemeral CI runners don't evaluate the same Nixpkgs again. Like with `--resume`, only
+    /// used for Nixpkgs in a git checkout without changes or in the Nix store.
+    #[arg(long, value_name = "DIR|URL", global = true)]
+    cache: Option<String>,
+
+    /// What kind of cache `--cache` is.
+    #[arg(long, value_enum, default_value_t, requires = "cache", global = true)]
+    cache_backend: cache::CacheBackend,
+
+    /// Also write a report of the results of the given kind to the given path, or `-` for
+    /// standard output. Can be repeated to write multiple reports. With `html`, the report is a
+    /// page to review the problems in a browser, and with `badge` the JSON of a shields.io badge.
+    #[arg(long, num_args = 2, value_names = ["KIND", "PATH"], global = true)]
+    report: Vec<String>,
+
+    /// Write metrics about the run to this file in the textfile format of Prometheus' node
+    /// exporter: the number of problems found by each check, whether the checks passed, how long
+    /// the run took, and the number of packages in `pkgs/by-name`.
+    #[arg(long, value_name = "PATH", global = true)]
+    metrics_out: Option<PathBuf>,
+
+    /// Append a Markdown summary of the run to the file in `$GITHUB_STEP_SUMMARY`, which GitHub
+    /// Actions shows on the page of the workflow run: the status, the number of problems of each
+    /// check, the first problems, and how long the checks took. Does nothing outside of GitHub
+    /// Actions.
+    #[arg(long, global = true)]
+    github_step_summary: bool,
+
+    /// The format to output the results in.
+    #[arg(long, value_enum, default_value_t, global = true)]
+    format: Format,
+
+    /// The columns of `--format csv`, separated by commas.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = CsvColumn::ALL,
+        global = true
+    )]
+    csv_columns: Vec<CsvColumn>,
+
+    /// How detailed the problems are in the human-readable output.
+    #[arg(long, value_enum, default_value_t, global = true)]
+    output_style: OutputStyle,
+
+    /// Group the problems in the human-readable output, with a header and count for each group.
+    #[arg(long, value_enum, value_name = "BY", global = true)]
+    group_by: Option<GroupBy>,
+
+    /// Log more details about what's happening, e.g. which files are read and which commands are
+    /// run. Can be repeated for even more details.
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log errors, and don't show progress.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log how long each check and evaluation takes, without the other details of `--verbose`.
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// How many jobs to run in parallel, both threads and Nix evaluations. Defaults to the
+    /// available parallelism. Fewer Nix evaluations run in parallel if the limit on open file
+    /// descriptors is too low for them, after raising it as far as permitted.
+    #[arg(short, long, global = true)]
+    jobs: Option<NonZeroUsize>,
+
+    /// The language of the problem messages, for which a message catalog needs to be installed.
+    /// Problem codes are the same in all languages.
+    #[arg(long, default_value = catalog::BUILTIN_LANG, global = true)]
+    lang: String,
+
+    /// When to use colors in the human-readable output.
+    #[arg(long, value_enum, default_value_t, global = true, value_name = "WHEN")]
+    color: ColorChoice,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check a GitHub PR against the commit its branch was forked from, downloading both via the
+    /// GitHub API. A token can be passed with the `GITHUB_TOKEN` environment variable.
+    Pr {
+        /// The number of the PR.
+        number: u64,
+
+        /// The GitHub repository of the PR.
+        #[arg(long, default_value = "NixOS/nixpkgs")]
+        repo: String,
+    },
+    /// Print how many packages are in `pkgs/by-name`, per shard, how many are still only defined
+    /// in `pkgs/top-level/all-packages.nix`, and how many have custom `callPackage` arguments.
+    Stats {
+        /// Path to the Nixpkgs to count the packages of.
+        nixpkgs: PathBuf,
+
+        /// Path to the base Nixpkgs to compare the migration progress with, like the one a PR is
+        /// based on.
+        #[arg(long)]
+        base: Option<PathBuf>,
+
+        /// Print the statistics as JSON instead.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Merge the reports that runs wrote with `--report json`, like the ones of sharded CI jobs,
+    /// into one without duplicate problems, exiting with the highest exit code of the runs.
+    MergeReports {
+        /// Paths to the reports to merge.
+        #[arg(required = true)]
+        reports: Vec<PathBuf>,
+
+        /// Print the merged report as JSON instead, which can be merged again.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create the directory of a new package in its shard of `pkgs/by-name`, with a `package.nix`
+    /// following the conventions of Nixpkgs to start from.
+    New {
+        /// The name of the package, which is also its attribute name and `pname`.
+        name: String,
+
+        /// Path to the Nixpkgs to create the package in.
+        #[arg(long, default_value = ".")]
+        nixpkgs: PathBuf,
+    },
+    /// Rewrite `mkDerivation rec { }` in the `package.nix` of packages in `pkgs/by-name` to
+    /// `mkDerivation (finalAttrs: { })`, keeping comments and formatting. No file changes if one
+    /// of the packages can't be migrated.
+    Migrate {
+        /// The names of the packages to migrate.
+        #[arg(required = true)]
+        packages: Vec<String>,
+
+        /// Path to the Nixpkgs the packages are in.
+        #[arg(long, default_value = ".")]
+        nixpkgs: PathBuf,
+    },
+    /// Install git hooks that check the staged changes before each commit, without the Nix
+    /// evaluation checks, and the pushed commits before each push. Only the problems the changes
+    /// introduce fail the hooks.
+    InstallHooks {
+        /// Path to the git checkout of Nixpkgs to install the hooks in.
+        #[arg(long, default_value = ".")]
+        nixpkgs: PathBuf,
+
+        /// The hooks to install.
+        #[arg(long, value_enum, value_delimiter = ',', default_values_t = hooks::Hook::ALL)]
+        hooks: Vec<hooks::Hook>,
+
+        /// The ref the pre-push hook checks the pushed commits against, from the commit they
+        /// were forked from of it.
+        #[arg(long, value_name = "REF", default_value = "origin/master")]
+        base_ref: String,
+
+        /// Replace existing hooks that weren't installed by nixpkgs-vet.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Serve an HTTP API to run checks and query their results as JSON: `POST /checks` with a
+    /// body like `{ "nixpkgs": "/path", "base_ref": "master" }` starts a check and responds with
+    /// its `id`, `GET /checks/<id>` responds with its state and its report once it's done, and
+    /// `GET /checks` with all checks. The results are only kept in memory.
+    Serve {
+        /// The address to listen on. There's no authentication, so only make it reachable by
+        /// trusted clients.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
 }
 
 fn main() -> ExitCode {
+    metrics::start();
+    report::install_panic_hook();
+    interrupt::install();
     let args = Args::parse();
-    let status: ColoredStatus = process(args.base, &args.nixpkgs).into();
-    eprintln!("{status}");
-    status.into()
+    args.color.apply();
+    init_logging(&args);
+    if let Some(jobs) = args.jobs {
+        jobs::set(jobs);
+    }
+    if let Some(command) = &args.command
+        && let Some(result) = run_command(command, &args)
+    {
+        return match result {
+            Ok(exit_code) => ExitCode::from(exit_code),
+            Err(error) => output::output(
+                args.format,
+                Status::from(error).into(),
+                None,
+                &args.csv_columns,
+            ),
+        };
+    }
+    let (status, diff) = match load_catalog(&args.lang)
+        .and_then(|()| args.diff.as_deref().map(Diff::read).transpose())
+    {
+        Ok(diff) => (run(&args, diff.as_ref()), diff),
+        Err(error) => (Err(error), None),
+    };
+    let status = status.unwrap_or_else(|error| Status::from(error).into());
+    let report = match status.status() {
+        Status::Error(error) => ErrorReport::new(error),
+        _ => None,
+    };
+    let exit_code = output::output(args.format, status, diff.as_ref(), &args.csv_columns);
+    if let Some(report) = report {
+        write_error_report(args.error_report.as_deref(), &report);
+    }
+    exit_code
+}
+
+/// Runs a subcommand that doesn't check Nixpkgs, returning its exit code, or `None` for `pr`, which
+/// checks Nixpkgs like without a subcommand, see `run`.
+fn run_command(command: &Command, args: &Args) -> Option<anyhow::Result<u8>> {
+    let result = match command {
+        Command::Pr { .. } => return None,
+        Command::Stats {
+            nixpkgs,
+            base,
+            json,
+        } => print_stats(nixpkgs, base.as_deref(), *json).map(|()| status::EXIT_SUCCESS),
+        Command::MergeReports { reports, json } => print_merged_reports(reports, *json),
+        Command::Migrate { packages, nixpkgs } => {
+            migrate::final_attrs(nixpkgs, packages).map(|paths| {
+                for path in paths {
+                    eprintln!("Migrated {path}");
+                }
+                status::EXIT_SUCCESS
+            })
+        }
+        Command::New { name, nixpkgs } => Config::read(args.config.as_deref())
+            .and_then(|config| scaffold::new_package(nixpkgs, name, &config))
+            .map(|path| {
+                eprintln!("Created {path}");
+                status::EXIT_SUCCESS
+            }),
+        Command::Serve { listen } => {
+            serve::serve(listen, args.config.as_deref()).map(|()| status::EXIT_SUCCESS)
+        }
+        Command::InstallHooks {
+            nixpkgs,
+            hooks,
+            base_ref,
+            force,
+        } => hooks::install(nixpkgs, hooks, base_ref, *force).map(|paths| {
+            for path in paths {
+                eprintln!("Installed {}", path.display());
+            }
+            status::EXIT_SUCCESS
+        }),
+    };
+    Some(result)
+}
+
+/// Prints the report of an internal error after the error, and writes it as JSON to the file
+/// given by `--error-report`, if any.
+fn write_error_report(path: Option<&Path>, report: &ErrorReport) {
+    eprintln!("{report}");
+    let Some(path) = path else {
+        return;
+    };
+    let json = serde_json::to_string_pretty(report).expect("reports can be serialised");
+    let result = if path == Path::new("-") {
+        writeln!(io::stdout(), "{json}")
+    } else {
+        fs::write(path, json)
+    };
+    if let Err(error) = result {
+        eprintln!(
+            "Could not write the error report to {}: {error}",
+            path.display()
+        );
+    }
+}
+
+/// Prints the statistics of the Nixpkgs to standard output, see `nixpkgs-vet stats`.
+fn print_stats(nixpkgs_path: &Path, base: Option<&Path>, json: bool) -> anyhow::Result<()> {
+    let mut stats = stats::Stats::collect(nixpkgs_path)?;
+    if let Some(base) = base {
+        stats = stats.compare(&stats::Stats::collect(base)?);
+    }
+    if json {
+        writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&stats)?)?;
+    } else {
+        writeln!(io::stdout(), "{stats}")?;
+    }
+    Ok(())
+}
+
+/// Prints the merged reports to standard output, returning the exit code of the merged report,
+/// see `nixpkgs-vet merge-reports`.
+fn print_merged_reports(paths: &[PathBuf], json: bool) -> anyhow::Result<u8> {
+    let report = merge::merge_reports(paths)?;
+    if json {
+        writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&report)?)?;
+    } else {
+        writeln!(io::stdout(), "{report}")?;
+    }
+    Ok(report.exit_code)
+}
+
+/// Uses the message catalog of the language, unless it's the built-in one.
+fn load_catalog(lang: &str) -> anyhow::Result<()> {
+    if lang != catalog::BUILTIN_LANG {
+        catalog::set(Catalog::read(lang)?);
+    }
+    Ok(())
+}
+
+/// Sets up logging to standard error with the verbosity given by the command line arguments.
+fn init_logging(args: &Args) {
+    let level = match (args.quiet, args.verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        // How long each step takes
+        (false, 1) => Level::INFO,
+        // Which files are read and which commands are run
+        (false, 2) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+    if args.quiet || args.verbose > 0 || args.timings {
+        // Progress bars would be interleaved with the log lines
+        progress::disable();
+    }
+    // The spans are logged with how long they took when they close
+    let timings = args.timings;
+    let filter = filter::filter_fn(move |metadata| {
+        metadata.level() <= &level
+            || (timings && metadata.is_span() && metadata.level() <= &Level::INFO)
+    });
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_ansi(colored::control::SHOULD_COLORIZE.should_colorize())
+                .with_span_events(FmtSpan::CLOSE)
+                .with_target(false)
+                .with_filter(filter),
+        )
+        .init();
+}
+
+/// Prepares the inputs given by the command line arguments before calling `process`.
+fn run(args: &Args, diff: Option<&Diff>) -> anyhow::Result<ColoredStatus> {
+    let mut config = Config::read(args.config.as_deref())?;
+    let reports = Report::parse_all(&args.report)?;
+    config.checks.extend(args.check.iter().copied());
+    if let Some(profile) = args.profile {
+        config.profile = profile;
+    }
+    if args.no_eval {
+        config.eval = false;
+    }
+    if args.only_new_problems {
+        config.only_new_problems = true;
+    }
+    if let Some(systems) = &args.systems {
+        config.systems.clone_from(systems);
+    }
+    if config.systems.is_empty() {
+        anyhow::bail!(Fatal::Usage(
+            "At least one system to evaluate Nixpkgs for is needed".to_owned()
+        ));
+    }
+    if config.eval {
+        eval::check_nix_version()?;
+    }
+    if let Some(state) = &args.resume {
+        checkpoint::init(state)?;
+    }
+    if let Some(location) = &args.cache {
+        cache::init(args.cache_backend, location)?;
+    }
+    if let Some(Command::Pr { number, repo }) = &args.command {
+        return run_pr(args, &config, &reports, *number, repo);
+    }
+    let nixpkgs = args
+        .nixpkgs
+        .as_deref()
+        .expect("clap requires NIXPKGS without a subcommand");
+
+    // These must stay alive until the checks are done, since dropping them removes the directories.
+    let unpacked_main;
+    let unpacked_base;
+    let reconstructed_base;
+    let base_worktree;
+    let head_worktree;
+
+    let main_nixpkgs = match &args.head_ref {
+        Some(head_ref) => {
+            let commit = git::resolve(nixpkgs, head_ref)?;
+            head_worktree = git::Worktree::new(nixpkgs, &commit)?;
+            head_worktree.path().to_owned()
+        }
+        None if archive::is_archive(nixpkgs) => {
+            unpacked_main = archive::Unpacked::new(nixpkgs)?;
+            unpacked_main.path().to_owned()
+        }
+        None => nixpkgs.to_owned(),
+    };
+
+    let base_nixpkgs = match (&args.base, &args.base_ref, diff) {
+        (Some(base), _, _) if archive::is_archive(base) => {
+            unpacked_base = archive::Unpacked::new(base)?;
+            unpacked_base.path().to_owned()
+        }
+        (Some(base), _, _) => base.clone(),
+        (None, Some(base_ref), _) => {
+            let head_ref = args.head_ref.as_deref().unwrap_or("HEAD");
+            let merge_base = git::merge_base(nixpkgs, base_ref, head_ref)?;
+            base_worktree = git::Worktree::new(nixpkgs, &merge_base)?;
+            base_worktree.path().to_owned()
+        }
+        (None, None, Some(diff)) => {
+            reconstructed_base = tempfile::Builder::new()
+                .prefix("nixpkgs-vet-base")
+                .tempdir()
+                .context("Failed to create a directory for the base Nixpkgs")?;
+            diff.reconstruct_base(&main_nixpkgs, reconstructed_base.path())?;
+            reconstructed_base.path().to_owned()
+        }
+        (None, None, None) => {
+            unreachable!("clap requires --base if neither --base-ref nor --diff are given")
+        }
+    };
+
+    let scopes = match (&args.files_from, diff) {
+        (Some(files_from), _) => {
+            let scope = Scope::read_files(files_from)?;
+            (scope.clone(), scope)
+        }
+        (None, Some(diff)) => diff.scopes(),
+        (None, None) => Default::default(),
+    };
+    let status = process(base_nixpkgs, &main_nixpkgs, scopes, &config);
+    annotate(args, &config, &reports, status, &main_nixpkgs)
+}
+
+/// Checks a GitHub PR, see `nixpkgs-vet pr`.
+fn run_pr(
+    args: &Args,
+    config: &Config,
+    reports: &[Report],
+    number: u64,
+    repo: &str,
+) -> anyhow::Result<ColoredStatus> {
+    let client = github::Client::new(repo);
+    let pull_request = client.pull_request(number)?;
+    let head = client.download_tree(&pull_request.head)?;
+    let base = client.download_tree(&pull_request.merge_base)?;
+    let status = process(
+        base.path().to_owned(),
+        head.path(),
+        Default::default(),
+        config,
+    );
+    annotate(args, config, reports, status, head.path())
+}
+
+/// Adds what the command line arguments ask for to the status, after dropping suppressed problems
+/// and the ones allowed by the `overrides` of the configuration, and writes the reports. This
+/// needs to happen while the checked Nixpkgs still exists.
+fn annotate(
+    args: &Args,
+    config: &Config,
+    reports: &[Report],
+    status: Status,
+    nixpkgs_path: &Path,
+) -> anyhow::Result<ColoredStatus> {
+    let mut suppressions = args
+        .suppressions
+        .as_deref()
+        .map(Suppressions::read)
+        .transpose()?;
+    let status = status.retain(|problem| !config.allows(problem));
+    let mut status = match &suppressions {
+        Some(suppressions) => status.retain(|problem| !suppressions.contains(problem)),
+        None => status,
+    };
+    if let Some(path) = &args.emit_fixes {
+        fix::FixExport::new(&status, nixpkgs_path, config.nixfmt.as_deref())?.write(path)?;
+    }
+    if args.fix && !args.dry_run {
+        store::ensure_writable(nixpkgs_path)?;
+    }
+    if args.fix && args.interactive {
+        status = triage::review_fixes(status, nixpkgs_path, config.nixfmt.as_deref())?;
+    } else if args.fix {
+        status = fix::apply_all(status, nixpkgs_path, args.dry_run, config.nixfmt.as_deref())?;
+    } else if args.interactive {
+        status = triage::triage(
+            status,
+            nixpkgs_path,
+            suppressions.as_mut(),
+            config.nixfmt.as_deref(),
+        )?;
+    }
+    for report in reports {
+        report.write(&status, nixpkgs_path)?;
+    }
+    if args.github_step_summary {
+        output::step_summary::append(&status)?;
+    }
+    if let Some(path) = &args.metrics_out {
+        metrics::write(path, &status, nixpkgs_path)?;
+    }
+
+    let mut status = ColoredStatus::from(status)
+        .with_style(args.output_style)
+        .with_grouping(args.group_by);
+
+    let retried = eval::retried_count();
+    if retried > 0 {
+        status = status.with_remark(format!(
+            "Note: {retried} Nix evaluation(s) failed in a way that looked transient and were \
+            retried, see `eval-retries` in the configuration."
+        ));
+    }
+
+    if args.output_style == OutputStyle::Full || args.format == Format::Diagnostic {
+        let snippets = status
+            .status()
+            .errors()
+            .into_iter()
+            .flatten()
+            .map(|problem| snippet::render(nixpkgs_path, &problem.location()?))
+            .collect();
+        status = status.with_snippets(snippets);
+    }
+
+    if args.blame {
+        let notes = blame_notes(nixpkgs_path, status.status().errors())?;
+        status = status.with_notes(notes);
+    }
+    Ok(status)
+}
+
+/// Blames the location of each problem, returning a note for each.
+fn blame_notes(
+    nixpkgs_path: &Path,
+    problems: Option<&Vec<Problem>>,
+) -> anyhow::Result<Vec<Option<String>>> {
+    git::run(nixpkgs_path, &["rev-parse", "--is-inside-work-tree"]).with_context(|| {
+        Fatal::Usage("--blame requires the Nixpkgs to check to be in a git repository".to_owned())
+    })?;
+
+    Ok(problems
+        .into_iter()
+        .flatten()
+        .map(|problem| {
+            let location = problem.location()?;
+            Some(match Blame::find(nixpkgs_path, &location) {
+                Ok(Some(blame)) => blame.to_string(),
+                Ok(None) => "Not committed yet".to_owned(),
+                Err(error) => format!("Could not determine the last change: {error:#}"),
+            })
+        })
+        .collect())
 }
 
 /// Does the actual work. This is the abstraction used both by `main` and the tests.
@@ -70,35 +799,116 @@ fn main() -> ExitCode {
 /// # Arguments
 /// - `base_nixpkgs`: Path to the base Nixpkgs to run ratchet checks against.
 /// - `main_nixpkgs`: Path to the main Nixpkgs to check.
-fn process(base_nixpkgs: PathBuf, main_nixpkgs: &Path) -> Status {
-    // Very easy to parallelise this, since both operations are totally independent of each other.
-    let base_thread = thread::spawn(move || check_nixpkgs(&base_nixpkgs));
-    let main_result = match check_nixpkgs(main_nixpkgs) {
-        Ok(result) => result,
-        Err(error) => {
-            return error.into();
-        }
+/// - `(base_scope, main_scope)`: What to restrict the checks of each Nixpkgs to, e.g. what a
+///   diff between the two affects.
+/// - `config`: The configuration of the checks.
+fn process(
+    base_nixpkgs: PathBuf,
+    main_nixpkgs: &Path,
+    (base_scope, main_scope): (Scope, Scope),
+    config: &Config,
+) -> Status {
+    // The breadcrumbs and the problems found so far need to be taken on the thread that checked
+    // the Nixpkgs
+    let check_base = || {
+        let result = check_nixpkgs(&base_nixpkgs, &base_scope, config, &Progress::new("base"))
+            .map_err(|error| error.context(Breadcrumbs::take("base")));
+        (result, interrupt::take_found())
+    };
+    let check_main = || {
+        let result = check_nixpkgs(main_nixpkgs, &main_scope, config, &Progress::new("main"))
+            .map_err(|error| error.context(Breadcrumbs::take("main")));
+        (result, interrupt::take_found())
     };
 
-    let base_result = match base_thread.join() {
-        Ok(Ok(status)) => status,
-        Ok(Err(error)) => {
-            return error.into();
+    // Very easy to parallelise this, since both operations are totally independent of each other.
+    let (base_result, main_result) = if jobs::count().get() > 1 {
+        thread::scope(|threads| {
+            let base_thread = threads.spawn(check_base);
+            let main_result = check_main();
+            match base_thread.join() {
+                Ok(base_result) => (base_result, main_result),
+                Err(e) => panic::resume_unwind(e),
+            }
+        })
+    } else {
+        (check_base(), check_main())
+    };
+    let ((base_result, _), (main_result, main_found)) = (base_result, main_result);
+    let (base_result, main_result) = match (base_result, main_result) {
+        (Ok(base_result), Ok(main_result)) => (base_result, main_result),
+        (_, Err(error)) | (Err(error), _) if interrupt::is_interrupted(&error) => {
+            return Status::Interrupted(main_found);
         }
-        Err(e) => panic::resume_unwind(e),
+        (_, Err(error)) | (Err(error), _) => return error.into(),
     };
 
-    match (base_result, main_result) {
-        (Failure(..), Failure(errors)) => Status::BranchStillBroken(errors),
-        (Success(..), Failure(errors)) => Status::ProblemsIntroduced(errors),
-        (Failure(..), Success(..)) => Status::BranchHealed,
-        (Success(base), Success(main)) => {
-            // Both base and main branch succeed. Check ratchet state between them...
-            match ratchet::Nixpkgs::compare(&base, main) {
-                Failure(errors) => Status::DiscouragedPatternedIntroduced(errors),
-                Success(..) => Status::ValidatedSuccessfully,
+    match (base_result.into_result(), main_result.into_result()) {
+        (Err(base_errors), Err(errors)) if config.only_new_problems => {
+            let (introduced, fixed) = delta::compare(base_errors, errors);
+            Status::ProblemsChanged { introduced, fixed }
+        }
+        (Err(..), Err(errors)) => Status::BranchStillBroken(errors),
+        (Ok(..), Err(errors)) => Status::ProblemsIntroduced(errors),
+        (Err(base_errors), Ok((_, warnings))) => {
+            // The problems of the base branch include its warnings, only new ones are discouraged
+            let (introduced, _) = delta::compare(base_errors, warnings);
+            if introduced.is_empty() {
+                Status::BranchHealed
+            } else {
+                Status::DiscouragedPatternedIntroduced(introduced)
             }
         }
+        (Ok((base, base_warnings)), Ok((main, warnings))) => {
+            // Both base and main branch succeed. Check ratchet state between them, the warnings
+            // the base branch doesn't have are discouraged just the same.
+            let (mut problems, _) = delta::compare(base_warnings, warnings);
+            if let Failure(errors) = ratchet::Nixpkgs::compare(&base, main) {
+                problems.extend(errors);
+            }
+            if problems.is_empty() {
+                Status::ValidatedSuccessfully
+            } else {
+                Status::DiscouragedPatternedIntroduced(problems)
+            }
+        }
+    }
+}
+
+/// Runs one of the checks of `check_nixpkgs` with the configured timeout, see `timeout::run`,
+/// unless the run was interrupted. The problems it finds are recorded, such that they can still be
+/// printed if the run is interrupted later.
+fn run_check<T>(
+    check: &str,
+    nixpkgs_path: &Path,
+    config: &Config,
+    f: impl FnOnce() -> validation::Result<T>,
+) -> validation::Result<T> {
+    interrupt::check()?;
+    let result = timeout::run(check, nixpkgs_path, config, f)?;
+    interrupt::record(&result);
+    Ok(result)
+}
+
+/// The checks of `check_nixpkgs` that check Nix files one after another, until one of them finds
+/// a fatal problem.
+#[derive(Clone, Copy)]
+enum FileCheck {
+    Maintainers,
+    AllPackages,
+    NixosModules,
+    Files,
+}
+
+impl FileCheck {
+    /// The name of the check for `run_check`.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Maintainers => "maintainers",
+            Self::AllPackages => "all-packages",
+            Self::NixosModules => "nixos-modules",
+            Self::Files => "files",
+        }
     }
 }
 
@@ -107,7 +917,14 @@ fn process(base_nixpkgs: PathBuf, main_nixpkgs: &Path) -> Status {
 /// This does not include ratchet checks, see ../README.md#ratchet-checks
 /// Instead a `ratchet::Nixpkgs` value is returned, whose `compare` method allows performing the
 /// ratchet check against another result.
-fn check_nixpkgs(nixpkgs_path: &Path) -> validation::Result<ratchet::Nixpkgs> {
+///
+/// Only the packages and attributes within the given scope are checked.
+fn check_nixpkgs(
+    nixpkgs_path: &Path,
+    scope: &Scope,
+    config: &Config,
+    progress: &Progress,
+) -> validation::Result<ratchet::Nixpkgs> {
     let nixpkgs_path = nixpkgs_path.canonicalize().with_context(|| {
         format!(
             "Nixpkgs path {} could not be resolved",
@@ -115,30 +932,244 @@ fn check_nixpkgs(nixpkgs_path: &Path) -> validation::Result<ratchet::Nixpkgs> {
         )
     })?;
 
+    let _span = tracing::info_span!("check", nixpkgs = %nixpkgs_path.display()).entered();
     let mut nix_file_store = NixFileStore::default();
 
     let package_result = {
         if !nixpkgs_path.join(structure::BASE_SUBPATH).exists() {
-            // No pkgs/by-name directory, always valid
-            Success(BTreeMap::new())
+            if git::is_sparse_checkout(&nixpkgs_path) {
+                // In a sparse checkout, pkgs/by-name may just not be checked out
+                npv_112::NixpkgsPathMissing::new(structure::BASE_SUBPATH).into()
+            } else {
+                // No pkgs/by-name directory, always valid
+                Success(eval::Evaluation::default())
+            }
         } else {
-            let structure = check_structure(&nixpkgs_path, &mut nix_file_store)?;
+            let structure = run_check("structure", &nixpkgs_path, config, || {
+                check_structure(&nixpkgs_path, &mut nix_file_store, scope, config, progress)
+            })?;
+            let package_sets = config
+                .package_sets
+                .iter()
+                .map(|package_set| {
+                    Ok(run_check("package-sets", &nixpkgs_path, config, || {
+                        structure::check_package_set_structure(
+                            &nixpkgs_path,
+                            package_set,
+                            &mut nix_file_store,
+                            scope,
+                            config,
+                            progress,
+                        )
+                    })?
+                    .annotate(&format!("In package set {}", package_set.attribute))
+                    .map(|names| (package_set, names)))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let structure = structure.and(package_sets.into_iter().collect(), |a, b| (a, b));
 
-            // Only if we could successfully parse the structure, we do the evaluation checks
-            structure.result_map(|package_names| {
-                eval::check_values(&nixpkgs_path, &mut nix_file_store, package_names.as_slice())
+            // Only if we could successfully parse the structure, we do the evaluation checks, as
+            // long as the paths needed for evaluation exist
+            structure.result_map(|(package_names, package_sets)| {
+                let alias_result = if config.profile == Profile::Overlay {
+                    Success(())
+                } else {
+                    run_check("aliases", &nixpkgs_path, config, || {
+                        aliases::check_aliases(
+                            &nixpkgs_path,
+                            &mut nix_file_store,
+                            &package_names,
+                            scope,
+                        )
+                    })?
+                };
+                let eval_result = if !config.eval {
+                    Success(eval::Evaluation::default())
+                } else {
+                    run_check("eval", &nixpkgs_path, config, || {
+                        structure::check_eval_required_paths(&nixpkgs_path).result_map(|()| {
+                            eval::check_values(
+                                &nixpkgs_path,
+                                &mut nix_file_store,
+                                package_names.as_slice(),
+                                &package_sets,
+                                scope,
+                                config,
+                                progress,
+                            )
+                        })
+                    })?
+                };
+                let no_aliases_result = match &eval_result {
+                    Success(_) | SuccessWithWarnings(..)
+                        if config.checks.contains(&Check::NoAliases)
+                            && config.profile != Profile::Overlay =>
+                    {
+                        run_check("no-aliases", &nixpkgs_path, config, || {
+                            no_aliases::check_no_aliases(
+                                &nixpkgs_path,
+                                &mut nix_file_store,
+                                &package_names,
+                                scope,
+                                config,
+                            )
+                        })?
+                    }
+                    _ => Success(()),
+                };
+                let update_scripts_result = match &eval_result {
+                    Success(_) | SuccessWithWarnings(..)
+                        if config.checks.contains(&Check::UpdateScripts) =>
+                    {
+                        run_check("update-scripts", &nixpkgs_path, config, || {
+                            update_scripts::check_update_scripts(
+                                &nixpkgs_path,
+                                &package_names,
+                                scope,
+                                config,
+                            )
+                        })?
+                    }
+                    _ => Success(()),
+                };
+                Ok(alias_result
+                    .and_(no_aliases_result)
+                    .and_(update_scripts_result)
+                    .and_(eval_result))
             })?
         }
     };
 
-    let file_result = files::check_files(&nixpkgs_path, &mut nix_file_store)?;
+    progress.phase("Checking the Nix files", None);
+    // Overlays don't have `pkgs/top-level`
+    let is_overlay = config.profile == Profile::Overlay;
+    // Modules that don't parse are fatal problems, since checking all Nix files would fail on them
+    let file_checks = [
+        config
+            .checks
+            .contains(&Check::Maintainers)
+            .then_some(FileCheck::Maintainers),
+        (!is_overlay).then_some(FileCheck::AllPackages),
+        config
+            .checks
+            .contains(&Check::NixosModules)
+            .then_some(FileCheck::NixosModules),
+        Some(FileCheck::Files),
+    ];
+    let file_result =
+        validation::sequence_until_fatal(file_checks.into_iter().flatten().map(|check| {
+            run_check(check.name(), &nixpkgs_path, config, || {
+                let result = match check {
+                    FileCheck::Maintainers => {
+                        maintainers::check_maintainers(&nixpkgs_path, &mut nix_file_store)?
+                    }
+                    FileCheck::AllPackages => {
+                        all_packages::check_all_packages(&nixpkgs_path, &mut nix_file_store, scope)?
+                    }
+                    FileCheck::NixosModules => {
+                        nixos_modules::check_nixos_modules(&nixpkgs_path, &mut nix_file_store)?
+                    }
+                    // Only this check has ratchet values for the files
+                    FileCheck::Files => {
+                        return files::check_files(&nixpkgs_path, config, &mut nix_file_store);
+                    }
+                };
+                Ok(result.map(|()| BTreeMap::new()))
+            })
+        }))?
+        .map(|files| files.into_iter().flatten().collect());
 
-    Ok(
-        package_result.and(file_result, |packages, files| ratchet::Nixpkgs {
-            packages,
-            files,
-        }),
+    let lib_functions = if config.checks.contains(&Check::LibTests) {
+        run_check("lib-tests", &nixpkgs_path, config, || {
+            Ok(Success(lib_tests::check_lib_tests(
+                &nixpkgs_path,
+                &mut nix_file_store,
+                scope,
+            )?))
+        })?
+    } else {
+        Success(BTreeMap::new())
+    };
+
+    let release_jobs = match &package_result {
+        Success(evaluation) | SuccessWithWarnings(evaluation, _)
+            if config.checks.contains(&Check::ReleaseJobs) =>
+        {
+            run_check("release-jobs", &nixpkgs_path, config, || {
+                release::check_release_jobs(&nixpkgs_path, &evaluation.packages, scope, config)
+            })?
+        }
+        _ => Success(BTreeMap::new()),
+    };
+
+    let cross = match &package_result {
+        Success(evaluation) | SuccessWithWarnings(evaluation, _)
+            if config.checks.contains(&Check::Cross) =>
+        {
+            run_check("cross", &nixpkgs_path, config, || {
+                cross::check_cross(&nixpkgs_path, &evaluation.packages, scope, config)
+            })?
+        }
+        _ => Success(BTreeMap::new()),
+    };
+
+    let meta_gating = match &package_result {
+        Success(evaluation) | SuccessWithWarnings(evaluation, _)
+            if config.checks.contains(&Check::MetaGating) =>
+        {
+            run_check("meta-gating", &nixpkgs_path, config, || {
+                meta_gating::check_meta_gating(&nixpkgs_path, &evaluation.packages, scope, config)
+            })?
+        }
+        _ => Success(BTreeMap::new()),
+    };
+
+    let aliases = if is_overlay {
+        Success(BTreeMap::new())
+    } else {
+        run_check("aliases", &nixpkgs_path, config, || {
+            Ok(Success(aliases::alias_ratchets(
+                &nixpkgs_path,
+                &mut nix_file_store,
+                scope,
+            )?))
+        })?
+    };
+
+    let broken_symlinks = run_check("broken-symlinks", &nixpkgs_path, config, || {
+        symlinks::check_broken_symlinks(&nixpkgs_path, scope, config)
+    })?;
+
+    Ok(validation::zip4(
+        package_result,
+        file_result,
+        release_jobs
+            .and(cross, |release_jobs, cross| (release_jobs, cross))
+            .and(meta_gating, |(release_jobs, cross), meta_gating| {
+                (release_jobs, cross, meta_gating)
+            }),
+        validation::zip3(lib_functions, aliases, broken_symlinks),
     )
+    .map(
+        |(
+            evaluation,
+            files,
+            (release_jobs, cross, meta_gating),
+            (lib_functions, aliases, broken_symlinks),
+        )| {
+            ratchet::Nixpkgs {
+                packages: evaluation.packages,
+                files,
+                lib_functions,
+                aliases,
+                release_jobs,
+                cross,
+                meta_gating,
+                broken_symlinks,
+                eval_warnings: evaluation.warnings,
+            }
+        },
+    ))
 }
 
 #[cfg(test)]
@@ -147,10 +1178,16 @@ mod tests {
     use std::path::Path;
 
     use anyhow::Context;
-    use pretty_assertions::StrComparison;
-    use tempfile::{TempDir, tempdir_in};
+    use tempfile::tempdir_in;
 
-    use super::{process, structure::BASE_SUBPATH};
+    use itertools::Itertools;
+
+    use super::config::{Check, Config};
+    use super::progress::Progress;
+    use super::scope::Scope;
+    use super::testing::{self, Tree, tempdir};
+    use super::validation::Validation;
+    use super::{check_nixpkgs, diff::Diff, git, process};
 
     #[test]
     fn tests_dir() -> anyhow::Result<()> {
@@ -171,36 +1208,24 @@ mod tests {
         Ok(())
     }
 
-    // tempfile::tempdir needs to be wrapped in temp_env lock
-    // because it accesses TMPDIR environment variable.
-    pub fn tempdir() -> anyhow::Result<TempDir> {
-        let empty_list: [(&str, Option<&str>); 0] = [];
-        Ok(temp_env::with_vars(empty_list, tempfile::tempdir)?)
-    }
-
     // We cannot check case-conflicting files into Nixpkgs (the channel would fail to build),
     // so we generate the case-conflicting file instead.
     #[test]
     fn test_case_sensitive() -> anyhow::Result<()> {
-        let temp_nixpkgs = tempdir()?;
-        let path = temp_nixpkgs.path();
+        let tree = Tree::new()?;
 
-        if is_case_insensitive_fs(path)? {
+        if is_case_insensitive_fs(tree.path())? {
             eprintln!("We're on a case-insensitive filesystem, skipping case-sensitivity test");
             return Ok(());
         }
 
-        let base = path.join("main").join(BASE_SUBPATH);
-
-        fs::create_dir_all(base.join("fo/foo"))?;
-        fs::write(base.join("fo/foo/package.nix"), "{ someDrv }: someDrv")?;
-
-        fs::create_dir_all(base.join("fo/foO"))?;
-        fs::write(base.join("fo/foO/package.nix"), "{ someDrv }: someDrv")?;
+        let tree = tree
+            .package("main", "foo", "{ someDrv }: someDrv")?
+            .package("main", "foO", "{ someDrv }: someDrv")?;
 
         test_nixpkgs(
             "case_sensitive",
-            path,
+            tree.path(),
             "- pkgs/by-name/fo: Duplicate case-sensitive package directories \"foO\" and \"foo\".\n\
             This PR introduces the problems listed above. Please fix them before merging, \
             otherwise the base branch would break.\n",
@@ -238,17 +1263,6 @@ mod tests {
     }
 
     fn test_nixpkgs(name: &str, path: &Path, expected_errors: &str) {
-        // Match the expected errors almost verbatim -- `@REDACTED@` turns into `.*`.
-        let pattern = format!(
-            "^{}$",
-            regex::escape(expected_errors).replace("@REDACTED@", ".*")
-        );
-
-        let expected_errors_regex = regex::RegexBuilder::new(&pattern)
-            .dot_matches_new_line(true)
-            .build()
-            .expect("valid regex");
-
         let main_path = path.join("main");
         let base_path = path.join("base");
         let base_nixpkgs = if base_path.exists() {
@@ -262,17 +1276,143 @@ mod tests {
         let nix_conf_dir = tempdir().expect("directory");
         let nix_conf_dir = nix_conf_dir.path().as_os_str();
 
+        let diff_path = path.join("diff.patch");
+        let diff = diff_path
+            .exists()
+            .then(|| Diff::read(&diff_path).expect("valid diff"));
+
+        let config_path = path.join("config.toml");
+        let config = Config::read(config_path.exists().then_some(config_path.as_path()))
+            .expect("valid config");
+
         let status = temp_env::with_var("NIX_CONF_DIR", Some(nix_conf_dir), || {
-            process(base_nixpkgs, &main_path)
+            process(
+                base_nixpkgs,
+                &main_path,
+                diff.as_ref().map(Diff::scopes).unwrap_or_default(),
+                &config,
+            )
         });
 
-        let actual_errors = format!("{status}\n");
+        testing::assert_golden(name, expected_errors, &format!("{status}\n"));
+    }
 
-        assert!(
-            expected_errors_regex.is_match(&actual_errors),
-            "Failed test case {name}: {}",
-            StrComparison::new(expected_errors, &actual_errors)
+    /// Tests that `pkgs/by-name` not being checked out in a sparse checkout doesn't go unnoticed.
+    #[test]
+    fn test_sparse_checkout() -> anyhow::Result<()> {
+        let tree = Tree::new()?
+            .package(".", "foo", "{ someDrv }: someDrv")?
+            .file("default.nix", "import <test-nixpkgs> { root = ./.; }")?;
+        let repo = tree.path();
+
+        git::run(repo, &["init", "--quiet"])?;
+        git::run(repo, &["add", "."])?;
+        git::run(
+            repo,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "--message",
+                "init",
+            ],
+        )?;
+        // Only checks out the top-level files and `lib`
+        git::run(repo, &["sparse-checkout", "set", "lib"])?;
+
+        let status = process(
+            Path::new("tests/empty-base").to_owned(),
+            repo,
+            Default::default(),
+            &Config::default(),
         );
+        assert_eq!(
+            format!("{status}\n"),
+            "- pkgs/by-name: This path is needed to check Nixpkgs, but it doesn't exist. If this \
+            is a sparse checkout, please include it with `git sparse-checkout add pkgs/by-name`.\n\
+            This PR introduces the problems listed above. Please fix them before merging, \
+            otherwise the base branch would break.\n",
+        );
+        Ok(())
+    }
+
+    /// Tests that the warnings of a check are reported, but only the ones the base branch doesn't
+    /// have.
+    #[test]
+    fn reports_new_warnings() -> anyhow::Result<()> {
+        let tree = Tree::new()?
+            .package("base", "foo", "{ someDrv }: someDrv")?
+            .file("base/pkgs/by-name/fo/foo/old.txt", "")?
+            .file("base/pkgs/by-name/ba/bar/default.nix", "{ }")?
+            .package("main", "foo", "{ someDrv }: someDrv")?
+            .file("main/pkgs/by-name/fo/foo/old.txt", "")?;
+        let config = Config {
+            eval: false,
+            ..Config::default()
+        };
+        let check = || {
+            process(
+                tree.path().join("base"),
+                &tree.path().join("main"),
+                Default::default(),
+                &config,
+            )
+        };
+
+        // The base branch is broken because of `bar`, but has the warning already
+        assert_eq!(
+            check().to_string(),
+            "The base branch is broken, but this PR fixes it. Nice job!"
+        );
+
+        fs::write(tree.path().join("main/pkgs/by-name/fo/foo/new.txt"), "")?;
+        assert_eq!(
+            check().to_string(),
+            "- pkgs/by-name/fo/foo: The file new.txt isn't referred to by `package.nix` or any Nix \
+            file it uses, so it has no effect. Please remove it if it's not needed anymore.\n\
+            This PR introduces additional instances of discouraged patterns as listed above. \
+            Merging is discouraged but would not break the base branch."
+        );
+        Ok(())
+    }
+
+    /// Tests that the checks that don't evaluate Nixpkgs also time out.
+    #[test]
+    fn times_out_on_all_checks() -> anyhow::Result<()> {
+        let tree = Tree::new()?
+            .file("lib/strings.nix", "{ concatLines = lines: lines; }")?
+            .file("lib/tests/misc.nix", "{ }")?
+            .file("pkgs/top-level/aliases.nix", "self: super: { }")?;
+        let config = Config {
+            check_timeout: Some(0),
+            checks: [Check::LibTests].into(),
+            ..Config::default()
+        };
+        let result = check_nixpkgs(
+            tree.path(),
+            &Scope::default(),
+            &config,
+            &Progress::new("main"),
+        )?;
+        let Validation::Failure(problems) = result else {
+            panic!("expected the checks to time out");
+        };
+        let messages = problems
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect_vec();
+        for check in ["lib-tests", "aliases", "broken-symlinks"] {
+            assert!(
+                messages
+                    .iter()
+                    .any(|message| message.contains(&format!("The {check} check timed out"))),
+                "expected the {check} check to time out, but got: {messages:?}"
+            );
+        }
+        Ok(())
     }
 
     /// Check whether a path is in a case-insensitive filesystem
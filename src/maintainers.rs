@@ -0,0 +1,325 @@
+//! Checks of the maintainers in `maintainers/maintainer-list.nix` and the teams in
+//! `maintainers/team-list.nix`, see `--check maintainers`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use relative_path::RelativePath;
+use rnix::ast::{self, HasEntry};
+use rowan::ast::AstNode;
+
+use crate::git;
+use crate::nix_file::{NixFile, NixFileStore};
+use crate::problem::{npv_112, npv_116, npv_135, npv_136, npv_137, npv_138, npv_139, npv_145};
+use crate::structure::{self, read_dir_sorted};
+use crate::validation::{self, Validation::Success};
+
+/// The file defining all maintainers, relative to Nixpkgs.
+pub const MAINTAINER_LIST_PATH: &str = "maintainers/maintainer-list.nix";
+
+/// The file defining all teams, relative to Nixpkgs.
+pub const TEAM_LIST_PATH: &str = "maintainers/team-list.nix";
+
+/// The fields every maintainer needs to have.
+const REQUIRED_FIELDS: [&str; 2] = ["github", "githubId"];
+
+/// Only catches obvious mistakes, since the actual syntax of email addresses is very loose.
+static EMAIL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").expect("valid regex"));
+
+/// A maintainer defined in `maintainers/maintainer-list.nix`.
+pub struct Maintainer {
+    /// The attribute name of the maintainer, like `lib.maintainers.<handle>`.
+    pub handle: String,
+    pub line: usize,
+    /// The fields of the maintainer, with the value if it's a literal string or integer.
+    pub fields: BTreeMap<String, Option<String>>,
+}
+
+impl Maintainer {
+    /// The literal value of a field.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name)?.as_deref()
+    }
+}
+
+/// Returns the maintainers defined by the outermost attribute set of the file, as long as their
+/// handles and definitions are attribute sets that can be known statically.
+pub fn read_maintainers(nix_file: &NixFile) -> Vec<Maintainer> {
+    let Some(ast::Expr::AttrSet(attr_set)) = nix_file.syntax_root.expr() else {
+        return vec![];
+    };
+    attr_set
+        .attrpath_values()
+        .filter_map(|attrpath_value| {
+            let handle = static_name(&attrpath_value)?;
+            let Some(ast::Expr::AttrSet(definition)) = attrpath_value.value() else {
+                return None;
+            };
+            let fields = definition
+                .attrpath_values()
+                .filter_map(|field| Some((static_name(&field)?, literal_value(field.value()?))))
+                .collect();
+            let offset = usize::from(attrpath_value.syntax().text_range().start());
+            Some(Maintainer {
+                handle,
+                line: nix_file.line_index.line(offset),
+                fields,
+            })
+        })
+        .collect()
+}
+
+/// A team defined in `maintainers/team-list.nix`.
+pub struct Team {
+    /// The attribute name of the team, like `lib.teams.<name>`.
+    pub name: String,
+    pub line: usize,
+    pub short_name: Option<String>,
+    /// The handles and lines of the members, like `foo` for both `lib.maintainers.foo` and `foo`
+    /// in `with lib.maintainers; [ foo ]`.
+    pub members: Vec<(String, usize)>,
+}
+
+/// Returns the teams defined by the file, which is like `{ lib }: with lib.maintainers; { ... }`.
+pub fn read_teams(nix_file: &NixFile) -> Vec<Team> {
+    let mut expr = nix_file.syntax_root.expr();
+    let attr_set = loop {
+        expr = match expr {
+            Some(ast::Expr::Lambda(lambda)) => lambda.body(),
+            Some(ast::Expr::With(with)) => with.body(),
+            Some(ast::Expr::LetIn(let_in)) => let_in.body(),
+            Some(ast::Expr::AttrSet(attr_set)) => break attr_set,
+            _ => return vec![],
+        };
+    };
+    attr_set
+        .attrpath_values()
+        .filter_map(|attrpath_value| {
+            let name = static_name(&attrpath_value)?;
+            let Some(ast::Expr::AttrSet(definition)) = attrpath_value.value() else {
+                return None;
+            };
+            let mut short_name = None;
+            let mut members = vec![];
+            for field in definition.attrpath_values() {
+                match (static_name(&field).as_deref(), field.value()) {
+                    (Some("shortName"), Some(value)) => short_name = literal_value(value),
+                    (Some("members"), Some(ast::Expr::List(list))) => {
+                        members.extend(list.items().filter_map(|item| {
+                            let handle = match &item {
+                                ast::Expr::Ident(ident) => ident.ident_token()?.text().to_owned(),
+                                ast::Expr::Select(select) => {
+                                    match select.attrpath()?.attrs().last()? {
+                                        ast::Attr::Ident(ident) => {
+                                            ident.ident_token()?.text().to_owned()
+                                        }
+                                        _ => return None,
+                                    }
+                                }
+                                _ => return None,
+                            };
+                            let offset = usize::from(item.syntax().text_range().start());
+                            Some((handle, nix_file.line_index.line(offset)))
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+            let offset = usize::from(attrpath_value.syntax().text_range().start());
+            Some(Team {
+                name,
+                line: nix_file.line_index.line(offset),
+                short_name,
+                members,
+            })
+        })
+        .collect()
+}
+
+/// The name of a binding like `foo = ...;` or `"foo" = ...;`, but not `foo.bar = ...;`.
+fn static_name(attrpath_value: &ast::AttrpathValue) -> Option<String> {
+    let mut attrs = attrpath_value.attrpath()?.attrs();
+    match (attrs.next()?, attrs.next()) {
+        (ast::Attr::Ident(ident), None) => Some(ident.ident_token()?.text().to_owned()),
+        (ast::Attr::Str(string), None) => match &string.normalized_parts()[..] {
+            [ast::InterpolPart::Literal(name)] => Some(name.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The value of a string without interpolations, or of an integer.
+fn literal_value(expr: ast::Expr) -> Option<String> {
+    match expr {
+        ast::Expr::Str(string) => match &string.normalized_parts()[..] {
+            [] => Some(String::new()),
+            [ast::InterpolPart::Literal(value)] => Some(value.clone()),
+            _ => None,
+        },
+        ast::Expr::Literal(literal) => Some(literal.syntax().text().to_string()),
+        _ => None,
+    }
+}
+
+/// Checks that the maintainers are sorted, have the required fields, unique GitHub IDs and valid
+/// email addresses, and that the teams only have known maintainers as members, unique short names,
+/// and that the teams in `meta.teams` of the packages in `pkgs/by-name` exist.
+pub fn check_maintainers(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+) -> validation::Result<()> {
+    let _span = tracing::info_span!("maintainers").entered();
+    let path = RelativePath::new(MAINTAINER_LIST_PATH).to_path(nixpkgs_path);
+    if !path.is_file() {
+        return Ok(if git::is_sparse_checkout(nixpkgs_path) {
+            // In a sparse checkout, the maintainers may just not be checked out
+            npv_112::NixpkgsPathMissing::new(MAINTAINER_LIST_PATH).into()
+        } else {
+            // No maintainers, always valid
+            Success(())
+        });
+    }
+    let maintainers = read_maintainers(nix_file_store.get(&path)?);
+
+    let unsorted = maintainers.windows(2).filter_map(|window| {
+        let [previous, maintainer] = window else {
+            return None;
+        };
+        (maintainer.handle.to_lowercase() < previous.handle.to_lowercase()).then(|| {
+            npv_135::MaintainerListUnsorted::new(
+                &maintainer.handle,
+                &previous.handle,
+                maintainer.line,
+            )
+            .into()
+        })
+    });
+
+    let missing_fields = maintainers.iter().flat_map(|maintainer| {
+        REQUIRED_FIELDS
+            .into_iter()
+            .filter(|field| !maintainer.fields.contains_key(*field))
+            .map(|field| {
+                npv_136::MaintainerMissingField::new(&maintainer.handle, field, maintainer.line)
+                    .into()
+            })
+    });
+
+    let mut by_github_id: BTreeMap<&str, Vec<&Maintainer>> = BTreeMap::new();
+    for maintainer in &maintainers {
+        if let Some(github_id) = maintainer.field("githubId") {
+            by_github_id.entry(github_id).or_default().push(maintainer);
+        }
+    }
+    let duplicate_github_ids = by_github_id
+        .into_iter()
+        .filter(|(_, maintainers)| maintainers.len() > 1)
+        .map(|(github_id, maintainers)| {
+            npv_137::MaintainerDuplicateGithubId::new(
+                github_id,
+                maintainers
+                    .iter()
+                    .map(|maintainer| maintainer.handle.clone())
+                    .collect::<Vec<_>>(),
+                maintainers[1].line,
+            )
+            .into()
+        });
+
+    let invalid_emails = maintainers.iter().filter_map(|maintainer| {
+        let email = maintainer.field("email")?;
+        (!EMAIL_REGEX.is_match(email)).then(|| {
+            npv_138::MaintainerInvalidEmail::new(&maintainer.handle, email, maintainer.line).into()
+        })
+    });
+
+    let maintainer_result: validation::Validation<()> = unsorted
+        .chain(missing_fields)
+        .chain(duplicate_github_ids)
+        .chain(invalid_emails)
+        .collect();
+    let team_result = check_teams(nixpkgs_path, nix_file_store, &maintainers)?;
+    Ok(maintainer_result.and_(team_result))
+}
+
+/// Checks the teams against the given maintainers, and the `meta.teams` of the packages in
+/// `pkgs/by-name` against the teams.
+fn check_teams(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    maintainers: &[Maintainer],
+) -> validation::Result<()> {
+    let path = RelativePath::new(TEAM_LIST_PATH).to_path(nixpkgs_path);
+    if !path.is_file() {
+        // Teams are optional
+        return Ok(Success(()));
+    }
+    let teams = read_teams(nix_file_store.get(&path)?);
+    let handles: BTreeSet<&str> = maintainers
+        .iter()
+        .map(|maintainer| maintainer.handle.as_str())
+        .collect();
+
+    let unknown_members = teams.iter().flat_map(|team| {
+        team.members
+            .iter()
+            .filter(|(handle, _)| !handles.contains(handle.as_str()))
+            .map(|(handle, line)| npv_139::TeamMemberUnknown::new(&team.name, handle, *line).into())
+    });
+
+    let mut by_short_name: BTreeMap<&str, Vec<&Team>> = BTreeMap::new();
+    for team in &teams {
+        if let Some(short_name) = &team.short_name {
+            by_short_name.entry(short_name).or_default().push(team);
+        }
+    }
+    let duplicate_short_names = by_short_name
+        .into_iter()
+        .filter(|(_, teams)| teams.len() > 1)
+        .map(|(short_name, teams)| {
+            npv_145::TeamDuplicateShortName::new(
+                short_name,
+                teams
+                    .iter()
+                    .map(|team| team.name.clone())
+                    .collect::<Vec<_>>(),
+                teams[1].line,
+            )
+            .into()
+        });
+
+    let mut problems: Vec<_> = unknown_members.chain(duplicate_short_names).collect();
+
+    let team_names: BTreeSet<&str> = teams.iter().map(|team| team.name.as_str()).collect();
+    let by_name_dir = nixpkgs_path.join(structure::BASE_SUBPATH);
+    if by_name_dir.is_dir() {
+        for shard in read_dir_sorted(&by_name_dir)? {
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for package in read_dir_sorted(&shard.path())? {
+                let package_name = package.file_name().to_string_lossy().into_owned();
+                let package_nix_path = package.path().join(structure::PACKAGE_NIX_FILENAME);
+                if !package_nix_path.is_file() {
+                    continue;
+                }
+                let nix_file = nix_file_store.get(&package_nix_path)?;
+                problems.extend(
+                    nix_file
+                        .meta_teams()
+                        .into_iter()
+                        .filter(|(team, _, _)| !team_names.contains(team.as_str()))
+                        .map(|(team, line, column)| {
+                            npv_116::ByNameUnknownTeam::new(&package_name, team, line, column)
+                                .into()
+                        }),
+                );
+            }
+        }
+    }
+    Ok(problems.into_iter().collect())
+}
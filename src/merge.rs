@@ -0,0 +1,112 @@
+//! Merges the JSON reports of runs that each checked a part of Nixpkgs, like sharded CI jobs, see
+//! `nixpkgs-vet merge-reports`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use itertools::Itertools as _;
+
+use crate::output::JsonReport;
+
+/// Reads the reports written with `--report json` at the paths and merges them into one.
+///
+/// Problems that more than one run found, like the ones about files every run checks, are only
+/// kept once. The exit code is the highest one of the runs, so the merged report only passes if
+/// all runs passed.
+pub fn merge_reports(paths: &[PathBuf]) -> anyhow::Result<JsonReport> {
+    let reports = paths
+        .iter()
+        .map(|path| {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Could not read the report {}", path.display()))?;
+            serde_json::from_str::<JsonReport>(&contents)
+                .with_context(|| format!("Could not parse the report {}", path.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(merge(reports))
+}
+
+fn merge(reports: Vec<JsonReport>) -> JsonReport {
+    let count = reports.len();
+    let exit_code = reports
+        .iter()
+        .map(|report| report.exit_code)
+        .max()
+        .unwrap_or_default();
+    let all_problems = reports
+        .into_iter()
+        .flat_map(|report| report.problems)
+        .collect::<Vec<_>>();
+    let total = all_problems.len();
+    let problems = all_problems
+        .into_iter()
+        .unique_by(|problem| (problem.code.clone(), problem.fields.to_string()))
+        .collect::<Vec<_>>();
+    let message = format!(
+        "Merged {count} {}, with {} {}{}.",
+        if count == 1 { "report" } else { "reports" },
+        problems.len(),
+        if problems.len() == 1 {
+            "problem"
+        } else {
+            "problems"
+        },
+        match total - problems.len() {
+            0 => String::new(),
+            duplicates => format!(" after removing {duplicates} found by more than one run"),
+        }
+    );
+    JsonReport::with_problems(exit_code, message, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::npv_143;
+    use crate::status::{EXIT_PROBLEMS, EXIT_SUCCESS, Status};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn merges_reports() {
+        let report = |paths: &[&str]| {
+            let problems = paths
+                .iter()
+                .map(|path| npv_143::PackageNixMissing::new(*path).into())
+                .collect::<Vec<_>>();
+            JsonReport::new(&if problems.is_empty() {
+                Status::ValidatedSuccessfully
+            } else {
+                Status::ProblemsIntroduced(problems)
+            })
+        };
+        let merged = merge(vec![
+            report(&["pkgs/by-name/fo/foo", "pkgs/by-name/ba/bar"]),
+            report(&[]),
+            report(&["pkgs/by-name/fo/foo"]),
+        ]);
+        assert_eq!(merged.exit_code, EXIT_PROBLEMS);
+        assert_eq!(
+            merged
+                .problems
+                .iter()
+                .map(|problem| problem.path.as_deref())
+                .collect::<Vec<_>>(),
+            [Some("pkgs/by-name/fo/foo"), Some("pkgs/by-name/ba/bar")]
+        );
+        assert_eq!(
+            merged.to_string(),
+            "- pkgs/by-name/fo/foo: Missing required \"package.nix\" file.\n\
+            - pkgs/by-name/ba/bar: Missing required \"package.nix\" file.\n\
+            Problems by check:\n  \
+            NPV-143: 2\n\
+            Merged 3 reports, with 2 problems after removing 1 found by more than one run."
+        );
+
+        // A merged report can be merged again
+        let json = serde_json::to_string(&merged).expect("reports can be serialised");
+        let parsed: JsonReport = serde_json::from_str(&json).expect("reports can be parsed");
+        assert_eq!(parsed, merged);
+        assert_eq!(merge(vec![report(&[])]).exit_code, EXIT_SUCCESS);
+    }
+}
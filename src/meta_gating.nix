@@ -0,0 +1,79 @@
+# Takes a path to nixpkgs and a path to the json-encoded list of attributes to evaluate, and
+# evaluates their derivations both with the default configuration and with `allowUnfree` and
+# `allowBroken`.
+#
+# Returns for each attribute how its evaluation disagrees with the gates in its `meta`, or null if
+# it doesn't or it doesn't evaluate either way. See ./meta_gating.rs for how this is used.
+{
+  attrsPath,
+  nixpkgsPath,
+  system ? "x86_64-linux",
+}:
+let
+  attrs = builtins.fromJSON (builtins.readFile attrsPath);
+
+  importWith =
+    config:
+    import nixpkgsPath {
+      # Only the given configuration, the user's home directory shouldn't influence this result.
+      inherit config system;
+      overlays = [ ];
+    };
+
+  restrictive = importWith { };
+  permissive = importWith {
+    allowUnfree = true;
+    allowBroken = true;
+  };
+  # Allowing a single gate, to tell which one the evaluation depends on
+  allowing = {
+    unfree = importWith { allowUnfree = true; };
+    broken = importWith { allowBroken = true; };
+  };
+
+  evaluates = set: name: (builtins.tryEval (builtins.seq set.${name}.drvPath true)).success;
+
+  # The gates the package is marked by, which `meta.unfree` and `meta.broken` are set to by
+  # `check-meta.nix` of `stdenv`
+  ownGates =
+    name:
+    let
+      meta = permissive.${name}.meta or { };
+      gates = builtins.filter (gate: meta.${gate} or false == true) (builtins.attrNames allowing);
+      result = builtins.tryEval (builtins.deepSeq gates gates);
+    in
+    if result.success then result.value else [ ];
+
+  check =
+    name:
+    let
+      gates = ownGates name;
+      # The gates that make the package evaluate when allowed on their own, or all of them if
+      # it needs more than one
+      neededGates =
+        let
+          needed = builtins.filter (gate: evaluates allowing.${gate} name) (builtins.attrNames allowing);
+        in
+        if needed == [ ] then builtins.attrNames allowing else needed;
+    in
+    if !evaluates permissive name then
+      null
+    else if gates != [ ] && evaluates restrictive name then
+      {
+        inconsistency = "bypassed";
+        inherit gates;
+      }
+    else if gates == [ ] && !evaluates restrictive name then
+      {
+        inconsistency = "undeclared";
+        gates = neededGates;
+      }
+    else
+      null;
+in
+builtins.listToAttrs (
+  map (name: {
+    inherit name;
+    value = check name;
+  }) attrs
+)
@@ -0,0 +1,86 @@
+//! Evaluates the changed packages in `pkgs/by-name` with and without allowing unfree and broken
+//! packages, to find the ones whose evaluation disagrees with their `meta`, see
+//! `--check meta-gating`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::eval;
+use crate::problem::npv_155::{Gate, GatingInconsistency};
+use crate::ratchet::{self, RatchetState};
+use crate::scope::Scope;
+use crate::validation::{self, Validation::Success};
+
+const META_GATING_NIX: &[u8] = include_bytes!("meta_gating.nix");
+
+/// How the evaluation of a package disagrees with its `meta`, as returned by
+/// `./meta_gating.nix`.
+#[derive(Deserialize)]
+struct GatingResult {
+    inconsistency: GatingInconsistency,
+    gates: Vec<Gate>,
+}
+
+/// Returns the ratchet value of each of the packages in `pkgs/by-name` that are in scope for the
+/// check that its evaluation agrees with the gates in its `meta`, see `eval::evaluate_json`.
+///
+/// Packages are only evaluated for the first of the configured systems, which is the only one the
+/// ratchets use, see `eval::check_values`.
+pub fn check_meta_gating(
+    nixpkgs_path: &Path,
+    packages: &BTreeMap<String, ratchet::Package>,
+    scope: &Scope,
+    config: &Config,
+) -> validation::Result<BTreeMap<String, ratchet::MetaGating>> {
+    let _span = tracing::info_span!("meta-gating").entered();
+    if scope.is_everything() {
+        return Ok(Success(BTreeMap::new()));
+    }
+    let attributes: Vec<&str> = packages
+        .iter()
+        // Only packages in `pkgs/by-name` have the hash of their `package.nix`
+        .filter(|(name, package)| {
+            package.package_nix_hash.is_some() && scope.contains_package(name)
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if attributes.is_empty() {
+        return Ok(Success(BTreeMap::new()));
+    }
+
+    let system = config
+        .systems
+        .first()
+        .context("No system to evaluate Nixpkgs for")?;
+    let results: BTreeMap<String, Option<GatingResult>> = match eval::evaluate_json(
+        "meta-gating",
+        META_GATING_NIX,
+        nixpkgs_path,
+        &[("attrsPath", json!(attributes))],
+        Some(system),
+        config,
+    )? {
+        Ok(results) => results,
+        Err(stderr) => return Ok(eval::eval_error(&stderr).into()),
+    };
+    Ok(Success(
+        results
+            .into_iter()
+            .map(|(name, result)| {
+                let consistent = match result {
+                    Some(GatingResult {
+                        inconsistency,
+                        gates,
+                    }) => RatchetState::Loose((inconsistency, gates)),
+                    None => RatchetState::Tight,
+                };
+                (name, ratchet::MetaGating { consistent })
+            })
+            .collect(),
+    ))
+}
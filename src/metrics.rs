@@ -0,0 +1,113 @@
+//! Metrics about a run in the textfile format of Prometheus' node exporter, see `--metrics-out`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+
+use crate::stats::Stats;
+use crate::status::Status;
+
+/// When the run started, see `start`.
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Records that the run starts now, for the duration of the run.
+pub fn start() {
+    START.get_or_init(Instant::now);
+}
+
+/// How long the run took so far, see `start`.
+pub fn elapsed() -> Duration {
+    START.get().map(Instant::elapsed).unwrap_or_default()
+}
+
+/// Writes the metrics of the run with the given status to the file. The file is replaced at once,
+/// such that the node exporter never reads a partial one.
+pub fn write(path: &Path, status: &Status, nixpkgs_path: &Path) -> anyhow::Result<()> {
+    let duration = elapsed();
+    let packages = Stats::collect(nixpkgs_path)?.by_name;
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, render(status, duration, packages))
+        .and_then(|()| fs::rename(&temporary_path, path))
+        .with_context(|| format!("Could not write the metrics to {}", path.display()))
+}
+
+fn render(status: &Status, duration: Duration, packages: usize) -> String {
+    let mut problems: BTreeMap<&str, usize> = BTreeMap::new();
+    for problem in status.errors().into_iter().flatten() {
+        *problems.entry(problem.code()).or_default() += 1;
+    }
+
+    let mut metrics = String::new();
+    let mut gauge = |name: &str, help: &str, values: &[(String, String)]| {
+        writeln!(metrics, "# HELP nixpkgs_vet_{name} {help}").expect("writing to a string");
+        writeln!(metrics, "# TYPE nixpkgs_vet_{name} gauge").expect("writing to a string");
+        for (labels, value) in values {
+            writeln!(metrics, "nixpkgs_vet_{name}{labels} {value}").expect("writing to a string");
+        }
+    };
+    gauge(
+        "problems",
+        "The number of problems found by each check.",
+        &problems
+            .into_iter()
+            .map(|(code, count)| (format!("{{code=\"{code}\"}}"), count.to_string()))
+            .collect::<Vec<_>>(),
+    );
+    gauge(
+        "success",
+        "Whether the checks passed.",
+        &[(
+            String::new(),
+            u8::from(status.severity().is_none()).to_string(),
+        )],
+    );
+    gauge(
+        "run_duration_seconds",
+        "How long the run took.",
+        &[(String::new(), format!("{:.3}", duration.as_secs_f64()))],
+    );
+    gauge(
+        "packages",
+        "The number of packages in pkgs/by-name of the checked Nixpkgs.",
+        &[(String::new(), packages.to_string())],
+    );
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::npv_143;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_gauges() {
+        let status = Status::ProblemsIntroduced(vec![
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/ba/bar").into(),
+        ]);
+        assert_eq!(
+            render(&status, Duration::from_millis(1500), 3),
+            indoc! {r#"
+                # HELP nixpkgs_vet_problems The number of problems found by each check.
+                # TYPE nixpkgs_vet_problems gauge
+                nixpkgs_vet_problems{code="NPV-143"} 2
+                # HELP nixpkgs_vet_success Whether the checks passed.
+                # TYPE nixpkgs_vet_success gauge
+                nixpkgs_vet_success 0
+                # HELP nixpkgs_vet_run_duration_seconds How long the run took.
+                # TYPE nixpkgs_vet_run_duration_seconds gauge
+                nixpkgs_vet_run_duration_seconds 1.500
+                # HELP nixpkgs_vet_packages The number of packages in pkgs/by-name of the checked Nixpkgs.
+                # TYPE nixpkgs_vet_packages gauge
+                nixpkgs_vet_packages 3
+            "#}
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Migrating packages in `pkgs/by-name` to newer conventions, see `nixpkgs-vet migrate`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use relative_path::RelativePathBuf;
+use rnix::ast;
+use rnix::{SyntaxNode, TextRange};
+use rowan::ast::AstNode as _;
+
+use crate::nix_file::{self, bound_names, ident_name};
+use crate::status::Fatal;
+use crate::{store, structure};
+
+/// The name of the argument the attributes of a derivation are passed to it with.
+const FINAL_ATTRS: &str = "finalAttrs";
+
+/// Rewrites `mkDerivation rec { }` in the `package.nix` files of the packages to
+/// `mkDerivation (finalAttrs: { })`, returning the paths of the files relative to Nixpkgs.
+///
+/// The files are only written once all of them could be migrated, such that nothing changes if
+/// one of the packages can't be.
+pub fn final_attrs(
+    nixpkgs_path: &Path,
+    package_names: &[String],
+) -> anyhow::Result<Vec<RelativePathBuf>> {
+    store::ensure_writable(nixpkgs_path)?;
+    let migrated = package_names
+        .iter()
+        .map(|package_name| {
+            let relative_file = structure::relative_file_for_package(package_name);
+            let path = relative_file.to_path(nixpkgs_path);
+            if !path.is_file() {
+                anyhow::bail!(Fatal::Usage(format!(
+                    "The package {package_name} isn't in pkgs/by-name"
+                )));
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {relative_file}"))?;
+            let migrated = rewrite_to_final_attrs(&contents)
+                .with_context(|| format!("Could not migrate {relative_file}"))?;
+            Ok((relative_file, migrated))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    migrated
+        .into_iter()
+        .map(|(relative_file, contents)| {
+            fs::write(relative_file.to_path(nixpkgs_path), contents)
+                .with_context(|| format!("Could not write {relative_file}"))?;
+            Ok(relative_file)
+        })
+        .collect()
+}
+
+/// The Nix file with its `mkDerivation rec { }` calls rewritten to use `finalAttrs`. Everything
+/// but the changed expressions is kept as is, including comments and formatting.
+///
+/// References to the attributes of the set, like `${version}`, become `${finalAttrs.version}`,
+/// unless a nested `let`, function argument or `rec` attribute set of the same name shadows them.
+fn rewrite_to_final_attrs(contents: &str) -> anyhow::Result<String> {
+    let root = rnix::Root::parse(contents);
+    if let Some(error) = root.errors().first() {
+        anyhow::bail!("The file can't be parsed: {error}");
+    }
+    let syntax = root.syntax();
+    let sets = syntax
+        .descendants()
+        .filter_map(ast::Apply::cast)
+        .filter(|apply| apply.lambda().as_ref().is_some_and(is_mk_derivation))
+        .filter_map(|apply| match apply.argument()? {
+            ast::Expr::AttrSet(set) if set.rec_token().is_some() => Some(set),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if sets.is_empty() {
+        anyhow::bail!("There is no `mkDerivation rec {{ }}` to migrate");
+    }
+    if syntax
+        .descendants()
+        .filter_map(ast::Ident::cast)
+        .any(|ident| ident_name(&ident).as_deref() == Some(FINAL_ATTRS))
+    {
+        anyhow::bail!("The file already uses the name `{FINAL_ATTRS}`");
+    }
+
+    let mut replacements = Vec::new();
+    for set in &sets {
+        replacements.extend(set_replacements(set));
+    }
+    // Nested sets are replaced from the inside out, such that the ranges never overlap
+    replacements.sort_by_key(|(range, _)| (range.start(), range.end()));
+    let mut migrated = contents.to_owned();
+    for (range, text) in replacements.into_iter().rev() {
+        migrated.replace_range(usize::from(range.start())..usize::from(range.end()), &text);
+    }
+    Ok(migrated)
+}
+
+/// Whether the expression is `mkDerivation` or an attribute selection of it, like
+/// `stdenv.mkDerivation`.
+fn is_mk_derivation(expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Ident(ident) => ident_name(ident).as_deref() == Some("mkDerivation"),
+        ast::Expr::Select(select) => select
+            .attrpath()
+            .and_then(|attrpath| attrpath.attrs().last())
+            .is_some_and(|attr| {
+                matches!(attr, ast::Attr::Ident(ident) if ident_name(&ident).as_deref() == Some("mkDerivation"))
+            }),
+        _ => false,
+    }
+}
+
+/// The text replacements that turn the `rec` attribute set into a function of `finalAttrs`.
+fn set_replacements(set: &ast::AttrSet) -> Vec<(TextRange, String)> {
+    let (Some(rec_token), Some(l_curly_token)) = (set.rec_token(), set.l_curly_token()) else {
+        return Vec::new();
+    };
+    let names = bound_names(set);
+    let set_end = set.syntax().text_range().end();
+    let mut replacements = vec![
+        (
+            TextRange::new(
+                rec_token.text_range().start(),
+                l_curly_token.text_range().start(),
+            ),
+            format!("({FINAL_ATTRS}: "),
+        ),
+        (TextRange::empty(set_end), ")".to_owned()),
+    ];
+
+    for ident in set.syntax().descendants().filter_map(ast::Ident::cast) {
+        let Some(name) = ident_name(&ident) else {
+            continue;
+        };
+        // Attribute names, function arguments and inherited attributes aren't references
+        let is_reference = ident.syntax().parent().is_some_and(|parent| {
+            !(ast::Attrpath::can_cast(parent.kind())
+                || ast::Inherit::can_cast(parent.kind())
+                || ast::IdentParam::can_cast(parent.kind())
+                || ast::PatBind::can_cast(parent.kind())
+                || ast::PatEntry::cast(parent)
+                    .and_then(|entry| entry.ident())
+                    .as_ref()
+                    == Some(&ident))
+        });
+        if is_reference && names.contains(&name) && !is_shadowed(ident.syntax(), set, &name) {
+            replacements.push((ident.syntax().text_range(), format!("{FINAL_ATTRS}.{name}")));
+        }
+    }
+
+    // The attributes a nested `inherit` takes from the set have to be inherited from `finalAttrs`
+    // instead, while the ones of the set itself come from further out and stay as they are
+    for inherit in set.syntax().descendants().filter_map(ast::Inherit::cast) {
+        let Some(container) = inherit.syntax().parent() else {
+            continue;
+        };
+        if inherit.from().is_some() || container == *set.syntax() {
+            continue;
+        }
+        let (from_set, rest): (Vec<_>, Vec<_>) = inherit
+            .attrs()
+            .filter_map(|attr| match attr {
+                ast::Attr::Ident(ident) => ident_name(&ident),
+                _ => None,
+            })
+            .partition(|name| {
+                names.contains(name)
+                    && container
+                        .parent()
+                        .is_none_or(|outer| !is_shadowed(&outer, set, name))
+            });
+        if from_set.is_empty() {
+            continue;
+        }
+        let mut text = format!("inherit ({FINAL_ATTRS}) {};", from_set.join(" "));
+        if !rest.is_empty() {
+            text = format!("inherit {}; {text}", rest.join(" "));
+        }
+        replacements.push((inherit.syntax().text_range(), text));
+    }
+    replacements
+}
+
+/// Whether something between the node and the `rec` attribute set binds the name.
+fn is_shadowed(node: &SyntaxNode, set: &ast::AttrSet, name: &str) -> bool {
+    node.ancestors()
+        .take_while(|ancestor| ancestor != set.syntax())
+        .any(|ancestor| nix_file::binds(&ancestor, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::tempdir;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn rewrites_rec_to_final_attrs() -> anyhow::Result<()> {
+        let migrated = rewrite_to_final_attrs(indoc! {r#"
+            { lib, stdenv, fetchurl }:
+
+            stdenv.mkDerivation rec {
+              pname = "foo";
+              # The version is also in the URL
+              version = "1.0";
+
+              src = fetchurl {
+                url = "https://example.org/${pname}-${version}.tar.gz";
+                inherit version;
+              };
+
+              passthru = let version = "2.0"; in { inherit version; };
+              postInstall = lib.concatMapStrings (pname: pname) [ src.name ];
+              meta.description = "Foo ${foo.version}";
+            }
+        "#})?;
+        assert_eq!(
+            migrated,
+            indoc! {r#"
+                { lib, stdenv, fetchurl }:
+
+                stdenv.mkDerivation (finalAttrs: {
+                  pname = "foo";
+                  # The version is also in the URL
+                  version = "1.0";
+
+                  src = fetchurl {
+                    url = "https://example.org/${finalAttrs.pname}-${finalAttrs.version}.tar.gz";
+                    inherit (finalAttrs) version;
+                  };
+
+                  passthru = let version = "2.0"; in { inherit version; };
+                  postInstall = lib.concatMapStrings (pname: pname) [ finalAttrs.src.name ];
+                  meta.description = "Foo ${foo.version}";
+                })
+            "#}
+        );
+
+        assert!(rewrite_to_final_attrs("stdenv.mkDerivation { }").is_err());
+        assert!(rewrite_to_final_attrs("finalAttrs: stdenv.mkDerivation rec { }").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn migrates_all_packages_or_none() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        let write = |package: &str, contents: &str| -> anyhow::Result<()> {
+            let file = structure::relative_file_for_package(package).to_path(nixpkgs);
+            fs::create_dir_all(file.parent().expect("package files have a parent"))?;
+            Ok(fs::write(file, contents)?)
+        };
+        write("foo", "mkDerivation rec { pname = \"foo\"; name = pname; }")?;
+        write("bar", "mkDerivation { }")?;
+
+        let names = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert!(final_attrs(nixpkgs, &names(&["foo", "bar"])).is_err());
+        assert!(final_attrs(nixpkgs, &names(&["baz"])).is_err());
+        let foo = structure::relative_file_for_package("foo");
+        assert_eq!(
+            fs::read_to_string(foo.to_path(nixpkgs))?,
+            "mkDerivation rec { pname = \"foo\"; name = pname; }"
+        );
+
+        assert_eq!(
+            final_attrs(nixpkgs, &names(&["foo"]))?,
+            ["pkgs/by-name/fo/foo/package.nix"]
+        );
+        assert_eq!(
+            fs::read_to_string(foo.to_path(nixpkgs))?,
+            "mkDerivation (finalAttrs: { pname = \"foo\"; name = finalAttrs.pname; })"
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 //! This is a utility module for interacting with the syntax of Nix files
 
 use crate::location::LineIndex;
+use crate::{interrupt, report, timeout};
 use anyhow::Context;
 use itertools::Either::{self, Left, Right};
 use relative_path::RelativePathBuf;
@@ -10,6 +11,7 @@ use rnix::ast::HasEntry;
 use rowan::TextSize;
 use rowan::TokenAtOffset;
 use rowan::ast::AstNode;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::fs::read_to_string;
@@ -30,9 +32,15 @@ impl NixFileStore {
     /// Note that this function only gives an `anyhow::Result::Err` for I/O errors.
     /// A parse error is `anyhow::Result::Ok(Result::Err(error))`
     pub fn get(&mut self, path: &Path) -> anyhow::Result<&NixFile> {
+        interrupt::check()?;
+        timeout::check(path)?;
         match self.entries.entry(path.to_owned()) {
             Entry::Occupied(entry) => Ok(entry.into_mut()),
-            Entry::Vacant(entry) => Ok(entry.insert(NixFile::new(path)?)),
+            Entry::Vacant(entry) => {
+                tracing::debug!(path = %path.display(), "Parsing Nix file");
+                report::set_file(path);
+                Ok(entry.insert(NixFile::new(path)?))
+            }
         }
     }
 }
@@ -63,7 +71,7 @@ impl NixFile {
         // correctly, though that uses mainline Nix instead of rnix, so it doesn't give the same
         // errors. In the future we should unify these two checks, ideally moving the other CI
         // check into this tool as well and checking for both mainline Nix and rnix.
-        rnix::Root::parse(&contents)
+        timeout::parse(path.as_ref(), contents)?
             // rnix's `rnix::Parse::ok` returns `Result<_, _>`, so no error is thrown away like it
             // would be with `std::result::Result::ok`.
             .ok()
@@ -77,6 +85,100 @@ impl NixFile {
     }
 }
 
+/// Whether all attributes of the `callPackage` argument are the ones of the same name from the
+/// scope, like `inherit foo;` or `foo = foo;`, and none of them is bound by a function argument,
+/// `let` or `rec` attribute set around it. Such attributes can then only come from a `with`, like
+/// the `with pkgs;` of `pkgs/top-level/all-packages.nix`, which `callPackage` uses too.
+fn passes_defaults(attrset: &ast::AttrSet) -> bool {
+    if attrset.rec_token().is_some() {
+        return false;
+    }
+    let mut names = Vec::new();
+    for entry in attrset.entries() {
+        match entry {
+            ast::Entry::Inherit(inherit) => {
+                if inherit.from().is_some() {
+                    return false;
+                }
+                for attr in inherit.attrs() {
+                    let ast::Attr::Ident(ident) = attr else {
+                        return false;
+                    };
+                    names.extend(ident_name(&ident));
+                }
+            }
+            ast::Entry::AttrpathValue(attrpath_value) => {
+                let mut attrs = attrpath_value
+                    .attrpath()
+                    .into_iter()
+                    .flat_map(|attrpath| attrpath.attrs());
+                let (Some(ast::Attr::Ident(attr)), None) = (attrs.next(), attrs.next()) else {
+                    return false;
+                };
+                let Some(Expr::Ident(value)) = attrpath_value.value() else {
+                    return false;
+                };
+                let name = ident_name(&attr);
+                if name.is_none() || name != ident_name(&value) {
+                    return false;
+                }
+                names.extend(name);
+            }
+        }
+    }
+    !attrset
+        .syntax()
+        .ancestors()
+        .skip(1)
+        .any(|ancestor| names.iter().any(|name| binds(&ancestor, name)))
+}
+
+/// Whether the node is a function, `let` or `rec` attribute set that binds the name within it.
+pub fn binds(node: &rnix::SyntaxNode, name: &str) -> bool {
+    let is_name =
+        |ident: Option<ast::Ident>| ident.as_ref().and_then(ident_name).as_deref() == Some(name);
+    if let Some(lambda) = ast::Lambda::cast(node.clone()) {
+        match lambda.param() {
+            Some(ast::Param::IdentParam(param)) => is_name(param.ident()),
+            Some(ast::Param::Pattern(pattern)) => {
+                pattern.pat_entries().any(|entry| is_name(entry.ident()))
+                    || is_name(pattern.pat_bind().and_then(|bind| bind.ident()))
+            }
+            None => false,
+        }
+    } else if let Some(let_in) = ast::LetIn::cast(node.clone()) {
+        bound_names(&let_in).iter().any(|bound| bound == name)
+    } else if let Some(attrset) = ast::AttrSet::cast(node.clone()) {
+        attrset.rec_token().is_some() && bound_names(&attrset).iter().any(|bound| bound == name)
+    } else {
+        false
+    }
+}
+
+/// The names of the attributes the entries bind, like the ones of a `let`.
+pub fn bound_names(entries: &impl HasEntry) -> Vec<String> {
+    entries
+        .entries()
+        .flat_map(|entry| match entry {
+            ast::Entry::AttrpathValue(value) => value
+                .attrpath()
+                .and_then(|attrpath| attrpath.attrs().next())
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ast::Entry::Inherit(inherit) => inherit.attrs().collect(),
+        })
+        .filter_map(|attr| match attr {
+            ast::Attr::Ident(ident) => ident_name(&ident),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The name of an identifier, which isn't known for `or`.
+pub fn ident_name(ident: &ast::Ident) -> Option<String> {
+    ident.ident_token().map(|token| token.text().to_owned())
+}
+
 /// Information about `callPackage` arguments.
 #[derive(Debug, PartialEq, Eq)]
 pub struct CallPackageArgumentInfo {
@@ -85,6 +187,11 @@ pub struct CallPackageArgumentInfo {
 
     /// Whether the second argument is an empty attribute set.
     pub empty_arg: bool,
+
+    /// Whether the second argument is an attribute set that only passes the attributes of the
+    /// same name from the scope, like `{ inherit foo; bar = bar; }`, which `callPackage` passes
+    /// anyway.
+    pub default_arg: bool,
 }
 
 impl NixFile {
@@ -296,13 +403,14 @@ impl NixFile {
         // At this point we know it's something like `foo = <fun> <arg>`.
         // For a callPackage, `<fun>` would be `callPackage ./file` and `<arg>` would be `{ }`.
 
-        let empty_arg = if let Expr::AttrSet(attrset) = arg1 {
+        let (empty_arg, default_arg) = if let Expr::AttrSet(attrset) = arg1 {
             // We can only statically determine whether the argument is empty if it's an attribute
             // set _expression_, even though other kind of expressions could evaluate to an
             // attribute set _value_. But this is what we want anyway.
-            attrset.entries().next().is_none()
+            let empty_arg = attrset.entries().next().is_none();
+            (empty_arg, !empty_arg && passes_defaults(&attrset))
         } else {
-            false
+            (false, false)
         };
 
         // Because `callPackage` takes two curried arguments, the first function needs to be a
@@ -385,6 +493,7 @@ impl NixFile {
             Ok(Some(CallPackageArgumentInfo {
                 relative_path: path,
                 empty_arg,
+                default_arg,
             }))
         } else {
             Ok(None)
@@ -404,8 +513,9 @@ pub enum ResolvedPath {
     /// have the right permissions.
     Unresolvable(std::io::Error),
 
-    /// The path is outside the given absolute path.
-    Outside,
+    /// The path is outside the given absolute path. The `PathBuf` is the absolute path it resolves
+    /// to.
+    Outside(PathBuf),
 
     /// The path is within the given absolute path. The `RelativePathBuf` is the relative path
     /// under the given absolute path.
@@ -441,7 +551,7 @@ impl NixFile {
             Ok(resolved) => {
                 // Check if it's within relative_to.
                 match resolved.strip_prefix(relative_to) {
-                    Err(_prefix_error) => ResolvedPath::Outside,
+                    Err(_prefix_error) => ResolvedPath::Outside(resolved),
                     Ok(suffix) => ResolvedPath::Within(
                         RelativePathBuf::from_path(suffix).expect("a relative path"),
                     ),
@@ -451,10 +561,274 @@ impl NixFile {
     }
 }
 
+impl NixFile {
+    /// Returns the names, lines and columns of the attributes defined by the outermost attribute
+    /// sets of the file, like the aliases in `mapAliases { foo = bar; }`. Attribute sets that are
+    /// the value of a binding, like in `let x = { }; in ...`, are skipped, as are attributes whose
+    /// names can't be known statically.
+    pub fn top_level_attributes(&self) -> Vec<(String, usize, usize)> {
+        self.top_level_bindings()
+            .filter_map(|attrpath_value| {
+                let attrpath = attrpath_value.attrpath()?;
+                let mut attrs = attrpath.attrs();
+                let name = match (attrs.next()?, attrs.next()) {
+                    (ast::Attr::Ident(ident), None) => ident.ident_token()?.text().to_owned(),
+                    (ast::Attr::Str(string), None) => match &string.normalized_parts()[..] {
+                        [ast::InterpolPart::Literal(name)] => name.clone(),
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                let offset = usize::from(attrpath.syntax().text_range().start());
+                Some((
+                    name,
+                    self.line_index.line(offset),
+                    self.line_index.column(offset),
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the bindings of the outermost attribute sets of the file, see
+    /// `top_level_attributes`.
+    pub fn top_level_bindings(&self) -> impl Iterator<Item = ast::AttrpathValue> {
+        self.syntax_root
+            .syntax()
+            .descendants()
+            .filter_map(ast::AttrpathValue::cast)
+            .filter(|attrpath_value| {
+                let Some(parent) = attrpath_value.syntax().parent() else {
+                    return false;
+                };
+                ast::AttrSet::can_cast(parent.kind())
+                    && !parent
+                        .ancestors()
+                        .any(|ancestor| ast::AttrpathValue::can_cast(ancestor.kind()))
+            })
+    }
+}
+
+/// A reference from `passthru.tests` of a package to an attribute of one of the arguments of the
+/// package function, like `nixosTests.foo` in `passthru.tests.foo = nixosTests.foo`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TestReference {
+    /// The attribute path, starting with the argument, like `["nixosTests", "foo"]`.
+    pub attribute_path: Vec<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl NixFile {
+    /// Returns the references in `passthru.tests` to attributes of the arguments of the function
+    /// defined by the file, as long as they're static attribute paths without a default.
+    pub fn passthru_test_references(&self) -> Vec<TestReference> {
+        let Some(Expr::Lambda(lambda)) = self.syntax_root.expr() else {
+            return vec![];
+        };
+        let Some(ast::Param::Pattern(pattern)) = lambda.param() else {
+            return vec![];
+        };
+        let arguments: Vec<String> = pattern
+            .pat_entries()
+            .filter_map(|entry| Some(entry.ident()?.ident_token()?.text().to_owned()))
+            .collect();
+
+        self.syntax_root
+            .syntax()
+            .descendants()
+            .filter_map(ast::Select::cast)
+            .filter(|select| select.default_expr().is_none() && is_in_passthru_tests(select))
+            .filter_map(|select| {
+                let Some(Expr::Ident(ident)) = select.expr() else {
+                    return None;
+                };
+                let argument = ident.ident_token()?.text().to_owned();
+                if !arguments.contains(&argument) {
+                    return None;
+                }
+                let mut attribute_path = vec![argument];
+                for attr in select.attrpath()?.attrs() {
+                    let ast::Attr::Ident(ident) = attr else {
+                        return None;
+                    };
+                    attribute_path.push(ident.ident_token()?.text().to_owned());
+                }
+                let offset = usize::from(select.syntax().text_range().start());
+                Some(TestReference {
+                    attribute_path,
+                    line: self.line_index.line(offset),
+                    column: self.line_index.column(offset),
+                })
+            })
+            .collect()
+    }
+}
+
+impl NixFile {
+    /// Returns the names, lines and columns of the teams in `meta.teams`, like `foo` for both
+    /// `lib.teams.foo` and `foo` in `with lib.teams; [ foo ]`.
+    pub fn meta_teams(&self) -> Vec<(String, usize, usize)> {
+        self.meta_list("teams")
+    }
+
+    /// Returns the handles, lines and columns of the maintainers in `meta.maintainers`, like
+    /// `foo` for both `lib.maintainers.foo` and `foo` in `with lib.maintainers; [ foo ]`.
+    pub fn meta_maintainers(&self) -> Vec<(String, usize, usize)> {
+        self.meta_list("maintainers")
+    }
+
+    /// Returns the names of the elements of the list in `meta.<attribute>`, ignoring elements that
+    /// aren't a name or a selection of one.
+    fn meta_list(&self, attribute: &str) -> Vec<(String, usize, usize)> {
+        self.syntax_root
+            .syntax()
+            .descendants()
+            .filter_map(ast::AttrpathValue::cast)
+            .filter(|binding| {
+                binding_names(binding.syntax())
+                    .ends_with(&["meta".to_owned(), attribute.to_owned()])
+            })
+            .filter_map(|binding| {
+                let mut value = binding.value()?;
+                while let Expr::With(with) = value {
+                    value = with.body()?;
+                }
+                match value {
+                    Expr::List(list) => Some(list),
+                    _ => None,
+                }
+            })
+            .flat_map(|list| list.items())
+            .filter_map(|item| {
+                let name = match &item {
+                    Expr::Ident(ident) => ident.ident_token()?.text().to_owned(),
+                    Expr::Select(select) if select.default_expr().is_none() => {
+                        match select.attrpath()?.attrs().last()? {
+                            ast::Attr::Ident(ident) => ident.ident_token()?.text().to_owned(),
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                };
+                let offset = usize::from(item.syntax().text_range().start());
+                Some((
+                    name,
+                    self.line_index.line(offset),
+                    self.line_index.column(offset),
+                ))
+            })
+            .collect()
+    }
+}
+
+/// A call of a builder function like `buildPythonPackage { ... }`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BuilderCall {
+    /// The names of the attributes passed to the builder, like `pyproject` for both
+    /// `pyproject = true;` and `inherit pyproject;`.
+    pub attributes: BTreeSet<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl NixFile {
+    /// Returns the calls of the builder with an attribute set, which may also be the result of a
+    /// function like `finalAttrs: { ... }`. The builder matches both `builder` and a selection
+    /// ending in it, like `python3.pkgs.builder`.
+    pub fn builder_calls(&self, builder: &str) -> Vec<BuilderCall> {
+        self.syntax_root
+            .syntax()
+            .descendants()
+            .filter_map(ast::Apply::cast)
+            .filter(|apply| {
+                let name = match apply.lambda() {
+                    Some(Expr::Ident(ident)) => {
+                        ident.ident_token().map(|token| token.text().to_owned())
+                    }
+                    Some(Expr::Select(select)) => match select
+                        .attrpath()
+                        .and_then(|attrpath| attrpath.attrs().last())
+                    {
+                        Some(ast::Attr::Ident(ident)) => {
+                            ident.ident_token().map(|token| token.text().to_owned())
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                name.as_deref() == Some(builder)
+            })
+            .filter_map(|apply| {
+                let mut argument = apply.argument()?;
+                let attr_set = loop {
+                    argument = match argument {
+                        Expr::Paren(paren) => paren.expr()?,
+                        Expr::Lambda(lambda) => lambda.body()?,
+                        Expr::AttrSet(attr_set) => break attr_set,
+                        _ => return None,
+                    };
+                };
+                let mut attributes = BTreeSet::new();
+                for entry in attr_set.entries() {
+                    match entry {
+                        ast::Entry::AttrpathValue(attrpath_value) => {
+                            if let Some(attr) = attrpath_value
+                                .attrpath()
+                                .and_then(|attrpath| attrpath.attrs().next())
+                            {
+                                attributes.insert(attr.syntax().text().to_string());
+                            }
+                        }
+                        ast::Entry::Inherit(inherit) => {
+                            attributes.extend(
+                                inherit.attrs().map(|attr| attr.syntax().text().to_string()),
+                            );
+                        }
+                    }
+                }
+                let offset = usize::from(apply.syntax().text_range().start());
+                Some(BuilderCall {
+                    attributes,
+                    line: self.line_index.line(offset),
+                    column: self.line_index.column(offset),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Whether the expression is in the value of `passthru.tests`, which may be defined in several
+/// bindings like `passthru = { tests = ...; }`.
+fn is_in_passthru_tests(node: &ast::Select) -> bool {
+    binding_names(node.syntax())
+        .windows(2)
+        .any(|window| window[0] == "passthru" && window[1] == "tests")
+}
+
+/// The names of the bindings the node is in from the outermost to the innermost, like
+/// `["meta", "teams"]` for a node in `meta = { teams = ...; }` or `meta.teams = ...`.
+fn binding_names(node: &rnix::SyntaxNode) -> Vec<String> {
+    let mut names: Vec<String> = node
+        .ancestors()
+        .filter_map(ast::AttrpathValue::cast)
+        .filter_map(|binding| binding.attrpath())
+        .flat_map(|attrpath| {
+            // Reversed to match the order of the ancestors
+            let names: Vec<String> = attrpath
+                .attrs()
+                .map(|attr| attr.syntax().text().to_string())
+                .collect();
+            names.into_iter().rev()
+        })
+        .collect();
+    names.reverse();
+    names
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests;
+    use crate::testing;
     use indoc::indoc;
     use pretty_assertions::assert_eq;
     use std::collections::BTreeMap;
@@ -467,7 +841,7 @@ mod tests {
 
     #[test]
     fn detects_attributes() -> anyhow::Result<()> {
-        let temp_dir = tests::tempdir()?;
+        let temp_dir = testing::tempdir()?;
         let file = temp_dir.path().join("file.nix");
         let contents = indoc! {r#"
             testG: {
@@ -582,7 +956,7 @@ mod tests {
 
     #[test]
     fn detects_call_package() -> anyhow::Result<()> {
-        let temp_dir = tests::tempdir()?;
+        let temp_dir = testing::tempdir()?;
         let file = temp_dir.path().join("file.nix");
         let contents = indoc! {r"
             self: with self; {
@@ -595,6 +969,9 @@ mod tests {
               g = callPackage ({ }: { }) { };
               h = callPackage ./file.nix { x = 0; };
               i = callPackage ({ }: { }) (let in { });
+              j = callPackage ./file.nix { inherit a; b = b; };
+              k = callPackage ./file.nix { inherit self; };
+              l = callPackage ./file.nix { inherit (self) a; };
             }
         "};
 
@@ -612,6 +989,7 @@ mod tests {
                 Some(CallPackageArgumentInfo {
                     relative_path: Some(RelativePathBuf::from("file.nix")),
                     empty_arg: true,
+                    default_arg: false,
                 }),
             ),
             (
@@ -619,6 +997,7 @@ mod tests {
                 Some(CallPackageArgumentInfo {
                     relative_path: Some(RelativePathBuf::from("file.nix")),
                     empty_arg: true,
+                    default_arg: false,
                 }),
             ),
             (
@@ -626,6 +1005,7 @@ mod tests {
                 Some(CallPackageArgumentInfo {
                     relative_path: None,
                     empty_arg: true,
+                    default_arg: false,
                 }),
             ),
             (
@@ -633,6 +1013,7 @@ mod tests {
                 Some(CallPackageArgumentInfo {
                     relative_path: Some(RelativePathBuf::from("file.nix")),
                     empty_arg: false,
+                    default_arg: false,
                 }),
             ),
             (
@@ -640,6 +1021,32 @@ mod tests {
                 Some(CallPackageArgumentInfo {
                     relative_path: None,
                     empty_arg: false,
+                    default_arg: false,
+                }),
+            ),
+            (
+                11,
+                Some(CallPackageArgumentInfo {
+                    relative_path: Some(RelativePathBuf::from("file.nix")),
+                    empty_arg: false,
+                    default_arg: true,
+                }),
+            ),
+            // `self` is the argument of the file, not the attribute of the scope
+            (
+                12,
+                Some(CallPackageArgumentInfo {
+                    relative_path: Some(RelativePathBuf::from("file.nix")),
+                    empty_arg: false,
+                    default_arg: false,
+                }),
+            ),
+            (
+                13,
+                Some(CallPackageArgumentInfo {
+                    relative_path: Some(RelativePathBuf::from("file.nix")),
+                    empty_arg: false,
+                    default_arg: false,
                 }),
             ),
         ];
@@ -653,4 +1060,137 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn detects_top_level_attributes() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let file = temp_dir.path().join("aliases.nix");
+        let contents = indoc! {r#"
+            lib: self: super:
+            let
+              helper = { notAnAlias = 1; };
+            in
+            lib.mapAliases {
+              foo = self.bar;
+              "baz-qux" = self.bar;
+              ${"dynamic"} = self.bar;
+              nested.attr = self.bar;
+            }
+        "#};
+        std::fs::write(&file, contents)?;
+
+        let attributes = NixFileStore::default().get(&file)?.top_level_attributes();
+        assert_eq!(
+            attributes,
+            vec![("foo".to_owned(), 6, 3), ("baz-qux".to_owned(), 7, 3)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detects_passthru_test_references() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let file = temp_dir.path().join("package.nix");
+        let contents = indoc! {r#"
+            { stdenv, nixosTests, callPackage }:
+            stdenv.mkDerivation {
+              passthru.tests = {
+                basic = nixosTests.foo;
+                nested = nixosTests.foo.bar;
+                withDefault = nixosTests.baz or null;
+                local = callPackage ./test.nix { };
+              };
+              passthru = {
+                tests.other = nixosTests.qux;
+              };
+              meta.broken = nixosTests.notATest;
+            }
+        "#};
+        std::fs::write(&file, contents)?;
+
+        let references = NixFileStore::default()
+            .get(&file)?
+            .passthru_test_references()
+            .into_iter()
+            .map(|reference| (reference.attribute_path.join("."), reference.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            references,
+            vec![
+                ("nixosTests.foo".to_owned(), 4),
+                ("nixosTests.foo.bar".to_owned(), 5),
+                ("nixosTests.qux".to_owned(), 10),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detects_meta_teams() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let file = temp_dir.path().join("package.nix");
+        let contents = indoc! {r#"
+            { lib, stdenv }:
+            stdenv.mkDerivation {
+              meta = {
+                teams = [ lib.teams.foo ];
+              };
+              passthru.teams = [ lib.teams.notInMeta ];
+              meta.teams = with lib.teams; [
+                bar
+                (lib.teams.dynamic or null)
+              ];
+            }
+        "#};
+        std::fs::write(&file, contents)?;
+
+        let teams = NixFileStore::default().get(&file)?.meta_teams();
+        assert_eq!(
+            teams,
+            vec![("foo".to_owned(), 4, 15), ("bar".to_owned(), 8, 5)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detects_builder_calls() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let file = temp_dir.path().join("default.nix");
+        let contents = indoc! {r#"
+            { lib, buildPythonPackage, python3 }:
+            {
+              foo = buildPythonPackage rec {
+                pname = "foo";
+                inherit (lib) version;
+                meta.description = "Foo";
+              };
+              bar = python3.pkgs.buildPythonPackage (finalAttrs: {
+                format = "setuptools";
+              });
+              baz = buildPythonApplication { pyproject = true; };
+            }
+        "#};
+        std::fs::write(&file, contents)?;
+
+        let calls = NixFileStore::default()
+            .get(&file)?
+            .builder_calls("buildPythonPackage");
+        let attributes = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect();
+        assert_eq!(
+            calls,
+            vec![
+                BuilderCall {
+                    attributes: attributes(&["meta", "pname", "version"]),
+                    line: 3,
+                    column: 9,
+                },
+                BuilderCall {
+                    attributes: attributes(&["format"]),
+                    line: 8,
+                    column: 9,
+                },
+            ]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,125 @@
+//! Checks of the NixOS modules in `nixos/modules`, see `--check nixos-modules`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use relative_path::{RelativePath, RelativePathBuf};
+use rowan::ast::AstNode;
+
+use crate::nix_file::{NixFileStore, ResolvedPath};
+use crate::problem::{npv_112, npv_132, npv_133, npv_134};
+use crate::validation::{self, Validation::Success};
+use crate::{files, git};
+
+/// The directory with the NixOS modules, relative to Nixpkgs.
+pub const MODULES_SUBPATH: &str = "nixos/modules";
+
+/// The file listing all NixOS modules, relative to `MODULES_SUBPATH`.
+pub const MODULE_LIST_FILENAME: &str = "module-list.nix";
+
+/// Checks that the modules in the module list exist, that every module is used by the module list,
+/// either directly or through `imports` of a used module, and that all modules parse.
+pub fn check_nixos_modules(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+) -> validation::Result<()> {
+    let _span = tracing::info_span!("nixos-modules").entered();
+    let relative_modules_dir = RelativePath::new(MODULES_SUBPATH);
+    let modules_dir = relative_modules_dir.to_path(nixpkgs_path);
+    let relative_module_list = relative_modules_dir.join(MODULE_LIST_FILENAME);
+    if !modules_dir.join(MODULE_LIST_FILENAME).is_file() {
+        return Ok(if git::is_sparse_checkout(nixpkgs_path) {
+            // In a sparse checkout, nixos/modules may just not be checked out
+            npv_112::NixpkgsPathMissing::new(relative_module_list).into()
+        } else {
+            // No NixOS modules, always valid
+            Success(())
+        });
+    }
+
+    let mut files = vec![];
+    files::collect_nix_files(&modules_dir, RelativePath::new(""), &mut files)?;
+
+    // Files that don't parse are reported, but can't be followed any further
+    let mut invalid = BTreeSet::new();
+    let mut problems = vec![];
+    for file in &files {
+        let path = file.to_path(&modules_dir);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read file {}", path.display()))?;
+        if let Some(error) = rnix::Root::parse(&contents).errors().first() {
+            invalid.insert(file.clone());
+            problems.push(
+                npv_134::NixosModuleInvalidSyntax::new(
+                    relative_modules_dir.join(file),
+                    error.to_string(),
+                )
+                .into(),
+            );
+        }
+    }
+
+    // Starting from the module list, follow the references of all used modules
+    let mut referenced = vec![RelativePathBuf::from(MODULE_LIST_FILENAME)];
+    let mut to_visit = vec![RelativePathBuf::from(MODULE_LIST_FILENAME)];
+    let mut visited = BTreeSet::new();
+    while let Some(file) = to_visit.pop() {
+        if invalid.contains(&file) || !visited.insert(file.clone()) {
+            continue;
+        }
+        let nix_file = nix_file_store.get(&file.to_path(&modules_dir))?;
+        let is_module_list = file == MODULE_LIST_FILENAME;
+        for path in nix_file
+            .syntax_root
+            .syntax()
+            .descendants()
+            .filter_map(rnix::ast::Path::cast)
+        {
+            match nix_file.static_resolve_path(&path, &modules_dir) {
+                ResolvedPath::Within(relative_path) => {
+                    // Referring to a directory uses the modules in it, like `default.nix`
+                    to_visit.extend(
+                        files
+                            .iter()
+                            .filter(|file| file.starts_with(&relative_path))
+                            .cloned(),
+                    );
+                    referenced.push(relative_path);
+                }
+                // Missing files referred to by modules are checked by evaluating NixOS, but the
+                // module list is what the manual and the tests are built from.
+                ResolvedPath::Unresolvable(err) if is_module_list => {
+                    let start = path.syntax().text_range().start().into();
+                    problems.push(
+                        npv_132::NixosModuleListEntryMissing::new(
+                            relative_module_list.clone(),
+                            nix_file.line_index.line(start),
+                            nix_file.line_index.column(start),
+                            path.syntax().text().to_string(),
+                            Arc::new(err),
+                        )
+                        .into(),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    problems.extend(
+        files
+            .into_iter()
+            .filter(|file| !referenced.iter().any(|path| file.starts_with(path)))
+            .map(|file| {
+                npv_133::NixosModuleUnused::new(
+                    relative_module_list.clone(),
+                    relative_modules_dir.join(file),
+                )
+                .into()
+            }),
+    );
+    Ok(problems.into_iter().collect())
+}
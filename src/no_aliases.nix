@@ -0,0 +1,32 @@
+# Takes a path to nixpkgs and a path to the json-encoded list of attributes in `pkgs/by-name`, and
+# evaluates the derivations of the attributes with `config.allowAliases = false` for `system`.
+#
+# An attribute that uses an alias doesn't evaluate then, and since `callPackage` aborts on missing
+# arguments instead of throwing, that can't be caught with `builtins.tryEval`. So the evaluation
+# fails if any of the attributes uses an alias. See ./no_aliases.rs for how the failing attributes
+# are found and the failures are attributed to the aliases.
+{
+  attrsPath,
+  nixpkgsPath,
+  system ? "x86_64-linux",
+}:
+let
+  attrs = builtins.fromJSON (builtins.readFile attrsPath);
+
+  pkgs = import nixpkgsPath {
+    config = {
+      allowAliases = false;
+    };
+    overlays = [ ];
+    inherit system;
+  };
+
+  # Failures that can be caught aren't about aliases, like the ones of unfree or broken packages
+  evaluates =
+    name:
+    let
+      value = pkgs.${name};
+    in
+    if pkgs.lib.isDerivation value then (builtins.tryEval value.drvPath).success else false;
+in
+map evaluates attrs
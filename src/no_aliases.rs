@@ -0,0 +1,139 @@
+//! Evaluates the changed packages in `pkgs/by-name` with `config.allowAliases = false`, to find
+//! the ones using aliases, see `--check no-aliases`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use itertools::concat;
+use regex::Regex;
+use serde::de::IgnoredAny;
+use serde_json::json;
+
+use crate::aliases;
+use crate::config::Config;
+use crate::eval;
+use crate::nix_file::NixFileStore;
+use crate::problem::npv_153;
+use crate::scope::Scope;
+use crate::validation::{self, Validation::Success};
+
+const NO_ALIASES_NIX: &[u8] = include_bytes!("no_aliases.nix");
+
+/// Matches the errors of Nix about a missing attribute, like `attribute 'foo' missing`, and the
+/// ones of `callPackage` about a missing argument, like `without required argument "foo"`.
+static MISSING_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"attribute '([^']+)' missing|without required argument "([^"]+)""#)
+        .expect("valid regex")
+});
+
+/// Checks that the packages in `pkgs/by-name` that are in scope evaluate without the aliases of
+/// `pkgs/top-level/aliases.nix`, which some users of Nixpkgs disable, see `eval::evaluate_json`.
+/// The packages are evaluated for each of the configured systems, see `eval::for_each_system`.
+///
+/// Failures are only reported if they're about an alias, since other ones are found by the other
+/// checks.
+pub fn check_no_aliases(
+    nixpkgs_path: &Path,
+    nix_file_store: &mut NixFileStore,
+    package_names: &[String],
+    scope: &Scope,
+    config: &Config,
+) -> validation::Result<()> {
+    let _span = tracing::info_span!("no-aliases").entered();
+    if scope.is_everything() {
+        return Ok(Success(()));
+    }
+    let aliases = aliases::alias_targets(nixpkgs_path, nix_file_store)?;
+    let attributes: Vec<&str> = package_names
+        .iter()
+        .filter(|name| scope.contains_package(name))
+        .map(String::as_str)
+        .collect();
+    if aliases.is_empty() || attributes.is_empty() {
+        return Ok(Success(()));
+    }
+
+    eval::for_each_system(config, |system| {
+        Ok(failing_packages(nixpkgs_path, &attributes, system, config)?
+            .into_iter()
+            .filter_map(|(package_name, stderr)| {
+                let alias = used_alias(&String::from_utf8_lossy(&stderr), &aliases)?;
+                let target = aliases[alias].clone();
+                Some(npv_153::ByNameUsesAlias::new(package_name, alias, target).into())
+            })
+            .collect())
+    })
+}
+
+/// The packages whose evaluation fails without aliases, with the error output of evaluating each
+/// of them on its own.
+///
+/// All packages are evaluated by a single process, which fails if any of them does, since such
+/// failures can't be caught. Then the halves of the packages are evaluated again, until each of
+/// the failing packages is evaluated on its own.
+fn failing_packages<'a>(
+    nixpkgs_path: &Path,
+    attributes: &[&'a str],
+    system: &str,
+    config: &Config,
+) -> anyhow::Result<Vec<(&'a str, Vec<u8>)>> {
+    let Err(stderr) = eval::evaluate_json::<IgnoredAny>(
+        "no-aliases",
+        NO_ALIASES_NIX,
+        nixpkgs_path,
+        &[("attrsPath", json!(attributes))],
+        Some(system),
+        config,
+    )?
+    else {
+        return Ok(vec![]);
+    };
+    if let [attribute] = attributes {
+        return Ok(vec![(*attribute, stderr)]);
+    }
+    let (first, second) = attributes.split_at(attributes.len() / 2);
+    Ok(concat([
+        failing_packages(nixpkgs_path, first, system, config)?,
+        failing_packages(nixpkgs_path, second, system, config)?,
+    ]))
+}
+
+/// The alias whose absence made the evaluation fail according to its error output, if any.
+fn used_alias<'a>(stderr: &str, aliases: &'a BTreeMap<String, Option<String>>) -> Option<&'a str> {
+    MISSING_REGEX.captures_iter(stderr).find_map(|captures| {
+        let name = captures.get(1).or_else(|| captures.get(2))?.as_str();
+        aliases.get_key_value(name).map(|(alias, _)| alias.as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_used_aliases() {
+        let aliases = BTreeMap::from([
+            ("foo".to_owned(), Some("bar".to_owned())),
+            ("baz".to_owned(), None),
+        ]);
+        assert_eq!(
+            used_alias(
+                "error: lib.customisation.callPackageWith: Function called without required argument \"foo\" at /nix/store/...-source/pkgs/by-name/qu/qux/package.nix:1:1",
+                &aliases
+            ),
+            Some("foo")
+        );
+        assert_eq!(
+            used_alias(
+                "error: attribute 'glibc' missing\n\nerror: attribute 'baz' missing",
+                &aliases
+            ),
+            Some("baz")
+        );
+        assert_eq!(
+            used_alias("error: attribute 'glibc' missing", &aliases),
+            None
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! The JSON of a shields.io badge with the results of the checks, see `--report badge`.
+
+use serde_json::{Value, json};
+
+use crate::stats::Stats;
+use crate::status::{Severity, Status};
+
+/// Creates the JSON for shields.io's endpoint badges, with the number of problems and how much
+/// of Nixpkgs is migrated to `pkgs/by-name`.
+///
+/// See <https://shields.io/badges/endpoint-badge>.
+pub fn badge(status: &Status, stats: &Stats) -> Value {
+    let problems = match status.errors().map_or(0, Vec::len) {
+        0 => "no problems".to_owned(),
+        1 => "1 problem".to_owned(),
+        count => format!("{count} problems"),
+    };
+    let color = match status.severity() {
+        None => "brightgreen",
+        Some(Severity::Warning) => "yellow",
+        Some(Severity::Error) => "red",
+    };
+    json!({
+        "schemaVersion": 1,
+        "label": "nixpkgs-vet",
+        "message": format!("{problems}, {:.1}% by-name", stats.migrated_percentage),
+        "color": color,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::npv_143;
+
+    #[test]
+    fn summarizes_status() {
+        let stats = Stats {
+            migrated_percentage: 93.25,
+            ..Stats::default()
+        };
+        assert_eq!(
+            badge(&Status::ValidatedSuccessfully, &stats),
+            json!({
+                "schemaVersion": 1,
+                "label": "nixpkgs-vet",
+                "message": "no problems, 93.2% by-name",
+                "color": "brightgreen",
+            })
+        );
+        let status = Status::ProblemsIntroduced(vec![
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/ba/bar").into(),
+        ]);
+        assert_eq!(
+            badge(&status, &stats)["message"],
+            "2 problems, 93.2% by-name"
+        );
+        assert_eq!(badge(&status, &stats)["color"], "red");
+    }
+}
@@ -0,0 +1,100 @@
+//! Problems as a GitLab Code Quality report, which merge requests show inline, see
+//! `--format codeclimate`.
+//!
+//! See <https://docs.gitlab.com/ci/testing/code_quality/#code-quality-report-format>.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+use sha2::{Digest as _, Sha256};
+
+use crate::delta;
+use crate::status::{Severity, Status};
+
+/// Renders a Code Quality issue for each problem.
+///
+/// GitLab tells the issues that a merge request resolves or introduces apart by their
+/// fingerprints, so they only depend on what tells a problem apart from others, not on its line,
+/// which unrelated changes to the file move. Problems that are the same otherwise are numbered.
+/// Fingerprints are SHA-256 hashes, such that they stay the same with other builds of nixpkgs-vet.
+pub fn render(status: &Status) -> Value {
+    let severity = match status.severity() {
+        Some(Severity::Warning) => "minor",
+        _ => "major",
+    };
+    let mut occurrences: BTreeMap<String, usize> = BTreeMap::new();
+    let issues = status
+        .errors()
+        .into_iter()
+        .flatten()
+        .map(|problem| {
+            let identity = delta::identity(problem);
+            let occurrence = occurrences.entry(identity.clone()).or_default();
+            let fingerprint = Sha256::new()
+                .chain_update((identity.len() as u64).to_be_bytes())
+                .chain_update(&identity)
+                .chain_update((*occurrence as u64).to_be_bytes())
+                .finalize();
+            *occurrence += 1;
+
+            let location = problem.location();
+            json!({
+                "description": problem.summary(),
+                "check_name": problem.code(),
+                "fingerprint": format!("{fingerprint:x}"),
+                "severity": severity,
+                "location": {
+                    // GitLab requires a path, so problems about all of Nixpkgs are about its root
+                    "path": location.as_ref().map_or(".", |location| location.path.as_str()),
+                    "lines": {
+                        "begin": location.and_then(|location| location.line).unwrap_or(1),
+                    },
+                },
+            })
+        })
+        .collect();
+    Value::Array(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{Problem, npv_143, npv_146};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_issues() {
+        let unsorted = |line: usize| -> Problem {
+            npv_146::TopLevelEntryUnsorted::new(
+                "foo",
+                "bar",
+                true,
+                Location::new("pkgs/top-level/all-packages.nix", line, 3),
+            )
+            .into()
+        };
+        let report = render(&Status::ProblemsIntroduced(vec![
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+            unsorted(2),
+            unsorted(5),
+        ]));
+        let issues = report.as_array().expect("the report is an array");
+        assert_eq!(issues[0]["check_name"], "NPV-143");
+        assert_eq!(issues[0]["severity"], "major");
+        assert_eq!(issues[0]["location"]["path"], "pkgs/by-name/fo/foo");
+        assert_eq!(issues[0]["location"]["lines"]["begin"], 1);
+        assert_eq!(issues[1]["location"]["lines"]["begin"], 2);
+
+        // The fingerprints don't change when the lines do, but tell the same problems apart
+        let moved = render(&Status::ProblemsIntroduced(vec![unsorted(7)]));
+        assert_eq!(moved[0]["fingerprint"], issues[1]["fingerprint"]);
+        assert_ne!(issues[1]["fingerprint"], issues[2]["fingerprint"]);
+        // GitLab compares them to the ones of earlier runs
+        assert_eq!(
+            issues[0]["fingerprint"],
+            "f14ad48f55ea62bd30179b3bebca36ee0f13363dc06c019476b4eb3e4dc915b0"
+        );
+        assert_eq!(render(&Status::ValidatedSuccessfully), json!([]));
+    }
+}
@@ -0,0 +1,124 @@
+//! Problems as comma-separated values, to triage them in a spreadsheet, see `--format csv`.
+
+use clap::ValueEnum;
+
+use crate::problem::Problem;
+use crate::status::{Severity, Status};
+
+/// The columns of the CSV output, see `--csv-columns`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CsvColumn {
+    /// The code of the check that found the problem, like `NPV-123`.
+    Code,
+    /// Whether the problem is an `error` or a `warning`.
+    Severity,
+    /// The path the problem is about, relative to Nixpkgs.
+    Path,
+    /// The line the problem is at, if it's about a specific one.
+    Line,
+    /// The first line of the message of the problem.
+    Message,
+}
+
+impl CsvColumn {
+    /// The columns in the order of the variants, which is the default of `--csv-columns`.
+    pub const ALL: [Self; 5] = [
+        Self::Code,
+        Self::Severity,
+        Self::Path,
+        Self::Line,
+        Self::Message,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Code => "code",
+            Self::Severity => "severity",
+            Self::Path => "path",
+            Self::Line => "line",
+            Self::Message => "message",
+        }
+    }
+
+    fn value(self, problem: &Problem, severity: Option<Severity>) -> String {
+        match self {
+            Self::Code => problem.code().to_owned(),
+            Self::Severity => match severity {
+                Some(Severity::Error) => "error".to_owned(),
+                Some(Severity::Warning) => "warning".to_owned(),
+                None => String::new(),
+            },
+            Self::Path => problem
+                .location()
+                .map(|location| location.path.into_string())
+                .unwrap_or_default(),
+            Self::Line => problem
+                .location()
+                .and_then(|location| location.line)
+                .map(|line| line.to_string())
+                .unwrap_or_default(),
+            Self::Message => problem.summary(),
+        }
+    }
+}
+
+/// Renders a header row with the names of the columns, followed by a row for each problem.
+pub fn render(status: &Status, columns: &[CsvColumn]) -> String {
+    let severity = status.severity();
+    let header = columns.iter().map(|column| column.name().to_owned());
+    let rows = status.errors().into_iter().flatten().map(|problem| {
+        columns
+            .iter()
+            .map(|column| column.value(problem, severity))
+            .collect::<Vec<_>>()
+    });
+    std::iter::once(header.collect())
+        .chain(rows)
+        .map(|row: Vec<String>| {
+            let fields = row.iter().map(|field| quote(field)).collect::<Vec<_>>();
+            format!("{}\r\n", fields.join(","))
+        })
+        .collect()
+}
+
+/// Quotes the field if needed, as in RFC 4180.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_143, npv_146};
+
+    #[test]
+    fn renders_rows() {
+        let status = Status::ProblemsIntroduced(vec![
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+            npv_146::TopLevelEntryUnsorted::new(
+                "foo",
+                "bar",
+                true,
+                Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+            )
+            .into(),
+        ]);
+        assert_eq!(
+            render(&status, &CsvColumn::ALL),
+            "code,severity,path,line,message\r\n\
+            NPV-143,error,pkgs/by-name/fo/foo,,\"pkgs/by-name/fo/foo: Missing required \"\"package.nix\"\" file.\"\r\n\
+            NPV-146,error,pkgs/top-level/all-packages.nix,2,\"pkgs/top-level/all-packages.nix: \
+            The entry `foo` at line 2 comes after `bar`, but the entries of each section need to be \
+            sorted alphabetically, ignoring case. Please move it to the right place.\"\r\n"
+        );
+        assert_eq!(
+            render(&status, &[CsvColumn::Line, CsvColumn::Code]),
+            "line,code\r\n,NPV-143\r\n2,NPV-146\r\n"
+        );
+    }
+}
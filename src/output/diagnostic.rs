@@ -0,0 +1,165 @@
+//! Problems rendered like the diagnostics of rustc, which many editors and people know how to
+//! read, see `--format diagnostic`.
+
+use std::fmt::Write as _;
+
+use colored::Colorize as _;
+
+use crate::status::{ColoredStatus, Severity};
+
+/// Renders each problem with a header of its severity, code and summary, the source code it's
+/// about if known, and the rest of its message and its fix as notes, followed by the message of
+/// the status.
+pub fn render(status: &ColoredStatus, use_color: bool) -> String {
+    let paint = |text: &str, severity: Option<Severity>| match severity {
+        Some(severity) if use_color => text.color(severity.color()).bold().to_string(),
+        None if use_color => text.bold().to_string(),
+        _ => text.to_owned(),
+    };
+    let severity = status.status().severity();
+    let level = match severity {
+        Some(Severity::Warning) => "warning",
+        _ => "error",
+    };
+
+    let mut output = String::new();
+    for (index, problem) in status.status().errors().into_iter().flatten().enumerate() {
+        let header = format!("{level}[{}]", problem.code());
+        writeln!(
+            output,
+            "{}{} {}",
+            paint(&header, severity),
+            paint(":", None),
+            paint(&problem.summary(), None)
+        )
+        .expect("writing to a string");
+
+        let snippet = status.snippets().get(index).cloned().flatten();
+        let location = problem.location();
+        // The notes line up with the gutter of the snippet, whose width is the one of the line
+        let gutter = " ".repeat(
+            location
+                .as_ref()
+                .and_then(|location| location.line)
+                .map_or(1, |line| line.to_string().len()),
+        );
+        match (snippet, location) {
+            (Some(snippet), _) => output.push_str(&snippet),
+            (None, Some(location)) => {
+                writeln!(output, "{gutter}--> {}", location.path).expect("writing to a string");
+            }
+            (None, None) => {}
+        }
+
+        let mut notes = details(&problem.to_string())
+            .into_iter()
+            .map(|note| ("note", note))
+            .collect::<Vec<_>>();
+        if let Some(fix) = problem.fix() {
+            notes.push(("help", format!("Fix: {fix}")));
+        }
+        if !notes.is_empty() {
+            writeln!(output, "{gutter} |").expect("writing to a string");
+        }
+        for (kind, note) in notes {
+            // Continuation lines are indented to where the note starts
+            let indent = format!("{gutter}   {}", " ".repeat(kind.len() + 2));
+            let mut lines = note.lines();
+            let first = lines.next().unwrap_or_default();
+            writeln!(output, "{gutter} = {}: {first}", paint(kind, None))
+                .expect("writing to a string");
+            for line in lines {
+                writeln!(output, "{}", format!("{indent}{line}").trim_end())
+                    .expect("writing to a string");
+            }
+        }
+        output.push('\n');
+    }
+    let message = status.status().message();
+    match severity {
+        Some(_) => writeln!(output, "{}", paint(&message, severity)),
+        None if use_color => writeln!(output, "{}", message.green()),
+        None => writeln!(output, "{message}"),
+    }
+    .expect("writing to a string");
+    output
+}
+
+/// The lines of the message of a problem after its summary, without their common indentation.
+/// Returns `None` if there are none.
+fn details(message: &str) -> Option<String> {
+    let rest = message
+        .lines()
+        .skip_while(|line| !line.starts_with("- "))
+        .skip(1)
+        .collect::<Vec<_>>();
+    let indent = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()?;
+    let dedented = rest
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default().trim_end())
+        .collect::<Vec<_>>();
+    Some(dedented.join("\n").trim_matches('\n').to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_107, npv_143};
+    use crate::status::Status;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_diagnostics() {
+        let status = ColoredStatus::from(Status::ProblemsIntroduced(vec![
+            npv_107::ByNameOverrideContainsEmptyArgument::new(
+                "foo",
+                Location::new("pkgs/top-level/all-packages.nix", 12, 3),
+                "foo = callPackage ../by-name/fo/foo/package.nix { };",
+            )
+            .into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/ba/bar").into(),
+        ]))
+        .with_snippets(vec![
+            Some(
+                indoc! {"
+                      --> pkgs/top-level/all-packages.nix:12:3
+                       |
+                    12 |   foo = callPackage ../by-name/fo/foo/package.nix { };
+                       |   ^
+                "}
+                .to_owned(),
+            ),
+            None,
+        ]);
+        assert_eq!(
+            render(&status, false),
+            indoc! {"
+                error[NPV-107]: Because pkgs/by-name/fo/foo exists, the attribute `pkgs.foo` must be defined like
+                  --> pkgs/top-level/all-packages.nix:12:3
+                   |
+                12 |   foo = callPackage ../by-name/fo/foo/package.nix { };
+                   |   ^
+                   |
+                   = note:   foo = callPackage ./../by-name/fo/foo/package.nix { /* ... */ };
+
+                           However, in this PR, the second argument is empty. See the definition in pkgs/top-level/all-packages.nix:12:
+
+                             foo = callPackage ../by-name/fo/foo/package.nix { };
+
+                           Such a definition is provided automatically and therefore not necessary. Please remove it.
+                   = help: Fix: Remove `foo = callPackage ../by-name/fo/foo/package.nix { };` from pkgs/top-level/all-packages.nix:12
+
+                error[NPV-143]: pkgs/by-name/ba/bar: Missing required \"package.nix\" file.
+                 --> pkgs/by-name/ba/bar
+
+                This PR introduces the problems listed above. Please fix them before merging, otherwise the base branch would break.
+            "}
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! The JSON body for Gerrit's "set review" API with robot comments, for forks of Nixpkgs that are
+//! reviewed in Gerrit, see `--format gerrit-review`.
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use crate::diff::Diff;
+use crate::status::Status;
+
+/// The ID the robot comments are attributed to.
+const ROBOT_ID: &str = "nixpkgs-vet";
+
+/// Creates the body of a request to Gerrit's "set review" API, with a robot comment for each
+/// problem, keyed by file.
+///
+/// See <https://gerrit-review.googlesource.com/Documentation/rest-api-changes.html#set-review>.
+///
+/// Gerrit only allows comments on files of the change, so with a diff, problems about other files
+/// are listed in the review message instead, like the ones without a location.
+pub fn review(status: &Status, diff: Option<&Diff>) -> Value {
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string();
+    let mut robot_comments: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut other_problems = vec![];
+
+    for problem in status.errors().into_iter().flatten() {
+        let Some(location) = problem.location().filter(|location| {
+            diff.is_none_or(|diff| {
+                diff.files
+                    .iter()
+                    .any(|file| file.new_path.as_ref() == Some(&location.path))
+            })
+        }) else {
+            other_problems.push(problem.to_string());
+            continue;
+        };
+        let message = problem.to_string();
+        let mut comment = json!({
+            "robot_id": ROBOT_ID,
+            "robot_run_id": run_id,
+            "message": message.strip_prefix("- ").unwrap_or(&message),
+            "properties": { "code": problem.code() },
+        });
+        // Comments without a line are about the whole file
+        if let Some(line) = location.line {
+            comment["line"] = json!(line);
+        }
+        robot_comments
+            .entry(location.path.into_string())
+            .or_default()
+            .push(comment);
+    }
+    let mut message = status.message();
+    if !other_problems.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&other_problems.join("\n"));
+    }
+
+    json!({
+        "message": message,
+        "tag": format!("autogenerated:{ROBOT_ID}"),
+        "robot_comments": robot_comments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_107, npv_143};
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn keys_comments_by_file() -> anyhow::Result<()> {
+        let diff = Diff::parse(
+            indoc! {"
+                --- a/pkgs/top-level/all-packages.nix
+                +++ b/pkgs/top-level/all-packages.nix
+                @@ -1,2 +1,3 @@
+                 self: super: {
+                +  foo = self.callPackage ../by-name/fo/foo/package.nix { };
+                 }
+            "}
+            .to_owned(),
+        )?;
+        let status = Status::ProblemsIntroduced(vec![
+            npv_107::ByNameOverrideContainsEmptyArgument::new(
+                "foo",
+                Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+                "foo = self.callPackage ../by-name/fo/foo/package.nix { };",
+            )
+            .into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/ba/bar").into(),
+        ]);
+
+        let review = review(&status, Some(&diff));
+        let comments = &review["robot_comments"]["pkgs/top-level/all-packages.nix"];
+        assert_eq!(comments.as_array().map(Vec::len), Some(1));
+        assert_eq!(comments[0]["robot_id"], "nixpkgs-vet");
+        assert_eq!(comments[0]["line"], 2);
+        assert_eq!(comments[0]["properties"]["code"], "NPV-107");
+        assert_eq!(review["tag"], "autogenerated:nixpkgs-vet");
+        assert_eq!(
+            review["message"],
+            "This PR introduces the problems listed above. Please fix them before merging, \
+             otherwise the base branch would break.\n\n\
+             - pkgs/by-name/ba/bar: Missing required \"package.nix\" file."
+        );
+
+        // Without a diff, there's a comment for every problem with a location
+        let review = super::review(&status, None);
+        assert_eq!(
+            review["robot_comments"]["pkgs/by-name/ba/bar"][0].get("line"),
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use serde_json::{Value, json};
+
+use crate::diff::Diff;
+use crate::problem::Problem;
+use crate::status::Status;
+
+/// Creates the body of a request to GitHub's "create a review" API.
+///
+/// See <https://docs.github.com/en/rest/pulls/reviews#create-a-review-for-a-pull-request>.
+///
+/// GitHub only allows inline comments on lines that are shown in the diff of the PR, so only
+/// problems within those lines become inline comments. Problems about files in the diff without a
+/// specific line become file comments, and all others are listed in the review body instead.
+pub fn review(status: &Status, diff: Option<&Diff>) -> Value {
+    let mut comments = vec![];
+    let mut other_problems = vec![];
+
+    for problem in status.errors().into_iter().flatten() {
+        match comment(problem, diff) {
+            Some(comment) => comments.push(comment),
+            None => other_problems.push(problem.to_string()),
+        }
+    }
+
+    let mut body = status.message();
+    if !other_problems.is_empty() {
+        body.push_str("\n\n");
+        body.push_str(&other_problems.join("\n"));
+    }
+
+    let event = match status {
+        Status::ProblemsIntroduced(..) => "REQUEST_CHANGES",
+        Status::ProblemsChanged { introduced, .. } if !introduced.is_empty() => "REQUEST_CHANGES",
+        _ => "COMMENT",
+    };
+
+    json!({
+        "event": event,
+        "body": body,
+        "comments": comments,
+    })
+}
+
+/// Creates an inline comment for a problem, if its location is shown in the diff.
+fn comment(problem: &Problem, diff: Option<&Diff>) -> Option<Value> {
+    let location = problem.location()?;
+    let file = diff?
+        .files
+        .iter()
+        .find(|file| file.new_path.as_ref() == Some(&location.path))?;
+
+    // The comment body shouldn't repeat the list marker of the human-readable output.
+    let message = problem.to_string();
+    let body = message.strip_prefix("- ").unwrap_or(&message);
+
+    match location.line {
+        Some(line) if file.shows_line(line) => Some(json!({
+            "path": location.path.as_str(),
+            "line": line,
+            "side": "RIGHT",
+            "body": body,
+        })),
+        // The line isn't part of the diff, so it can't be commented on.
+        Some(_) => None,
+        None => Some(json!({
+            "path": location.path.as_str(),
+            "subject_type": "file",
+            "body": body,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_107, npv_143};
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn places_comments_in_diff() -> anyhow::Result<()> {
+        let diff = Diff::parse(
+            indoc! {"
+                --- a/pkgs/top-level/all-packages.nix
+                +++ b/pkgs/top-level/all-packages.nix
+                @@ -1,2 +1,3 @@
+                 self: super: {
+                +  foo = self.callPackage ../by-name/fo/foo/package.nix { };
+                 }
+            "}
+            .to_owned(),
+        )?;
+        let status = Status::ProblemsIntroduced(vec![
+            npv_107::ByNameOverrideContainsEmptyArgument::new(
+                "foo",
+                Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+                "foo = self.callPackage ../by-name/fo/foo/package.nix { };",
+            )
+            .into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/ba/bar").into(),
+        ]);
+
+        let review = review(&status, Some(&diff));
+
+        assert_eq!(review["event"], "REQUEST_CHANGES");
+        assert_eq!(review["comments"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            review["comments"][0]["path"],
+            "pkgs/top-level/all-packages.nix"
+        );
+        assert_eq!(review["comments"][0]["line"], 2);
+        assert_eq!(review["comments"][0]["side"], "RIGHT");
+        assert_eq!(
+            review["body"],
+            "This PR introduces the problems listed above. Please fix them before merging, \
+             otherwise the base branch would break.\n\n\
+             - pkgs/by-name/ba/bar: Missing required \"package.nix\" file."
+        );
+        Ok(())
+    }
+}
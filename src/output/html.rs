@@ -0,0 +1,165 @@
+//! A self-contained HTML page with the results of the checks, see `--report html`.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use itertools::Itertools as _;
+
+use super::snippet;
+use crate::problem::Problem;
+use crate::status::Status;
+
+/// Filters the rows of all tables by the text of the filter input, and sorts a table by the column
+/// whose header is clicked, alternating between ascending and descending order.
+const SCRIPT: &str = r#"
+const filter = document.getElementById("filter");
+filter.addEventListener("input", () => {
+  const text = filter.value.toLowerCase();
+  for (const row of document.querySelectorAll("tbody tr")) {
+    row.hidden = !row.textContent.toLowerCase().includes(text);
+  }
+});
+for (const header of document.querySelectorAll("th")) {
+  header.addEventListener("click", () => {
+    const tbody = header.closest("table").querySelector("tbody");
+    const index = [...header.parentNode.children].indexOf(header);
+    const ascending = header.dataset.order !== "ascending";
+    header.dataset.order = ascending ? "ascending" : "descending";
+    const key = (row) => row.children[index].dataset.key ?? row.children[index].textContent;
+    [...tbody.rows]
+      .sort((a, b) => key(a).localeCompare(key(b), undefined, { numeric: true }) * (ascending ? 1 : -1))
+      .forEach((row) => tbody.appendChild(row));
+  });
+}
+"#;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; width: 100%; }
+th { cursor: pointer; text-align: left; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.3em; vertical-align: top; }
+pre { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+.fixed { color: #1a7f37; }
+";
+
+/// Renders the page for the status, with a section for the problems of each check, and the source
+/// code at the location of each problem from the given Nixpkgs.
+pub fn render(status: &Status, nixpkgs_path: &Path) -> String {
+    let problems = status.errors().map(Vec::as_slice).unwrap_or_default();
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>nixpkgs-vet report</title>\n");
+    writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>").expect("writing to a string");
+    writeln!(html, "<h1>nixpkgs-vet report</h1>").expect("writing to a string");
+
+    if !problems.is_empty() {
+        html.push_str("<input id=\"filter\" type=\"search\" placeholder=\"Filter problems\">\n");
+        let by_code = problems
+            .iter()
+            .into_group_map_by(|problem| problem.code())
+            .into_iter()
+            .sorted_by_key(|(code, _)| *code);
+        for (code, problems) in by_code {
+            writeln!(
+                html,
+                "<section id=\"{code}\">\n<h2>{code} ({} {})</h2>",
+                problems.len(),
+                if problems.len() == 1 {
+                    "problem"
+                } else {
+                    "problems"
+                }
+            )
+            .expect("writing to a string");
+            html.push_str("<table>\n<thead><tr><th>Path</th><th>Line</th><th>Problem</th></tr></thead>\n<tbody>\n");
+            for problem in problems {
+                row(&mut html, problem, nixpkgs_path);
+            }
+            html.push_str("</tbody>\n</table>\n</section>\n");
+        }
+    }
+
+    if let Status::ProblemsChanged { fixed, .. } = status
+        && !fixed.is_empty()
+    {
+        html.push_str("<section id=\"fixed\">\n<h2>Fixed problems of the base branch</h2>\n<ul>\n");
+        for problem in fixed {
+            writeln!(
+                html,
+                "<li class=\"fixed\">{}</li>",
+                escape(&problem.to_string())
+            )
+            .expect("writing to a string");
+        }
+        html.push_str("</ul>\n</section>\n");
+    }
+
+    // Like in the terminal, the message refers to the problems above it
+    writeln!(html, "<p>{}</p>", escape(&status.message())).expect("writing to a string");
+    writeln!(html, "<script>{SCRIPT}</script>\n</body>\n</html>").expect("writing to a string");
+    html
+}
+
+/// Renders the row of the table for a problem.
+fn row(html: &mut String, problem: &Problem, nixpkgs_path: &Path) {
+    let location = problem.location();
+    let path = location
+        .as_ref()
+        .map(|location| location.path.to_string())
+        .unwrap_or_default();
+    let line = location
+        .as_ref()
+        .and_then(|location| location.line)
+        .map(|line| line.to_string())
+        .unwrap_or_default();
+    html.push_str("<tr>");
+    write!(
+        html,
+        "<td>{}</td><td data-key=\"{line:0>8}\">{line}</td><td>{}",
+        escape(&path),
+        escape(&problem.to_string())
+    )
+    .expect("writing to a string");
+    if let Some(fix) = problem.fix() {
+        write!(html, "<br>Fix: {}", escape(&fix.to_string())).expect("writing to a string");
+    }
+    if let Some(snippet) = location.and_then(|location| snippet::render(nixpkgs_path, &location)) {
+        write!(html, "<pre>{}</pre>", escape(&snippet)).expect("writing to a string");
+    }
+    html.push_str("</td></tr>\n");
+}
+
+/// Escapes the characters that have a meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_143, npv_146};
+
+    #[test]
+    fn renders_sections() {
+        let status = Status::ProblemsIntroduced(vec![
+            npv_146::TopLevelEntryUnsorted::new(
+                "foo",
+                "<bar>",
+                true,
+                Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+            )
+            .into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+        ]);
+        let html = render(&status, Path::new("/nonexistent"));
+        assert!(html.contains("<h2>NPV-143 (1 problem)</h2>"));
+        assert!(html.find("NPV-143").unwrap() < html.find("NPV-146").unwrap());
+        assert!(html.contains("&lt;bar&gt;"));
+        assert!(!html.contains("<bar>"));
+        assert!(html.contains("<td data-key=\"00000002\">2</td>"));
+    }
+}
@@ -0,0 +1,95 @@
+//! The results of the checks as JSON, to process them with other tools or to merge the results of
+//! sharded runs with `nixpkgs-vet merge-reports`, see `--report json`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::status::{Severity, Status};
+
+/// The results of a run, or of several merged ones.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JsonReport {
+    /// The exit code of the run.
+    pub exit_code: u8,
+    /// The message of the status, like in the human-readable output.
+    pub message: String,
+    pub problems: Vec<JsonProblem>,
+    /// The number of problems found by each check.
+    pub counts: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JsonProblem {
+    /// The code of the check that found the problem, like `NPV-123`.
+    pub code: String,
+    /// Whether the problem is an `error` or a `warning`.
+    pub severity: String,
+    /// The path the problem is about, relative to Nixpkgs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The line the problem is at, if it's about a specific one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The problem like in the human-readable output.
+    pub text: String,
+    /// The fields of the problem, which differ between checks.
+    pub fields: Value,
+}
+
+impl JsonReport {
+    pub fn new(status: &Status) -> Self {
+        let severity = match status.severity() {
+            Some(Severity::Warning) => "warning",
+            _ => "error",
+        };
+        let problems = status
+            .errors()
+            .into_iter()
+            .flatten()
+            .map(|problem| {
+                let location = problem.location();
+                JsonProblem {
+                    code: problem.code().to_owned(),
+                    severity: severity.to_owned(),
+                    path: location.as_ref().map(|location| location.path.to_string()),
+                    line: location.and_then(|location| location.line),
+                    text: problem.to_string(),
+                    fields: serde_json::to_value(problem).expect("problems can be serialised"),
+                }
+            })
+            .collect();
+        Self::with_problems(status.exit_code(), status.message(), problems)
+    }
+
+    /// A report with the problems, counting them by check.
+    pub fn with_problems(exit_code: u8, message: String, problems: Vec<JsonProblem>) -> Self {
+        let mut counts = BTreeMap::new();
+        for problem in &problems {
+            *counts.entry(problem.code.clone()).or_default() += 1;
+        }
+        Self {
+            exit_code,
+            message,
+            problems,
+            counts,
+        }
+    }
+}
+
+impl fmt::Display for JsonReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for problem in &self.problems {
+            writeln!(f, "{}", problem.text)?;
+        }
+        if !self.counts.is_empty() {
+            writeln!(f, "Problems by check:")?;
+            for (code, count) in &self.counts {
+                writeln!(f, "  {code}: {count}")?;
+            }
+        }
+        write!(f, "{}", self.message)
+    }
+}
@@ -0,0 +1,158 @@
+//! A Markdown report of the problems grouped by the maintainers of the packages they're about, to
+//! ping them during cleanups, see `--report maintainers`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use relative_path::RelativePath;
+
+use crate::maintainers::{self, MAINTAINER_LIST_PATH};
+use crate::nix_file::NixFileStore;
+use crate::problem::Problem;
+use crate::status::Status;
+use crate::structure;
+
+/// Renders the report for the status, with a section for each maintainer of a package in
+/// `pkgs/by-name` that has problems, and one for the problems without maintainers.
+///
+/// The maintainers are the ones in `meta.maintainers` of the `package.nix` files in the given
+/// Nixpkgs, and are mentioned with their GitHub handle from `maintainers/maintainer-list.nix` when
+/// it's known.
+pub fn render(status: &Status, nixpkgs_path: &Path) -> anyhow::Result<String> {
+    let mut nix_file_store = NixFileStore::default();
+    let github_handles = github_handles(&mut nix_file_store, nixpkgs_path)?;
+
+    let mut by_maintainer: BTreeMap<String, Vec<&Problem>> = BTreeMap::new();
+    let mut unmaintained = Vec::new();
+    for problem in status.errors().into_iter().flatten() {
+        let mut maintainers = Vec::new();
+        if let Some(package) = problem
+            .location()
+            .and_then(|location| structure::package_for_path(&location.path))
+        {
+            let path = structure::relative_file_for_package(&package).to_path(nixpkgs_path);
+            if path.is_file() {
+                maintainers = nix_file_store.get(&path)?.meta_maintainers();
+            }
+        }
+        if maintainers.is_empty() {
+            unmaintained.push(problem);
+        }
+        for (handle, _, _) in maintainers {
+            let mention = match github_handles.get(&handle) {
+                Some(github) => format!("@{github}"),
+                None => handle,
+            };
+            let problems = by_maintainer.entry(mention).or_default();
+            // A maintainer may be listed more than once
+            if !problems.iter().any(|other| std::ptr::eq(*other, problem)) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    let mut markdown = String::from("# Problems by maintainer\n");
+    for (mention, problems) in &by_maintainer {
+        section(&mut markdown, mention, problems);
+    }
+    if !unmaintained.is_empty() {
+        section(&mut markdown, "Without maintainers", &unmaintained);
+    }
+    Ok(markdown)
+}
+
+fn section(markdown: &mut String, title: &str, problems: &[&Problem]) {
+    writeln!(
+        markdown,
+        "\n## {title} ({} {})\n",
+        problems.len(),
+        if problems.len() == 1 {
+            "problem"
+        } else {
+            "problems"
+        }
+    )
+    .expect("writing to a string");
+    for problem in problems {
+        writeln!(markdown, "{problem}").expect("writing to a string");
+    }
+}
+
+/// The GitHub handles of the maintainers in `maintainers/maintainer-list.nix`, if it exists.
+fn github_handles(
+    nix_file_store: &mut NixFileStore,
+    nixpkgs_path: &Path,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let path = RelativePath::new(MAINTAINER_LIST_PATH).to_path(nixpkgs_path);
+    if !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(maintainers::read_maintainers(nix_file_store.get(&path)?)
+        .into_iter()
+        .filter_map(|maintainer| {
+            let github = maintainer.field("github")?.to_owned();
+            Some((maintainer.handle, github))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_143, npv_146};
+    use crate::testing::{Tree, assert_golden};
+    use indoc::indoc;
+
+    #[test]
+    fn groups_problems_by_maintainer() -> anyhow::Result<()> {
+        let tree = Tree::new()?
+            .package(
+                ".",
+                "foo",
+                "{ lib, someDrv }: someDrv // { meta.maintainers = with lib.maintainers; [ alice bob alice ]; }",
+            )?
+            .package(
+                ".",
+                "bar",
+                "{ lib, someDrv }: someDrv // { meta.maintainers = [ lib.maintainers.bob ]; }",
+            )?
+            .file(
+                MAINTAINER_LIST_PATH,
+                r#"{ alice = { github = "alice-gh"; githubId = 1; }; }"#,
+            )?;
+        let status = Status::ProblemsIntroduced(vec![
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/ba/bar").into(),
+            npv_146::TopLevelEntryUnsorted::new(
+                "foo",
+                "bar",
+                true,
+                Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+            )
+            .into(),
+        ]);
+        assert_golden(
+            "maintainers",
+            indoc! {r#"
+                # Problems by maintainer
+
+                ## @alice-gh (1 problem)
+
+                - pkgs/by-name/fo/foo: Missing required "package.nix" file.
+
+                ## bob (2 problems)
+
+                - pkgs/by-name/fo/foo: Missing required "package.nix" file.
+                - pkgs/by-name/ba/bar: Missing required "package.nix" file.
+
+                ## Without maintainers (1 problem)
+
+                @REDACTED@
+            "#},
+            &render(&status, tree.path())?,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+//! The different formats the results of the checks can be output in, see `--format`.
+
+mod badge;
+mod codeclimate;
+mod csv;
+mod diagnostic;
+mod gerrit_review;
+mod github_review;
+mod html;
+mod json;
+mod maintainers;
+mod pr_comment;
+mod quickfix;
+pub mod snippet;
+pub mod step_summary;
+
+use std::io::{self, IsTerminal as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs};
+
+use anyhow::Context as _;
+use clap::ValueEnum;
+
+pub use self::csv::CsvColumn;
+pub use self::json::JsonReport;
+use crate::diff::Diff;
+use crate::problem::Problem;
+use crate::stats::Stats;
+use crate::status::{ColoredStatus, Fatal, Status};
+use crate::structure;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Human-readable problems and status on standard error.
+    #[default]
+    Human,
+    /// The JSON body for GitHub's "create a review" API on standard output. Problems within the
+    /// lines shown by `--diff` become inline comments, all others are listed in the review body.
+    GithubReview,
+    /// A row of comma-separated values for each problem on standard output, after a header row
+    /// with the names of the columns given by `--csv-columns`.
+    Csv,
+    /// A GitLab Code Quality report on standard output, which merge requests show inline, with
+    /// fingerprints that don't change when unrelated changes move the problems.
+    Codeclimate,
+    /// The JSON body for Gerrit's "set review" API on standard output, with a robot comment for
+    /// each problem. With `--diff`, problems about files outside of it are listed in the review
+    /// message.
+    GerritReview,
+    /// Problems like the diagnostics of rustc on standard error, with the source code they're
+    /// about, carets pointing to the location, and the rest of the message and the fix as notes.
+    Diagnostic,
+    /// A line like `path:line:column: error: message [NPV-123]` for each problem on standard
+    /// output, which Vim's quickfix list and Emacs' compilation mode jump to without
+    /// configuration.
+    Quickfix,
+}
+
+/// The kinds of reports that can be written in addition to the output, see `--report`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportKind {
+    /// A self-contained HTML page, with a section for the problems of each check that can be
+    /// filtered and sorted, and the source code each problem is about.
+    Html,
+    /// The JSON of a shields.io endpoint badge, with the number of problems and the percentage of
+    /// packages in `pkgs/by-name`.
+    Badge,
+    /// The JSON of the exit code, the problems and the number of problems of each check, which
+    /// `nixpkgs-vet merge-reports` can merge with the ones of other runs.
+    Json,
+    /// A Markdown comment to post on a PR, with the number of problems of each check and the
+    /// collapsed problems. It starts with a hidden marker to find and update the previous
+    /// comment by.
+    PrComment,
+    /// Markdown with a section for each maintainer in `meta.maintainers` of the packages with
+    /// problems, to ping them about the problems of their packages.
+    Maintainers,
+}
+
+/// A report to write, with the path to write it to, or `-` for standard output.
+pub struct Report {
+    kind: ReportKind,
+    path: PathBuf,
+}
+
+impl Report {
+    /// Parses the pairs of kinds and paths of `--report`.
+    pub fn parse_all(values: &[String]) -> anyhow::Result<Vec<Self>> {
+        values
+            .chunks(2)
+            .map(|pair| {
+                let [kind, path] = pair else {
+                    unreachable!("clap requires a path after each kind of report");
+                };
+                let kind = ReportKind::from_str(kind, false).map_err(|_| {
+                    let kinds = ReportKind::value_variants()
+                        .iter()
+                        .filter_map(|kind| Some(kind.to_possible_value()?.get_name().to_owned()))
+                        .collect::<Vec<_>>();
+                    anyhow::anyhow!(Fatal::Usage(format!(
+                        "Unknown kind of report `{kind}`, expected one of: {}",
+                        kinds.join(", ")
+                    )))
+                })?;
+                Ok(Self {
+                    kind,
+                    path: path.into(),
+                })
+            })
+            .collect()
+    }
+
+    /// Writes the report of the status, with the source code from the checked Nixpkgs.
+    pub fn write(&self, status: &Status, nixpkgs_path: &Path) -> anyhow::Result<()> {
+        let contents = match self.kind {
+            ReportKind::Html => html::render(status, nixpkgs_path),
+            ReportKind::Badge => {
+                let stats = Stats::collect(nixpkgs_path)?;
+                format!("{:#}\n", badge::badge(status, &stats))
+            }
+            ReportKind::Json => format!(
+                "{}\n",
+                serde_json::to_string_pretty(&JsonReport::new(status))?
+            ),
+            ReportKind::PrComment => pr_comment::render(status),
+            ReportKind::Maintainers => maintainers::render(status, nixpkgs_path)?,
+        };
+        if self.path == Path::new("-") {
+            io::stdout().write_all(contents.as_bytes())?;
+            Ok(())
+        } else {
+            fs::write(&self.path, contents)
+                .with_context(|| format!("Could not write the report to {}", self.path.display()))
+        }
+    }
+}
+
+/// How detailed the problems are in the human-readable output, see `--output-style`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputStyle {
+    /// One line per problem, like `path:line:column: NPV-123: message`.
+    Compact,
+    /// The full message of each problem.
+    #[default]
+    Short,
+    /// The full message of each problem, followed by the source code it's about.
+    Full,
+}
+
+/// How to group problems in the human-readable output, see `--group-by`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// By the code of the check that found the problem, like `NPV-123`.
+    Check,
+    /// By the `pkgs/by-name` package the problem is located in.
+    Package,
+    /// By the file the problem is located in.
+    File,
+}
+
+impl GroupBy {
+    /// The name of the group the problem belongs to, if it belongs to any.
+    pub fn group(self, problem: &Problem) -> Option<String> {
+        match self {
+            Self::Check => Some(problem.code().to_owned()),
+            Self::Package => structure::package_for_path(&problem.location()?.path),
+            Self::File => Some(problem.location()?.path.into_string()),
+        }
+    }
+}
+
+/// When to use colors in the human-readable output, see `--color`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Use colors if standard error is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Enables or disables colors for the rest of the program.
+    pub fn apply(self) {
+        let use_color = match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none_or(|s| s.is_empty())
+            }
+        };
+        colored::control::set_override(use_color);
+    }
+}
+
+/// Outputs the status in the given format, returning the exit code to use.
+pub fn output(
+    format: Format,
+    status: ColoredStatus,
+    diff: Option<&Diff>,
+    csv_columns: &[CsvColumn],
+) -> ExitCode {
+    match (format, status.status()) {
+        // Errors aren't results, so they're always printed for humans.
+        (Format::Human, _) | (_, Status::Error(..)) => eprintln!("{status}"),
+        (Format::GithubReview, status) => {
+            println!("{:#}", github_review::review(status, diff));
+        }
+        (Format::Csv, status) => print!("{}", csv::render(status, csv_columns)),
+        (Format::GerritReview, status) => {
+            println!("{:#}", gerrit_review::review(status, diff));
+        }
+        (Format::Diagnostic, _) => eprint!("{}", diagnostic::render(&status, true)),
+        (Format::Quickfix, status) => print!("{}", quickfix::render(status)),
+        (Format::Codeclimate, status) => println!("{:#}", codeclimate::render(status)),
+    }
+    status.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{npv_120, npv_123};
+
+    #[test]
+    fn groups_problems() {
+        let problem: Problem = npv_123::NixFileContainsPathOutsideDirectory::new(
+            "pkgs/by-name/fo/foo",
+            "sub/default.nix",
+            1,
+            1,
+            "../.",
+        )
+        .into();
+        assert_eq!(GroupBy::Check.group(&problem).as_deref(), Some("NPV-123"));
+        assert_eq!(GroupBy::Package.group(&problem).as_deref(), Some("foo"));
+        assert_eq!(
+            GroupBy::File.group(&problem).as_deref(),
+            Some("pkgs/by-name/fo/foo/sub/default.nix")
+        );
+
+        // Without a location, a problem is only in the group of its check
+        let problem: Problem =
+            npv_120::NixEvalError::new(npv_120::EvalFailureKind::Other, None, "error").into();
+        assert_eq!(GroupBy::Check.group(&problem).as_deref(), Some("NPV-120"));
+        assert_eq!(GroupBy::Package.group(&problem), None);
+        assert_eq!(GroupBy::File.group(&problem), None);
+    }
+}
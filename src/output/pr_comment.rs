@@ -0,0 +1,125 @@
+//! A Markdown comment for a bot to post on a PR, see `--report pr-comment`.
+
+use std::fmt::Write as _;
+
+use itertools::Itertools as _;
+
+use crate::problem::Problem;
+use crate::status::{Severity, Status};
+
+/// The first line of every comment, which is hidden when rendered. A bot can find its previous
+/// comment by it, to update the comment instead of posting a new one for every push.
+pub const MARKER: &str = "<!-- nixpkgs-vet -->";
+
+/// Renders the comment for the status, with a table of the number of problems of each check,
+/// followed by the collapsed problems of each check and the message of the status.
+pub fn render(status: &Status) -> String {
+    let emoji = match status.severity() {
+        None => "✅",
+        Some(Severity::Warning) => "⚠️",
+        Some(Severity::Error) => "❌",
+    };
+    let mut markdown = format!("{MARKER}\n## {emoji} nixpkgs-vet\n");
+
+    let problems = status.errors().map(Vec::as_slice).unwrap_or_default();
+    let by_code = problems
+        .iter()
+        .into_group_map_by(|problem| problem.code())
+        .into_iter()
+        .sorted_by_key(|(code, _)| *code)
+        .collect::<Vec<_>>();
+    if !by_code.is_empty() {
+        markdown.push_str("\n| Check | Problems |\n| --- | ---: |\n");
+        for (code, problems) in &by_code {
+            writeln!(markdown, "| {code} | {} |", problems.len()).expect("writing to a string");
+        }
+    }
+    for (code, problems) in &by_code {
+        details(
+            &mut markdown,
+            &format!("{code}: {}", count(problems.len())),
+            problems,
+        );
+    }
+    if let Status::ProblemsChanged { fixed, .. } = status
+        && !fixed.is_empty()
+    {
+        let fixed = fixed.iter().collect::<Vec<_>>();
+        details(
+            &mut markdown,
+            &format!("Fixed problems of the base branch: {}", count(fixed.len())),
+            &fixed,
+        );
+    }
+    // Like in the terminal, the message refers to the problems above it
+    writeln!(markdown, "\n{}", status.message()).expect("writing to a string");
+    markdown
+}
+
+/// Renders a collapsed section with the problems.
+fn details(markdown: &mut String, summary: &str, problems: &[&Problem]) {
+    writeln!(markdown, "\n<details>\n<summary>{summary}</summary>\n").expect("writing to a string");
+    for problem in problems {
+        // Indenting the lines after the first keeps multi-line problems within their list item
+        let text = problem.to_string().replace('\n', "\n  ");
+        writeln!(markdown, "{text}").expect("writing to a string");
+    }
+    markdown.push_str("\n</details>\n");
+}
+
+fn count(problems: usize) -> String {
+    if problems == 1 {
+        "1 problem".to_owned()
+    } else {
+        format!("{problems} problems")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::npv_143;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_comment() {
+        let missing = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let status = Status::ProblemsChanged {
+            introduced: vec![
+                missing("pkgs/by-name/fo/foo"),
+                missing("pkgs/by-name/ba/bar"),
+            ],
+            fixed: vec![missing("pkgs/by-name/ba/baz")],
+        };
+        assert_eq!(
+            render(&status),
+            indoc! {r#"
+                <!-- nixpkgs-vet -->
+                ## ❌ nixpkgs-vet
+
+                | Check | Problems |
+                | --- | ---: |
+                | NPV-143 | 2 |
+
+                <details>
+                <summary>NPV-143: 2 problems</summary>
+
+                - pkgs/by-name/fo/foo: Missing required "package.nix" file.
+                - pkgs/by-name/ba/bar: Missing required "package.nix" file.
+
+                </details>
+
+                <details>
+                <summary>Fixed problems of the base branch: 1 problem</summary>
+
+                - pkgs/by-name/ba/baz: Missing required "package.nix" file.
+
+                </details>
+
+                The base branch is broken, and this PR introduces the new problems listed above. Please fix them before merging. It also fixes 1 problem of the base branch.
+            "#}
+        );
+        assert!(render(&Status::ValidatedSuccessfully).starts_with(&format!("{MARKER}\n## ✅")));
+    }
+}
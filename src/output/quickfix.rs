@@ -0,0 +1,64 @@
+//! Problems as lines that Vim's quickfix list and Emacs' compilation mode understand without
+//! configuration, see `--format quickfix`.
+
+use crate::status::{Severity, Status};
+
+/// Renders a line like `path:line:column: error: message [NPV-123]` for each problem, which both
+/// the default `errorformat` of Vim and `compilation-mode` of Emacs parse.
+///
+/// Problems about a whole file are at its first line, and the ones about all of Nixpkgs can't be
+/// jumped to, so they're only prefixed with the severity.
+pub fn render(status: &Status) -> String {
+    let severity = match status.severity() {
+        Some(Severity::Warning) => "warning",
+        _ => "error",
+    };
+    status
+        .errors()
+        .into_iter()
+        .flatten()
+        .map(|problem| {
+            let message = format!("{severity}: {} [{}]", problem.summary(), problem.code());
+            match problem.location() {
+                Some(location) => format!(
+                    "{}:{}:{}: {message}\n",
+                    location.path,
+                    location.line.unwrap_or(1),
+                    location.column.unwrap_or(1)
+                ),
+                None => format!("{message}\n"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::problem::{npv_143, npv_146};
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_lines() {
+        let status = Status::ProblemsIntroduced(vec![
+            npv_146::TopLevelEntryUnsorted::new(
+                "foo",
+                "bar",
+                true,
+                Location::new("pkgs/top-level/all-packages.nix", 2, 3),
+            )
+            .into(),
+            npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+        ]);
+        assert_eq!(
+            render(&status),
+            indoc! {r#"
+                pkgs/top-level/all-packages.nix:2:3: error: pkgs/top-level/all-packages.nix: The entry `foo` at line 2 comes after `bar`, but the entries of each section need to be sorted alphabetically, ignoring case. Please move it to the right place. [NPV-146]
+                pkgs/by-name/fo/foo:1:1: error: pkgs/by-name/fo/foo: Missing required "package.nix" file. [NPV-143]
+            "#}
+        );
+        assert_eq!(render(&Status::ValidatedSuccessfully), "");
+    }
+}
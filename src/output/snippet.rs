@@ -0,0 +1,83 @@
+//! Rendering of the source code at the location of a problem, see `--output-style full`.
+
+use std::fs;
+use std::path::Path;
+
+use crate::location::ProblemLocation;
+
+/// Renders the line of a location within the given Nixpkgs, with carets pointing to the column if
+/// known, or underlining the entire line otherwise. Returns `None` if the location isn't about a
+/// specific line of a readable file.
+pub fn render(nixpkgs_path: &Path, location: &ProblemLocation) -> Option<String> {
+    let line = location.line?;
+    let contents = fs::read_to_string(location.path.to_path(nixpkgs_path)).ok()?;
+    let text = contents.lines().nth(line.checked_sub(1)?)?;
+
+    let (offset, width) = match location.column {
+        Some(column) => (column.saturating_sub(1), 1),
+        None => {
+            let trimmed = text.trim_start();
+            (text.len() - trimmed.len(), trimmed.trim_end().len().max(1))
+        }
+    };
+
+    let position = match location.column {
+        Some(column) => format!("{}:{line}:{column}", location.path),
+        None => format!("{}:{line}", location.path),
+    };
+    let gutter = " ".repeat(line.to_string().len());
+    let padding = " ".repeat(offset);
+    let carets = "^".repeat(width);
+    Some(format!(
+        "{gutter}--> {position}\n{gutter} |\n{line} | {text}\n{gutter} | {padding}{carets}\n"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_carets() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        fs::write(
+            temp_dir.path().join("file.nix"),
+            "{\n  src = ./../foo;\n}\n",
+        )?;
+
+        let location = ProblemLocation {
+            path: "file.nix".into(),
+            line: Some(2),
+            column: Some(9),
+        };
+        assert_eq!(
+            render(temp_dir.path(), &location).as_deref(),
+            Some(indoc! {"
+                 --> file.nix:2:9
+                  |
+                2 |   src = ./../foo;
+                  |         ^
+            "})
+        );
+
+        let location = ProblemLocation::new("file.nix", Some(2));
+        assert_eq!(
+            render(temp_dir.path(), &location).as_deref(),
+            Some(indoc! {"
+                 --> file.nix:2
+                  |
+                2 |   src = ./../foo;
+                  |   ^^^^^^^^^^^^^^^
+            "})
+        );
+
+        assert_eq!(
+            render(temp_dir.path(), &ProblemLocation::new("file.nix", None)),
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! A Markdown summary of the run for the page of a GitHub Actions workflow run, see
+//! `--github-step-summary`.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use itertools::Itertools as _;
+
+use crate::metrics;
+use crate::status::{self, Severity, Status};
+
+/// How many problems are listed at most, such that the summary stays readable, since GitHub
+/// only shows up to 1 MiB of it anyway.
+const TOP_PROBLEMS: usize = 10;
+
+/// Appends the summary of the run to the file in `$GITHUB_STEP_SUMMARY`, which GitHub Actions
+/// shows on the page of the workflow run. Nothing is written outside of GitHub Actions.
+pub fn append(status: &Status) -> anyhow::Result<()> {
+    let Some(path) = env::var_os("GITHUB_STEP_SUMMARY") else {
+        tracing::debug!("Not writing a step summary, $GITHUB_STEP_SUMMARY isn't set");
+        return Ok(());
+    };
+    let summary = render(status, metrics::elapsed());
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(summary.as_bytes()))
+        .with_context(|| {
+            format!(
+                "Could not write the step summary to {}",
+                path.to_string_lossy()
+            )
+        })
+}
+
+/// Renders the summary, with the status, the number of problems of each check, the first
+/// problems, and how long the run took.
+fn render(status: &Status, duration: Duration) -> String {
+    let emoji = match status.severity() {
+        None => "✅",
+        Some(Severity::Warning) => "⚠️",
+        Some(Severity::Error) => "❌",
+    };
+    let mut markdown = format!("## {emoji} nixpkgs-vet\n\n{}\n", status.message());
+
+    let problems = status.errors().map(Vec::as_slice).unwrap_or_default();
+    if !problems.is_empty() {
+        markdown.push_str("\n| Check | Problems |\n| --- | ---: |\n");
+        for (code, count) in problems
+            .iter()
+            .counts_by(|problem| problem.code())
+            .into_iter()
+            .sorted()
+        {
+            writeln!(markdown, "| {code} | {count} |").expect("writing to a string");
+        }
+        markdown.push_str("\n### Problems\n\n");
+        for problem in problems.iter().take(TOP_PROBLEMS) {
+            writeln!(markdown, "- {}", status::compact(problem)).expect("writing to a string");
+        }
+        if let Some(more) = problems
+            .len()
+            .checked_sub(TOP_PROBLEMS)
+            .filter(|more| *more > 0)
+        {
+            writeln!(markdown, "- … and {more} more").expect("writing to a string");
+        }
+    }
+    writeln!(
+        markdown,
+        "\nThe checks took {:.1}s.",
+        duration.as_secs_f64()
+    )
+    .expect("writing to a string");
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{Problem, npv_143};
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn renders_summary() {
+        let problems = (0..12)
+            .map(|index| -> Problem {
+                npv_143::PackageNixMissing::new(format!("pkgs/by-name/fo/foo{index}")).into()
+            })
+            .collect();
+        assert_eq!(
+            render(
+                &Status::ProblemsIntroduced(problems),
+                Duration::from_millis(1234)
+            ),
+            indoc! {r#"
+                ## ❌ nixpkgs-vet
+
+                This PR introduces the problems listed above. Please fix them before merging, otherwise the base branch would break.
+
+                | Check | Problems |
+                | --- | ---: |
+                | NPV-143 | 12 |
+
+                ### Problems
+
+                - pkgs/by-name/fo/foo0: NPV-143: pkgs/by-name/fo/foo0: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo1: NPV-143: pkgs/by-name/fo/foo1: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo2: NPV-143: pkgs/by-name/fo/foo2: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo3: NPV-143: pkgs/by-name/fo/foo3: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo4: NPV-143: pkgs/by-name/fo/foo4: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo5: NPV-143: pkgs/by-name/fo/foo5: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo6: NPV-143: pkgs/by-name/fo/foo6: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo7: NPV-143: pkgs/by-name/fo/foo7: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo8: NPV-143: pkgs/by-name/fo/foo8: Missing required "package.nix" file.
+                - pkgs/by-name/fo/foo9: NPV-143: pkgs/by-name/fo/foo9: Missing required "package.nix" file.
+                - … and 2 more
+
+                The checks took 1.2s.
+            "#}
+        );
+        assert!(
+            render(&Status::ValidatedSuccessfully, Duration::ZERO).starts_with("## ✅ nixpkgs-vet")
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::problem::Problem;
+
+/// A problem of a check that doesn't need a type of its own, with the code and message given by
+/// the check, see `Problem::at`.
+#[derive(Clone, Serialize)]
+pub struct Custom {
+    #[serde(skip)]
+    pub(super) code: &'static str,
+    path: RelativePathBuf,
+    line: Option<usize>,
+    message: String,
+    suggestion: Option<String>,
+}
+
+impl Custom {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.path.clone(), self.line)
+    }
+}
+
+impl fmt::Display for Custom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            path,
+            message,
+            suggestion,
+            ..
+        } = self;
+        write!(f, "- {path}: {message}")?;
+        if let Some(suggestion) = suggestion {
+            write!(f, " {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a `Custom` problem about a path, which needs at least a code and a message.
+#[must_use]
+pub struct ProblemBuilder {
+    path: RelativePathBuf,
+    line: Option<usize>,
+    code: Option<&'static str>,
+    message: Option<String>,
+    suggestion: Option<String>,
+}
+
+impl ProblemBuilder {
+    pub(super) fn new(path: RelativePathBuf) -> Self {
+        Self {
+            path,
+            line: None,
+            code: None,
+            message: None,
+            suggestion: None,
+        }
+    }
+
+    /// The line of the path the problem is at.
+    pub fn line(self, line: usize) -> Self {
+        Self {
+            line: Some(line),
+            ..self
+        }
+    }
+
+    /// The code of the problem, like `NPV-150`, which is unique to the check.
+    pub fn code(self, code: &'static str) -> Self {
+        Self {
+            code: Some(code),
+            ..self
+        }
+    }
+
+    /// What the problem is, as a sentence.
+    pub fn message(self, message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..self
+        }
+    }
+
+    /// How to fix the problem, as a sentence following the message.
+    pub fn suggestion(self, suggestion: impl Into<String>) -> Self {
+        Self {
+            suggestion: Some(suggestion.into()),
+            ..self
+        }
+    }
+}
+
+impl From<ProblemBuilder> for Problem {
+    fn from(builder: ProblemBuilder) -> Self {
+        Problem::Custom(Custom {
+            code: builder.code.expect("problems have a code"),
+            path: builder.path,
+            line: builder.line,
+            message: builder.message.expect("problems have a message"),
+            suggestion: builder.suggestion,
+        })
+    }
+}
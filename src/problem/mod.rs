@@ -1,7 +1,16 @@
+use std::ffi::OsStr;
+use std::fmt;
+
 use derive_enum_from_into::EnumFrom;
-use derive_more::Display;
-use relative_path::RelativePath;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::{Serialize, Serializer};
+
+use crate::catalog;
+use crate::fix::Fix;
+use crate::location::ProblemLocation;
+use crate::validation::IsFatal;
 
+pub mod custom;
 pub mod npv_100;
 pub mod npv_101;
 pub mod npv_102;
@@ -14,6 +23,12 @@ pub mod npv_108;
 pub mod npv_109;
 pub mod npv_110;
 pub mod npv_111;
+pub mod npv_112;
+pub mod npv_113;
+pub mod npv_114;
+pub mod npv_115;
+pub mod npv_116;
+pub mod npv_117;
 
 pub mod npv_120;
 pub mod npv_121;
@@ -22,19 +37,57 @@ pub mod npv_123;
 pub mod npv_124;
 pub mod npv_125;
 pub mod npv_126;
+pub mod npv_127;
+pub mod npv_128;
+pub mod npv_129;
+pub mod npv_130;
+pub mod npv_131;
+pub mod npv_132;
+pub mod npv_133;
+pub mod npv_134;
+pub mod npv_135;
+pub mod npv_136;
+pub mod npv_137;
+pub mod npv_138;
+pub mod npv_139;
 
 pub mod npv_140;
 pub mod npv_141;
 pub mod npv_142;
 pub mod npv_143;
 pub mod npv_144;
+pub mod npv_145;
+pub mod npv_146;
+pub mod npv_147;
+pub mod npv_148;
+pub mod npv_149;
+pub mod npv_151;
+pub mod npv_152;
+pub mod npv_153;
+pub mod npv_154;
+pub mod npv_155;
+pub mod npv_156;
+pub mod npv_157;
 
 pub mod npv_160;
 pub mod npv_161;
 pub mod npv_162;
 pub mod npv_163;
-
-#[derive(Clone, Display, EnumFrom)]
+pub mod npv_164;
+pub mod npv_165;
+pub mod npv_166;
+pub mod npv_167;
+pub mod npv_168;
+pub mod npv_169;
+pub mod npv_170;
+
+/// A problem found by the checks. It's displayed with the message from the catalog for its code if
+/// there is one, see `--lang`. Otherwise the English message of the problem is used.
+///
+/// The fields of the problem are serialized without the name of the variant, such that they can
+/// be filled into the placeholders of the message templates.
+#[derive(Clone, EnumFrom, Serialize)]
+#[serde(untagged)]
 pub enum Problem {
     /// NPV-100: attribute is not defined but it should be defined automatically
     ByNameUndefinedAttribute(npv_100::ByNameUndefinedAttribute),
@@ -72,6 +125,24 @@ pub enum Problem {
     /// NPV-111: by-name shard is case-sensitive duplicate
     ByNameShardIsCaseSensitiveDuplicate(npv_111::ByNameShardIsCaseSensitiveDuplicate),
 
+    /// NPV-112: path needed for the checks doesn't exist, e.g. in a sparse checkout
+    NixpkgsPathMissing(npv_112::NixpkgsPathMissing),
+
+    /// NPV-113: by-name package is also defined in aliases.nix
+    ByNameAliasConflict(npv_113::ByNameAliasConflict),
+
+    /// NPV-114: multiple by-name packages have the same pname
+    ByNameDuplicatePname(npv_114::ByNameDuplicatePname),
+
+    /// NPV-115: passthru.tests of by-name package refers to an attribute that doesn't exist
+    ByNameTestReferenceMissing(npv_115::ByNameTestReferenceMissing),
+
+    /// NPV-116: by-name package refers to a team that doesn't exist
+    ByNameUnknownTeam(npv_116::ByNameUnknownTeam),
+
+    /// NPV-117: manual refers to an attribute that doesn't exist
+    DocReferenceMissing(npv_117::DocReferenceMissing),
+
     /// NPV-120: Nix evaluation failed
     NixEvalError(npv_120::NixEvalError),
 
@@ -93,6 +164,45 @@ pub enum Problem {
     /// NPV-126: Package contains unresolvable symlink
     PackageContainsUnresolvableSymlink(npv_126::PackageContainsUnresolvableSymlink),
 
+    /// NPV-127: Nix file imports a file from the internals of Nixpkgs
+    NixFileImportsInternalFile(npv_127::NixFileImportsInternalFile),
+
+    /// NPV-128: Nix file refers to an update script that doesn't exist or isn't executable
+    NixFileContainsInvalidUpdateScript(npv_128::NixFileContainsInvalidUpdateScript),
+
+    /// NPV-129: Nix file applies a patch that doesn't exist
+    NixFileContainsMissingPatch(npv_129::NixFileContainsMissingPatch),
+
+    /// NPV-130: package directory contains a patch that isn't used
+    PackageContainsUnusedPatch(npv_130::PackageContainsUnusedPatch),
+
+    /// NPV-131: package directory contains a file that isn't used
+    PackageContainsUnusedFile(npv_131::PackageContainsUnusedFile),
+
+    /// NPV-132: NixOS module list refers to a module that doesn't exist
+    NixosModuleListEntryMissing(npv_132::NixosModuleListEntryMissing),
+
+    /// NPV-133: NixOS module file that isn't used by the module list
+    NixosModuleUnused(npv_133::NixosModuleUnused),
+
+    /// NPV-134: NixOS module file cannot be parsed
+    NixosModuleInvalidSyntax(npv_134::NixosModuleInvalidSyntax),
+
+    /// NPV-135: maintainer list isn't sorted
+    MaintainerListUnsorted(npv_135::MaintainerListUnsorted),
+
+    /// NPV-136: maintainer is missing a required field
+    MaintainerMissingField(npv_136::MaintainerMissingField),
+
+    /// NPV-137: maintainers with the same GitHub ID
+    MaintainerDuplicateGithubId(npv_137::MaintainerDuplicateGithubId),
+
+    /// NPV-138: maintainer has an invalid email address
+    MaintainerInvalidEmail(npv_138::MaintainerInvalidEmail),
+
+    /// NPV-139: team member isn't a maintainer
+    TeamMemberUnknown(npv_139::TeamMemberUnknown),
+
     /// NPV-140: Package directory is not directory
     PackageDirectoryIsNotDirectory(npv_140::PackageDirectoryIsNotDirectory),
 
@@ -108,6 +218,42 @@ pub enum Problem {
     /// NPV-144: `package.nix` is not a file
     PackageNixIsNotFile(npv_144::PackageNixIsNotFile),
 
+    /// NPV-145: teams with the same shortName
+    TeamDuplicateShortName(npv_145::TeamDuplicateShortName),
+
+    /// NPV-146: all-packages.nix entry isn't sorted within its section
+    TopLevelEntryUnsorted(npv_146::TopLevelEntryUnsorted),
+
+    /// NPV-147: all-packages.nix entries on the same line
+    TopLevelEntriesOnSameLine(npv_147::TopLevelEntriesOnSameLine),
+
+    /// NPV-148: path couldn't be read
+    PathUnreadable(npv_148::PathUnreadable),
+
+    /// NPV-149: symlink points to a path that doesn't exist
+    BrokenSymlink(npv_149::BrokenSymlink),
+
+    /// NPV-151: evaluating Nixpkgs emits a new warning
+    NewEvalWarning(npv_151::NewEvalWarning),
+
+    /// NPV-152: derivation name of by-name package is not `${pname}-${version}`
+    ByNameDerivationNameMismatch(npv_152::ByNameDerivationNameMismatch),
+
+    /// NPV-153: by-name package only evaluates with aliases
+    ByNameUsesAlias(npv_153::ByNameUsesAlias),
+
+    /// NPV-154: by-name package doesn't evaluate for a cross target
+    CrossEvalFailure(npv_154::CrossEvalFailure),
+
+    /// NPV-155: by-name package's meta doesn't gate its evaluation
+    MetaGatingInconsistent(npv_155::MetaGatingInconsistent),
+
+    /// NPV-156: by-name package has an update script that can't run
+    UpdateScriptInvalid(npv_156::UpdateScriptInvalid),
+
+    /// NPV-157: by-name override only passes arguments that callPackage passes anyway
+    ByNameOverrideContainsDefaultArguments(npv_157::ByNameOverrideContainsDefaultArguments),
+
     /// NPV-160: top-level package moved out of by-name
     TopLevelPackageMovedOutOfByName(npv_160::TopLevelPackageMovedOutOfByName),
 
@@ -123,6 +269,374 @@ pub enum Problem {
     NewTopLevelPackageShouldBeByNameWithCustomArgument(
         npv_163::NewTopLevelPackageShouldBeByNameWithCustomArgument,
     ),
+
+    /// NPV-164: package in by-name renamed without updating references to the old name
+    ByNamePackageRenamedIncompletely(npv_164::ByNamePackageRenamedIncompletely),
+
+    /// NPV-165: top-level package removed without a replacement
+    TopLevelPackageDroppedSilently(npv_165::TopLevelPackageDroppedSilently),
+
+    /// NPV-166: by-name package has no updateScript
+    ByNamePackageWithoutUpdateScript(npv_166::ByNamePackageWithoutUpdateScript),
+
+    /// NPV-167: library function isn't used by any test
+    LibFunctionWithoutTests(npv_167::LibFunctionWithoutTests),
+
+    /// NPV-168: new alias has no date comment
+    AliasWithoutDate(npv_168::AliasWithoutDate),
+
+    /// NPV-169: new or changed file doesn't follow a configured convention
+    ConventionViolated(npv_169::ConventionViolated),
+
+    /// NPV-170: Hydra job of a changed package fails to evaluate
+    ReleaseJobEvalFailure(npv_170::ReleaseJobEvalFailure),
+
+    /// A problem built with `Problem::at`, like NPV-150 for a check that timed out.
+    Custom(custom::Custom),
+
+    /// Another problem with context added to its message, see `Problem::with_context`.
+    WithContext(WithContext),
+}
+
+impl Problem {
+    /// The code of this kind of problem, like `NPV-100`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ByNameUndefinedAttribute(..) => "NPV-100",
+            Self::ByNameNonDerivation(..) => "NPV-101",
+            Self::ByNameInternalCallPackageUsed(..) => "NPV-102",
+            Self::ByNameCannotDetermineAttributeLocation(..) => "NPV-103",
+            Self::ByNameOverrideOfNonSyntacticCallPackage(..) => "NPV-104",
+            Self::ByNameOverrideOfNonTopLevelPackage(..) => "NPV-105",
+            Self::ByNameOverrideContainsWrongCallPackagePath(..) => "NPV-106",
+            Self::ByNameOverrideContainsEmptyArgument(..) => "NPV-107",
+            Self::ByNameOverrideContainsEmptyPath(..) => "NPV-108",
+            Self::ByNameShardIsNotDirectory(..) => "NPV-109",
+            Self::ByNameShardIsInvalid(..) => "NPV-110",
+            Self::ByNameShardIsCaseSensitiveDuplicate(..) => "NPV-111",
+            Self::NixpkgsPathMissing(..) => "NPV-112",
+            Self::ByNameAliasConflict(..) => "NPV-113",
+            Self::ByNameDuplicatePname(..) => "NPV-114",
+            Self::ByNameTestReferenceMissing(..) => "NPV-115",
+            Self::ByNameUnknownTeam(..) => "NPV-116",
+            Self::DocReferenceMissing(..) => "NPV-117",
+            Self::NixEvalError(..) => "NPV-120",
+            Self::NixFileContainsPathInterpolation(..) => "NPV-121",
+            Self::NixFileContainsSearchPath(..) => "NPV-122",
+            Self::NixFileContainsPathOutsideDirectory(..) => "NPV-123",
+            Self::NixFileContainsUnresolvablePath(..) => "NPV-124",
+            Self::PackageContainsSymlinkPointingOutside(..) => "NPV-125",
+            Self::PackageContainsUnresolvableSymlink(..) => "NPV-126",
+            Self::NixFileImportsInternalFile(..) => "NPV-127",
+            Self::NixFileContainsInvalidUpdateScript(..) => "NPV-128",
+            Self::NixFileContainsMissingPatch(..) => "NPV-129",
+            Self::PackageContainsUnusedPatch(..) => "NPV-130",
+            Self::PackageContainsUnusedFile(..) => "NPV-131",
+            Self::NixosModuleListEntryMissing(..) => "NPV-132",
+            Self::NixosModuleUnused(..) => "NPV-133",
+            Self::NixosModuleInvalidSyntax(..) => "NPV-134",
+            Self::MaintainerListUnsorted(..) => "NPV-135",
+            Self::MaintainerMissingField(..) => "NPV-136",
+            Self::MaintainerDuplicateGithubId(..) => "NPV-137",
+            Self::MaintainerInvalidEmail(..) => "NPV-138",
+            Self::TeamMemberUnknown(..) => "NPV-139",
+            Self::PackageDirectoryIsNotDirectory(..) => "NPV-140",
+            Self::InvalidPackageDirectoryName(..) => "NPV-141",
+            Self::PackageInWrongShard(..) => "NPV-142",
+            Self::PackageNixMissing(..) => "NPV-143",
+            Self::PackageNixIsNotFile(..) => "NPV-144",
+            Self::TeamDuplicateShortName(..) => "NPV-145",
+            Self::TopLevelEntryUnsorted(..) => "NPV-146",
+            Self::TopLevelEntriesOnSameLine(..) => "NPV-147",
+            Self::PathUnreadable(..) => "NPV-148",
+            Self::BrokenSymlink(..) => "NPV-149",
+            Self::NewEvalWarning(..) => "NPV-151",
+            Self::ByNameDerivationNameMismatch(..) => "NPV-152",
+            Self::ByNameUsesAlias(..) => "NPV-153",
+            Self::CrossEvalFailure(..) => "NPV-154",
+            Self::MetaGatingInconsistent(..) => "NPV-155",
+            Self::UpdateScriptInvalid(..) => "NPV-156",
+            Self::ByNameOverrideContainsDefaultArguments(..) => "NPV-157",
+            Self::TopLevelPackageMovedOutOfByName(..) => "NPV-160",
+            Self::TopLevelPackageMovedOutOfByNameWithCustomArguments(..) => "NPV-161",
+            Self::NewTopLevelPackageShouldBeByName(..) => "NPV-162",
+            Self::NewTopLevelPackageShouldBeByNameWithCustomArgument(..) => "NPV-163",
+            Self::ByNamePackageRenamedIncompletely(..) => "NPV-164",
+            Self::TopLevelPackageDroppedSilently(..) => "NPV-165",
+            Self::ByNamePackageWithoutUpdateScript(..) => "NPV-166",
+            Self::LibFunctionWithoutTests(..) => "NPV-167",
+            Self::AliasWithoutDate(..) => "NPV-168",
+            Self::ConventionViolated(..) => "NPV-169",
+            Self::ReleaseJobEvalFailure(..) => "NPV-170",
+            Self::Custom(problem) => problem.code,
+            Self::WithContext(with_context) => with_context.problem.code(),
+        }
+    }
+
+    /// The English message of the problem, no matter the catalog.
+    fn untranslated(&self) -> &dyn fmt::Display {
+        match self {
+            Self::ByNameUndefinedAttribute(problem) => problem,
+            Self::ByNameNonDerivation(problem) => problem,
+            Self::ByNameInternalCallPackageUsed(problem) => problem,
+            Self::ByNameCannotDetermineAttributeLocation(problem) => problem,
+            Self::ByNameOverrideOfNonSyntacticCallPackage(problem) => problem,
+            Self::ByNameOverrideOfNonTopLevelPackage(problem) => problem,
+            Self::ByNameOverrideContainsWrongCallPackagePath(problem) => problem,
+            Self::ByNameOverrideContainsEmptyArgument(problem) => problem,
+            Self::ByNameOverrideContainsEmptyPath(problem) => problem,
+            Self::ByNameShardIsNotDirectory(problem) => problem,
+            Self::ByNameShardIsInvalid(problem) => problem,
+            Self::ByNameShardIsCaseSensitiveDuplicate(problem) => problem,
+            Self::NixpkgsPathMissing(problem) => problem,
+            Self::ByNameAliasConflict(problem) => problem,
+            Self::ByNameDuplicatePname(problem) => problem,
+            Self::ByNameTestReferenceMissing(problem) => problem,
+            Self::ByNameUnknownTeam(problem) => problem,
+            Self::DocReferenceMissing(problem) => problem,
+            Self::NixEvalError(problem) => problem,
+            Self::NixFileContainsPathInterpolation(problem) => problem,
+            Self::NixFileContainsSearchPath(problem) => problem,
+            Self::NixFileContainsPathOutsideDirectory(problem) => problem,
+            Self::NixFileContainsUnresolvablePath(problem) => problem,
+            Self::PackageContainsSymlinkPointingOutside(problem) => problem,
+            Self::PackageContainsUnresolvableSymlink(problem) => problem,
+            Self::NixFileImportsInternalFile(problem) => problem,
+            Self::NixFileContainsInvalidUpdateScript(problem) => problem,
+            Self::NixFileContainsMissingPatch(problem) => problem,
+            Self::PackageContainsUnusedPatch(problem) => problem,
+            Self::PackageContainsUnusedFile(problem) => problem,
+            Self::NixosModuleListEntryMissing(problem) => problem,
+            Self::NixosModuleUnused(problem) => problem,
+            Self::NixosModuleInvalidSyntax(problem) => problem,
+            Self::MaintainerListUnsorted(problem) => problem,
+            Self::MaintainerMissingField(problem) => problem,
+            Self::MaintainerDuplicateGithubId(problem) => problem,
+            Self::MaintainerInvalidEmail(problem) => problem,
+            Self::TeamMemberUnknown(problem) => problem,
+            Self::PackageDirectoryIsNotDirectory(problem) => problem,
+            Self::InvalidPackageDirectoryName(problem) => problem,
+            Self::PackageInWrongShard(problem) => problem,
+            Self::PackageNixMissing(problem) => problem,
+            Self::PackageNixIsNotFile(problem) => problem,
+            Self::TeamDuplicateShortName(problem) => problem,
+            Self::TopLevelEntryUnsorted(problem) => problem,
+            Self::TopLevelEntriesOnSameLine(problem) => problem,
+            Self::PathUnreadable(problem) => problem,
+            Self::BrokenSymlink(problem) => problem,
+            Self::NewEvalWarning(problem) => problem,
+            Self::ByNameDerivationNameMismatch(problem) => problem,
+            Self::ByNameUsesAlias(problem) => problem,
+            Self::CrossEvalFailure(problem) => problem,
+            Self::MetaGatingInconsistent(problem) => problem,
+            Self::UpdateScriptInvalid(problem) => problem,
+            Self::ByNameOverrideContainsDefaultArguments(problem) => problem,
+            Self::TopLevelPackageMovedOutOfByName(problem) => problem,
+            Self::TopLevelPackageMovedOutOfByNameWithCustomArguments(problem) => problem,
+            Self::NewTopLevelPackageShouldBeByName(problem) => problem,
+            Self::NewTopLevelPackageShouldBeByNameWithCustomArgument(problem) => problem,
+            Self::ByNamePackageRenamedIncompletely(problem) => problem,
+            Self::TopLevelPackageDroppedSilently(problem) => problem,
+            Self::ByNamePackageWithoutUpdateScript(problem) => problem,
+            Self::LibFunctionWithoutTests(problem) => problem,
+            Self::AliasWithoutDate(problem) => problem,
+            Self::ConventionViolated(problem) => problem,
+            Self::ReleaseJobEvalFailure(problem) => problem,
+            Self::Custom(problem) => problem,
+            Self::WithContext(with_context) => with_context,
+        }
+    }
+
+    /// Starts building a problem about the path relative to Nixpkgs, for checks whose problems
+    /// don't need a type of their own, like
+    /// `Problem::at(path).code("NPV-150").message("...").suggestion("...").into()`.
+    pub fn at(path: impl Into<RelativePathBuf>) -> custom::ProblemBuilder {
+        custom::ProblemBuilder::new(path.into())
+    }
+
+    /// Prefixes the context to the message of the problem, like the package set it was found in.
+    pub fn with_context(self, context: &str) -> Problem {
+        WithContext::new(context, self).into()
+    }
+
+    /// The first line of the message of the problem, without any explanations.
+    pub fn summary(&self) -> String {
+        let message = self.to_string();
+        // Some problems show other output before their message, like Nix evaluation errors
+        let summary = message
+            .lines()
+            .find_map(|line| line.strip_prefix("- "))
+            .or_else(|| message.lines().next())
+            .unwrap_or_default();
+        summary.to_owned()
+    }
+
+    /// Where in Nixpkgs the problem is, if it's about anything specific.
+    pub fn location(&self) -> Option<ProblemLocation> {
+        match self {
+            Self::ByNameUndefinedAttribute(problem) => Some(problem.location()),
+            Self::ByNameNonDerivation(problem) => Some(problem.location()),
+            Self::ByNameInternalCallPackageUsed(problem) => Some(problem.location()),
+            Self::ByNameCannotDetermineAttributeLocation(problem) => Some(problem.location()),
+            Self::ByNameOverrideOfNonSyntacticCallPackage(problem) => Some(problem.location()),
+            Self::ByNameOverrideOfNonTopLevelPackage(problem) => Some(problem.location()),
+            Self::ByNameOverrideContainsWrongCallPackagePath(problem) => Some(problem.location()),
+            Self::ByNameOverrideContainsEmptyArgument(problem) => Some(problem.location()),
+            Self::ByNameOverrideContainsEmptyPath(problem) => Some(problem.location()),
+            Self::ByNameShardIsNotDirectory(problem) => Some(problem.location()),
+            Self::ByNameShardIsInvalid(problem) => Some(problem.location()),
+            Self::ByNameShardIsCaseSensitiveDuplicate(problem) => Some(problem.location()),
+            Self::NixpkgsPathMissing(problem) => Some(problem.location()),
+            Self::ByNameAliasConflict(problem) => Some(problem.location()),
+            Self::ByNameDuplicatePname(problem) => Some(problem.location()),
+            Self::ByNameTestReferenceMissing(problem) => Some(problem.location()),
+            Self::ByNameUnknownTeam(problem) => Some(problem.location()),
+            Self::DocReferenceMissing(problem) => Some(problem.location()),
+            Self::NixEvalError(problem) => problem.location(),
+            Self::NixFileContainsPathInterpolation(problem) => Some(problem.location()),
+            Self::NixFileContainsSearchPath(problem) => Some(problem.location()),
+            Self::NixFileContainsPathOutsideDirectory(problem) => Some(problem.location()),
+            Self::NixFileContainsUnresolvablePath(problem) => Some(problem.location()),
+            Self::PackageContainsSymlinkPointingOutside(problem) => Some(problem.location()),
+            Self::PackageContainsUnresolvableSymlink(problem) => Some(problem.location()),
+            Self::NixFileImportsInternalFile(problem) => Some(problem.location()),
+            Self::NixFileContainsInvalidUpdateScript(problem) => Some(problem.location()),
+            Self::NixFileContainsMissingPatch(problem) => Some(problem.location()),
+            Self::PackageContainsUnusedPatch(problem) => Some(problem.location()),
+            Self::PackageContainsUnusedFile(problem) => Some(problem.location()),
+            Self::NixosModuleListEntryMissing(problem) => Some(problem.location()),
+            Self::NixosModuleUnused(problem) => Some(problem.location()),
+            Self::NixosModuleInvalidSyntax(problem) => Some(problem.location()),
+            Self::MaintainerListUnsorted(problem) => Some(problem.location()),
+            Self::MaintainerMissingField(problem) => Some(problem.location()),
+            Self::MaintainerDuplicateGithubId(problem) => Some(problem.location()),
+            Self::MaintainerInvalidEmail(problem) => Some(problem.location()),
+            Self::TeamMemberUnknown(problem) => Some(problem.location()),
+            Self::PackageDirectoryIsNotDirectory(problem) => Some(problem.location()),
+            Self::InvalidPackageDirectoryName(problem) => Some(problem.location()),
+            Self::PackageInWrongShard(problem) => Some(problem.location()),
+            Self::PackageNixMissing(problem) => Some(problem.location()),
+            Self::PackageNixIsNotFile(problem) => Some(problem.location()),
+            Self::TeamDuplicateShortName(problem) => Some(problem.location()),
+            Self::TopLevelEntryUnsorted(problem) => Some(problem.location()),
+            Self::TopLevelEntriesOnSameLine(problem) => Some(problem.location()),
+            Self::PathUnreadable(problem) => Some(problem.location()),
+            Self::BrokenSymlink(problem) => Some(problem.location()),
+            Self::NewEvalWarning(..) => None,
+            Self::ByNameDerivationNameMismatch(problem) => Some(problem.location()),
+            Self::ByNameUsesAlias(problem) => Some(problem.location()),
+            Self::CrossEvalFailure(problem) => Some(problem.location()),
+            Self::MetaGatingInconsistent(problem) => Some(problem.location()),
+            Self::UpdateScriptInvalid(problem) => Some(problem.location()),
+            Self::ByNameOverrideContainsDefaultArguments(problem) => Some(problem.location()),
+            Self::TopLevelPackageMovedOutOfByName(problem) => Some(problem.location()),
+            Self::TopLevelPackageMovedOutOfByNameWithCustomArguments(problem) => {
+                Some(problem.location())
+            }
+            Self::NewTopLevelPackageShouldBeByName(problem) => Some(problem.location()),
+            Self::NewTopLevelPackageShouldBeByNameWithCustomArgument(problem) => {
+                Some(problem.location())
+            }
+            Self::ByNamePackageRenamedIncompletely(problem) => Some(problem.location()),
+            Self::TopLevelPackageDroppedSilently(problem) => Some(problem.location()),
+            Self::ByNamePackageWithoutUpdateScript(problem) => Some(problem.location()),
+            Self::LibFunctionWithoutTests(problem) => Some(problem.location()),
+            Self::AliasWithoutDate(problem) => Some(problem.location()),
+            Self::ConventionViolated(problem) => Some(problem.location()),
+            Self::ReleaseJobEvalFailure(problem) => Some(problem.location()),
+            Self::Custom(problem) => Some(problem.location()),
+            Self::WithContext(with_context) => with_context.problem.location(),
+        }
+    }
+
+    /// A fix that can be applied without any judgement needed, if there is one.
+    pub fn fix(&self) -> Option<Fix> {
+        match self {
+            Self::PackageInWrongShard(problem) => Some(problem.fix()),
+            Self::ByNameOverrideContainsEmptyArgument(problem) => Some(problem.fix()),
+            Self::ByNameOverrideContainsDefaultArguments(problem) => Some(problem.fix()),
+            Self::WithContext(with_context) => with_context.problem.fix(),
+            _ => None,
+        }
+    }
+}
+
+impl IsFatal for Problem {
+    /// Nixpkgs that doesn't evaluate, and Nix files that don't parse, make the checks that
+    /// evaluate or parse them fail.
+    fn is_fatal(&self) -> bool {
+        match self {
+            Self::NixEvalError(..) | Self::NixosModuleInvalidSyntax(..) => true,
+            Self::WithContext(with_context) => with_context.problem.is_fatal(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The message of the problem with context is translated on its own
+        if let Self::WithContext(with_context) = self {
+            return with_context.fmt(f);
+        }
+        match catalog::translate(self) {
+            Some(message) => f.write_str(&message),
+            None => self.untranslated().fmt(f),
+        }
+    }
+}
+
+/// A problem with context prefixed to its message, like the package set it was found in. It has
+/// the code and location of the problem.
+#[derive(Clone, Serialize)]
+pub struct WithContext {
+    context: String,
+    problem: Box<Problem>,
+}
+
+impl WithContext {
+    pub fn new(context: impl Into<String>, problem: Problem) -> Self {
+        Self {
+            context: context.into(),
+            problem: Box::new(problem),
+        }
+    }
+}
+
+impl fmt::Display for WithContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { context, problem } = self;
+        let message = problem.to_string();
+        match message.strip_prefix("- ") {
+            Some(rest) => write!(f, "- {context}: {rest}"),
+            // Like Nix evaluation errors, which show other output before their message
+            None => write!(f, "{context}:\n{message}"),
+        }
+    }
+}
+
+/// Serializes a field using its `Display` implementation, for fields like `io::Error`.
+fn serialize_display<S: Serializer>(
+    value: &impl fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Like `serialize_display`, but for optional fields.
+fn serialize_option_display<S: Serializer>(
+    value: &Option<impl fmt::Display>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serializes a file name, which may not be valid UTF-8.
+fn serialize_os_str<S: Serializer>(value: &OsStr, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&value.display())
 }
 
 fn indent_definition(column: usize, definition: &str) -> String {
@@ -1,19 +1,31 @@
 use std::fmt;
 
 use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::structure;
+use crate::location::ProblemLocation;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameUndefinedAttribute {
     #[new(into)]
     attribute_name: String,
+    #[new(into)]
+    relative_package_file: RelativePathBuf,
+}
+
+impl ByNameUndefinedAttribute {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_file.clone(), None)
+    }
 }
 
 impl fmt::Display for ByNameUndefinedAttribute {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let Self { attribute_name } = self;
-        let relative_package_file = structure::relative_file_for_package(attribute_name);
+        let Self {
+            attribute_name,
+            relative_package_file,
+        } = self;
         write!(
             f,
             "- pkgs.{attribute_name}: This attribute is not defined but it should be defined automatically as {relative_package_file}",
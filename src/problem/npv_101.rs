@@ -1,22 +1,79 @@
 use std::fmt;
 
 use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::structure;
+use crate::location::ProblemLocation;
 
-#[derive(Clone, new)]
+/// What an attribute that isn't a derivation is instead.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValueShape {
+    /// A function, like when a file takes its arguments in more than one function.
+    Function,
+    /// An attribute set, with its `type` attribute if it has one.
+    AttributeSet { type_attribute: Option<String> },
+    /// Any other value, with its type as returned by `builtins.typeOf`.
+    Other(String),
+}
+
+impl ValueShape {
+    /// The shape of a value that isn't an attribute set, by its type as returned by
+    /// `builtins.typeOf`.
+    pub fn non_attribute_set(value_type: String) -> Self {
+        if value_type == "lambda" {
+            Self::Function
+        } else {
+            Self::Other(value_type)
+        }
+    }
+}
+
+#[derive(Clone, new, Serialize)]
 pub struct ByNameNonDerivation {
     #[new(into)]
     attribute_name: String,
+    #[new(into)]
+    relative_package_file: RelativePathBuf,
+    shape: ValueShape,
+}
+
+impl ByNameNonDerivation {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_file.clone(), None)
+    }
 }
 
 impl fmt::Display for ByNameNonDerivation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let Self { attribute_name } = self;
-        let relative_package_file = structure::relative_file_for_package(attribute_name);
+        let Self {
+            attribute_name,
+            relative_package_file,
+            shape,
+        } = self;
         write!(
             f,
             "- pkgs.{attribute_name}: This attribute defined by {relative_package_file} is not a derivation",
-        )
+        )?;
+        match shape {
+            ValueShape::Function => write!(
+                f,
+                ", but a function. `callPackage` only passes the arguments to the first function, so the file needs to take all of them at once, like `{{ lib, stdenv }}: ...`.",
+            ),
+            ValueShape::AttributeSet {
+                type_attribute: None,
+            } => write!(
+                f,
+                ", but a plain attribute set. Collections of packages need to be a package set instead.",
+            ),
+            ValueShape::AttributeSet {
+                type_attribute: Some(type_attribute),
+            } => write!(
+                f,
+                ", but an attribute set with `type = \"{type_attribute}\"`. If such attributes are intended, they can be allowed with `non-derivation-types` in the configuration of nixpkgs-vet.",
+            ),
+            ValueShape::Other(value_type) => write!(f, ", but a value of type {value_type}."),
+        }
     }
 }
@@ -1,13 +1,26 @@
 use std::fmt;
 
 use derive_new::new;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
 pub struct ByNameInternalCallPackageUsed {
     #[new(into)]
     attribute_name: String,
 }
 
+impl ByNameInternalCallPackageUsed {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.attribute_name),
+            None,
+        )
+    }
+}
+
 impl fmt::Display for ByNameInternalCallPackageUsed {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self { attribute_name } = self;
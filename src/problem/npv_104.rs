@@ -2,13 +2,14 @@ use std::fmt;
 
 use derive_new::new;
 use indoc::writedoc;
+use serde::Serialize;
 
-use crate::location::Location;
+use crate::location::{Location, ProblemLocation};
 use crate::structure;
 
 use super::{create_path_expr, indent_definition};
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameOverrideOfNonSyntacticCallPackage {
     #[new(into)]
     package_name: String,
@@ -17,6 +18,12 @@ pub struct ByNameOverrideOfNonSyntacticCallPackage {
     definition: String,
 }
 
+impl ByNameOverrideOfNonSyntacticCallPackage {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::from(&self.location)
+    }
+}
+
 impl fmt::Display for ByNameOverrideOfNonSyntacticCallPackage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
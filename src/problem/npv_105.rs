@@ -2,13 +2,14 @@ use std::fmt;
 
 use derive_new::new;
 use indoc::writedoc;
+use serde::Serialize;
 
-use crate::location::Location;
+use crate::location::{Location, ProblemLocation};
 use crate::structure;
 
 use super::{create_path_expr, indent_definition};
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameOverrideOfNonTopLevelPackage {
     #[new(into)]
     package_name: String,
@@ -17,6 +18,12 @@ pub struct ByNameOverrideOfNonTopLevelPackage {
     definition: String,
 }
 
+impl ByNameOverrideOfNonTopLevelPackage {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::from(&self.location)
+    }
+}
+
 impl fmt::Display for ByNameOverrideOfNonTopLevelPackage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
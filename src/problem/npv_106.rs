@@ -3,13 +3,14 @@ use std::fmt;
 use derive_new::new;
 use indoc::writedoc;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::location::Location;
+use crate::location::{Location, ProblemLocation};
 use crate::structure;
 
 use super::create_path_expr;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameOverrideContainsWrongCallPackagePath {
     #[new(into)]
     package_name: String,
@@ -18,6 +19,12 @@ pub struct ByNameOverrideContainsWrongCallPackagePath {
     location: Location,
 }
 
+impl ByNameOverrideContainsWrongCallPackagePath {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::from(&self.location)
+    }
+}
+
 impl fmt::Display for ByNameOverrideContainsWrongCallPackagePath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
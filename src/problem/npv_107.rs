@@ -2,13 +2,15 @@ use std::fmt;
 
 use derive_new::new;
 use indoc::writedoc;
+use serde::Serialize;
 
-use crate::location::Location;
+use crate::fix::Fix;
+use crate::location::{Location, ProblemLocation};
 use crate::structure;
 
 use super::{create_path_expr, indent_definition};
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameOverrideContainsEmptyArgument {
     #[new(into)]
     package_name: String,
@@ -17,6 +19,22 @@ pub struct ByNameOverrideContainsEmptyArgument {
     definition: String,
 }
 
+impl ByNameOverrideContainsEmptyArgument {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::from(&self.location)
+    }
+
+    /// Removes the definition, since the one of `pkgs/by-name` is the same.
+    pub fn fix(&self) -> Fix {
+        Fix::Remove {
+            file: self.location.file.clone(),
+            line: self.location.line,
+            column: self.location.column,
+            text: self.definition.clone(),
+        }
+    }
+}
+
 impl fmt::Display for ByNameOverrideContainsEmptyArgument {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
@@ -1,21 +1,29 @@
 use std::fmt;
 
 use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::structure;
+use crate::location::ProblemLocation;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameShardIsNotDirectory {
     #[new(into)]
-    shard_name: String,
+    relative_shard_dir: RelativePathBuf,
+}
+
+impl ByNameShardIsNotDirectory {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_shard_dir.clone(), None)
+    }
 }
 
 impl fmt::Display for ByNameShardIsNotDirectory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let relative_shard_path = structure::relative_dir_for_shard(&self.shard_name);
+        let Self { relative_shard_dir } = self;
         write!(
             f,
-            "- {relative_shard_path}: This is a file, but it should be a directory.",
+            "- {relative_shard_dir}: This is a file, but it should be a directory.",
         )
     }
 }
@@ -1,22 +1,34 @@
 use std::fmt;
 
 use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::structure;
+use crate::location::ProblemLocation;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameShardIsInvalid {
     #[new(into)]
     shard_name: String,
+    #[new(into)]
+    relative_shard_dir: RelativePathBuf,
+}
+
+impl ByNameShardIsInvalid {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_shard_dir.clone(), None)
+    }
 }
 
 impl fmt::Display for ByNameShardIsInvalid {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let shard_name = &self.shard_name;
-        let relative_shard_path = structure::relative_dir_for_shard(shard_name);
+        let Self {
+            shard_name,
+            relative_shard_dir,
+        } = self;
         write!(
             f,
-            "- {relative_shard_path}: Invalid directory name \"{shard_name}\", must be at most 2 ASCII characters consisting of a-z, 0-9, \"-\" or \"_\".",
+            "- {relative_shard_dir}: Invalid directory name \"{shard_name}\", must be at most 2 ASCII characters consisting of a-z, 0-9, \"-\" or \"_\".",
         )
     }
 }
@@ -2,25 +2,39 @@ use std::ffi::OsString;
 use std::fmt;
 
 use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::structure;
+use crate::location::ProblemLocation;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct ByNameShardIsCaseSensitiveDuplicate {
     #[new(into)]
-    shard_name: String,
+    relative_shard_dir: RelativePathBuf,
+    #[serde(serialize_with = "super::serialize_os_str")]
     first: OsString,
+    #[serde(serialize_with = "super::serialize_os_str")]
     second: OsString,
 }
 
+impl ByNameShardIsCaseSensitiveDuplicate {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            self.relative_shard_dir
+                .join(self.first.to_string_lossy().as_ref()),
+            None,
+        )
+    }
+}
+
 impl fmt::Display for ByNameShardIsCaseSensitiveDuplicate {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let relative_shard_path = structure::relative_dir_for_shard(&self.shard_name);
+        let relative_shard_dir = &self.relative_shard_dir;
         let first = self.first.to_string_lossy();
         let second = self.second.to_string_lossy();
         write!(
             f,
-            "- {relative_shard_path}: Duplicate case-sensitive package directories \"{first}\" and \"{second}\"."
+            "- {relative_shard_dir}: Duplicate case-sensitive package directories \"{first}\" and \"{second}\"."
         )
     }
 }
@@ -0,0 +1,29 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixpkgsPathMissing {
+    #[new(into)]
+    path: RelativePathBuf,
+}
+
+impl NixpkgsPathMissing {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.path.clone(), None)
+    }
+}
+
+impl fmt::Display for NixpkgsPathMissing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { path } = self;
+        write!(
+            f,
+            "- {path}: This path is needed to check Nixpkgs, but it doesn't exist. If this is a sparse checkout, please include it with `git sparse-checkout add {path}`."
+        )
+    }
+}
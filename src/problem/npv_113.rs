@@ -0,0 +1,35 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameAliasConflict {
+    #[new(into)]
+    package_name: String,
+    location: Location,
+}
+
+impl ByNameAliasConflict {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::from(&self.location)
+    }
+}
+
+impl fmt::Display for ByNameAliasConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            location,
+        } = self;
+        let relative_package_dir = structure::relative_dir_for_package(package_name);
+        let Location { file, line, .. } = location;
+        write!(
+            f,
+            "- pkgs.{package_name}: This attribute is defined both by the package in {relative_package_dir} and as an alias in {file} on line {line}. Because aliases are applied last, the alias wins by default, and the package is only used with `allowAliases = false`. Please remove the alias or rename the package.",
+        )
+    }
+}
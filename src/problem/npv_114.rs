@@ -0,0 +1,42 @@
+use std::fmt;
+
+use derive_new::new;
+use itertools::Itertools as _;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameDuplicatePname {
+    #[new(into)]
+    pname: String,
+    /// The packages with this `pname`, sorted by name.
+    package_names: Vec<String>,
+}
+
+impl ByNameDuplicatePname {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_dir_for_package(&self.package_names[0]),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for ByNameDuplicatePname {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            pname,
+            package_names,
+        } = self;
+        let attributes = package_names
+            .iter()
+            .map(|package_name| format!("pkgs.{package_name}"))
+            .join(", ");
+        write!(
+            f,
+            "- {attributes}: These packages all have `pname = \"{pname}\"`, which usually means that one of them was copied from another without changing it, or is an unintended fork of it. Please give each package its own `pname`, or remove the duplicate.",
+        )
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameTestReferenceMissing {
+    #[new(into)]
+    package_name: String,
+    /// The referenced attribute path, like `nixosTests.foo`.
+    #[new(into)]
+    attribute_path: String,
+    line: usize,
+    column: usize,
+}
+
+impl ByNameTestReferenceMissing {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: structure::relative_file_for_package(&self.package_name),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for ByNameTestReferenceMissing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            attribute_path,
+            line,
+            column: _,
+        } = self;
+        let relative_package_file = structure::relative_file_for_package(package_name);
+        write!(
+            f,
+            "- pkgs.{package_name}: File {relative_package_file} at line {line} refers to `{attribute_path}` in `passthru.tests`, but `pkgs.{attribute_path}` doesn't exist. This breaks the evaluation of the release jobs, please remove or fix the reference.",
+        )
+    }
+}
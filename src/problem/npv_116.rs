@@ -0,0 +1,44 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::TEAM_LIST_PATH;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameUnknownTeam {
+    #[new(into)]
+    package_name: String,
+    #[new(into)]
+    team: String,
+    line: usize,
+    column: usize,
+}
+
+impl ByNameUnknownTeam {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: structure::relative_file_for_package(&self.package_name),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for ByNameUnknownTeam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            team,
+            line,
+            column: _,
+        } = self;
+        let relative_package_file = structure::relative_file_for_package(package_name);
+        write!(
+            f,
+            "- pkgs.{package_name}: File {relative_package_file} at line {line} has the team `{team}` in `meta.teams`, but there's no such team in {TEAM_LIST_PATH}. Please fix the team, or add it there.",
+        )
+    }
+}
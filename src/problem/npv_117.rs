@@ -0,0 +1,43 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct DocReferenceMissing {
+    #[new(into)]
+    file: RelativePathBuf,
+    /// The referenced attribute path below `pkgs`, like `foo.bar`.
+    #[new(into)]
+    attribute_path: String,
+    line: usize,
+    column: usize,
+}
+
+impl DocReferenceMissing {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.file.clone(),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for DocReferenceMissing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            file,
+            attribute_path,
+            line,
+            column: _,
+        } = self;
+        write!(
+            f,
+            "- {file}: The manual refers to `pkgs.{attribute_path}` at line {line}, but it doesn't exist. Please update the manual, e.g. to refer to the new name of the package.",
+        )
+    }
+}
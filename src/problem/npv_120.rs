@@ -1,19 +1,86 @@
 use std::fmt;
 
 use derive_new::new;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
 pub struct NixEvalError {
+    kind: EvalFailureKind,
+    /// The package in `pkgs/by-name` closest to the error in the trace, if any.
+    package_name: Option<String>,
+    /// The output of Nix, with the middle of long traces left out, see `eval::trim_trace`.
     #[new(into)]
-    stderr: String,
+    trace: String,
+}
+
+/// Why Nix evaluation failed, as far as it can be told from the error, see `eval::classify`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvalFailureKind {
+    /// A function was called without an argument it requires, like a `callPackage` argument
+    /// that doesn't exist.
+    MissingArgument,
+    /// An `assert` failed.
+    Assertion,
+    InfiniteRecursion,
+    /// A package is refused by the checks of its `meta`, because it's unfree, broken, insecure
+    /// or not available on the system.
+    UnfreeOrBroken,
+    /// Something called `throw`.
+    Throw,
+    /// A path like `<nixpkgs>` was looked up in the Nix search path, which is empty, such that
+    /// the evaluation doesn't depend on the environment.
+    SearchPathLookup,
+    /// Any other error.
+    Other,
+}
+
+impl NixEvalError {
+    pub fn location(&self) -> Option<ProblemLocation> {
+        let package_name = self.package_name.as_ref()?;
+        Some(ProblemLocation::new(
+            structure::relative_file_for_package(package_name),
+            None,
+        ))
+    }
 }
 
 impl fmt::Display for NixEvalError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&self.stderr)?;
-        write!(
-            f,
-            "- Nix evaluation failed for some package in `pkgs/by-name`, see error above"
-        )
+        let Self {
+            kind,
+            package_name,
+            trace,
+        } = self;
+        f.write_str(trace)?;
+        let reason = match kind {
+            EvalFailureKind::MissingArgument => {
+                " because a function was called without a required argument"
+            }
+            EvalFailureKind::Assertion => " because an assertion failed",
+            EvalFailureKind::InfiniteRecursion => " because of an infinite recursion",
+            EvalFailureKind::UnfreeOrBroken => {
+                " because a package is unfree, broken, insecure or not available on the system"
+            }
+            EvalFailureKind::Throw => " because of a `throw`",
+            EvalFailureKind::SearchPathLookup => {
+                " because a path like `<nixpkgs>` was looked up in the Nix search path, which \
+                packages can't depend on"
+            }
+            EvalFailureKind::Other => "",
+        };
+        match package_name {
+            Some(package_name) => write!(
+                f,
+                "- pkgs.{package_name}: Nix evaluation failed{reason}, see error above"
+            ),
+            None => write!(
+                f,
+                "- Nix evaluation failed for some package in `pkgs/by-name`{reason}, see error above"
+            ),
+        }
     }
 }
@@ -2,24 +2,39 @@ use std::fmt;
 
 use derive_new::new;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
 pub struct NixFileContainsSearchPath {
     #[new(into)]
     relative_package_dir: RelativePathBuf,
     #[new(into)]
     subpath: RelativePathBuf,
     line: usize,
+    column: usize,
     #[new(into)]
     text: String,
 }
 
+impl NixFileContainsSearchPath {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.relative_package_dir.join(&self.subpath),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
 impl fmt::Display for NixFileContainsSearchPath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
             relative_package_dir,
             subpath,
             line,
+            column: _,
             text,
         } = self;
         write!(
@@ -3,26 +3,40 @@ use std::fmt;
 use derive_new::new;
 use indoc::writedoc;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
+use crate::location::ProblemLocation;
 use crate::structure::PACKAGE_NIX_FILENAME;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct NixFileContainsPathOutsideDirectory {
     #[new(into)]
     relative_package_dir: RelativePathBuf,
     #[new(into)]
     subpath: RelativePathBuf,
     line: usize,
+    column: usize,
     #[new(into)]
     text: String,
 }
 
+impl NixFileContainsPathOutsideDirectory {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.relative_package_dir.join(&self.subpath),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
 impl fmt::Display for NixFileContainsPathOutsideDirectory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
             relative_package_dir,
             subpath,
             line,
+            column: _,
             text,
         } = self;
         writedoc!(
@@ -3,26 +3,42 @@ use std::{fmt, io};
 
 use derive_new::new;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
 pub struct NixFileContainsUnresolvablePath {
     #[new(into)]
     relative_package_dir: RelativePathBuf,
     #[new(into)]
     subpath: RelativePathBuf,
     line: usize,
+    column: usize,
     #[new(into)]
     text: String,
     #[new(into)]
+    #[serde(serialize_with = "super::serialize_display")]
     io_error: Arc<io::Error>,
 }
 
+impl NixFileContainsUnresolvablePath {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.relative_package_dir.join(&self.subpath),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
 impl fmt::Display for NixFileContainsUnresolvablePath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
             relative_package_dir,
             subpath,
             line,
+            column: _,
             text,
             io_error,
         } = self;
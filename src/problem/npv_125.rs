@@ -2,8 +2,11 @@ use std::fmt;
 
 use derive_new::new;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
 pub struct PackageContainsSymlinkPointingOutside {
     #[new(into)]
     relative_package_dir: RelativePathBuf,
@@ -11,6 +14,12 @@ pub struct PackageContainsSymlinkPointingOutside {
     subpath: RelativePathBuf,
 }
 
+impl PackageContainsSymlinkPointingOutside {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.join(&self.subpath), None)
+    }
+}
+
 impl fmt::Display for PackageContainsSymlinkPointingOutside {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
@@ -3,17 +3,27 @@ use std::{fmt, io};
 
 use derive_new::new;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
 pub struct PackageContainsUnresolvableSymlink {
     #[new(into)]
     relative_package_dir: RelativePathBuf,
     #[new(into)]
     subpath: RelativePathBuf,
     #[new(into)]
+    #[serde(serialize_with = "super::serialize_display")]
     io_error: Arc<io::Error>,
 }
 
+impl PackageContainsUnresolvableSymlink {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.join(&self.subpath), None)
+    }
+}
+
 impl fmt::Display for PackageContainsUnresolvableSymlink {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
@@ -0,0 +1,50 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure::PACKAGE_NIX_FILENAME;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixFileImportsInternalFile {
+    #[new(into)]
+    relative_package_dir: RelativePathBuf,
+    #[new(into)]
+    subpath: RelativePathBuf,
+    line: usize,
+    column: usize,
+    /// The whole `import` expression.
+    #[new(into)]
+    text: String,
+    #[new(into)]
+    internal_dir: String,
+}
+
+impl NixFileImportsInternalFile {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.relative_package_dir.join(&self.subpath),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for NixFileImportsInternalFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            relative_package_dir,
+            subpath,
+            line,
+            column,
+            text,
+            internal_dir,
+        } = self;
+        write!(
+            f,
+            "- {relative_package_dir}: File {subpath} at line {line}, column {column} contains the expression \"{text}\", which imports a file from {internal_dir}. Packages must not depend on the internals of Nixpkgs. Instead, take what is needed as an argument of the function in {PACKAGE_NIX_FILENAME}, which `callPackage` then passes automatically.",
+        )
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use std::{fmt, io};
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixFileContainsInvalidUpdateScript {
+    #[new(into)]
+    relative_package_dir: RelativePathBuf,
+    #[new(into)]
+    subpath: RelativePathBuf,
+    line: usize,
+    column: usize,
+    #[new(into)]
+    text: String,
+    /// Why the path can't be resolved, otherwise it's not an executable file.
+    #[serde(serialize_with = "super::serialize_option_display")]
+    io_error: Option<Arc<io::Error>>,
+}
+
+impl NixFileContainsInvalidUpdateScript {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.relative_package_dir.join(&self.subpath),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for NixFileContainsInvalidUpdateScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            relative_package_dir,
+            subpath,
+            line,
+            column: _,
+            text,
+            io_error,
+        } = self;
+        match io_error {
+            Some(io_error) => write!(
+                f,
+                "- {relative_package_dir}: File {subpath} at line {line} refers to \"{text}\" in its `updateScript`, which cannot be resolved: {io_error}.",
+            ),
+            None => write!(
+                f,
+                "- {relative_package_dir}: File {subpath} at line {line} uses \"{text}\" as its `updateScript`, but it's not an executable file. Please make it executable with `chmod +x`.",
+            ),
+        }
+    }
+}
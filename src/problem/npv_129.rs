@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::{fmt, io};
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixFileContainsMissingPatch {
+    #[new(into)]
+    relative_package_dir: RelativePathBuf,
+    #[new(into)]
+    subpath: RelativePathBuf,
+    line: usize,
+    column: usize,
+    #[new(into)]
+    text: String,
+    #[new(into)]
+    #[serde(serialize_with = "super::serialize_display")]
+    io_error: Arc<io::Error>,
+}
+
+impl NixFileContainsMissingPatch {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.relative_package_dir.join(&self.subpath),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for NixFileContainsMissingPatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            relative_package_dir,
+            subpath,
+            line,
+            column: _,
+            text,
+            io_error,
+        } = self;
+        write!(
+            f,
+            "- {relative_package_dir}: File {subpath} at line {line} applies the patch \"{text}\", which cannot be resolved: {io_error}. Please add the patch to the package directory, or remove it from `patches`.",
+        )
+    }
+}
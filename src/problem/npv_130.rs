@@ -0,0 +1,34 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct PackageContainsUnusedPatch {
+    #[new(into)]
+    relative_package_dir: RelativePathBuf,
+    #[new(into)]
+    subpath: RelativePathBuf,
+}
+
+impl PackageContainsUnusedPatch {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.join(&self.subpath), None)
+    }
+}
+
+impl fmt::Display for PackageContainsUnusedPatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            relative_package_dir,
+            subpath,
+        } = self;
+        write!(
+            f,
+            "- {relative_package_dir}: The patch {subpath} isn't referred to by any Nix file of the package. Please apply it with `patches = [ ./{subpath} ];`, or remove it if it's not needed anymore.",
+        )
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct PackageContainsUnusedFile {
+    #[new(into)]
+    relative_package_dir: RelativePathBuf,
+    #[new(into)]
+    subpath: RelativePathBuf,
+}
+
+impl PackageContainsUnusedFile {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.join(&self.subpath), None)
+    }
+}
+
+impl fmt::Display for PackageContainsUnusedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            relative_package_dir,
+            subpath,
+        } = self;
+        write!(
+            f,
+            "- {relative_package_dir}: The file {subpath} isn't referred to by `package.nix` or any Nix file it uses, so it has no effect. Please remove it if it's not needed anymore.",
+        )
+    }
+}
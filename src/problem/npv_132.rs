@@ -0,0 +1,47 @@
+use std::sync::Arc;
+use std::{fmt, io};
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixosModuleListEntryMissing {
+    #[new(into)]
+    module_list: RelativePathBuf,
+    line: usize,
+    column: usize,
+    #[new(into)]
+    text: String,
+    #[new(into)]
+    #[serde(serialize_with = "super::serialize_display")]
+    io_error: Arc<io::Error>,
+}
+
+impl NixosModuleListEntryMissing {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.module_list.clone(),
+            line: Some(self.line),
+            column: Some(self.column),
+        }
+    }
+}
+
+impl fmt::Display for NixosModuleListEntryMissing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            module_list,
+            line,
+            column: _,
+            text,
+            io_error,
+        } = self;
+        write!(
+            f,
+            "- {module_list}: The module \"{text}\" at line {line} cannot be resolved: {io_error}. Please add the module, or remove it from the list.",
+        )
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixosModuleUnused {
+    #[new(into)]
+    module_list: RelativePathBuf,
+    #[new(into)]
+    path: RelativePathBuf,
+}
+
+impl NixosModuleUnused {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.path.clone(), None)
+    }
+}
+
+impl fmt::Display for NixosModuleUnused {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { module_list, path } = self;
+        write!(
+            f,
+            "- {path}: This file is neither listed in {module_list} nor imported by any module that is, so it has no effect. Please add it to {module_list}, or remove it if it's not needed anymore.",
+        )
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct NixosModuleInvalidSyntax {
+    #[new(into)]
+    path: RelativePathBuf,
+    #[new(into)]
+    error: String,
+}
+
+impl NixosModuleInvalidSyntax {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.path.clone(), None)
+    }
+}
+
+impl fmt::Display for NixosModuleInvalidSyntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { path, error } = self;
+        write!(
+            f,
+            "- {path}: This NixOS module cannot be parsed: {error}. Please fix its syntax.",
+        )
+    }
+}
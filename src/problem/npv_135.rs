@@ -0,0 +1,36 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::MAINTAINER_LIST_PATH;
+
+#[derive(Clone, new, Serialize)]
+pub struct MaintainerListUnsorted {
+    #[new(into)]
+    handle: String,
+    #[new(into)]
+    previous_handle: String,
+    line: usize,
+}
+
+impl MaintainerListUnsorted {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(MAINTAINER_LIST_PATH, Some(self.line))
+    }
+}
+
+impl fmt::Display for MaintainerListUnsorted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            handle,
+            previous_handle,
+            line,
+        } = self;
+        write!(
+            f,
+            "- {MAINTAINER_LIST_PATH}: The maintainer \"{handle}\" at line {line} comes after \"{previous_handle}\", but the maintainers need to be sorted alphabetically, ignoring case. Please move it to the right place.",
+        )
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::MAINTAINER_LIST_PATH;
+
+#[derive(Clone, new, Serialize)]
+pub struct MaintainerMissingField {
+    #[new(into)]
+    handle: String,
+    field: &'static str,
+    line: usize,
+}
+
+impl MaintainerMissingField {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(MAINTAINER_LIST_PATH, Some(self.line))
+    }
+}
+
+impl fmt::Display for MaintainerMissingField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            handle,
+            field,
+            line,
+        } = self;
+        write!(
+            f,
+            "- {MAINTAINER_LIST_PATH}: The maintainer \"{handle}\" at line {line} has no `{field}`. Please add it, such that the maintainer can be notified and identified on GitHub.",
+        )
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use derive_new::new;
+use itertools::Itertools as _;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::MAINTAINER_LIST_PATH;
+
+#[derive(Clone, new, Serialize)]
+pub struct MaintainerDuplicateGithubId {
+    #[new(into)]
+    github_id: String,
+    #[new(into)]
+    handles: Vec<String>,
+    line: usize,
+}
+
+impl MaintainerDuplicateGithubId {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(MAINTAINER_LIST_PATH, Some(self.line))
+    }
+}
+
+impl fmt::Display for MaintainerDuplicateGithubId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            github_id,
+            handles,
+            line,
+        } = self;
+        let handles = handles
+            .iter()
+            .map(|handle| format!("\"{handle}\""))
+            .join(", ");
+        write!(
+            f,
+            "- {MAINTAINER_LIST_PATH}: The maintainers {handles} at line {line} have the same `githubId = {github_id};`. Please merge them into a single maintainer, or fix the ID.",
+        )
+    }
+}
@@ -0,0 +1,36 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::MAINTAINER_LIST_PATH;
+
+#[derive(Clone, new, Serialize)]
+pub struct MaintainerInvalidEmail {
+    #[new(into)]
+    handle: String,
+    #[new(into)]
+    email: String,
+    line: usize,
+}
+
+impl MaintainerInvalidEmail {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(MAINTAINER_LIST_PATH, Some(self.line))
+    }
+}
+
+impl fmt::Display for MaintainerInvalidEmail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            handle,
+            email,
+            line,
+        } = self;
+        write!(
+            f,
+            "- {MAINTAINER_LIST_PATH}: The maintainer \"{handle}\" at line {line} has the invalid email address \"{email}\". Please fix it, or remove it if the maintainer has none.",
+        )
+    }
+}
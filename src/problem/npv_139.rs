@@ -0,0 +1,32 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::{MAINTAINER_LIST_PATH, TEAM_LIST_PATH};
+
+#[derive(Clone, new, Serialize)]
+pub struct TeamMemberUnknown {
+    #[new(into)]
+    team: String,
+    #[new(into)]
+    member: String,
+    line: usize,
+}
+
+impl TeamMemberUnknown {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(TEAM_LIST_PATH, Some(self.line))
+    }
+}
+
+impl fmt::Display for TeamMemberUnknown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { team, member, line } = self;
+        write!(
+            f,
+            "- {TEAM_LIST_PATH}: The team \"{team}\" has the member \"{member}\" at line {line}, but there's no such maintainer in {MAINTAINER_LIST_PATH}. Please add the maintainer there, or fix the member.",
+        )
+    }
+}
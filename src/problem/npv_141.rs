@@ -2,13 +2,26 @@ use std::fmt;
 
 use derive_new::new;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-#[derive(Clone, new)]
+use crate::config::DEFAULT_PACKAGE_NAME_PATTERN;
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
 pub struct InvalidPackageDirectoryName {
     #[new(into)]
     package_name: String,
     #[new(into)]
     relative_package_dir: RelativePathBuf,
+    /// The regex the name needs to match, see `package-name-pattern`.
+    #[new(into)]
+    pattern: String,
+}
+
+impl InvalidPackageDirectoryName {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.clone(), None)
+    }
 }
 
 impl fmt::Display for InvalidPackageDirectoryName {
@@ -16,10 +29,18 @@ impl fmt::Display for InvalidPackageDirectoryName {
         let Self {
             package_name,
             relative_package_dir,
+            pattern,
         } = self;
-        write!(
-            f,
-            "- {relative_package_dir}: Invalid package directory name \"{package_name}\", must be ASCII characters consisting of a-z, A-Z, 0-9, \"-\" or \"_\".",
-        )
+        if pattern == DEFAULT_PACKAGE_NAME_PATTERN {
+            write!(
+                f,
+                "- {relative_package_dir}: Invalid package directory name \"{package_name}\", must be ASCII characters consisting of a-z, A-Z, 0-9, \"-\" or \"_\".",
+            )
+        } else {
+            write!(
+                f,
+                "- {relative_package_dir}: Invalid package directory name \"{package_name}\", must match the regex `{pattern}`.",
+            )
+        }
     }
 }
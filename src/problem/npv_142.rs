@@ -1,25 +1,54 @@
 use std::fmt;
 
 use derive_new::new;
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::Serialize;
 
-use crate::structure;
+use crate::fix::Fix;
+use crate::location::ProblemLocation;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct PackageInWrongShard {
     #[new(into)]
     package_name: String,
     #[new(into)]
     relative_package_dir: RelativePathBuf,
+    /// The shard the package should be in, see `structure::shard_for_package`.
+    #[new(into)]
+    correct_shard: String,
+}
+
+impl PackageInWrongShard {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.clone(), None)
+    }
+
+    /// The directory the package should be in instead.
+    fn correct_relative_package_dir(&self) -> RelativePathBuf {
+        // The package directory is in a shard directory, which is in the directory with the shards
+        let by_name_dir = self
+            .relative_package_dir
+            .parent()
+            .and_then(|shard_dir| shard_dir.parent())
+            .unwrap_or(RelativePath::new(""));
+        by_name_dir
+            .join(&self.correct_shard)
+            .join(&self.package_name)
+    }
+
+    /// Moves the package directory into the correct shard.
+    pub fn fix(&self) -> Fix {
+        Fix::Move {
+            from: self.relative_package_dir.clone(),
+            to: self.correct_relative_package_dir(),
+        }
+    }
 }
 
 impl fmt::Display for PackageInWrongShard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let Self {
-            package_name,
-            relative_package_dir,
-        } = self;
-        let correct_relative_package_dir = structure::relative_dir_for_package(package_name);
+        let relative_package_dir = &self.relative_package_dir;
+        let correct_relative_package_dir = self.correct_relative_package_dir();
         write!(
             f,
             "- {relative_package_dir}: Incorrect directory location, should be {correct_relative_package_dir} instead.",
@@ -1,19 +1,29 @@
 use std::fmt;
 
 use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
 
-use crate::structure::{self, PACKAGE_NIX_FILENAME};
+use crate::location::ProblemLocation;
+use crate::structure::PACKAGE_NIX_FILENAME;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct PackageNixIsNotFile {
     #[new(into)]
-    package_name: String,
+    relative_package_dir: RelativePathBuf,
+}
+
+impl PackageNixIsNotFile {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.relative_package_dir.join(PACKAGE_NIX_FILENAME), None)
+    }
 }
 
 impl fmt::Display for PackageNixIsNotFile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let Self { package_name } = self;
-        let relative_package_dir = structure::relative_dir_for_package(package_name);
+        let Self {
+            relative_package_dir,
+        } = self;
         write!(
             f,
             "- {relative_package_dir}: \"{PACKAGE_NIX_FILENAME}\" must be a file.",
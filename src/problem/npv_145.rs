@@ -0,0 +1,38 @@
+use std::fmt;
+
+use derive_new::new;
+use itertools::Itertools as _;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::maintainers::TEAM_LIST_PATH;
+
+#[derive(Clone, new, Serialize)]
+pub struct TeamDuplicateShortName {
+    #[new(into)]
+    short_name: String,
+    #[new(into)]
+    teams: Vec<String>,
+    line: usize,
+}
+
+impl TeamDuplicateShortName {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(TEAM_LIST_PATH, Some(self.line))
+    }
+}
+
+impl fmt::Display for TeamDuplicateShortName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            short_name,
+            teams,
+            line,
+        } = self;
+        let teams = teams.iter().map(|team| format!("\"{team}\"")).join(", ");
+        write!(
+            f,
+            "- {TEAM_LIST_PATH}: The teams {teams} at line {line} have the same `shortName = \"{short_name}\";`. Please give each team its own `shortName`.",
+        )
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+
+#[derive(Clone, new, Serialize)]
+pub struct TopLevelEntryUnsorted {
+    #[new(into)]
+    name: String,
+    /// The entry of the same section that the entry is out of order with.
+    #[new(into)]
+    neighbour: String,
+    /// Whether the neighbour comes before the entry, otherwise it comes after it
+    neighbour_before: bool,
+    location: Location,
+}
+
+impl TopLevelEntryUnsorted {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.location.file.clone(),
+            line: Some(self.location.line),
+            column: Some(self.location.column),
+        }
+    }
+}
+
+impl fmt::Display for TopLevelEntryUnsorted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            name,
+            neighbour,
+            neighbour_before,
+            location,
+        } = self;
+        let Location { file, line, .. } = location;
+        let order = if *neighbour_before { "after" } else { "before" };
+        write!(
+            f,
+            "- {file}: The entry `{name}` at line {line} comes {order} `{neighbour}`, but the entries of each section need to be sorted alphabetically, ignoring case. Please move it to the right place.",
+        )
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+
+#[derive(Clone, new, Serialize)]
+pub struct TopLevelEntriesOnSameLine {
+    #[new(into)]
+    name: String,
+    #[new(into)]
+    previous: String,
+    location: Location,
+}
+
+impl TopLevelEntriesOnSameLine {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.location.file.clone(),
+            line: Some(self.location.line),
+            column: Some(self.location.column),
+        }
+    }
+}
+
+impl fmt::Display for TopLevelEntriesOnSameLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            name,
+            previous,
+            location,
+        } = self;
+        let Location { file, line, .. } = location;
+        write!(
+            f,
+            "- {file}: The entry `{name}` at line {line} is on the same line as `{previous}`. Please put each entry on a line of its own.",
+        )
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct PathUnreadable {
+    /// A package directory or a file, relative to Nixpkgs.
+    #[new(into)]
+    path: RelativePathBuf,
+    /// The underlying I/O error.
+    #[new(into)]
+    error: String,
+}
+
+impl PathUnreadable {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.path.clone(), None)
+    }
+}
+
+impl fmt::Display for PathUnreadable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { path, error } = self;
+        write!(
+            f,
+            "- {path}: This couldn't be checked because of an I/O error: {error}. Please make sure it can be read.",
+        )
+    }
+}
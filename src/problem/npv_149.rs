@@ -0,0 +1,36 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+
+#[derive(Clone, new, Serialize)]
+pub struct BrokenSymlink {
+    /// The symlink, relative to Nixpkgs.
+    #[new(into)]
+    path: RelativePathBuf,
+    /// The target of the symlink as it's written, usually relative to the directory of the
+    /// symlink.
+    #[new(into)]
+    target: PathBuf,
+}
+
+impl BrokenSymlink {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.path.clone(), None)
+    }
+}
+
+impl fmt::Display for BrokenSymlink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { path, target } = self;
+        write!(
+            f,
+            "- {path}: This is a symlink to {}, which doesn't exist. Please remove it or make it point to an existing path.",
+            target.display(),
+        )
+    }
+}
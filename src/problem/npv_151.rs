@@ -0,0 +1,21 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+#[derive(Clone, new, Serialize)]
+pub struct NewEvalWarning {
+    /// The warning as emitted by Nix, like the message of a `lib.warn`.
+    #[new(into)]
+    warning: String,
+}
+
+impl fmt::Display for NewEvalWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { warning } = self;
+        write!(
+            f,
+            "- Evaluating Nixpkgs emits a new warning: {warning}\n  Please don't introduce usages of deprecated functionality, which is what these warnings are usually about.",
+        )
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameDerivationNameMismatch {
+    #[new(into)]
+    package_name: String,
+    /// The `name` of the derivation.
+    #[new(into)]
+    name: String,
+    /// The name it should have, `${pname}-${version}`.
+    #[new(into)]
+    expected_name: String,
+}
+
+impl ByNameDerivationNameMismatch {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.package_name),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for ByNameDerivationNameMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            name,
+            expected_name,
+        } = self;
+        let relative_package_file = structure::relative_file_for_package(package_name);
+        write!(
+            f,
+            "- pkgs.{package_name}: The derivation name is \"{name}\", but it should be \"{expected_name}\", which is `${{pname}}-${{version}}`. Please make sure that {relative_package_file} doesn't override `name` inconsistently with `pname` and `version`.",
+        )
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameUsesAlias {
+    #[new(into)]
+    package_name: String,
+    /// The alias in `pkgs/top-level/aliases.nix` that the package uses.
+    #[new(into)]
+    alias: String,
+    /// The attribute the alias refers to, if it's a plain reference to another attribute.
+    target: Option<String>,
+}
+
+impl ByNameUsesAlias {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.package_name),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for ByNameUsesAlias {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            alias,
+            target,
+        } = self;
+        let relative_package_file = structure::relative_file_for_package(package_name);
+        let instead = match target {
+            Some(target) => format!("`{target}`, which the alias refers to"),
+            None => "the attribute the alias refers to".to_owned(),
+        };
+        write!(
+            f,
+            "- pkgs.{package_name}: Doesn't evaluate with `config.allowAliases = false`, because it uses the alias `{alias}`. Please use {instead} in {relative_package_file} instead, since aliases aren't available to all users of Nixpkgs.",
+        )
+    }
+}
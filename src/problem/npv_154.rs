@@ -0,0 +1,53 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct CrossEvalFailure {
+    #[new(into)]
+    package_name: String,
+    /// The attribute of `pkgsCross`, like `aarch64-multiplatform`.
+    #[new(into)]
+    target: String,
+    /// Whether the package is new, otherwise it evaluated for the target before
+    is_new: bool,
+}
+
+impl CrossEvalFailure {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.package_name),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for CrossEvalFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            target,
+            is_new,
+        } = self;
+        let attribute = format!("`pkgsCross.{target}.{package_name}`");
+        if *is_new {
+            write!(
+                f,
+                "- pkgs.{package_name}: {attribute} of this new package fails to evaluate.",
+            )?;
+        } else {
+            write!(
+                f,
+                "- pkgs.{package_name}: This PR makes {attribute} fail to evaluate.",
+            )?;
+        }
+        write!(
+            f,
+            " This usually means that a dependency is taken from the wrong package set when cross-compiling, like a build tool in `buildInputs` instead of `nativeBuildInputs`. Please check the error with `nix-instantiate -A pkgsCross.{target}.{package_name}`."
+        )
+    }
+}
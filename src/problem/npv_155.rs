@@ -0,0 +1,104 @@
+use std::fmt;
+
+use derive_new::new;
+use itertools::Itertools as _;
+use serde::{Deserialize, Serialize};
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+/// A reason of `meta` to refuse evaluating a package, unless the configuration allows it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Gate {
+    /// `meta.unfree`, allowed with `config.allowUnfree`.
+    Unfree,
+    /// `meta.broken`, allowed with `config.allowBroken`.
+    Broken,
+}
+
+impl Gate {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Unfree => "unfree",
+            Self::Broken => "broken",
+        }
+    }
+
+    /// The attribute of `meta` that marks packages with the gate.
+    fn attribute(self) -> &'static str {
+        match self {
+            Self::Unfree => "meta.license",
+            Self::Broken => "meta.broken",
+        }
+    }
+
+    /// The option of the configuration of Nixpkgs that allows such packages.
+    fn option(self) -> &'static str {
+        match self {
+            Self::Unfree => "allowUnfree",
+            Self::Broken => "allowBroken",
+        }
+    }
+}
+
+/// How the evaluation of a package disagrees with its `meta`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GatingInconsistency {
+    /// The package is marked by the gates, but evaluates without allowing them.
+    Bypassed,
+    /// The package only evaluates when allowing the gates, but isn't marked by them itself.
+    Undeclared,
+}
+
+#[derive(Clone, new, Serialize)]
+pub struct MetaGatingInconsistent {
+    #[new(into)]
+    package_name: String,
+    inconsistency: GatingInconsistency,
+    gates: Vec<Gate>,
+    /// Whether the package is new, otherwise it was consistent before
+    is_new: bool,
+}
+
+impl MetaGatingInconsistent {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.package_name),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for MetaGatingInconsistent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            inconsistency,
+            gates,
+            is_new,
+        } = self;
+        let relative_package_file = structure::relative_file_for_package(package_name);
+        let marked = gates.iter().map(|gate| gate.name()).join(" and ");
+        let attributes = gates
+            .iter()
+            .map(|gate| format!("`{}`", gate.attribute()))
+            .join(" or ");
+        let options = gates
+            .iter()
+            .map(|gate| format!("`config.{} = true`", gate.option()))
+            .join(" and ");
+        let pr = if *is_new { "" } else { " after this PR" };
+        match inconsistency {
+            GatingInconsistency::Bypassed => write!(
+                f,
+                "- pkgs.{package_name}: Is marked as {marked} in `meta`, but{pr} evaluates without {options}. Please make sure that `meta` in {relative_package_file} reaches the derivation, like by passing it to `stdenv.mkDerivation`, so that it's refused like other {marked} packages.",
+            ),
+            GatingInconsistency::Undeclared => write!(
+                f,
+                "- pkgs.{package_name}: Only evaluates with {options}{pr}, but isn't marked as {marked} in `meta` itself. This usually means that it depends on a package that is {marked}, which should be reflected in `meta` in {relative_package_file}, like with {attributes}.",
+            ),
+        }
+    }
+}
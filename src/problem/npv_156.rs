@@ -0,0 +1,74 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+/// What's wrong with an update script, as far as it can be told without running it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateScriptDefect {
+    /// It's an attribute set without `command`.
+    NoCommand,
+    /// The command is an empty list.
+    EmptyCommand,
+    /// A part of the command isn't a string, a path or a derivation.
+    InvalidCommandPart,
+    /// `attrPath` isn't a string.
+    InvalidAttrPath,
+    /// `supportedFeatures` isn't a list of strings.
+    InvalidSupportedFeatures,
+    /// Evaluating it fails.
+    EvalFailure,
+    /// The command refers to a file that doesn't exist, relative to Nixpkgs if it's within.
+    MissingFile(String),
+}
+
+#[derive(Clone, new, Serialize)]
+pub struct UpdateScriptInvalid {
+    #[new(into)]
+    package_name: String,
+    defect: UpdateScriptDefect,
+}
+
+impl UpdateScriptInvalid {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.package_name),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for UpdateScriptInvalid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            defect,
+        } = self;
+        let defect = match defect {
+            UpdateScriptDefect::NoCommand => "is an attribute set without `command`",
+            UpdateScriptDefect::EmptyCommand => "has an empty command",
+            UpdateScriptDefect::InvalidCommandPart => {
+                "has a command with parts that aren't strings, paths or derivations"
+            }
+            UpdateScriptDefect::InvalidAttrPath => "has an `attrPath` that isn't a string",
+            UpdateScriptDefect::InvalidSupportedFeatures => {
+                "has `supportedFeatures` that aren't a list of strings"
+            }
+            UpdateScriptDefect::EvalFailure => "fails to evaluate",
+            UpdateScriptDefect::MissingFile(path) => {
+                return write!(
+                    f,
+                    "- pkgs.{package_name}: The `passthru.updateScript` refers to {path}, which doesn't exist, so updating the package fails.",
+                );
+            }
+        };
+        write!(
+            f,
+            "- pkgs.{package_name}: The `passthru.updateScript` {defect}, so maintainers/scripts/update.nix and the bots using it can't run it. Please make it a script, a list of a script and its arguments, or an attribute set with such a list as `command`.",
+        )
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+
+use derive_new::new;
+use indoc::writedoc;
+use serde::Serialize;
+
+use crate::fix::Fix;
+use crate::location::{Location, ProblemLocation};
+use crate::structure;
+
+use super::{create_path_expr, indent_definition};
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNameOverrideContainsDefaultArguments {
+    #[new(into)]
+    package_name: String,
+    location: Location,
+    #[new(into)]
+    definition: String,
+}
+
+impl ByNameOverrideContainsDefaultArguments {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::from(&self.location)
+    }
+
+    /// Removes the definition, since the one of `pkgs/by-name` passes the same arguments.
+    pub fn fix(&self) -> Fix {
+        Fix::Remove {
+            file: self.location.file.clone(),
+            line: self.location.line,
+            column: self.location.column,
+            text: self.definition.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ByNameOverrideContainsDefaultArguments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            location,
+            definition,
+        } = self;
+        let Location { file, line, column } = location;
+        let expected_package_path = structure::relative_file_for_package(package_name);
+        let expected_path_expr = create_path_expr(file, expected_package_path);
+        let relative_package_dir = structure::relative_dir_for_package(package_name);
+        let indented_definition = indent_definition(*column, definition);
+
+        writedoc!(
+            f,
+            "
+            - Because {relative_package_dir} exists, the attribute `pkgs.{package_name}` must be defined like
+
+                {package_name} = callPackage {expected_path_expr} {{ /* ... */ }};
+
+              However, in this PR, the second argument only passes the attributes of the same name that `callPackage` passes anyway. See the definition in {file}:{line}:
+
+            {indented_definition}
+
+              Such a definition is provided automatically and therefore not necessary. Please remove it.
+            ",
+        )
+    }
+}
@@ -3,10 +3,12 @@ use std::fmt;
 use derive_new::new;
 use indoc::writedoc;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
+use crate::location::ProblemLocation;
 use crate::structure;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct TopLevelPackageMovedOutOfByNameWithCustomArguments {
     #[new(into)]
     package_name: String,
@@ -16,6 +18,12 @@ pub struct TopLevelPackageMovedOutOfByNameWithCustomArguments {
     file: RelativePathBuf,
 }
 
+impl TopLevelPackageMovedOutOfByNameWithCustomArguments {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.file.clone(), None)
+    }
+}
+
 impl fmt::Display for TopLevelPackageMovedOutOfByNameWithCustomArguments {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
@@ -3,10 +3,12 @@ use std::fmt;
 use derive_new::new;
 use indoc::writedoc;
 use relative_path::RelativePathBuf;
+use serde::Serialize;
 
+use crate::location::ProblemLocation;
 use crate::structure;
 
-#[derive(Clone, new)]
+#[derive(Clone, new, Serialize)]
 pub struct NewTopLevelPackageShouldBeByNameWithCustomArgument {
     #[new(into)]
     package_name: String,
@@ -16,6 +18,12 @@ pub struct NewTopLevelPackageShouldBeByNameWithCustomArgument {
     file: RelativePathBuf,
 }
 
+impl NewTopLevelPackageShouldBeByNameWithCustomArgument {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.file.clone(), None)
+    }
+}
+
 impl fmt::Display for NewTopLevelPackageShouldBeByNameWithCustomArgument {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Self {
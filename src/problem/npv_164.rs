@@ -0,0 +1,58 @@
+use std::fmt;
+
+use derive_new::new;
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNamePackageRenamedIncompletely {
+    #[new(into)]
+    old_name: String,
+    #[new(into)]
+    new_name: String,
+    alias_missing: bool,
+    #[new(into)]
+    stale_definition: Option<RelativePathBuf>,
+}
+
+impl ByNamePackageRenamedIncompletely {
+    pub fn location(&self) -> ProblemLocation {
+        match &self.stale_definition {
+            Some(file) => ProblemLocation::new(file.clone(), None),
+            None => ProblemLocation::new(structure::relative_dir_for_package(&self.new_name), None),
+        }
+    }
+}
+
+impl fmt::Display for ByNamePackageRenamedIncompletely {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            old_name,
+            new_name,
+            alias_missing,
+            stale_definition,
+        } = self;
+        let old_dir = structure::relative_dir_for_package(old_name);
+        let new_dir = structure::relative_dir_for_package(new_name);
+        writeln!(
+            f,
+            "- Attribute `pkgs.{old_name}` was renamed to `pkgs.{new_name}` by moving {old_dir} to {new_dir}, but the rename is incomplete:"
+        )?;
+        if *alias_missing {
+            writeln!(
+                f,
+                "  - `pkgs.{old_name}` doesn't exist anymore. Please add an alias like `{old_name} = {new_name};` to pkgs/top-level/aliases.nix."
+            )?;
+        }
+        if let Some(file) = stale_definition {
+            writeln!(
+                f,
+                "  - `pkgs.{old_name}` is still manually defined in {file}, using the `package.nix` file of `pkgs.{new_name}`. Please rename that definition to `{new_name}` as well."
+            )?;
+        }
+        Ok(())
+    }
+}
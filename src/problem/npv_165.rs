@@ -0,0 +1,43 @@
+use std::fmt;
+
+use derive_new::new;
+use indoc::writedoc;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct TopLevelPackageDroppedSilently {
+    #[new(into)]
+    package_name: String,
+    /// The location of the definition in the base branch
+    location: Location,
+}
+
+impl TopLevelPackageDroppedSilently {
+    pub fn location(&self) -> ProblemLocation {
+        // The line refers to the base branch, so it's meaningless for this PR
+        ProblemLocation::new(self.location.file.clone(), None)
+    }
+}
+
+impl fmt::Display for TopLevelPackageDroppedSilently {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            location,
+        } = self;
+        let Location { file, line, .. } = location;
+        let relative_package_dir = structure::relative_dir_for_package(package_name);
+        writedoc!(
+            f,
+            "
+            - Attribute `pkgs.{package_name}` was previously defined in {file}:{line}, but was removed in this PR without a replacement.
+              If the package was moved, it should now be in {relative_package_dir}. If it was removed intentionally, please add an alias to pkgs/top-level/aliases.nix like
+
+                {package_name} = throw \"'{package_name}' has been removed\";
+            ",
+        )
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::structure;
+
+#[derive(Clone, new, Serialize)]
+pub struct ByNamePackageWithoutUpdateScript {
+    #[new(into)]
+    package_name: String,
+    /// Whether the package is new, otherwise its `updateScript` was removed
+    is_new: bool,
+}
+
+impl ByNamePackageWithoutUpdateScript {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(
+            structure::relative_file_for_package(&self.package_name),
+            None,
+        )
+    }
+}
+
+impl fmt::Display for ByNamePackageWithoutUpdateScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            package_name,
+            is_new,
+        } = self;
+        let relative_package_file = structure::relative_file_for_package(package_name);
+        if *is_new {
+            write!(
+                f,
+                "- pkgs.{package_name}: This new package has no `passthru.updateScript`. Please add one to {relative_package_file}, such that the package can be updated automatically, e.g. `passthru.updateScript = nix-update-script {{ }};`.",
+            )
+        } else {
+            write!(
+                f,
+                "- pkgs.{package_name}: This PR removes the `passthru.updateScript` of this package. Please keep one in {relative_package_file}, such that the package can still be updated automatically.",
+            )
+        }
+    }
+}
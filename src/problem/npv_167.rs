@@ -0,0 +1,47 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+
+#[derive(Clone, new, Serialize)]
+pub struct LibFunctionWithoutTests {
+    #[new(into)]
+    function_name: String,
+    definition: Location,
+    /// Whether the function is new, otherwise its tests were removed
+    is_new: bool,
+}
+
+impl LibFunctionWithoutTests {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.definition.file.clone(),
+            line: Some(self.definition.line),
+            column: Some(self.definition.column),
+        }
+    }
+}
+
+impl fmt::Display for LibFunctionWithoutTests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            function_name,
+            definition,
+            is_new,
+        } = self;
+        let Location { file, line, .. } = definition;
+        if *is_new {
+            write!(
+                f,
+                "- lib.{function_name}: This new library function defined in {file} at line {line} isn't used by any test in lib/tests. Please add tests for it, e.g. to lib/tests/misc.nix.",
+            )
+        } else {
+            write!(
+                f,
+                "- lib.{function_name}: This PR removes the last test in lib/tests using this library function defined in {file} at line {line}. Please keep it tested.",
+            )
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+
+#[derive(Clone, new, Serialize)]
+pub struct AliasWithoutDate {
+    #[new(into)]
+    alias_name: String,
+    /// The package in `pkgs/by-name` the alias refers to.
+    #[new(into)]
+    package_name: String,
+    location: Location,
+    /// Whether the alias is new, otherwise its date comment was removed
+    is_new: bool,
+}
+
+impl AliasWithoutDate {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.location.file.clone(),
+            line: Some(self.location.line),
+            column: Some(self.location.column),
+        }
+    }
+}
+
+impl fmt::Display for AliasWithoutDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            alias_name,
+            package_name,
+            location,
+            is_new,
+        } = self;
+        let Location { file, line, .. } = location;
+        if *is_new {
+            write!(
+                f,
+                "- pkgs.{alias_name}: This new alias for pkgs.{package_name} in {file} at line {line} has no comment with the date it was added, which is needed to know when it can be removed. Please add one at the end of the line, like `{alias_name} = {package_name}; # Added YYYY-MM-DD`.",
+            )
+        } else {
+            write!(
+                f,
+                "- pkgs.{alias_name}: This PR removes the comment with the date the alias for pkgs.{package_name} in {file} at line {line} was added, which is needed to know when it can be removed. Please keep it at the end of the line, like `{alias_name} = {package_name}; # Added YYYY-MM-DD`.",
+            )
+        }
+    }
+}
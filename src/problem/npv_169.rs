@@ -0,0 +1,67 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::{Location, ProblemLocation};
+
+#[derive(Clone, new, Serialize)]
+pub struct ConventionViolated {
+    /// The name of the convention in the configuration.
+    #[new(into)]
+    convention: String,
+    #[new(into)]
+    builder: String,
+    /// The attribute that's missing or forbidden.
+    #[new(into)]
+    attribute: String,
+    /// Whether the attribute is forbidden, otherwise it's missing
+    forbidden: bool,
+    reason: Option<String>,
+    location: Location,
+    /// Whether the file is new, otherwise it followed the convention before
+    is_new: bool,
+}
+
+impl ConventionViolated {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation {
+            path: self.location.file.clone(),
+            line: Some(self.location.line),
+            column: Some(self.location.column),
+        }
+    }
+}
+
+impl fmt::Display for ConventionViolated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            convention,
+            builder,
+            attribute,
+            forbidden,
+            reason,
+            location,
+            is_new,
+        } = self;
+        let Location { file, line, .. } = location;
+        let change = if *is_new {
+            format!("This new file {file} calls `{builder}` at line {line} in a way")
+        } else {
+            format!("This PR changes the call of `{builder}` in {file} at line {line} in a way")
+        };
+        let violation = if *forbidden {
+            format!("passes the `{attribute}` attribute, which is forbidden")
+        } else {
+            format!("doesn't pass the `{attribute}` attribute, which is required")
+        };
+        write!(
+            f,
+            "- {change} that doesn't follow the \"{convention}\" convention: It {violation}.",
+        )?;
+        if let Some(reason) = reason {
+            write!(f, " {reason}")?;
+        }
+        Ok(())
+    }
+}
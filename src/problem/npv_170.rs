@@ -0,0 +1,46 @@
+use std::fmt;
+
+use derive_new::new;
+use serde::Serialize;
+
+use crate::location::ProblemLocation;
+use crate::release::RELEASE_PATH;
+
+#[derive(Clone, new, Serialize)]
+pub struct ReleaseJobEvalFailure {
+    #[new(into)]
+    attribute_name: String,
+    /// Whether the package is new, otherwise its job evaluated before
+    is_new: bool,
+}
+
+impl ReleaseJobEvalFailure {
+    pub fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(RELEASE_PATH, None)
+    }
+}
+
+impl fmt::Display for ReleaseJobEvalFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            attribute_name,
+            is_new,
+        } = self;
+        let job = format!("`{attribute_name}.x86_64-linux` in {RELEASE_PATH}");
+        if *is_new {
+            write!(
+                f,
+                "- pkgs.{attribute_name}: The Hydra job {job} of this new package fails to evaluate, which breaks the evaluation of the channels.",
+            )?;
+        } else {
+            write!(
+                f,
+                "- pkgs.{attribute_name}: This PR makes the Hydra job {job} fail to evaluate, which breaks the evaluation of the channels.",
+            )?;
+        }
+        write!(
+            f,
+            " Please check the error with `nix-instantiate {RELEASE_PATH} -A {attribute_name}.x86_64-linux`."
+        )
+    }
+}
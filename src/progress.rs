@@ -0,0 +1,116 @@
+//! Progress reporting for the longer phases of the checks.
+//!
+//! On interactive terminals this shows a progress bar on standard error. Otherwise, e.g. in CI,
+//! a plain line is logged every once in a while instead.
+
+use std::io::{self, IsTerminal as _};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::report;
+
+/// The base and main Nixpkgs are checked at the same time, so their bars need to be drawn together.
+static BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Whether progress should be reported at all, see `disable`.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// How often to log progress when standard error isn't a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The progress of checking a single Nixpkgs.
+pub struct Progress {
+    label: String,
+    bar: ProgressBar,
+    enabled: bool,
+    interactive: bool,
+    last_log: Mutex<Instant>,
+}
+
+/// Stops reporting progress for the rest of the program, e.g. because it would interfere with
+/// logging.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+impl Progress {
+    /// Starts reporting progress, with each line prefixed by the label, e.g. `main`.
+    pub fn new(label: &str) -> Progress {
+        let enabled = ENABLED.load(Ordering::Relaxed);
+        let interactive = io::stderr().is_terminal();
+        let bar = if enabled && interactive {
+            // Until the first phase starts, there's nothing to draw a bar for
+            let style = ProgressStyle::with_template("{prefix}: {msg}").expect("valid template");
+            BARS.add(ProgressBar::new(0).with_style(style))
+        } else {
+            ProgressBar::hidden()
+        };
+        Progress {
+            label: label.to_owned(),
+            bar: bar.with_prefix(label.to_owned()),
+            enabled,
+            interactive,
+            last_log: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Starts a new phase, counting up to the given total, or waiting for something to finish if
+    /// there's no total.
+    pub fn phase(&self, message: &'static str, total: Option<u64>) {
+        report::set_phase(message);
+        self.bar.reset();
+        self.bar.set_message(message);
+        match total {
+            Some(total) => {
+                self.bar.disable_steady_tick();
+                self.bar.set_style(
+                    ProgressStyle::with_template(
+                        "{prefix}: {msg} [{bar:40}] {pos}/{len} (ETA {eta})",
+                    )
+                    .expect("valid template")
+                    .progress_chars("=> "),
+                );
+                self.bar.set_length(total);
+            }
+            None => {
+                self.bar.set_style(
+                    ProgressStyle::with_template("{prefix}: {spinner} {msg} ({elapsed})")
+                        .expect("valid template"),
+                );
+                self.bar.enable_steady_tick(Duration::from_millis(100));
+            }
+        }
+    }
+
+    /// Counts one more package as done.
+    pub fn inc(&self) {
+        self.bar.inc(1);
+        if !self.enabled || self.interactive {
+            return;
+        }
+        let mut last_log = self.last_log.lock().expect("not poisoned");
+        if last_log.elapsed() >= LOG_INTERVAL {
+            *last_log = Instant::now();
+            eprintln!(
+                "{}: {} {}/{} (ETA {}s)",
+                self.label,
+                self.bar.message(),
+                self.bar.position(),
+                self.bar.length().unwrap_or_default(),
+                self.bar.eta().as_secs(),
+            );
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        if self.enabled && self.interactive {
+            BARS.remove(&self.bar);
+        }
+    }
+}
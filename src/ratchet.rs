@@ -2,14 +2,21 @@
 //!
 //! Each type has a `compare` method that validates the ratchet checks for that item.
 
+use itertools::Itertools as _;
 use relative_path::RelativePath;
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 use relative_path::RelativePathBuf;
 
+use crate::all_packages::ALL_PACKAGES_PATH;
+use crate::location::Location;
 use crate::nix_file::CallPackageArgumentInfo;
-use crate::problem::{Problem, npv_160, npv_161, npv_162, npv_163};
-use crate::validation::{self, Validation, Validation::Success};
+use crate::problem::{
+    Problem, npv_149, npv_151, npv_154, npv_155, npv_160, npv_161, npv_162, npv_163, npv_164,
+    npv_165, npv_166, npv_167, npv_168, npv_169, npv_170,
+};
+use crate::validation::{Validation, Validation::Success};
 
 /// The ratchet value for the entirety of Nixpkgs.
 #[derive(Default)]
@@ -17,21 +24,178 @@ pub struct Nixpkgs {
     /// The ratchet values for all packages
     pub packages: BTreeMap<String, Package>,
     pub files: BTreeMap<RelativePathBuf, File>,
+    /// The ratchet values for the functions in `lib`, if enabled with `--check lib-tests`
+    pub lib_functions: BTreeMap<String, LibFunction>,
+    /// The ratchet values for the aliases in `pkgs/top-level/aliases.nix` referring to packages
+    /// in `pkgs/by-name`
+    pub aliases: BTreeMap<String, Alias>,
+    /// The ratchet values for the Hydra jobs of the changed packages, if enabled with
+    /// `--check release-jobs`
+    pub release_jobs: BTreeMap<String, ReleaseJob>,
+    /// The ratchet values for the changed packages for each target of `pkgsCross`, by
+    /// `pkgsCross.<target>.<name>`, if enabled with `--check cross`
+    pub cross: BTreeMap<String, CrossEval>,
+    /// The ratchet values for the changed packages for the gates of their `meta`, if enabled with
+    /// `--check meta-gating`
+    pub meta_gating: BTreeMap<String, MetaGating>,
+    /// The ratchet values for the broken symlinks, if `broken-symlinks = "warning"` is configured
+    pub broken_symlinks: BTreeMap<RelativePathBuf, BrokenSymlink>,
+    /// The ratchet values for the warnings emitted when evaluating Nixpkgs, by their message
+    pub eval_warnings: BTreeMap<String, EvalWarning>,
 }
 
 impl Nixpkgs {
     /// Validates the ratchet checks for Nixpkgs
     pub fn compare(from: &Self, to: Self) -> Validation<()> {
-        validation::sequence_(
+        let renames = Self::compare_renames(from, &to);
+        let removals = Self::compare_removals(from, &to);
+        renames.and_(removals).and_(
             // We only loop over the current attributes,
             // we don't need to check ones that were removed
             to.packages
                 .into_iter()
-                .map(|(name, pkg)| Package::compare(&name, from.packages.get(&name), &pkg)),
+                .map(|(name, pkg)| Package::compare(&name, from.packages.get(&name), &pkg))
+                .collect::<Validation<()>>()
+                .and_(
+                    to.files
+                        .into_iter()
+                        .map(|(name, file)| File::compare(&name, from.files.get(&name), &file))
+                        .collect(),
+                )
+                .and_(
+                    to.lib_functions
+                        .into_iter()
+                        .map(|(name, function)| {
+                            LibFunction::compare(&name, from.lib_functions.get(&name), &function)
+                        })
+                        .collect(),
+                )
+                .and_(
+                    to.aliases
+                        .into_iter()
+                        .map(|(name, alias)| Alias::compare(&name, from.aliases.get(&name), &alias))
+                        .collect(),
+                )
+                .and_(
+                    to.release_jobs
+                        .into_iter()
+                        .map(|(name, job)| {
+                            ReleaseJob::compare(&name, from.release_jobs.get(&name), &job)
+                        })
+                        .collect(),
+                )
+                .and_(
+                    to.cross
+                        .into_iter()
+                        .map(|(name, cross)| {
+                            CrossEval::compare(&name, from.cross.get(&name), &cross)
+                        })
+                        .collect(),
+                )
+                .and_(
+                    to.meta_gating
+                        .into_iter()
+                        .map(|(name, gating)| {
+                            MetaGating::compare(&name, from.meta_gating.get(&name), &gating)
+                        })
+                        .collect(),
+                )
+                .and_(
+                    to.broken_symlinks
+                        .into_iter()
+                        .map(|(path, symlink)| {
+                            BrokenSymlink::compare(&path, from.broken_symlinks.get(&path), &symlink)
+                        })
+                        .collect(),
+                )
+                .and_(
+                    to.eval_warnings
+                        .into_iter()
+                        .map(|(warning, value)| {
+                            EvalWarning::compare(&warning, from.eval_warnings.get(&warning), &value)
+                        })
+                        .collect(),
+                ),
         )
-        .and_(validation::sequence_(to.files.into_iter().map(
-            |(name, file)| File::compare(&name, from.files.get(&name), &file),
-        )))
+    }
+
+    /// Validates that packages in `pkgs/by-name` that were renamed are renamed completely.
+    ///
+    /// A package counts as renamed if its `package.nix` file was moved to the directory of a new
+    /// package without changing its contents. Packages with identical contents are ignored, since
+    /// it can't be determined which one was renamed to which.
+    fn compare_renames(from: &Self, to: &Self) -> Validation<()> {
+        let removed = Self::by_name_only_in(from, to);
+        let added = Self::by_name_only_in(to, from);
+
+        // Sorted by the old name for deterministic output
+        let renames = removed
+            .into_iter()
+            .filter_map(
+                |(hash, old_names)| match (&old_names[..], added.get(&hash)) {
+                    ([old_name], Some(new_names)) if new_names.len() == 1 => {
+                        Some((*old_name, new_names[0]))
+                    }
+                    _ => None,
+                },
+            )
+            .sorted();
+
+        renames
+            .filter_map(|(old_name, new_name)| {
+                let (alias_missing, stale_definition) = match to.packages.get(old_name) {
+                    // The old attribute doesn't exist anymore
+                    None => (true, None),
+                    // The old attribute still exists, but is a leftover manual definition that now
+                    // refers to the new package
+                    Some(Package {
+                        variant_of: Some((variant_of, file)),
+                        ..
+                    }) if variant_of == new_name => (false, Some(file.clone())),
+                    // The old attribute still exists in some other way, e.g. as an alias
+                    Some(_) => return None,
+                };
+                Some(
+                    npv_164::ByNamePackageRenamedIncompletely::new(
+                        old_name,
+                        new_name,
+                        alias_missing,
+                        stale_definition,
+                    )
+                    .into(),
+                )
+            })
+            .collect()
+    }
+
+    /// Validates that attributes removed from `pkgs/top-level/all-packages.nix` didn't disappear
+    /// entirely, but were moved to `pkgs/by-name` or replaced by an alias.
+    fn compare_removals(from: &Self, to: &Self) -> Validation<()> {
+        from.packages
+            .iter()
+            .filter_map(|(name, package)| {
+                let location = package.definition.as_ref()?;
+                (location.file == ALL_PACKAGES_PATH && !to.packages.contains_key(name)).then(|| {
+                    npv_165::TopLevelPackageDroppedSilently::new(name, location.clone()).into()
+                })
+            })
+            .collect()
+    }
+
+    /// The packages in `pkgs/by-name` of `this` that aren't in `pkgs/by-name` of `other`, grouped
+    /// by the hash of their `package.nix` file.
+    fn by_name_only_in<'a>(this: &'a Self, other: &Self) -> BTreeMap<[u8; 32], Vec<&'a str>> {
+        let mut by_hash: BTreeMap<[u8; 32], Vec<&str>> = BTreeMap::new();
+        for (name, package) in &this.packages {
+            let is_by_name_in_other = other
+                .packages
+                .get(name)
+                .is_some_and(|other| other.package_nix_hash.is_some());
+            if let (Some(hash), false) = (package.package_nix_hash, is_by_name_in_other) {
+                by_hash.entry(hash).or_default().push(name);
+            }
+        }
+        by_hash
     }
 }
 
@@ -42,12 +206,29 @@ pub struct Package {
 
     /// The ratchet value for the check for new packages using pkgs/by-name
     pub uses_by_name: RatchetState<UsesByName>,
+
+    /// The ratchet value for the check for packages in pkgs/by-name having an `updateScript`,
+    /// see `require-update-script`
+    pub update_script: RatchetState<UpdateScript>,
+
+    /// A hash of the contents of the `package.nix` file if the package is in `pkgs/by-name`, used
+    /// to detect renamed packages.
+    pub package_nix_hash: Option<[u8; 32]>,
+
+    /// If the attribute is manually defined as a `callPackage` of the `package.nix` file of a
+    /// different package in `pkgs/by-name`, the name of that package and the file of the
+    /// definition.
+    pub variant_of: Option<(String, RelativePathBuf)>,
+
+    /// The location of the definition of the attribute if it's not in `pkgs/by-name`, used to
+    /// detect attributes that were removed.
+    pub definition: Option<Location>,
 }
 
 impl Package {
     /// Validates the ratchet checks for a top-level package
     pub fn compare(name: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
-        validation::sequence_([
+        [
             RatchetState::<ManualDefinition>::compare(
                 name,
                 optional_from.map(|x| &x.manual_definition),
@@ -58,20 +239,154 @@ impl Package {
                 optional_from.map(|x| &x.uses_by_name),
                 &to.uses_by_name,
             ),
-        ])
+            RatchetState::<UpdateScript>::compare(
+                name,
+                optional_from.map(|x| &x.update_script),
+                &to.update_script,
+            ),
+        ]
+        .into_iter()
+        .collect()
     }
 }
 
-pub struct File {}
+/// The ratchet value for a Nix file
+pub struct File {
+    /// The ratchet values for the configured conventions of language ecosystems, by their name
+    pub conventions: BTreeMap<String, RatchetState<FollowsConvention>>,
+}
+
+/// The ratchet value for a function in `lib`, named like `strings.concatLines`
+pub struct LibFunction {
+    /// The ratchet value for the check that library functions are tested
+    pub tested: RatchetState<LibTests>,
+}
+
+impl LibFunction {
+    /// Validates the ratchet checks for a library function
+    pub fn compare(name: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<LibTests>::compare(name, optional_from.map(|x| &x.tested), &to.tested)
+    }
+}
 
 impl File {
-    /// Validates the ratchet checks for a top-level package
+    /// Validates the ratchet checks for a Nix file
     pub fn compare(
         _name: &RelativePath,
-        _optional_from: Option<&Self>,
-        _to: &Self,
+        optional_from: Option<&Self>,
+        to: &Self,
     ) -> Validation<()> {
-        Success(())
+        to.conventions
+            .iter()
+            .map(|(convention, state)| {
+                RatchetState::<FollowsConvention>::compare(
+                    convention,
+                    optional_from.and_then(|x| x.conventions.get(convention)),
+                    state,
+                )
+            })
+            .collect()
+    }
+}
+
+/// The ratchet value for an alias in `pkgs/top-level/aliases.nix` referring to a package in
+/// `pkgs/by-name`
+pub struct Alias {
+    /// The ratchet value for the check that aliases have a comment with the date they were added
+    pub dated: RatchetState<AliasDate>,
+}
+
+impl Alias {
+    /// Validates the ratchet checks for an alias
+    pub fn compare(name: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<AliasDate>::compare(name, optional_from.map(|x| &x.dated), &to.dated)
+    }
+}
+
+/// The ratchet value for the Hydra job of a package in `pkgs/top-level/release.nix`
+pub struct ReleaseJob {
+    /// The ratchet value for the check that the job evaluates
+    pub evaluates: RatchetState<ReleaseJobEval>,
+}
+
+impl ReleaseJob {
+    /// Validates the ratchet checks for a Hydra job
+    pub fn compare(name: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<ReleaseJobEval>::compare(
+            name,
+            optional_from.map(|x| &x.evaluates),
+            &to.evaluates,
+        )
+    }
+}
+
+/// The ratchet value for a package evaluated for a target of `pkgsCross`
+pub struct CrossEval {
+    /// The ratchet value for the check that the package evaluates for the target
+    pub evaluates: RatchetState<CrossEvaluates>,
+}
+
+impl CrossEval {
+    /// Validates the ratchet checks for a package evaluated for a target of `pkgsCross`
+    pub fn compare(name: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<CrossEvaluates>::compare(
+            name,
+            optional_from.map(|x| &x.evaluates),
+            &to.evaluates,
+        )
+    }
+}
+
+/// The ratchet value for a package for the gates of its `meta`
+pub struct MetaGating {
+    /// The ratchet value for the check that its evaluation agrees with the gates
+    pub consistent: RatchetState<MetaGatingConsistent>,
+}
+
+impl MetaGating {
+    /// Validates the ratchet checks for the gates of a package
+    pub fn compare(name: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<MetaGatingConsistent>::compare(
+            name,
+            optional_from.map(|x| &x.consistent),
+            &to.consistent,
+        )
+    }
+}
+
+/// The ratchet value for a symlink that points to a path that doesn't exist
+pub struct BrokenSymlink {
+    /// The ratchet value for the check that the symlink resolves
+    pub resolves: RatchetState<SymlinkResolves>,
+}
+
+impl BrokenSymlink {
+    /// Validates the ratchet checks for a broken symlink
+    pub fn compare(path: &RelativePath, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<SymlinkResolves>::compare(
+            path.as_str(),
+            optional_from.map(|x| &x.resolves),
+            &to.resolves,
+        )
+    }
+}
+
+/// The ratchet value for a warning emitted when evaluating Nixpkgs, like a `lib.warn` about
+/// deprecated functionality
+pub struct EvalWarning {
+    /// The ratchet value for the check that the warning isn't emitted, which is always loose,
+    /// since only emitted warnings are known
+    pub emitted: RatchetState<EvalWarningEmitted>,
+}
+
+impl EvalWarning {
+    /// Validates the ratchet checks for an evaluation warning
+    pub fn compare(warning: &str, optional_from: Option<&Self>, to: &Self) -> Validation<()> {
+        RatchetState::<EvalWarningEmitted>::compare(
+            warning,
+            optional_from.map(|x| &x.emitted),
+            &to.emitted,
+        )
     }
 }
 
@@ -191,3 +506,172 @@ impl ToProblem for UsesByName {
         }
     }
 }
+
+/// The ratchet value of an attribute in `pkgs/by-name` for the check that packages have an
+/// `updateScript`, such that they can be updated automatically.
+///
+/// This is only applicable if enabled with `require-update-script`, in which case new packages
+/// need an `updateScript`, and existing ones can't lose theirs.
+pub enum UpdateScript {}
+
+impl ToProblem for UpdateScript {
+    type ToContext = ();
+
+    fn to_problem(name: &str, optional_from: Option<()>, _to: &Self::ToContext) -> Problem {
+        npv_166::ByNamePackageWithoutUpdateScript::new(name, optional_from.is_none()).into()
+    }
+}
+
+/// The ratchet value of a function in `lib` for the check that it's used by a test in `lib/tests`.
+///
+/// This is only applicable if enabled with `--check lib-tests`, in which case new functions need
+/// tests, and existing ones can't lose theirs.
+pub enum LibTests {}
+
+impl ToProblem for LibTests {
+    type ToContext = Location;
+
+    fn to_problem(name: &str, optional_from: Option<()>, to: &Self::ToContext) -> Problem {
+        npv_167::LibFunctionWithoutTests::new(name, to.clone(), optional_from.is_none()).into()
+    }
+}
+
+/// The ratchet value of an alias for the check that it has a comment with the date it was added,
+/// like `foo = bar; # Added 2024-01-31`, such that it's known when it can be removed.
+///
+/// Many old aliases don't have one, so only new aliases need one.
+pub enum AliasDate {}
+
+impl ToProblem for AliasDate {
+    /// The package the alias refers to and the location of the alias
+    type ToContext = (String, Location);
+
+    fn to_problem(
+        name: &str,
+        optional_from: Option<()>,
+        (target, location): &Self::ToContext,
+    ) -> Problem {
+        npv_168::AliasWithoutDate::new(name, target, location.clone(), optional_from.is_none())
+            .into()
+    }
+}
+
+/// The ratchet value of a Nix file for the check that it follows a convention configured with
+/// `[[conventions]]`, like passing `pyproject` to `buildPythonPackage`.
+///
+/// The ratchet is loose if any call of the builder in the file doesn't follow the convention, and
+/// not applicable to files outside the path of the convention. Since existing files often predate
+/// conventions, only new files need to follow them, and files following them can't stop doing so.
+pub enum FollowsConvention {}
+
+/// The first violation of a convention in a file.
+pub struct ConventionViolation {
+    pub builder: String,
+    /// The attribute that's missing or forbidden.
+    pub attribute: String,
+    /// Whether the attribute is forbidden, otherwise it's missing.
+    pub forbidden: bool,
+    pub reason: Option<String>,
+    pub location: Location,
+}
+
+impl ToProblem for FollowsConvention {
+    type ToContext = ConventionViolation;
+
+    fn to_problem(name: &str, optional_from: Option<()>, to: &Self::ToContext) -> Problem {
+        npv_169::ConventionViolated::new(
+            name,
+            &to.builder,
+            &to.attribute,
+            to.forbidden,
+            to.reason.clone(),
+            to.location.clone(),
+            optional_from.is_none(),
+        )
+        .into()
+    }
+}
+
+/// The ratchet value of a package for the check that its Hydra job in
+/// `pkgs/top-level/release.nix` evaluates, which would otherwise break the evaluation of the
+/// channels.
+///
+/// This is only applicable if enabled with `--check release-jobs`, and only to jobs that exist.
+/// Jobs that already failed to evaluate are left to Hydra.
+pub enum ReleaseJobEval {}
+
+impl ToProblem for ReleaseJobEval {
+    type ToContext = ();
+
+    fn to_problem(name: &str, optional_from: Option<()>, _to: &Self::ToContext) -> Problem {
+        npv_170::ReleaseJobEvalFailure::new(name, optional_from.is_none()).into()
+    }
+}
+
+/// The ratchet value of a package for the check that it evaluates for a target of `pkgsCross`,
+/// which catches dependencies taken from the wrong package set when cross-compiling.
+///
+/// This is only applicable if enabled with `--check cross`, and only to targets the package is
+/// available on.
+pub enum CrossEvaluates {}
+
+impl ToProblem for CrossEvaluates {
+    /// The name of the package and the target.
+    type ToContext = (String, String);
+
+    fn to_problem(_name: &str, optional_from: Option<()>, to: &Self::ToContext) -> Problem {
+        let (package_name, target) = to;
+        npv_154::CrossEvalFailure::new(package_name, target, optional_from.is_none()).into()
+    }
+}
+
+/// The ratchet value of a package for the check that it's refused without `allowUnfree` or
+/// `allowBroken` exactly if `meta` marks it as unfree or broken, since otherwise users get
+/// packages they didn't allow, or errors they can't tell the reason of.
+///
+/// This is only applicable if enabled with `--check meta-gating`, and only to packages that
+/// evaluate when allowing both.
+pub enum MetaGatingConsistent {}
+
+impl ToProblem for MetaGatingConsistent {
+    /// How the evaluation disagrees, and with which gates.
+    type ToContext = (npv_155::GatingInconsistency, Vec<npv_155::Gate>);
+
+    fn to_problem(name: &str, optional_from: Option<()>, to: &Self::ToContext) -> Problem {
+        let (inconsistency, gates) = to;
+        npv_155::MetaGatingInconsistent::new(
+            name,
+            *inconsistency,
+            gates.clone(),
+            optional_from.is_none(),
+        )
+        .into()
+    }
+}
+
+/// The ratchet value of a symlink for the check that it points to a path that exists.
+///
+/// This is only applicable with `broken-symlinks = "warning"`, otherwise broken symlinks are
+/// either always problems or never checked.
+pub enum SymlinkResolves {}
+
+impl ToProblem for SymlinkResolves {
+    /// The target of the symlink as it's written.
+    type ToContext = PathBuf;
+
+    fn to_problem(name: &str, _optional_from: Option<()>, to: &Self::ToContext) -> Problem {
+        npv_149::BrokenSymlink::new(name, to.clone()).into()
+    }
+}
+
+/// The ratchet value of a warning for the check that evaluating Nixpkgs doesn't emit it, such
+/// that PRs can't introduce new usages of deprecated functionality.
+pub enum EvalWarningEmitted {}
+
+impl ToProblem for EvalWarningEmitted {
+    type ToContext = ();
+
+    fn to_problem(name: &str, _optional_from: Option<()>, _to: &Self::ToContext) -> Problem {
+        npv_151::NewEvalWarning::new(name).into()
+    }
+}
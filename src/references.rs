@@ -1,20 +1,32 @@
+use std::collections::BTreeSet;
 use std::ffi::OsStr;
+use std::io;
+use std::os::unix::fs::PermissionsExt as _;
 use std::path::Path;
+use std::sync::Arc;
 
 use anyhow::Context;
-use relative_path::RelativePath;
+use relative_path::{RelativePath, RelativePathBuf};
 use rowan::ast::AstNode;
 
 use crate::NixFileStore;
 use crate::nix_file::ResolvedPath;
-use crate::problem::{npv_121, npv_122, npv_123, npv_124, npv_125, npv_126};
-use crate::structure::read_dir_sorted;
-use crate::validation::{self, ResultIteratorExt, Validation::Success};
+use crate::problem::{
+    npv_121, npv_122, npv_123, npv_124, npv_125, npv_126, npv_127, npv_128, npv_129, npv_130,
+    npv_131,
+};
+use crate::structure::{PACKAGE_NIX_FILENAME, read_dir_sorted};
+use crate::validation::{self, ResultIteratorExt, Validation, Validation::Success};
+
+/// Directories of Nixpkgs with its internal machinery, which packages must not import from.
+/// Whatever they need from there should be taken as an argument of the package function instead.
+const INTERNAL_DIRS: &[&str] = &["pkgs/top-level", "pkgs/stdenv", "pkgs/build-support"];
 
 /// Check that every package directory in pkgs/by-name doesn't link to outside that directory.
 /// Both symlinks and Nix path expressions are checked.
 pub fn check_references(
     nix_file_store: &mut NixFileStore,
+    nixpkgs_path: &Path,
     relative_package_dir: &RelativePath,
     absolute_package_dir: &Path,
 ) -> validation::Result<()> {
@@ -23,8 +35,9 @@ pub fn check_references(
     // We don't use `./.` to keep the error messages cleaner, since there's no canonicalisation
     // going on underneath.
     let subpath = RelativePath::new("");
-    check_path(
+    let result = check_path(
         nix_file_store,
+        nixpkgs_path,
         relative_package_dir,
         absolute_package_dir,
         subpath,
@@ -34,7 +47,98 @@ pub fn check_references(
             "While checking the references in package directory {}",
             relative_package_dir
         )
-    })
+    })?;
+    Ok(result.and_(
+        check_unused_files(nix_file_store, relative_package_dir, absolute_package_dir)
+            .with_context(|| {
+                format!("While checking the files in package directory {relative_package_dir}")
+            })?,
+    ))
+}
+
+/// Checks that every file in the package directory is used, meaning that it's referred to by
+/// `package.nix` or by another Nix file that's used, either directly or through one of its parent
+/// directories like with `./.`. Unused patches get a problem of their own, while other unused
+/// files are only warnings, since they don't break anything.
+fn check_unused_files(
+    nix_file_store: &mut NixFileStore,
+    relative_package_dir: &RelativePath,
+    absolute_package_dir: &Path,
+) -> validation::Result<()> {
+    if !absolute_package_dir.join(PACKAGE_NIX_FILENAME).is_file() {
+        // Reported already, and there's nothing that uses the files in this case
+        return Ok(Success(()));
+    }
+    let mut files = vec![];
+    list_files(absolute_package_dir, RelativePath::new(""), &mut files)?;
+
+    // Starting from `package.nix`, follow the references of all used Nix files
+    let mut referenced = vec![RelativePathBuf::from(PACKAGE_NIX_FILENAME)];
+    let mut to_visit = vec![RelativePathBuf::from(PACKAGE_NIX_FILENAME)];
+    let mut visited = BTreeSet::new();
+    while let Some(file) = to_visit.pop() {
+        if !visited.insert(file.clone()) {
+            continue;
+        }
+        let nix_file = nix_file_store.get(&file.to_path(absolute_package_dir))?;
+        for path in nix_file
+            .syntax_root
+            .syntax()
+            .descendants()
+            .filter_map(rnix::ast::Path::cast)
+        {
+            match nix_file.static_resolve_path(&path, absolute_package_dir) {
+                ResolvedPath::Within(relative_path) => {
+                    // Referring to a directory uses the Nix files in it, like `default.nix`
+                    to_visit.extend(
+                        files
+                            .iter()
+                            .filter(|file| {
+                                file.extension() == Some("nix") && file.starts_with(&relative_path)
+                            })
+                            .cloned(),
+                    );
+                    referenced.push(relative_path);
+                }
+                // It can't be known which files this refers to, but it's reported already anyways
+                ResolvedPath::Interpolated => return Ok(Success(())),
+                _ => {}
+            }
+        }
+    }
+
+    let (patches, files): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .filter(|file| !referenced.iter().any(|path| file.starts_with(path)))
+        .partition(|file| file.extension() == Some("patch"));
+    let patches: Validation<()> = patches
+        .into_iter()
+        .map(|patch| npv_130::PackageContainsUnusedPatch::new(relative_package_dir, patch).into())
+        .collect();
+    let files = files
+        .into_iter()
+        .map(|file| npv_131::PackageContainsUnusedFile::new(relative_package_dir, file).into())
+        .collect();
+    Ok(patches.and_(Validation::with_warnings((), files)))
+}
+
+/// Lists the files within the given subpath of the package directory recursively, without
+/// following symlinks.
+fn list_files(
+    absolute_package_dir: &Path,
+    subpath: &RelativePath,
+    files: &mut Vec<RelativePathBuf>,
+) -> anyhow::Result<()> {
+    for entry in read_dir_sorted(&subpath.to_path(absolute_package_dir))? {
+        let entry_subpath = subpath.join(entry.file_name().to_string_lossy().as_ref());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(absolute_package_dir, &entry_subpath, files)?;
+        } else if file_type.is_file() {
+            files.push(entry_subpath);
+        }
+    }
+    Ok(())
 }
 
 /// Checks for a specific path to not have references outside.
@@ -44,11 +148,13 @@ pub fn check_references(
 /// The absolute package directory gets prepended before doing anything with it though.
 fn check_path(
     nix_file_store: &mut NixFileStore,
+    nixpkgs_path: &Path,
     relative_package_dir: &RelativePath,
     absolute_package_dir: &Path,
     subpath: &RelativePath,
 ) -> validation::Result<()> {
     let path = subpath.to_path(absolute_package_dir);
+    tracing::trace!(path = %path.display(), "Checking references");
 
     Ok(if path.is_symlink() {
         // Check whether the symlink resolves to outside the package directory.
@@ -66,6 +172,8 @@ fn check_path(
                     Success(())
                 }
             }
+            // Symlinks to paths that don't exist are checked for all of Nixpkgs, see `symlinks`
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Success(()),
             Err(err) => {
                 npv_126::PackageContainsUnresolvableSymlink::new(relative_package_dir, subpath, err)
                     .into()
@@ -73,27 +181,29 @@ fn check_path(
         }
     } else if path.is_dir() {
         // Recursively check each entry
-        validation::sequence_(
-            read_dir_sorted(&path)?
-                .into_iter()
-                .map(|entry| {
-                    check_path(
-                        nix_file_store,
-                        relative_package_dir,
-                        absolute_package_dir,
-                        // TODO: The relative_path crate doesn't seem to support OsStr
-                        &subpath.join(entry.file_name().to_string_lossy().to_string()),
-                    )
-                })
-                .collect_vec()
-                .with_context(|| format!("Error while recursing into {}", subpath))?,
-        )
+        read_dir_sorted(&path)?
+            .into_iter()
+            .map(|entry| {
+                check_path(
+                    nix_file_store,
+                    nixpkgs_path,
+                    relative_package_dir,
+                    absolute_package_dir,
+                    // TODO: The relative_path crate doesn't seem to support OsStr
+                    &subpath.join(entry.file_name().to_string_lossy().to_string()),
+                )
+            })
+            .collect_vec()
+            .with_context(|| format!("Error while recursing into {}", subpath))?
+            .into_iter()
+            .collect()
     } else if path.is_file() {
         // Only check Nix files
         if let Some(ext) = path.extension() {
             if ext == OsStr::new("nix") {
                 check_nix_file(
                     nix_file_store,
+                    nixpkgs_path,
                     relative_package_dir,
                     absolute_package_dir,
                     subpath,
@@ -115,6 +225,7 @@ fn check_path(
 /// directory.
 fn check_nix_file(
     nix_file_store: &mut NixFileStore,
+    nixpkgs_path: &Path,
     relative_package_dir: &RelativePath,
     absolute_package_dir: &Path,
     subpath: &RelativePath,
@@ -123,9 +234,14 @@ fn check_nix_file(
 
     let nix_file = nix_file_store.get(&path)?;
 
-    Ok(validation::sequence_(
-        nix_file.syntax_root.syntax().descendants().map(|node| {
-            let line = nix_file.line_index.line(node.text_range().start().into());
+    Ok(nix_file
+        .syntax_root
+        .syntax()
+        .descendants()
+        .map(|node| {
+            let start = node.text_range().start().into();
+            let line = nix_file.line_index.line(start);
+            let column = nix_file.line_index.column(start);
             let text = node.text().to_string();
 
             // We're only interested in Path expressions
@@ -138,6 +254,7 @@ fn check_nix_file(
                     relative_package_dir,
                     subpath,
                     line,
+                    column,
                     text,
                 )
                 .into(),
@@ -145,30 +262,159 @@ fn check_nix_file(
                     relative_package_dir,
                     subpath,
                     line,
+                    column,
                     text,
                 )
                 .into(),
-                ResolvedPath::Outside => npv_123::NixFileContainsPathOutsideDirectory::new(
-                    relative_package_dir,
-                    subpath,
-                    line,
-                    text,
-                )
-                .into(),
+                ResolvedPath::Outside(target) => {
+                    // Importing internal files is worse than referring to other packages' files,
+                    // so it gets its own problem.
+                    if let Some((import, internal_dir)) =
+                        internal_import(&path, &target, nixpkgs_path)
+                    {
+                        let start = import.syntax().text_range().start().into();
+                        npv_127::NixFileImportsInternalFile::new(
+                            relative_package_dir,
+                            subpath,
+                            nix_file.line_index.line(start),
+                            nix_file.line_index.column(start),
+                            import.syntax().text().to_string(),
+                            internal_dir,
+                        )
+                        .into()
+                    } else {
+                        npv_123::NixFileContainsPathOutsideDirectory::new(
+                            relative_package_dir,
+                            subpath,
+                            line,
+                            column,
+                            text,
+                        )
+                        .into()
+                    }
+                }
+                // A missing update script only shows when the package is updated, so it's
+                // pointed out specifically.
+                ResolvedPath::Unresolvable(err) if update_script_role(&path).is_some() => {
+                    npv_128::NixFileContainsInvalidUpdateScript::new(
+                        relative_package_dir,
+                        subpath,
+                        line,
+                        column,
+                        text,
+                        Some(Arc::new(err)),
+                    )
+                    .into()
+                }
+                ResolvedPath::Unresolvable(err)
+                    if enclosing_binding(&path, "patches").is_some() =>
+                {
+                    npv_129::NixFileContainsMissingPatch::new(
+                        relative_package_dir,
+                        subpath,
+                        line,
+                        column,
+                        text,
+                        err,
+                    )
+                    .into()
+                }
                 ResolvedPath::Unresolvable(err) => npv_124::NixFileContainsUnresolvablePath::new(
                     relative_package_dir,
                     subpath,
                     line,
+                    column,
                     text,
                     err,
                 )
                 .into(),
+                ResolvedPath::Within(relative_path)
+                    if update_script_role(&path) == Some(UpdateScriptRole::Command)
+                        && !is_executable_file(&relative_path.to_path(absolute_package_dir)) =>
+                {
+                    npv_128::NixFileContainsInvalidUpdateScript::new(
+                        relative_package_dir,
+                        subpath,
+                        line,
+                        column,
+                        text,
+                        None,
+                    )
+                    .into()
+                }
                 ResolvedPath::Within(..) => {
                     // No need to handle the case of it being inside the directory, since we scan
                     // through the entire directory recursively in any case.
                     Success(())
                 }
             }
-        }),
-    ))
+        })
+        .collect())
+}
+
+/// If the path expression is the argument of `import` and points into one of the `INTERNAL_DIRS`,
+/// returns the `import` expression and that directory.
+fn internal_import(
+    path: &rnix::ast::Path,
+    target: &Path,
+    nixpkgs_path: &Path,
+) -> Option<(rnix::ast::Apply, &'static str)> {
+    let import = rnix::ast::Apply::cast(path.syntax().parent()?)?;
+    let function = import.lambda()?.syntax().text().to_string();
+    if !matches!(function.as_str(), "import" | "builtins.import")
+        || import.argument()?.syntax() != path.syntax()
+    {
+        return None;
+    }
+    let internal_dir = INTERNAL_DIRS
+        .iter()
+        .find(|dir| target.starts_with(RelativePath::new(dir).to_path(nixpkgs_path)))?;
+    Some((import, internal_dir))
+}
+
+/// How a path expression is used in the value of an `updateScript` attribute.
+#[derive(PartialEq, Eq)]
+enum UpdateScriptRole {
+    /// The path is the command that's run, like in `updateScript = ./update.sh` or
+    /// `updateScript = [ ./update.sh "--verbose" ]`.
+    Command,
+    /// The path is used in some other way, like as an argument of the command.
+    Other,
+}
+
+/// Returns how the path expression is used if it's part of the value of an `updateScript`
+/// attribute, like `passthru.updateScript`.
+fn update_script_role(path: &rnix::ast::Path) -> Option<UpdateScriptRole> {
+    let value = enclosing_binding(path, "updateScript")?.value()?;
+    let command = match &value {
+        rnix::ast::Expr::List(list) => list.items().next(),
+        _ => Some(value.clone()),
+    };
+    Some(
+        if command.is_some_and(|command| command.syntax() == path.syntax()) {
+            UpdateScriptRole::Command
+        } else {
+            UpdateScriptRole::Other
+        },
+    )
+}
+
+/// Whether the path is a file that anyone may execute.
+fn is_executable_file(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Returns the innermost binding of an attribute with the given name, like `patches` in
+/// `patches = [ ./fix.patch ];`, whose value contains the path expression.
+fn enclosing_binding(path: &rnix::ast::Path, name: &str) -> Option<rnix::ast::AttrpathValue> {
+    path.syntax()
+        .ancestors()
+        .filter_map(rnix::ast::AttrpathValue::cast)
+        .find(|binding| {
+            binding
+                .attrpath()
+                .and_then(|attrpath| attrpath.attrs().last())
+                .is_some_and(|attr| attr.syntax().text() == name)
+        })
 }
@@ -0,0 +1,32 @@
+# Takes a path to nixpkgs and a path to the json-encoded list of attributes whose Hydra jobs in
+# `pkgs/top-level/release.nix` should be evaluated, only for x86_64-linux.
+#
+# Returns for each attribute whether its job evaluates, or null if there's no such job, like for
+# packages that Hydra doesn't build. See ./release.rs for how this is used.
+{
+  attrsPath,
+  nixpkgsPath,
+}:
+let
+  attrs = builtins.fromJSON (builtins.readFile attrsPath);
+
+  jobs = import (nixpkgsPath + "/pkgs/top-level/release.nix") {
+    supportedSystems = [ "x86_64-linux" ];
+  };
+
+  # Like Hydra, this needs the derivation of the job, which is where most evaluation failures show
+  evaluates =
+    name:
+    let
+      result = builtins.tryEval (
+        if jobs ? ${name}.x86_64-linux then builtins.seq jobs.${name}.x86_64-linux.drvPath true else null
+      );
+    in
+    if result.success then result.value else false;
+in
+builtins.listToAttrs (
+  map (name: {
+    inherit name;
+    value = evaluates name;
+  }) attrs
+)
@@ -0,0 +1,77 @@
+//! Evaluates the Hydra jobs in `pkgs/top-level/release.nix` of the changed packages, see
+//! `--check release-jobs`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::config::Config;
+use crate::eval;
+use crate::ratchet::{self, RatchetState};
+use crate::scope::Scope;
+use crate::validation::{self, Validation::Success};
+
+/// The file defining the jobs Hydra builds for the channels, relative to Nixpkgs.
+pub const RELEASE_PATH: &str = "pkgs/top-level/release.nix";
+
+const RELEASE_NIX: &[u8] = include_bytes!("release.nix");
+
+/// Returns the ratchet value of the job of each of the packages that are in scope, either by the
+/// package directory or by the line of the definition.
+///
+/// Evaluating all jobs is what Hydra does, so if everything is in scope, nothing is evaluated, see
+/// `eval::evaluate_json`. The jobs are only evaluated for `x86_64-linux`, see `./release.nix`.
+pub fn check_release_jobs(
+    nixpkgs_path: &Path,
+    packages: &BTreeMap<String, ratchet::Package>,
+    scope: &Scope,
+    config: &Config,
+) -> validation::Result<BTreeMap<String, ratchet::ReleaseJob>> {
+    let _span = tracing::info_span!("release-jobs").entered();
+    if scope.is_everything() || !nixpkgs_path.join(RELEASE_PATH).is_file() {
+        return Ok(Success(BTreeMap::new()));
+    }
+    let attributes: Vec<&str> = packages
+        .iter()
+        .filter(|(name, package)| {
+            scope.contains_package(name)
+                || package
+                    .definition
+                    .as_ref()
+                    .is_some_and(|location| scope.contains_line(&location.file, location.line))
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if attributes.is_empty() {
+        return Ok(Success(BTreeMap::new()));
+    }
+
+    let results: BTreeMap<String, Option<bool>> = match eval::evaluate_json(
+        "release",
+        RELEASE_NIX,
+        nixpkgs_path,
+        &[("attrsPath", json!(attributes))],
+        // Like the jobs of Hydra, which are always for the same systems
+        None,
+        config,
+    )? {
+        Ok(results) => results,
+        // `release.nix` itself doesn't evaluate, which breaks all jobs
+        Err(stderr) => return Ok(eval::eval_error(&stderr).into()),
+    };
+    Ok(Success(
+        results
+            .into_iter()
+            .map(|(name, evaluates)| {
+                let evaluates = match evaluates {
+                    Some(true) => RatchetState::Tight,
+                    Some(false) => RatchetState::Loose(()),
+                    // Not built by Hydra
+                    None => RatchetState::NonApplicable,
+                };
+                (name, ratchet::ReleaseJob { evaluates })
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,273 @@
+//! Reports of internal errors with the context needed to make bug reports actionable, see
+//! `--error-report`, and of crashes, see `install_panic_hook`.
+//!
+//! While a Nixpkgs is checked, the current phase and file are tracked for the thread checking it,
+//! and attached to its error as `Breadcrumbs`.
+
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, panic, process};
+
+use serde::Serialize;
+
+use crate::jobs;
+use crate::status::{EXIT_ERROR, Fatal};
+
+/// Where bugs are reported.
+const NEW_ISSUE_URL: &str = "https://github.com/NixOS/nixpkgs-vet/issues/new";
+
+thread_local! {
+    /// The phase of checking a Nixpkgs that's running on this thread, see `Progress::phase`.
+    static PHASE: RefCell<Option<&'static str>> = const { RefCell::new(None) };
+
+    /// The file that was processed last on this thread.
+    static FILE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Records the phase this thread is in.
+pub fn set_phase(phase: &'static str) {
+    PHASE.with_borrow_mut(|current| *current = Some(phase));
+}
+
+/// Records the file this thread is processing.
+pub fn set_file(path: &Path) {
+    FILE.with_borrow_mut(|current| *current = Some(path.to_owned()));
+}
+
+/// Where checking a Nixpkgs was when an error happened, attached to the error as context.
+#[derive(Clone, Debug, Serialize)]
+pub struct Breadcrumbs {
+    /// Which Nixpkgs was checked, like `base` or `main`.
+    pub nixpkgs: String,
+    pub phase: Option<&'static str>,
+    pub file: Option<PathBuf>,
+}
+
+impl Breadcrumbs {
+    /// The breadcrumbs of this thread, which checked the given Nixpkgs. Resets them for the next
+    /// check on this thread.
+    pub fn take(nixpkgs: &str) -> Breadcrumbs {
+        Breadcrumbs {
+            nixpkgs: nixpkgs.to_owned(),
+            phase: PHASE.with_borrow_mut(Option::take),
+            file: FILE.with_borrow_mut(Option::take),
+        }
+    }
+}
+
+impl fmt::Display for Breadcrumbs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Failed to check the {} Nixpkgs", self.nixpkgs)
+    }
+}
+
+/// A report of an internal error, as opposed to problems found in Nixpkgs or errors by the user
+/// like invalid arguments.
+#[derive(Serialize)]
+pub struct ErrorReport {
+    pub version: &'static str,
+    /// The error, followed by its causes.
+    pub errors: Vec<String>,
+    pub breadcrumbs: Option<Breadcrumbs>,
+    pub environment: Environment,
+}
+
+/// A summary of the environment nixpkgs-vet runs in.
+#[derive(Serialize)]
+pub struct Environment {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub jobs: usize,
+    /// The Nix used for evaluation, see `NIXPKGS_VET_NIX_PACKAGE`.
+    pub nix_package: Option<String>,
+    pub args: Vec<String>,
+}
+
+impl ErrorReport {
+    /// A report of the error if it's an internal one, otherwise `None`.
+    pub fn new(error: &anyhow::Error) -> Option<ErrorReport> {
+        if error.downcast_ref::<Fatal>().is_some() {
+            return None;
+        }
+        Some(ErrorReport {
+            version: env!("CARGO_PKG_VERSION"),
+            errors: error.chain().map(ToString::to_string).collect(),
+            breadcrumbs: error.downcast_ref::<Breadcrumbs>().cloned(),
+            environment: Environment {
+                os: env::consts::OS,
+                arch: env::consts::ARCH,
+                jobs: jobs::count().get(),
+                nix_package: env::var("NIXPKGS_VET_NIX_PACKAGE").ok(),
+                args: env::args().collect(),
+            },
+        })
+    }
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "This is likely a bug in nixpkgs-vet. When reporting it, please include the following:"
+        )?;
+        writeln!(f, "  version: {}", self.version)?;
+        if let Some(Breadcrumbs {
+            nixpkgs,
+            phase,
+            file,
+        }) = &self.breadcrumbs
+        {
+            writeln!(f, "  nixpkgs: {nixpkgs}")?;
+            if let Some(phase) = phase {
+                writeln!(f, "  phase: {phase}")?;
+            }
+            if let Some(file) = file {
+                writeln!(f, "  last file: {}", file.display())?;
+            }
+        }
+        let Environment {
+            os,
+            arch,
+            jobs,
+            nix_package,
+            args,
+        } = &self.environment;
+        writeln!(f, "  system: {arch}-{os}")?;
+        writeln!(f, "  jobs: {jobs}")?;
+        writeln!(
+            f,
+            "  nix: {}",
+            nix_package
+                .as_deref()
+                .unwrap_or("NIXPKGS_VET_NIX_PACKAGE not set")
+        )?;
+        write!(f, "  arguments: {}", args.join(" "))
+    }
+}
+
+/// Replaces the default message of panics by one with a backtrace and a pre-filled bug report,
+/// and exits with the exit code for unexpected errors instead of unwinding.
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        // The hook runs on the thread that panicked, so these are where it was
+        let phase = PHASE.with_borrow(|phase| *phase);
+        let file = FILE.with_borrow(Clone::clone);
+        let args: Vec<String> = env::args().collect();
+        eprintln!(
+            "{}",
+            crash_report(
+                &info.to_string(),
+                phase,
+                file.as_deref(),
+                &args,
+                &Backtrace::force_capture()
+            )
+        );
+        process::exit(EXIT_ERROR.into());
+    }));
+}
+
+/// The message for a crash, with a bug report in Markdown to copy into a new GitHub issue.
+fn crash_report(
+    panic: &str,
+    phase: Option<&str>,
+    file: Option<&Path>,
+    args: &[String],
+    backtrace: &impl fmt::Display,
+) -> String {
+    format!(
+        "nixpkgs-vet crashed, which is a bug. Please report it at {NEW_ISSUE_URL} with the \
+        following, after checking that it doesn't contain anything private:
+
+### Description
+
+<!-- What were you checking, and how can the crash be reproduced? -->
+
+### Crash
+
+- Version: {version}
+- Command line: `{command_line}`
+- System: {arch}-{os}
+- Phase: {phase}
+- Path: {file}
+
+```
+{panic}
+```
+
+<details><summary>Backtrace</summary>
+
+```
+{backtrace}
+```
+
+</details>",
+        version = env!("CARGO_PKG_VERSION"),
+        command_line = args.join(" "),
+        arch = env::consts::ARCH,
+        os = env::consts::OS,
+        phase = phase.unwrap_or("unknown"),
+        file = file.map_or_else(|| "unknown".to_owned(), |file| file.display().to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_internal_errors() {
+        set_phase("Checking the structure");
+        set_file(Path::new("/nixpkgs/pkgs/by-name/fo/foo/package.nix"));
+        let error = anyhow::anyhow!("Permission denied")
+            .context("Could not read file")
+            .context(Breadcrumbs::take("main"));
+
+        let report = ErrorReport::new(&error).expect("an internal error");
+        assert_eq!(
+            report.errors,
+            [
+                "Failed to check the main Nixpkgs",
+                "Could not read file",
+                "Permission denied"
+            ]
+        );
+        let breadcrumbs = report.breadcrumbs.expect("breadcrumbs");
+        assert_eq!(breadcrumbs.phase, Some("Checking the structure"));
+        assert_eq!(
+            breadcrumbs.file.as_deref(),
+            Some(Path::new("/nixpkgs/pkgs/by-name/fo/foo/package.nix"))
+        );
+        // Taking them resets them
+        assert_eq!(Breadcrumbs::take("main").phase, None);
+
+        let error = anyhow::anyhow!("no repo").context(Fatal::Usage("--blame".to_owned()));
+        assert!(ErrorReport::new(&error).is_none());
+    }
+
+    #[test]
+    fn crash_report_template() {
+        let report = crash_report(
+            "panicked at src/eval.rs:1:2:\nboom",
+            Some("Evaluating Nixpkgs"),
+            Some(Path::new("/nixpkgs/pkgs/by-name/fo/foo/package.nix")),
+            &[
+                "nixpkgs-vet".to_owned(),
+                "--base".to_owned(),
+                "base".to_owned(),
+            ],
+            &"0: main",
+        );
+        assert!(report.starts_with("nixpkgs-vet crashed, which is a bug."));
+        assert!(report.contains(NEW_ISSUE_URL));
+        assert!(report.contains("- Command line: `nixpkgs-vet --base base`\n"));
+        assert!(report.contains("- Phase: Evaluating Nixpkgs\n"));
+        assert!(report.contains("- Path: /nixpkgs/pkgs/by-name/fo/foo/package.nix\n"));
+        assert!(report.contains("```\npanicked at src/eval.rs:1:2:\nboom\n```"));
+        assert!(report.contains("```\n0: main\n```"));
+
+        let report = crash_report("panicked", None, None, &[], &"");
+        assert!(report.contains("- Phase: unknown\n- Path: unknown\n"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Creating new packages in `pkgs/by-name` that follow the conventions, see `nixpkgs-vet new`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use indoc::formatdoc;
+use relative_path::RelativePathBuf;
+
+use crate::config::Config;
+use crate::status::Fatal;
+use crate::structure;
+
+/// Creates the directory of a new package in the correct shard, with a `package.nix` to start
+/// from, returning the path of the file relative to Nixpkgs.
+pub fn new_package(
+    nixpkgs_path: &Path,
+    package_name: &str,
+    config: &Config,
+) -> anyhow::Result<RelativePathBuf> {
+    let relative_package_dir = structure::relative_dir_for_package(package_name);
+    let pattern = config.package_name_pattern_for(&relative_package_dir);
+    if !pattern.is_match(package_name) {
+        anyhow::bail!(Fatal::Usage(format!(
+            "The package name {package_name:?} doesn't match the pattern {:?} of package names",
+            pattern.as_str()
+        )));
+    }
+    let package_dir = relative_package_dir.to_path(nixpkgs_path);
+    if package_dir.symlink_metadata().is_ok() {
+        anyhow::bail!(Fatal::Usage(format!(
+            "The package {relative_package_dir} already exists"
+        )));
+    }
+    fs::create_dir_all(&package_dir)
+        .with_context(|| format!("Could not create the directory {relative_package_dir}"))?;
+    let relative_package_file = structure::relative_file_for_package(package_name);
+    fs::write(
+        relative_package_file.to_path(nixpkgs_path),
+        skeleton(package_name),
+    )
+    .with_context(|| format!("Could not write {relative_package_file}"))?;
+    Ok(relative_package_file)
+}
+
+/// The contents of the `package.nix` of a new package, using `finalAttrs` and with placeholders
+/// to fill in.
+fn skeleton(package_name: &str) -> String {
+    formatdoc! {r#"
+        {{
+          lib,
+          stdenv,
+          fetchFromGitHub,
+        }}:
+
+        stdenv.mkDerivation (finalAttrs: {{
+          pname = "{package_name}";
+          version = "0.1.0";
+
+          src = fetchFromGitHub {{
+            owner = "TODO";
+            repo = "{package_name}";
+            tag = "v${{finalAttrs.version}}";
+            hash = lib.fakeHash;
+          }};
+
+          meta = {{
+            description = "TODO";
+            homepage = "https://github.com/TODO/{package_name}";
+            changelog = "https://github.com/TODO/{package_name}/releases/tag/v${{finalAttrs.version}}";
+            # TODO: The license of the package, like `lib.licenses.mit`
+            license = [ ];
+            maintainers = with lib.maintainers; [ ];
+            mainProgram = "{package_name}";
+            platforms = lib.platforms.all;
+          }};
+        }})
+    "#}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::tempdir;
+
+    #[test]
+    fn creates_packages() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let nixpkgs = temp_dir.path();
+        let config = Config::default();
+
+        let file = new_package(nixpkgs, "foo", &config)?;
+        assert_eq!(file, "pkgs/by-name/fo/foo/package.nix");
+        let contents = fs::read_to_string(file.to_path(nixpkgs))?;
+        assert!(contents.contains("stdenv.mkDerivation (finalAttrs: {\n  pname = \"foo\";"));
+        assert!(rnix::Root::parse(&contents).errors().is_empty());
+
+        // Neither existing packages nor invalid names can be created
+        assert!(new_package(nixpkgs, "foo", &config).is_err());
+        assert!(new_package(nixpkgs, "foo/bar", &config).is_err());
+        Ok(())
+    }
+}
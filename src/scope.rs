@@ -0,0 +1,133 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Read as _};
+use std::path::Path;
+
+use anyhow::Context;
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::structure;
+
+/// Which parts of Nixpkgs the checks should be restricted to.
+///
+/// By default everything is checked. When only some files are changed, e.g. by a PR, the checks
+/// can be restricted to the packages and attributes affected by those changes.
+#[derive(Clone, Default)]
+pub struct Scope {
+    /// The changed lines of each changed file, or `None` if everything should be checked.
+    /// The lines are `None` if the whole file should be checked.
+    changed: Option<BTreeMap<RelativePathBuf, Option<BTreeSet<usize>>>>,
+}
+
+impl Scope {
+    /// A scope restricted to the given changed lines of the given files.
+    pub fn changed(files: impl IntoIterator<Item = (RelativePathBuf, BTreeSet<usize>)>) -> Self {
+        let mut changed: BTreeMap<RelativePathBuf, Option<BTreeSet<usize>>> = BTreeMap::new();
+        for (path, lines) in files {
+            changed
+                .entry(path)
+                .or_default()
+                .get_or_insert_default()
+                .extend(lines);
+        }
+        Self {
+            changed: Some(changed),
+        }
+    }
+
+    /// A scope restricted to the given files, all lines of which are checked.
+    pub fn files(files: impl IntoIterator<Item = RelativePathBuf>) -> Self {
+        Self {
+            changed: Some(files.into_iter().map(|path| (path, None)).collect()),
+        }
+    }
+
+    /// Reads a newline-separated list of files relative to Nixpkgs from a file, or from stdin if
+    /// the path is `-`, e.g. from `git diff --name-only`.
+    pub fn read_files(path: &Path) -> anyhow::Result<Self> {
+        let text = if path == Path::new("-") {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .context("Could not read the list of files from stdin")?;
+            text
+        } else {
+            fs::read_to_string(path)
+                .with_context(|| format!("Could not read the list of files {}", path.display()))?
+        };
+        let files = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                RelativePathBuf::from_path(line.trim()).with_context(|| {
+                    format!("Path {line:?} in the list of files is not relative to Nixpkgs")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // Paths with `./` or `..` need to match the ones found in Nixpkgs
+        Ok(Self::files(files.into_iter().map(|path| path.normalize())))
+    }
+
+    /// Whether everything is in scope, i.e. the checks aren't restricted to any changes.
+    pub fn is_everything(&self) -> bool {
+        self.changed.is_none()
+    }
+
+    /// Whether the given path or any file within it is in scope.
+    pub fn contains_path(&self, path: &RelativePath) -> bool {
+        self.changed
+            .as_ref()
+            .is_none_or(|changed| changed.keys().any(|changed| changed.starts_with(path)))
+    }
+
+    /// Whether the `pkgs/by-name` package directory of the given package is in scope.
+    pub fn contains_package(&self, package_name: &str) -> bool {
+        self.contains_path(&structure::relative_dir_for_package(package_name))
+    }
+
+    /// Whether the given line of the given file is in scope.
+    pub fn contains_line(&self, file: &RelativePath, line: usize) -> bool {
+        self.changed.as_ref().is_none_or(|changed| {
+            changed
+                .get(file)
+                .is_some_and(|lines| lines.as_ref().is_none_or(|lines| lines.contains(&line)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restricts_to_changes() {
+        let scope = Scope::changed([
+            (
+                RelativePathBuf::from("pkgs/by-name/fo/foo/package.nix"),
+                BTreeSet::from([3]),
+            ),
+            (
+                RelativePathBuf::from("pkgs/top-level/all-packages.nix"),
+                BTreeSet::from([10, 11]),
+            ),
+        ]);
+
+        assert!(scope.contains_package("foo"));
+        assert!(!scope.contains_package("fo"));
+        assert!(!scope.contains_package("bar"));
+        assert!(scope.contains_path(RelativePath::new("pkgs/by-name/fo")));
+        assert!(!scope.contains_path(RelativePath::new("pkgs/by-name/ba")));
+        assert!(scope.contains_line(RelativePath::new("pkgs/top-level/all-packages.nix"), 11));
+        assert!(!scope.contains_line(RelativePath::new("pkgs/top-level/all-packages.nix"), 12));
+
+        let files = Scope::files([RelativePathBuf::from("pkgs/top-level/all-packages.nix")]);
+        assert!(!files.contains_package("foo"));
+        assert!(files.contains_line(RelativePath::new("pkgs/top-level/all-packages.nix"), 12));
+
+        let everything = Scope::default();
+        assert!(everything.is_everything());
+        assert!(!files.is_everything());
+        assert!(everything.contains_package("bar"));
+        assert!(everything.contains_line(RelativePath::new("default.nix"), 1));
+    }
+}
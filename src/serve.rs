@@ -0,0 +1,508 @@
+//! An HTTP server to run checks and query their results as JSON, for internal tooling and
+//! dashboards, see `nixpkgs-vet serve`.
+//!
+//! - `POST /checks` with a body like `{ "nixpkgs": "/path", "base_ref": "master" }` starts a
+//!   check in the background and responds with its `id`.
+//! - `GET /checks` responds with the last checks, without their reports.
+//! - `GET /checks/<id>` responds with the check, and its report once it's done, which is the one
+//!   of `--report json`.
+//!
+//! Each check runs in a separate process of nixpkgs-vet, since the state of a run, like
+//! `--resume`, is global to its process. Checking Nixpkgs already uses all jobs, so the checks
+//! are queued and run one after another, and only the results of the last ones are kept, in
+//! memory.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read as _, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::output::JsonReport;
+
+/// The largest request body that's accepted, which is plenty for the JSON of a check.
+const MAX_BODY: usize = 1 << 20;
+/// The largest request line and headers that are accepted together.
+const MAX_HEAD: u64 = 16 << 10;
+/// The most headers a request can have.
+const MAX_HEADERS: usize = 100;
+/// How long a client may wait between the parts of its request or of the response.
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// The most connections that are handled at the same time. Further ones are closed right away.
+const MAX_CONNECTIONS: usize = 64;
+/// How many checks run at the same time.
+const MAX_RUNNING: usize = 1;
+/// The most checks that wait to run. Further ones are refused until one of them runs.
+const MAX_QUEUED: usize = 16;
+/// The most checks that are kept. Beyond that, the oldest finished ones are dropped.
+const MAX_KEPT: usize = 100;
+
+/// What to check, like the arguments of a run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckRequest {
+    /// Path to the main Nixpkgs to check, as seen by the server.
+    nixpkgs: PathBuf,
+    /// Path to the base Nixpkgs, like `--base`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<PathBuf>,
+    /// Git ref to check against, like `--base-ref`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base_ref: Option<String>,
+    /// Git ref to check, like `--head-ref`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    head_ref: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+enum CheckState {
+    Queued,
+    Running,
+    Done {
+        report: JsonReport,
+    },
+    /// The check couldn't be done, like when the Nixpkgs doesn't exist.
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Clone, Serialize)]
+struct Check {
+    id: usize,
+    request: CheckRequest,
+    #[serde(flatten)]
+    state: CheckState,
+}
+
+/// The checks that are kept, with the binary and configuration to run them with.
+struct Server {
+    executable: PathBuf,
+    config: Option<PathBuf>,
+    checks: Mutex<Checks>,
+    /// Notified when a check is queued.
+    queued: Condvar,
+}
+
+#[derive(Default)]
+struct Checks {
+    /// The checks by their ID, which counts up from 0.
+    by_id: BTreeMap<usize, Check>,
+    next_id: usize,
+    /// The IDs of the checks that wait to run, in the order they were started.
+    queue: VecDeque<usize>,
+}
+
+impl Checks {
+    /// Queues a check, dropping the oldest finished checks beyond `MAX_KEPT`, and returns its ID.
+    fn insert(&mut self, request: CheckRequest) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.by_id.insert(
+            id,
+            Check {
+                id,
+                request,
+                state: CheckState::Queued,
+            },
+        );
+        self.queue.push_back(id);
+        while self.by_id.len() > MAX_KEPT {
+            let Some(oldest) = self
+                .by_id
+                .values()
+                .find(|check| {
+                    matches!(
+                        check.state,
+                        CheckState::Done { .. } | CheckState::Failed { .. }
+                    )
+                })
+                .map(|check| check.id)
+            else {
+                break;
+            };
+            self.by_id.remove(&oldest);
+        }
+        id
+    }
+}
+
+/// Listens on the address until the process is stopped, handling each connection in a thread.
+/// The checks use the configuration file if given, like `--config`.
+pub fn serve(listen: &str, config: Option<&Path>) -> anyhow::Result<()> {
+    let listener =
+        TcpListener::bind(listen).with_context(|| format!("Could not listen on {listen}"))?;
+    let server = Server::new(
+        env::current_exe().context("Could not find the nixpkgs-vet binary")?,
+        config.map(Path::to_owned),
+    );
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::Relaxed);
+            tracing::warn!("Closing a connection, since there are {MAX_CONNECTIONS} already");
+            continue;
+        }
+        let server = Arc::clone(&server);
+        let connections = Arc::clone(&connections);
+        thread::spawn(move || {
+            if let Err(error) = server.connection(stream) {
+                tracing::warn!("Could not handle a request: {error:#}");
+            }
+            connections.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+    Ok(())
+}
+
+impl Server {
+    /// A server without any checks, with the threads that run the queued ones.
+    fn new(executable: PathBuf, config: Option<PathBuf>) -> Arc<Server> {
+        let server = Arc::new(Server {
+            executable,
+            config,
+            checks: Mutex::default(),
+            queued: Condvar::new(),
+        });
+        for _ in 0..MAX_RUNNING {
+            let server = Arc::clone(&server);
+            thread::spawn(move || server.work());
+        }
+        server
+    }
+
+    fn checks(&self) -> MutexGuard<'_, Checks> {
+        self.checks.lock().expect("not poisoned")
+    }
+
+    fn connection(&self, mut stream: TcpStream) -> anyhow::Result<()> {
+        // Such that a client that doesn't send or receive anything doesn't keep the thread forever
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let (status, body) = match read_request(&mut BufReader::new(&stream)) {
+            Ok((method, path, body)) => self.handle(&method, &path, &body),
+            Err(error) => (400, json!({ "error": format!("{error:#}") })),
+        };
+        let body = format!("{body:#}\n");
+        let reason = match status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Service Unavailable",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        Ok(())
+    }
+
+    /// Responds to the request with a status code and a JSON body.
+    fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        match (method, path.trim_end_matches('/')) {
+            ("POST", "/checks") => match parse_check(body) {
+                Ok(request) => match self.start(request) {
+                    Some(id) => (202, json!({ "id": id })),
+                    None => {
+                        let error = format!(
+                            "There are {MAX_QUEUED} checks waiting to run already, please try \
+                             again later"
+                        );
+                        (503, json!({ "error": error }))
+                    }
+                },
+                Err(error) => (400, json!({ "error": format!("Invalid check: {error:#}") })),
+            },
+            ("GET", "/checks") => {
+                // Reports can be large, so they're only in the responses of single checks
+                let summaries = self
+                    .checks()
+                    .by_id
+                    .values()
+                    .map(|check| {
+                        let mut summary = serde_json::to_value(check).expect("checks serialise");
+                        if let Some(report) =
+                            summary.as_object_mut().and_then(|s| s.remove("report"))
+                        {
+                            summary["exit_code"] = report["exit_code"].clone();
+                            summary["counts"] = report["counts"].clone();
+                        }
+                        summary
+                    })
+                    .collect();
+                (200, Value::Array(summaries))
+            }
+            ("GET", path) => match path
+                .strip_prefix("/checks/")
+                .and_then(|id| id.parse::<usize>().ok())
+                .and_then(|id| self.checks().by_id.get(&id).cloned())
+            {
+                Some(check) => (200, serde_json::to_value(check).expect("checks serialise")),
+                None => (
+                    404,
+                    json!({ "error": format!("There is nothing at {path}") }),
+                ),
+            },
+            (_, path) if path.starts_with("/checks") => (
+                405,
+                json!({ "error": format!("{method} isn't supported for {path}") }),
+            ),
+            (_, path) => (
+                404,
+                json!({ "error": format!("There is nothing at {path}") }),
+            ),
+        }
+    }
+
+    /// Queues a check to run in the background, returning its ID, unless too many checks wait to
+    /// run already.
+    fn start(&self, request: CheckRequest) -> Option<usize> {
+        let mut checks = self.checks();
+        if checks.queue.len() >= MAX_QUEUED {
+            return None;
+        }
+        let id = checks.insert(request);
+        self.queued.notify_one();
+        Some(id)
+    }
+
+    /// Runs the queued checks one after another, forever.
+    fn work(&self) {
+        loop {
+            let (id, request) = {
+                let mut checks = self.checks();
+                let id = loop {
+                    match checks.queue.pop_front() {
+                        Some(id) => break id,
+                        None => checks = self.queued.wait(checks).expect("not poisoned"),
+                    }
+                };
+                let check = checks.by_id.get_mut(&id).expect("queued checks are kept");
+                check.state = CheckState::Running;
+                (id, check.request.clone())
+            };
+            let state = self
+                .run(&request)
+                .unwrap_or_else(|error| CheckState::Failed {
+                    error: format!("{error:#}"),
+                });
+            if let Some(check) = self.checks().by_id.get_mut(&id) {
+                check.state = state;
+            }
+        }
+    }
+
+    /// Runs the check in a process of nixpkgs-vet, reading its `--report json`.
+    fn run(&self, request: &CheckRequest) -> anyhow::Result<CheckState> {
+        let report_dir = tempfile::Builder::new()
+            .prefix("nixpkgs-vet-serve")
+            .tempdir()
+            .context("Could not create a temporary directory for the report")?;
+        let report_path = report_dir.path().join("report.json");
+        let mut command = process::Command::new(&self.executable);
+        command
+            .args(["--color", "never", "--report", "json"])
+            .arg(&report_path);
+        if let Some(config) = &self.config {
+            command.arg("--config").arg(config);
+        }
+        if let Some(base) = &request.base {
+            command.arg("--base").arg(base);
+        }
+        if let Some(base_ref) = &request.base_ref {
+            command.args(["--base-ref", base_ref]);
+        }
+        if let Some(head_ref) = &request.head_ref {
+            command.args(["--head-ref", head_ref]);
+        }
+        // The paths can't be mistaken for options after this
+        command.arg("--").arg(&request.nixpkgs);
+        tracing::debug!(?command, "Running a check");
+        let output = command
+            .stdin(process::Stdio::null())
+            .output()
+            .context("Could not run nixpkgs-vet")?;
+        match fs::read_to_string(&report_path) {
+            Ok(report) => Ok(CheckState::Done {
+                report: serde_json::from_str(&report).context("Could not parse the report")?,
+            }),
+            // Without a report, the check couldn't be done, which the output says why
+            Err(_) => Ok(CheckState::Failed {
+                error: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            }),
+        }
+    }
+}
+
+/// Parses the body of `POST /checks`.
+fn parse_check(body: &[u8]) -> anyhow::Result<CheckRequest> {
+    let request: CheckRequest = serde_json::from_slice(body)?;
+    // The refs are passed on to nixpkgs-vet and git, which would take them for options
+    for git_ref in [&request.base_ref, &request.head_ref].into_iter().flatten() {
+        if git_ref.starts_with('-') {
+            anyhow::bail!("The ref {git_ref:?} starts with `-`");
+        }
+    }
+    Ok(request)
+}
+
+/// Reads the method, path and body of an HTTP/1.1 request.
+fn read_request(reader: &mut impl BufRead) -> anyhow::Result<(String, String, Vec<u8>)> {
+    let mut head = reader.by_ref().take(MAX_HEAD);
+    let request_line = read_line(&mut head)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        anyhow::bail!("Invalid request line {request_line:?}");
+    };
+    let mut content_length = 0;
+    for count in 0.. {
+        let header = read_line(&mut head)?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            anyhow::bail!("The request has more than {MAX_HEADERS} headers");
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().context("Invalid Content-Length")?;
+        }
+    }
+    if content_length > MAX_BODY {
+        anyhow::bail!("The body is larger than {MAX_BODY} bytes");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    // Query strings aren't used by any endpoint
+    let path = path.split('?').next().unwrap_or(path);
+    Ok((method.to_owned(), path.to_owned(), body))
+}
+
+/// Reads a line of the request line and headers, which are at most `MAX_HEAD` bytes together.
+fn read_line(head: &mut impl BufRead) -> anyhow::Result<String> {
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        anyhow::bail!(
+            "The request ended within its headers, or they're larger than {MAX_HEAD} bytes"
+        );
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::os::unix::fs::PermissionsExt as _;
+    use std::time::Duration;
+
+    #[test]
+    fn reads_requests() -> anyhow::Result<()> {
+        let request =
+            b"POST /checks?wait HTTP/1.1\r\nHost: localhost\r\ncontent-length: 2\r\n\r\n{}";
+        let (method, path, body) = read_request(&mut &request[..])?;
+        assert_eq!(
+            (method.as_str(), path.as_str(), body.as_slice()),
+            ("POST", "/checks", &b"{}"[..])
+        );
+        assert!(read_request(&mut &b"\r\n"[..]).is_err());
+
+        // The request line and headers are limited
+        let request = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD as usize));
+        assert!(read_request(&mut request.as_bytes()).is_err());
+        let request = format!("GET / HTTP/1.1\r\n{}\r\n", "Foo: bar\r\n".repeat(101));
+        assert!(read_request(&mut request.as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn runs_checks() -> anyhow::Result<()> {
+        // Writes a report like nixpkgs-vet, to the path after `--report json`
+        let temp_dir = testing::tempdir()?;
+        let executable = temp_dir.path().join("nixpkgs-vet");
+        fs::write(
+            &executable,
+            "#!/bin/sh\nwhile [ \"$1\" != json ]; do shift; done\n\
+             echo '{\"exit_code\":0,\"message\":\"ok\",\"problems\":[],\"counts\":{}}' > \"$2\"\n",
+        )?;
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755))?;
+        let server = Server::new(executable, None);
+
+        let (status, body) = server.handle("POST", "/checks", br#"{ "nixpkgs": "/nixpkgs" }"#);
+        assert_eq!((status, &body["id"]), (202, &json!(0)));
+        let check = loop {
+            let (status, check) = server.handle("GET", "/checks/0", b"");
+            assert_eq!(status, 200);
+            if check["state"] != "queued" && check["state"] != "running" {
+                break check;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(check["state"], "done");
+        assert_eq!(check["request"]["nixpkgs"], "/nixpkgs");
+        assert_eq!(check["report"]["message"], "ok");
+
+        let (_, checks) = server.handle("GET", "/checks", b"");
+        assert_eq!(checks[0]["exit_code"], 0);
+        assert_eq!(checks[0].get("report"), None);
+        assert_eq!(server.handle("GET", "/checks/1", b"").0, 404);
+        assert_eq!(server.handle("POST", "/checks", b"{ \"foo\": 1 }").0, 400);
+        assert_eq!(server.handle("DELETE", "/checks/0", b"").0, 405);
+        // Refs that git would take for options are refused
+        let (status, body) = server.handle(
+            "POST",
+            "/checks",
+            br#"{ "nixpkgs": "/nixpkgs", "base_ref": "--output=x" }"#,
+        );
+        assert_eq!(status, 400);
+        assert_eq!(
+            body["error"],
+            r#"Invalid check: The ref "--output=x" starts with `-`"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_last_checks() {
+        let request = CheckRequest {
+            nixpkgs: PathBuf::from("/nixpkgs"),
+            base: None,
+            base_ref: None,
+            head_ref: None,
+        };
+        let mut checks = Checks::default();
+        for _ in 0..MAX_KEPT {
+            let id = checks.insert(request.clone());
+            checks.by_id.get_mut(&id).expect("inserted").state = CheckState::Failed {
+                error: String::new(),
+            };
+        }
+        checks.queue.clear();
+        // Checks that didn't finish yet are kept
+        checks.by_id.get_mut(&0).expect("inserted").state = CheckState::Running;
+
+        checks.insert(request);
+        assert_eq!(checks.by_id.len(), MAX_KEPT);
+        assert!(checks.by_id.contains_key(&0));
+        assert!(!checks.by_id.contains_key(&1));
+        assert!(checks.by_id.contains_key(&MAX_KEPT));
+    }
+}
@@ -0,0 +1,182 @@
+//! Statistics about how far Nixpkgs is migrated to `pkgs/by-name`, see `nixpkgs-vet stats`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::all_packages::ALL_PACKAGES_PATH;
+use crate::nix_file::NixFileStore;
+use crate::structure::{self, BASE_SUBPATH, read_dir_sorted};
+
+/// The census of the packages of a Nixpkgs. Since nothing is evaluated, the top-level packages
+/// are the attributes defined in `pkgs/top-level/all-packages.nix`.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    /// The number of packages in each shard of `pkgs/by-name`.
+    pub by_name_per_shard: BTreeMap<String, usize>,
+    /// The number of packages in `pkgs/by-name`.
+    pub by_name: usize,
+    /// The number of top-level packages that are only defined in `all-packages.nix`.
+    pub all_packages_only: usize,
+    /// The number of packages in `pkgs/by-name` that are also defined in `all-packages.nix`,
+    /// which is only done to pass custom arguments to `callPackage`.
+    pub custom_arguments: usize,
+    /// The percentage of the top-level packages that are in `pkgs/by-name`.
+    pub migrated_percentage: f64,
+    /// How many percentage points more are migrated than in the base Nixpkgs, see `compare`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migrated_percentage_delta: Option<f64>,
+}
+
+impl Stats {
+    /// Counts the packages of the Nixpkgs at the given path.
+    pub fn collect(nixpkgs_path: &Path) -> anyhow::Result<Self> {
+        let mut stats = Self::default();
+        let mut by_name = BTreeSet::new();
+        let by_name_dir = nixpkgs_path.join(BASE_SUBPATH);
+        if by_name_dir.is_dir() {
+            for shard in read_dir_sorted(&by_name_dir)? {
+                if !shard.file_type()?.is_dir() {
+                    continue;
+                }
+                let mut count = 0;
+                for package in read_dir_sorted(&shard.path())? {
+                    if package.file_type()?.is_dir() {
+                        by_name.insert(package.file_name().to_string_lossy().into_owned());
+                        count += 1;
+                    }
+                }
+                let shard_name = shard.file_name().to_string_lossy().into_owned();
+                stats.by_name_per_shard.insert(shard_name, count);
+            }
+        }
+        stats.by_name = by_name.len();
+
+        let all_packages_path = nixpkgs_path.join(ALL_PACKAGES_PATH);
+        if all_packages_path.exists() {
+            let mut nix_file_store = NixFileStore::default();
+            let attributes = nix_file_store
+                .get(&all_packages_path)?
+                .top_level_attributes();
+            for (name, _, _) in attributes {
+                if by_name.contains(&name) {
+                    stats.custom_arguments += 1;
+                } else {
+                    stats.all_packages_only += 1;
+                }
+            }
+        }
+
+        let total = stats.by_name + stats.all_packages_only;
+        stats.migrated_percentage = if total == 0 {
+            100.0
+        } else {
+            stats.by_name as f64 * 100.0 / total as f64
+        };
+        Ok(stats)
+    }
+
+    /// Adds how much the migration progressed compared to the base Nixpkgs, like the one a PR is
+    /// based on. Negative if packages were moved out of `pkgs/by-name` or added outside of it.
+    pub fn compare(self, base: &Self) -> Self {
+        Self {
+            migrated_percentage_delta: Some(self.migrated_percentage - base.migrated_percentage),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Packages in {BASE_SUBPATH}: {}", self.by_name)?;
+        for (shard, count) in &self.by_name_per_shard {
+            writeln!(f, "  {}: {count}", structure::relative_dir_for_shard(shard))?;
+        }
+        writeln!(
+            f,
+            "Packages only defined in {ALL_PACKAGES_PATH}: {}",
+            self.all_packages_only
+        )?;
+        writeln!(
+            f,
+            "Packages in {BASE_SUBPATH} with custom callPackage arguments: {}",
+            self.custom_arguments
+        )?;
+        write!(
+            f,
+            "Migrated to {BASE_SUBPATH}: {:.2}%",
+            self.migrated_percentage
+        )?;
+        match self.migrated_percentage_delta {
+            Some(delta) if delta > 0.0 => write!(f, " (up {delta:.2} from the base, thank you!)"),
+            Some(delta) if delta < 0.0 => write!(
+                f,
+                " (down {:.2} from the base, please consider adding packages to \
+                {BASE_SUBPATH} instead)",
+                -delta
+            ),
+            Some(_) => write!(f, " (the same as the base)"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Tree;
+
+    #[test]
+    fn counts_packages() -> anyhow::Result<()> {
+        let tree = Tree::new()?
+            .package(".", "foo", "{ someDrv }: someDrv")?
+            .package(".", "fob", "{ someDrv }: someDrv")?
+            .package(".", "bar", "{ someDrv }: someDrv")?
+            .file(
+                ALL_PACKAGES_PATH,
+                "self: super: { foo = self.callPackage ./foo { }; baz = self.callPackage ./baz { }; }",
+            )?;
+        let stats = Stats::collect(tree.path())?;
+        assert_eq!(
+            stats,
+            Stats {
+                by_name_per_shard: BTreeMap::from([("ba".to_owned(), 1), ("fo".to_owned(), 2)]),
+                by_name: 3,
+                all_packages_only: 1,
+                custom_arguments: 1,
+                migrated_percentage: 75.0,
+                migrated_percentage_delta: None,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "Packages in pkgs/by-name: 3\n  \
+            pkgs/by-name/ba: 1\n  \
+            pkgs/by-name/fo: 2\n\
+            Packages only defined in pkgs/top-level/all-packages.nix: 1\n\
+            Packages in pkgs/by-name with custom callPackage arguments: 1\n\
+            Migrated to pkgs/by-name: 75.00%"
+        );
+
+        let base = Stats::collect(
+            Tree::new()?
+                .package(".", "foo", "{ someDrv }: someDrv")?
+                .path(),
+        )?;
+        assert!(
+            stats
+                .to_string()
+                .ends_with("Migrated to pkgs/by-name: 75.00%")
+        );
+        assert_eq!(
+            stats.compare(&base).to_string().lines().last(),
+            Some(
+                "Migrated to pkgs/by-name: 75.00% (down 25.00 from the base, please consider \
+                adding packages to pkgs/by-name instead)"
+            )
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,39 @@
 use std::fmt;
 use std::process::ExitCode;
 
-use colored::Colorize as _;
+use colored::{Color, Colorize as _};
+use derive_more::Display;
 
+use itertools::Itertools as _;
+
+use crate::output::{GroupBy, OutputStyle};
 use crate::problem::Problem;
 
+/// The exit code when the checks pass.
+pub const EXIT_SUCCESS: u8 = 0;
+/// The exit code when problems were found in the checked Nixpkgs.
+pub const EXIT_PROBLEMS: u8 = 1;
+/// The exit code when the command line arguments are invalid. This is also what clap uses.
+pub const EXIT_USAGE: u8 = 2;
+/// The exit code when an unexpected error occurred.
+pub const EXIT_ERROR: u8 = 3;
+/// The exit code when the environment is set up incorrectly, e.g. because `git` isn't installed.
+pub const EXIT_MISCONFIGURED: u8 = 4;
+/// The exit code when the run was interrupted by `SIGINT` or `SIGTERM`, like shells use for Ctrl-C.
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// Context to attach to errors that aren't caused by the checked Nixpkgs, choosing the exit code.
+#[derive(Debug, Display)]
+pub enum Fatal {
+    /// The command line arguments can't be used together or with the given Nixpkgs.
+    #[display("{_0}")]
+    Usage(String),
+
+    /// A required tool or environment variable is missing.
+    #[display("{_0}")]
+    Misconfiguration(String),
+}
+
 pub enum Status {
     /// It's all green.
     ValidatedSuccessfully,
@@ -20,60 +49,324 @@ pub enum Status {
     /// branch would break.
     ProblemsIntroduced(Vec<Problem>),
 
+    /// The base branch fails, and these are the problems the PR introduces and the ones it fixes,
+    /// see `only-new-problems`. There's nothing to fix in the PR if it doesn't introduce any.
+    ProblemsChanged {
+        introduced: Vec<Problem>,
+        fixed: Vec<Problem>,
+    },
+
     /// This PR introduces additional instances of discouraged patterns. Merging is discouraged but
     /// would not break the base branch.
     DiscouragedPatternedIntroduced(Vec<Problem>),
 
+    /// The run was interrupted before the checks were done. These are the problems the checked
+    /// Nixpkgs had in the checks that completed until then.
+    Interrupted(Vec<Problem>),
+
     /// Some other error occurred.
     Error(anyhow::Error),
 }
 
 impl Status {
-    fn errors(&self) -> Option<&Vec<Problem>> {
+    pub fn errors(&self) -> Option<&Vec<Problem>> {
         match self {
             Self::ValidatedSuccessfully | Self::BranchHealed | Self::Error(..) => None,
             Self::BranchStillBroken(errors)
             | Self::ProblemsIntroduced(errors)
-            | Self::DiscouragedPatternedIntroduced(errors) => Some(errors),
+            | Self::ProblemsChanged {
+                introduced: errors, ..
+            }
+            | Self::DiscouragedPatternedIntroduced(errors)
+            | Self::Interrupted(errors) => Some(errors),
         }
     }
 
-    fn fmt(&self, f: &mut fmt::Formatter, use_color: bool) -> fmt::Result {
-        // These all respect the NO_COLOR environment variable even if `use_color` is true.
-        let maybe_green = |s: &str| if use_color { s.green() } else { s.into() };
-        let maybe_yellow = |s: &str| if use_color { s.yellow() } else { s.into() };
-        let maybe_red = |s: &str| if use_color { s.red() } else { s.into() };
-
-        // If there are errors, print them all out first in red.
-        if let Some(errors) = self.errors() {
-            for error in errors {
-                let error = format!("{error}\n");
-                fmt::Display::fmt(&maybe_red(&error), f)?;
+    /// Drops the problems for which the predicate is false, e.g. because they're suppressed.
+    /// Without any problems left, the validation succeeds.
+    ///
+    /// The predicate is called once for each of the problems of `errors`, in their order. The
+    /// problems a PR fixes are kept as they are, since they're not in the checked Nixpkgs anymore.
+    pub fn retain(self, mut keep: impl FnMut(&Problem) -> bool) -> Status {
+        let (mut errors, variant): (_, fn(_) -> _) = match self {
+            Self::BranchStillBroken(errors) => (errors, Self::BranchStillBroken),
+            Self::ProblemsIntroduced(errors) => (errors, Self::ProblemsIntroduced),
+            Self::DiscouragedPatternedIntroduced(errors) => {
+                (errors, Self::DiscouragedPatternedIntroduced)
+            }
+            Self::ProblemsChanged {
+                mut introduced,
+                fixed,
+            } => {
+                // Still broken without any problems
+                introduced.retain(|problem| keep(problem));
+                return Self::ProblemsChanged { introduced, fixed };
+            }
+            Self::Interrupted(mut errors) => {
+                // Still incomplete without any problems
+                errors.retain(|problem| keep(problem));
+                return Self::Interrupted(errors);
             }
+            status => return status,
+        };
+        errors.retain(|problem| keep(problem));
+        if errors.is_empty() {
+            Self::ValidatedSuccessfully
+        } else {
+            variant(errors)
         }
+    }
 
-        // Then, print out the message for this status.
-        let message = match self {
-            Self::Error(error) => format!("{} {:#}", &maybe_yellow("I/O error: "), error).into(),
-            Self::ValidatedSuccessfully => maybe_green("Validated successfully"),
+    /// The message summarizing this status, without the problems.
+    pub fn message(&self) -> String {
+        match self {
+            Self::Error(error) => format!("I/O error:  {error:#}"),
+            Self::ValidatedSuccessfully => "Validated successfully".to_owned(),
             Self::BranchHealed => {
-                maybe_green("The base branch is broken, but this PR fixes it. Nice job!")
+                "The base branch is broken, but this PR fixes it. Nice job!".to_owned()
             }
-            Self::BranchStillBroken(..) => maybe_yellow(
+            Self::BranchStillBroken(..) => {
                 "The base branch is broken and still has above problems with this PR, which need \
                  to be fixed first.\nConsider reverting the PR that introduced these problems \
-                 in order to prevent more failures of unrelated PRs.",
-            ),
-            Self::ProblemsIntroduced(..) => maybe_yellow(
+                 in order to prevent more failures of unrelated PRs."
+                    .to_owned()
+            }
+            Self::ProblemsIntroduced(..) => {
                 "This PR introduces the problems listed above. Please fix them before merging, \
-                 otherwise the base branch would break.",
-            ),
-            Self::DiscouragedPatternedIntroduced(..) => maybe_yellow(
+                 otherwise the base branch would break."
+                    .to_owned()
+            }
+            Self::ProblemsChanged { introduced, fixed } => {
+                let introduced = if introduced.is_empty() {
+                    "The base branch is broken, but this PR doesn't introduce any new problems."
+                } else {
+                    "The base branch is broken, and this PR introduces the new problems listed \
+                     above. Please fix them before merging."
+                };
+                match fixed.len() {
+                    0 => introduced.to_owned(),
+                    1 => format!("{introduced} It also fixes 1 problem of the base branch."),
+                    count => {
+                        format!("{introduced} It also fixes {count} problems of the base branch.")
+                    }
+                }
+            }
+            Self::DiscouragedPatternedIntroduced(..) => {
                 "This PR introduces additional instances of discouraged patterns as listed above. \
-                 Merging is discouraged but would not break the base branch.",
-            ),
+                 Merging is discouraged but would not break the base branch."
+                    .to_owned()
+            }
+            Self::Interrupted(errors) if errors.is_empty() => {
+                "The run was interrupted before the checks were done.".to_owned()
+            }
+            Self::Interrupted(..) => {
+                "The run was interrupted before the checks were done. The problems listed above \
+                 were found until then, some of which the base branch may already have."
+                    .to_owned()
+            }
+        }
+    }
+
+    /// Formats the status in the given style, printing the snippet and note with the same index
+    /// below each problem if any. Problems are grouped below headers if `group_by` is given.
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+        use_color: bool,
+        style: OutputStyle,
+        group_by: Option<GroupBy>,
+        snippets: &[Option<String>],
+        notes: &[Option<String>],
+    ) -> fmt::Result {
+        // These all respect the NO_COLOR environment variable and `--color` even if `use_color`
+        // is true.
+        let paint = |s: &str, color: Color| {
+            if use_color {
+                highlight(s, color)
+            } else {
+                s.to_owned()
+            }
         };
-        fmt::Display::fmt(&message, f)
+        let color = self.severity().map_or(Color::Green, Severity::color);
+
+        // If there are errors, print them all out first in the color of their severity.
+        if let Some(errors) = self.errors() {
+            let write_error = |f: &mut fmt::Formatter, index: usize| -> fmt::Result {
+                let error = &errors[index];
+                let error = match style {
+                    OutputStyle::Compact => format!("{}\n", compact(error)),
+                    OutputStyle::Short | OutputStyle::Full => format!("{error}\n"),
+                };
+                f.write_str(&paint(&error, color))?;
+                if let Some(Some(snippet)) = snippets.get(index) {
+                    f.write_str(snippet)?;
+                }
+                if let Some(fix) = errors[index].fix()
+                    && style != OutputStyle::Compact
+                {
+                    writeln!(f, "  Fix: {fix}")?;
+                }
+                if let Some(Some(note)) = notes.get(index) {
+                    let note = format!("  {note}");
+                    if use_color {
+                        writeln!(f, "{}", note.dimmed())?;
+                    } else {
+                        writeln!(f, "{note}")?;
+                    }
+                }
+                Ok(())
+            };
+
+            match group_by {
+                None => (0..errors.len()).try_for_each(|index| write_error(f, index))?,
+                Some(group_by) => {
+                    // Problems that aren't in any group come last
+                    let groups = errors
+                        .iter()
+                        .enumerate()
+                        .map(|(index, error)| (group_by.group(error), index))
+                        .into_group_map();
+                    let groups = groups
+                        .into_iter()
+                        .sorted_by_key(|(group, _)| (group.is_none(), group.clone()));
+                    for (group, indices) in groups {
+                        let header = format!(
+                            "{} ({} {})",
+                            group.as_deref().unwrap_or("Other"),
+                            indices.len(),
+                            if indices.len() == 1 {
+                                "problem"
+                            } else {
+                                "problems"
+                            }
+                        );
+                        if use_color {
+                            writeln!(f, "{}", header.bold().underline())?;
+                        } else {
+                            writeln!(f, "{header}")?;
+                        }
+                        for index in indices {
+                            write_error(f, index)?;
+                        }
+                        writeln!(f)?;
+                    }
+                }
+            }
+        }
+
+        // The fixed problems aren't problems of this PR, so they're listed after the others
+        if let Self::ProblemsChanged { fixed, .. } = self
+            && !fixed.is_empty()
+        {
+            writeln!(f, "Fixed problems of the base branch:")?;
+            for problem in fixed {
+                f.write_str(&paint(&format!("{problem}\n"), Color::Green))?;
+            }
+        }
+
+        // Then, print out the message for this status.
+        let message = match self {
+            Self::Error(error) => format!("{} {:#}", paint("I/O error: ", Color::Yellow), error),
+            _ => paint(&self.message(), color),
+        };
+        f.write_str(&message)
+    }
+
+    /// The number of the exit code this status results in.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::ValidatedSuccessfully | Self::BranchHealed => EXIT_SUCCESS,
+            Self::ProblemsChanged { introduced, .. } if introduced.is_empty() => EXIT_SUCCESS,
+            Self::BranchStillBroken(..)
+            | Self::ProblemsChanged { .. }
+            | Self::ProblemsIntroduced(..)
+            | Self::DiscouragedPatternedIntroduced(..) => EXIT_PROBLEMS,
+            Self::Interrupted(..) => EXIT_INTERRUPTED,
+            Self::Error(error) => match error.downcast_ref::<Fatal>() {
+                Some(Fatal::Usage(..)) => EXIT_USAGE,
+                Some(Fatal::Misconfiguration(..)) => EXIT_MISCONFIGURED,
+                None => EXIT_ERROR,
+            },
+        }
+    }
+
+    /// How severe the problems of this status are, if there are any.
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            Self::ValidatedSuccessfully | Self::BranchHealed => None,
+            Self::ProblemsChanged { introduced, .. } if introduced.is_empty() => None,
+            Self::BranchStillBroken(..)
+            | Self::ProblemsChanged { .. }
+            | Self::ProblemsIntroduced(..)
+            | Self::Interrupted(..)
+            | Self::Error(..) => Some(Severity::Error),
+            Self::DiscouragedPatternedIntroduced(..) => Some(Severity::Warning),
+        }
+    }
+}
+
+/// How severe problems are, which determines the color they're printed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The problems would break the base branch.
+    Error,
+    /// The problems are discouraged, but don't break anything.
+    Warning,
+}
+
+impl Severity {
+    pub fn color(self) -> Color {
+        match self {
+            Self::Error => Color::Red,
+            Self::Warning => Color::Yellow,
+        }
+    }
+}
+
+/// Colors the text, additionally making spans in backticks like `pkgs.foo` bold.
+fn highlight(text: &str, color: Color) -> String {
+    code_spans(text)
+        .into_iter()
+        .map(|(span, is_code)| {
+            let span = span.color(color);
+            if is_code { span.bold() } else { span }.to_string()
+        })
+        .collect()
+}
+
+/// Splits the text into spans, indicating whether each is code surrounded by backticks. The
+/// backticks are kept so that the text stays the same without colors.
+fn code_spans(text: &str) -> Vec<(&str, bool)> {
+    let mut spans = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        let Some(length) = rest[start + 1..].find('`') else {
+            break;
+        };
+        let end = start + length + 2;
+        spans.push((&rest[..start], false));
+        spans.push((&rest[start..end], true));
+        rest = &rest[end..];
+    }
+    spans.push((rest, false));
+    spans.retain(|(span, _)| !span.is_empty());
+    spans
+}
+
+/// Formats a problem on a single line, prefixed with its location and code.
+pub fn compact(problem: &Problem) -> String {
+    let code = problem.code();
+    let summary = problem.summary();
+    match problem.location() {
+        Some(location) => {
+            let position = match (location.line, location.column) {
+                (Some(line), Some(column)) => format!("{}:{line}:{column}", location.path),
+                (Some(line), None) => format!("{}:{line}", location.path),
+                (None, _) => location.path.to_string(),
+            };
+            format!("{position}: {code}: {summary}")
+        }
+        None => format!("{code}: {summary}"),
     }
 }
 
@@ -85,38 +378,175 @@ impl From<anyhow::Error> for Status {
 
 impl From<Status> for ExitCode {
     fn from(status: Status) -> Self {
-        match status {
-            Status::ValidatedSuccessfully | Status::BranchHealed => ExitCode::SUCCESS,
-            Status::BranchStillBroken(..)
-            | Status::ProblemsIntroduced(..)
-            | Status::DiscouragedPatternedIntroduced(..) => ExitCode::from(1),
-            Status::Error(..) => ExitCode::from(2),
-        }
+        ExitCode::from(status.exit_code())
     }
 }
 
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Status::fmt(self, f, /* use_color */ false)
+        Status::fmt(
+            self,
+            f,
+            /* use_color */ false,
+            OutputStyle::default(),
+            /* group_by */ None,
+            &[],
+            &[],
+        )
     }
 }
 
-pub struct ColoredStatus(Status);
+pub struct ColoredStatus {
+    status: Status,
+    style: OutputStyle,
+    group_by: Option<GroupBy>,
+    /// Source code snippets for the problems with the same index, see `--output-style full`.
+    snippets: Vec<Option<String>>,
+    /// Notes for the problems with the same index, e.g. from `--blame`.
+    notes: Vec<Option<String>>,
+    /// Notes on the whole run printed after the message, e.g. that evaluations were retried.
+    remarks: Vec<String>,
+}
+
+impl ColoredStatus {
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Sets the style to print the problems in.
+    pub fn with_style(self, style: OutputStyle) -> Self {
+        Self { style, ..self }
+    }
+
+    /// Groups the problems below headers.
+    pub fn with_grouping(self, group_by: Option<GroupBy>) -> Self {
+        Self { group_by, ..self }
+    }
+
+    /// The source code snippets for the problems with the same index, if any.
+    pub fn snippets(&self) -> &[Option<String>] {
+        &self.snippets
+    }
+
+    /// Adds source code snippets to be printed below the problems with the same index.
+    pub fn with_snippets(self, snippets: Vec<Option<String>>) -> Self {
+        Self { snippets, ..self }
+    }
+
+    /// Adds notes to be printed below the problems with the same index.
+    pub fn with_notes(self, notes: Vec<Option<String>>) -> Self {
+        Self { notes, ..self }
+    }
+
+    /// Adds a note on the whole run to be printed after the message.
+    pub fn with_remark(mut self, remark: String) -> Self {
+        self.remarks.push(remark);
+        self
+    }
+}
 
 impl From<Status> for ColoredStatus {
     fn from(status: Status) -> Self {
-        Self(status)
+        Self {
+            status,
+            style: OutputStyle::default(),
+            group_by: None,
+            snippets: vec![],
+            notes: vec![],
+            remarks: vec![],
+        }
     }
 }
 
 impl fmt::Display for ColoredStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Status::fmt(&self.0, f, /* use_color */ true)
+        Status::fmt(
+            &self.status,
+            f,
+            /* use_color */ true,
+            self.style,
+            self.group_by,
+            &self.snippets,
+            &self.notes,
+        )?;
+        for remark in &self.remarks {
+            write!(f, "\n{}", remark.dimmed())?;
+        }
+        Ok(())
     }
 }
 
 impl From<ColoredStatus> for ExitCode {
     fn from(status: ColoredStatus) -> Self {
-        status.0.into()
+        status.status.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::npv_143;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn exit_codes() {
+        let exit_code = |status: Status| ExitCode::from(status);
+        assert_eq!(
+            exit_code(Status::ValidatedSuccessfully),
+            ExitCode::from(EXIT_SUCCESS)
+        );
+        assert_eq!(
+            exit_code(Status::ProblemsIntroduced(vec![])),
+            ExitCode::from(EXIT_PROBLEMS)
+        );
+        assert_eq!(
+            exit_code(anyhow::anyhow!("oops").into()),
+            ExitCode::from(EXIT_ERROR)
+        );
+        // The kind of a fatal error is preserved through more context
+        let error = anyhow::anyhow!("not found")
+            .context(Fatal::Misconfiguration("is git installed?".to_owned()))
+            .context("Could not check Nixpkgs");
+        assert_eq!(exit_code(error.into()), ExitCode::from(EXIT_MISCONFIGURED));
+        let error = anyhow::anyhow!("no repo").context(Fatal::Usage("--blame".to_owned()));
+        assert_eq!(exit_code(error.into()), ExitCode::from(EXIT_USAGE));
+    }
+
+    #[test]
+    fn retains_introduced_problems() {
+        let missing = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let status = Status::ProblemsChanged {
+            introduced: vec![
+                missing("pkgs/by-name/fo/foo"),
+                missing("pkgs/by-name/ba/bar"),
+            ],
+            fixed: vec![missing("pkgs/by-name/ba/baz")],
+        };
+        let mut calls = 0;
+        let status = status.retain(|problem| {
+            calls += 1;
+            !problem.to_string().contains("foo")
+        });
+        // The fixed problems aren't filtered
+        assert_eq!(calls, 2);
+        let Status::ProblemsChanged { introduced, fixed } = status else {
+            panic!("the base branch is still broken");
+        };
+        assert_eq!(introduced.len(), 1);
+        assert_eq!(fixed.len(), 1);
+    }
+
+    #[test]
+    fn splits_code_spans() {
+        assert_eq!(
+            code_spans("- Attribute `pkgs.foo` is `null`, see `broken"),
+            vec![
+                ("- Attribute ", false),
+                ("`pkgs.foo`", true),
+                (" is ", false),
+                ("`null`", true),
+                (", see `broken", false),
+            ]
+        );
     }
 }
@@ -0,0 +1,67 @@
+//! Nixpkgs in the read-only Nix store, like the result of `builtins.fetchGit`, which allows
+//! checking it in a derivation build without a checkout.
+//!
+//! Checking doesn't need to write to Nixpkgs, but fixing problems does, and the store can't be
+//! identified by git, whose trees identify Nixpkgs for `--resume` and `--cache`. Store paths are
+//! immutable, so their names identify them instead.
+
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use crate::status::Fatal;
+
+/// The directory of the Nix store, `/nix/store` unless derivation builds say otherwise.
+fn store_dir() -> PathBuf {
+    env::var_os("NIX_STORE").map_or_else(|| PathBuf::from("/nix/store"), PathBuf::from)
+}
+
+/// The name of the store path, like `<hash>-source`, if the path is a store path itself.
+pub fn store_path_name(path: &Path) -> Option<String> {
+    let mut components = path.strip_prefix(store_dir()).ok()?.components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) => name.to_str().map(str::to_owned),
+        _ => None,
+    }
+}
+
+/// Whether the path is in the Nix store, including a file or directory within a store path.
+pub fn is_in_store(path: &Path) -> bool {
+    path.strip_prefix(store_dir())
+        .is_ok_and(|rest| rest.components().next().is_some())
+}
+
+/// Fails if the Nixpkgs is in the Nix store, where it can't be changed, like by `--fix`.
+pub fn ensure_writable(nixpkgs_path: &Path) -> anyhow::Result<()> {
+    let nixpkgs_path = nixpkgs_path.canonicalize()?;
+    if is_in_store(&nixpkgs_path) {
+        anyhow::bail!(Fatal::Usage(format!(
+            "The Nixpkgs {} is in the read-only Nix store, so it can't be changed. Check out \
+             or copy it elsewhere first, or use `--fix --dry-run` or `--emit-fixes` to get the \
+             changes without making them.",
+            nixpkgs_path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_store_paths() {
+        temp_env::with_var("NIX_STORE", Some("/nix/store"), || {
+            let name = |path: &str| store_path_name(Path::new(path));
+            assert_eq!(
+                name("/nix/store/0123-source").as_deref(),
+                Some("0123-source")
+            );
+            assert_eq!(name("/nix/store/0123-source/pkgs"), None);
+            assert_eq!(name("/nix/store"), None);
+            assert_eq!(name("/home/nixpkgs"), None);
+            assert!(is_in_store(Path::new("/nix/store/0123-source/pkgs")));
+            assert!(!is_in_store(Path::new("/nix/store")));
+            assert!(!is_in_store(Path::new("/home/nixpkgs")));
+        });
+    }
+}
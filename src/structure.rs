@@ -5,20 +5,29 @@ use std::sync::LazyLock;
 use anyhow::Context;
 use itertools::{concat, process_results};
 use regex::Regex;
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
 
 use crate::NixFileStore;
-use crate::problem::{npv_109, npv_110, npv_111, npv_140, npv_141, npv_142, npv_143, npv_144};
+use crate::config::{Config, PackageSet};
+use crate::problem::{
+    npv_109, npv_110, npv_111, npv_112, npv_140, npv_141, npv_142, npv_143, npv_144, npv_148,
+};
+use crate::progress::Progress;
 use crate::references;
+use crate::scope::Scope;
+use crate::status::Fatal;
 use crate::validation::{self, ResultIteratorExt, Validation::Success};
+use crate::{interrupt, timeout};
 
 pub const BASE_SUBPATH: &str = "pkgs/by-name";
 pub const PACKAGE_NIX_FILENAME: &str = "package.nix";
 
+/// Paths outside `pkgs/by-name` that must exist for Nixpkgs to be evaluated. These may be
+/// missing if only parts of Nixpkgs are checked out, e.g. in a sparse checkout.
+const EVAL_REQUIRED_PATHS: &[&str] = &["default.nix"];
+
 static SHARD_NAME_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^[a-z0-9_-]{1,2}$").unwrap());
-static PACKAGE_NAME_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_-]+$").unwrap());
 
 /// Deterministic file listing so that tests are reproducible.
 pub fn read_dir_sorted(base_dir: &Path) -> anyhow::Result<Vec<DirEntry>> {
@@ -32,6 +41,29 @@ pub fn read_dir_sorted(base_dir: &Path) -> anyhow::Result<Vec<DirEntry>> {
     .with_context(ctx)
 }
 
+/// Whether an error only affects the path that was checked, like an I/O error, rather than the
+/// whole run, like a missing tool, a timeout or an interruption.
+pub fn is_path_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Fatal>().is_none()
+        && !timeout::is_timed_out(error)
+        && !interrupt::is_interrupted(error)
+}
+
+/// Checks that the paths needed for evaluating Nixpkgs exist.
+pub fn check_eval_required_paths(nixpkgs_path: &Path) -> validation::Validation<()> {
+    EVAL_REQUIRED_PATHS
+        .iter()
+        .map(|path| {
+            let path = RelativePath::new(path);
+            if path.to_path(nixpkgs_path).exists() {
+                Success(())
+            } else {
+                npv_112::NixpkgsPathMissing::new(path).into()
+            }
+        })
+        .collect()
+}
+
 // Some utility functions for the basic structure
 
 pub fn shard_for_package(package_name: &str) -> String {
@@ -50,31 +82,122 @@ pub fn relative_file_for_package(package_name: &str) -> RelativePathBuf {
     relative_dir_for_package(package_name).join(PACKAGE_NIX_FILENAME)
 }
 
+/// The inverse of `relative_file_for_package`, returning `None` if the file isn't the
+/// `package.nix` file of any package.
+pub fn package_for_relative_file(file: &RelativePath) -> Option<String> {
+    let package_name = file.parent()?.file_name()?;
+    (relative_file_for_package(package_name) == file).then(|| package_name.to_owned())
+}
+
+/// The name of the package whose package directory contains the path, if any.
+pub fn package_for_path(path: &RelativePath) -> Option<String> {
+    let mut components = path.strip_prefix(BASE_SUBPATH).ok()?.components();
+    let _shard = components.next()?;
+    Some(components.next()?.as_str().to_owned())
+}
+
 /// Check the structure of Nixpkgs, returning the attribute names that are defined in
 /// `pkgs/by-name`
+///
+/// Package directories outside the given scope aren't checked, but their names are still returned.
 pub fn check_structure(
     path: &Path,
     nix_file_store: &mut NixFileStore,
+    scope: &Scope,
+    config: &Config,
+    progress: &Progress,
 ) -> validation::Result<Vec<String>> {
-    let base_dir = path.join(BASE_SUBPATH);
+    let _span = tracing::info_span!("structure").entered();
+    check_by_name_dir(
+        path,
+        RelativePath::new(BASE_SUBPATH),
+        nix_file_store,
+        scope,
+        config,
+        progress,
+    )
+}
 
-    let shard_results = read_dir_sorted(&base_dir)?
+/// Like `check_structure`, but for a package set configured with `package-sets`, returning the
+/// names of its packages.
+pub fn check_package_set_structure(
+    path: &Path,
+    package_set: &PackageSet,
+    nix_file_store: &mut NixFileStore,
+    scope: &Scope,
+    config: &Config,
+    progress: &Progress,
+) -> validation::Result<Vec<String>> {
+    let _span = tracing::info_span!("structure", package_set = package_set.attribute).entered();
+    if !package_set.path.to_path(path).is_dir() {
+        return Ok(npv_112::NixpkgsPathMissing::new(package_set.path.as_str()).into());
+    }
+    check_by_name_dir(
+        path,
+        &package_set.path,
+        nix_file_store,
+        scope,
+        config,
+        progress,
+    )
+}
+
+/// Checks a directory with the layout of `pkgs/by-name`, returning the names of the packages in it.
+fn check_by_name_dir(
+    path: &Path,
+    by_name_dir: &RelativePath,
+    nix_file_store: &mut NixFileStore,
+    scope: &Scope,
+    config: &Config,
+    progress: &Progress,
+) -> validation::Result<Vec<String>> {
+    let base_dir = by_name_dir.to_path(path);
+    let shard_entries = read_dir_sorted(&base_dir)?;
+
+    // Listing the package directories up front is fast, and gives an idea how long it will take
+    let package_count = shard_entries
+        .iter()
+        .filter_map(|shard_entry| shard_entry.path().read_dir().ok())
+        .map(|entries| entries.count() as u64)
+        .sum();
+    progress.phase("Checking the structure", Some(package_count));
+
+    let shard_results = shard_entries
         .into_iter()
         .map(|shard_entry| -> validation::Result<_> {
             let shard_path = shard_entry.path();
             let shard_name = shard_entry.file_name().to_string_lossy().into_owned();
+            let relative_shard_dir = by_name_dir.join(&shard_name);
 
             Ok(if shard_name == "README.md" {
                 // README.md is allowed to be a file and not checked
                 Success(vec![])
+            } else if !scope.contains_path(&relative_shard_dir) {
+                // Nothing in this shard is in scope, but the packages in it still need to be
+                // known for the evaluation checks.
+                Success(if shard_path.is_dir() {
+                    read_dir_sorted(&shard_path)?
+                        .into_iter()
+                        .map(|entry| {
+                            progress.inc();
+                            entry.file_name().to_string_lossy().into_owned()
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                })
             } else if !shard_path.is_dir() {
                 // We can't check for any other errors if it's not a directory, since there are no
                 // subdirectories to check.
-                npv_109::ByNameShardIsNotDirectory::new(shard_name).into()
+                npv_109::ByNameShardIsNotDirectory::new(relative_shard_dir).into()
             } else {
                 let shard_name_valid = SHARD_NAME_REGEX.is_match(&shard_name);
                 let result = if !shard_name_valid {
-                    npv_110::ByNameShardIsInvalid::new(shard_name.clone()).into()
+                    npv_110::ByNameShardIsInvalid::new(
+                        shard_name.clone(),
+                        relative_shard_dir.clone(),
+                    )
+                    .into()
                 } else {
                     Success(())
                 };
@@ -87,71 +210,99 @@ pub fn check_structure(
                     .filter(|(l, r)| l.file_name().eq_ignore_ascii_case(r.file_name()))
                     .map(|(l, r)| {
                         npv_111::ByNameShardIsCaseSensitiveDuplicate::new(
-                            shard_name.clone(),
+                            relative_shard_dir.clone(),
                             l.file_name(),
                             r.file_name(),
                         )
                         .into()
                     });
 
-                let result = result.and_(validation::sequence_(duplicate_results));
+                let result = result.and_(duplicate_results.collect());
 
                 let package_results = entries
                     .into_iter()
                     .map(|package_entry| {
-                        check_package(
+                        let result = check_package(
                             nix_file_store,
                             path,
-                            &shard_name,
+                            scope,
+                            config,
+                            &relative_shard_dir,
                             shard_name_valid,
                             &package_entry,
-                        )
+                        );
+                        progress.inc();
+                        match result {
+                            // An I/O error in one package directory, like a file that can't be
+                            // read, shouldn't prevent checking the others
+                            Err(error) if is_path_error(&error) => {
+                                let relative_package_dir = relative_shard_dir
+                                    .join(package_entry.file_name().to_string_lossy().as_ref());
+                                Ok(npv_148::PathUnreadable::new(
+                                    relative_package_dir,
+                                    error.root_cause().to_string(),
+                                )
+                                .into())
+                            }
+                            result => result,
+                        }
                     })
                     .collect_vec()?;
 
-                result.and_(validation::sequence(package_results))
+                result.and_(package_results.into_iter().collect())
             })
         })
         .collect_vec()?;
 
     // Combine the package names contained within each shard into a longer list.
-    Ok(validation::sequence(shard_results).map(concat))
+    Ok(shard_results
+        .into_iter()
+        .collect::<validation::Validation<Vec<_>>>()
+        .map(concat))
 }
 
 fn check_package(
     nix_file_store: &mut NixFileStore,
     path: &Path,
-    shard_name: &str,
+    scope: &Scope,
+    config: &Config,
+    relative_shard_dir: &RelativePath,
     shard_name_valid: bool,
     package_entry: &DirEntry,
 ) -> validation::Result<String> {
     let package_path = package_entry.path();
     let package_name = package_entry.file_name().to_string_lossy().into_owned();
-    let relative_package_dir =
-        RelativePathBuf::from(format!("{BASE_SUBPATH}/{shard_name}/{package_name}"));
+    let relative_package_dir = relative_shard_dir.join(&package_name);
+    let _span = tracing::debug_span!("package", package = package_name).entered();
 
-    Ok(if !package_path.is_dir() {
-        npv_140::PackageDirectoryIsNotDirectory::new(package_name).into()
+    Ok(if !scope.contains_path(&relative_package_dir) {
+        Success(package_name)
+    } else if !package_path.is_dir() {
+        npv_140::PackageDirectoryIsNotDirectory::new(relative_package_dir).into()
     } else {
-        let package_name_valid = PACKAGE_NAME_REGEX.is_match(&package_name);
+        let pattern = config.package_name_pattern_for(&relative_package_dir);
+        let package_name_valid = pattern.is_match(&package_name);
         let result = if !package_name_valid {
             npv_141::InvalidPackageDirectoryName::new(
                 package_name.clone(),
                 relative_package_dir.clone(),
+                pattern.as_str(),
             )
             .into()
         } else {
             Success(())
         };
 
-        let correct_relative_package_dir = relative_dir_for_package(&package_name);
-        let result = result.and_(if relative_package_dir != correct_relative_package_dir {
+        let correct_shard = shard_for_package(&package_name);
+        let shard_name = relative_shard_dir.file_name().unwrap_or_default();
+        let result = result.and_(if shard_name != correct_shard {
             // Only show this error if we have a valid shard and package name.
             // If one of those is wrong, you should fix that first.
             if shard_name_valid && package_name_valid {
                 npv_142::PackageInWrongShard::new(
                     package_name.clone(),
                     relative_package_dir.clone(),
+                    correct_shard,
                 )
                 .into()
             } else {
@@ -163,15 +314,16 @@ fn check_package(
 
         let package_nix_path = package_path.join(PACKAGE_NIX_FILENAME);
         let result = result.and_(if !package_nix_path.exists() {
-            npv_143::PackageNixMissing::new(package_name.clone()).into()
+            npv_143::PackageNixMissing::new(relative_package_dir.clone()).into()
         } else if !package_nix_path.is_file() {
-            npv_144::PackageNixIsNotFile::new(package_name.clone()).into()
+            npv_144::PackageNixIsNotFile::new(relative_package_dir.clone()).into()
         } else {
             Success(())
         });
 
         let result = result.and_(references::check_references(
             nix_file_store,
+            path,
             &relative_package_dir,
             &relative_package_dir.to_path(path),
         )?);
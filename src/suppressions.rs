@@ -0,0 +1,124 @@
+//! Problems that maintainers decided not to fix, see `--suppressions`.
+//!
+//! The suppressions file contains one problem code per line, optionally followed by the path
+//! relative to Nixpkgs the problem is about. Without a path, the problem is suppressed
+//! everywhere. Empty lines and lines starting with `#` are ignored:
+//!
+//! ```text
+//! # Evaluation is checked separately
+//! NPV-120
+//! NPV-123 pkgs/by-name/fo/foo/package.nix
+//! ```
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use relative_path::RelativePathBuf;
+
+use crate::problem::Problem;
+
+/// The suppressions read from a suppressions file.
+pub struct Suppressions {
+    path: PathBuf,
+    entries: BTreeSet<(String, Option<RelativePathBuf>)>,
+}
+
+impl Suppressions {
+    /// Reads the suppressions file at the given path. A missing file contains no suppressions.
+    pub fn read(path: &Path) -> anyhow::Result<Suppressions> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                return Err(error).with_context(|| {
+                    format!("Could not read suppressions file {}", path.display())
+                });
+            }
+        };
+        let entries = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.split_once(char::is_whitespace) {
+                Some((code, path)) => (code.to_owned(), Some(RelativePathBuf::from(path.trim()))),
+                None => (line.to_owned(), None),
+            })
+            .collect();
+        Ok(Suppressions {
+            path: path.to_owned(),
+            entries,
+        })
+    }
+
+    /// The entry that would suppress only this problem.
+    fn entry(problem: &Problem) -> (String, Option<RelativePathBuf>) {
+        (
+            problem.code().to_owned(),
+            problem.location().map(|location| location.path),
+        )
+    }
+
+    /// Whether the problem is suppressed, either everywhere or at its location.
+    pub fn contains(&self, problem: &Problem) -> bool {
+        let (code, path) = Self::entry(problem);
+        self.entries.contains(&(code.clone(), None))
+            || (path.is_some() && self.entries.contains(&(code, path)))
+    }
+
+    /// Suppresses the problem at its location from now on, appending it to the file.
+    pub fn add(&mut self, problem: &Problem) -> anyhow::Result<()> {
+        let entry = Self::entry(problem);
+        let line = match &entry {
+            (code, Some(path)) => format!("{code} {path}"),
+            (code, None) => code.clone(),
+        };
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{line}"))
+            .with_context(|| format!("Could not write to {}", self.path.display()))?;
+        self.entries.insert(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::npv_123;
+    use crate::testing;
+
+    #[test]
+    fn suppresses_by_code_and_path() -> anyhow::Result<()> {
+        let temp_dir = testing::tempdir()?;
+        let path = temp_dir.path().join("suppressions");
+        fs::write(&path, "# Comment\n\nNPV-120\n")?;
+
+        let problem = |package: &str| -> Problem {
+            npv_123::NixFileContainsPathOutsideDirectory::new(
+                format!("pkgs/by-name/{package}"),
+                "package.nix",
+                1,
+                1,
+                "../.",
+            )
+            .into()
+        };
+
+        let mut suppressions = Suppressions::read(&path)?;
+        assert!(!suppressions.contains(&problem("fo/foo")));
+        suppressions.add(&problem("fo/foo"))?;
+        assert!(suppressions.contains(&problem("fo/foo")));
+        assert!(!suppressions.contains(&problem("ba/bar")));
+
+        // Suppressions are kept in the file
+        let suppressions = Suppressions::read(&path)?;
+        assert!(suppressions.contains(&problem("fo/foo")));
+        assert!(!suppressions.contains(&problem("ba/bar")));
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Checks for symlinks pointing to paths that don't exist, see `BrokenSymlinks`.
+
+use std::collections::BTreeMap;
+use std::fs::{self, DirEntry};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use itertools::Itertools;
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::config::{BrokenSymlinks, Config};
+use crate::problem::npv_149;
+use crate::ratchet::{self, RatchetState};
+use crate::scope::Scope;
+use crate::structure::read_dir_sorted;
+use crate::timeout;
+use crate::validation::{self, Validation::Success};
+
+/// Finds the broken symlinks anywhere in Nixpkgs, except in `.git`, and handles them according to
+/// the `broken-symlinks` configuration: They're either problems, ratchet values, or not checked.
+///
+/// A symlink counts as broken if its target doesn't exist, other symlinks that can't be resolved,
+/// like ones in a loop, are only checked within package directories, see `references`.
+pub fn check_broken_symlinks(
+    nixpkgs_path: &Path,
+    scope: &Scope,
+    config: &Config,
+) -> validation::Result<BTreeMap<RelativePathBuf, ratchet::BrokenSymlink>> {
+    let _span = tracing::info_span!("symlinks").entered();
+    // Walking all of Nixpkgs takes a while, so each top-level entry is walked in parallel
+    let walks = || -> anyhow::Result<_> {
+        Ok(read_dir_sorted(nixpkgs_path)?
+            .into_iter()
+            .map(|entry| {
+                move || -> anyhow::Result<_> {
+                    let subpath =
+                        RelativePathBuf::from(entry.file_name().to_string_lossy().as_ref());
+                    let mut broken_symlinks = BTreeMap::new();
+                    collect_entry(nixpkgs_path, &subpath, &entry, &mut broken_symlinks)?;
+                    broken_symlinks.retain(|path, _| scope.contains_path(path));
+                    Ok(broken_symlinks)
+                }
+            })
+            .collect_vec())
+    };
+
+    Ok(match config.broken_symlinks {
+        BrokenSymlinks::Error => validation::par_sequence_(walks()?.into_iter().map(|walk| {
+            move || {
+                Ok(walk()?
+                    .into_iter()
+                    .map(|(path, target)| npv_149::BrokenSymlink::new(path, target).into())
+                    .collect())
+            }
+        }))?
+        .map(|()| BTreeMap::new()),
+        BrokenSymlinks::Warning => validation::par_sequence(
+            walks()?
+                .into_iter()
+                .map(|walk| move || Ok(Success(walk()?))),
+        )?
+        .map(|walks| {
+            walks
+                .into_iter()
+                .flatten()
+                .map(|(path, target)| {
+                    let resolves = RatchetState::Loose(target);
+                    (path, ratchet::BrokenSymlink { resolves })
+                })
+                .collect()
+        }),
+        BrokenSymlinks::Ignore => Success(BTreeMap::new()),
+    })
+}
+
+/// Recursively collects the broken symlinks within the given directory relative to Nixpkgs, with
+/// their targets, without following symlinks.
+fn collect_broken_symlinks(
+    nixpkgs_path: &Path,
+    dir: &RelativePath,
+    broken_symlinks: &mut BTreeMap<RelativePathBuf, PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in read_dir_sorted(&dir.to_path(nixpkgs_path))? {
+        let subpath = dir.join(entry.file_name().to_string_lossy().as_ref());
+        collect_entry(nixpkgs_path, &subpath, &entry, broken_symlinks)?;
+    }
+    Ok(())
+}
+
+/// Collects the broken symlinks at the directory entry with the given path relative to Nixpkgs,
+/// which is either the entry itself or those within it.
+fn collect_entry(
+    nixpkgs_path: &Path,
+    subpath: &RelativePath,
+    entry: &DirEntry,
+    broken_symlinks: &mut BTreeMap<RelativePathBuf, PathBuf>,
+) -> anyhow::Result<()> {
+    timeout::check(&entry.path())?;
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+        if subpath != ".git" {
+            collect_broken_symlinks(nixpkgs_path, subpath, broken_symlinks)?;
+        }
+    } else if file_type.is_symlink() {
+        let path = entry.path();
+        // Following the symlink fails with `NotFound` only if the target doesn't exist
+        if matches!(fs::metadata(&path), Err(error) if error.kind() == io::ErrorKind::NotFound) {
+            let target = fs::read_link(&path)
+                .with_context(|| format!("Could not read symlink {}", path.display()))?;
+            broken_symlinks.insert(subpath.to_owned(), target);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Helpers for testing the checks, with synthetic Nixpkgs trees in temporary directories and
+//! assertions about the problems found in them.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use pretty_assertions::StrComparison;
+use tempfile::TempDir;
+
+use crate::structure::{self, BASE_SUBPATH, PACKAGE_NIX_FILENAME};
+use crate::validation::Validation;
+
+// tempfile::tempdir needs to be wrapped in temp_env lock
+// because it accesses TMPDIR environment variable.
+pub fn tempdir() -> anyhow::Result<TempDir> {
+    let empty_list: [(&str, Option<&str>); 0] = [];
+    Ok(temp_env::with_vars(empty_list, tempfile::tempdir)?)
+}
+
+/// A Nixpkgs tree in a temporary directory, which is removed when it's dropped.
+pub struct Tree {
+    dir: TempDir,
+}
+
+impl Tree {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self { dir: tempdir()? })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes a file at the path relative to the root of the tree, creating its directories.
+    pub fn file(self, path: &str, contents: &str) -> anyhow::Result<Self> {
+        let path = self.path().join(path);
+        fs::create_dir_all(path.parent().expect("a parent directory"))?;
+        fs::write(path, contents)?;
+        Ok(self)
+    }
+
+    /// Writes the `package.nix` file of a package in `pkgs/by-name`, within the given
+    /// subdirectory of the tree, like `main` for the Nixpkgs of a test case.
+    pub fn package(self, nixpkgs: &str, name: &str, contents: &str) -> anyhow::Result<Self> {
+        let shard = structure::shard_for_package(name);
+        self.file(
+            &format!("{nixpkgs}/{BASE_SUBPATH}/{shard}/{name}/{PACKAGE_NIX_FILENAME}"),
+            contents,
+        )
+    }
+}
+
+/// Asserts that the validation is successful without any warnings, returning its value.
+#[track_caller]
+pub fn assert_success<A>(validation: Validation<A>) -> A {
+    match validation {
+        Validation::Success(value) => value,
+        Validation::SuccessWithWarnings(_, problems) | Validation::Failure(problems) => {
+            panic!("expected a success, but got: {}", lines(problems))
+        }
+    }
+}
+
+/// Asserts that the validation failed with problems that are displayed as the given lines.
+#[track_caller]
+pub fn assert_problems_matching<A>(validation: Validation<A>, expected: &[&str]) {
+    let Validation::Failure(problems) = validation else {
+        panic!("expected a failure");
+    };
+    assert_golden("problems", &expected.join("\n"), &lines(problems));
+}
+
+/// Asserts that the output matches the expected one almost verbatim, where `@REDACTED@` in the
+/// expected output matches anything, like the paths of temporary directories.
+#[track_caller]
+pub fn assert_golden(name: &str, expected: &str, actual: &str) {
+    let pattern = format!("^{}$", regex::escape(expected).replace("@REDACTED@", ".*"));
+    let regex = regex::RegexBuilder::new(&pattern)
+        .dot_matches_new_line(true)
+        .build()
+        .expect("valid regex");
+    assert!(
+        regex.is_match(actual),
+        "Failed test case {name}: {}",
+        StrComparison::new(expected, actual)
+    );
+}
+
+fn lines(items: impl IntoIterator<Item = impl Display>) -> String {
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,194 @@
+//! Timeouts for the checks, see `check-timeout`.
+//!
+//! Since a check can't be interrupted from the outside, it's given a deadline that's checked
+//! whenever it gets a Nix file, see `NixFileStore::get`. Only parsing a file, which is where a
+//! pathological file takes the longest, runs in a separate thread that's abandoned once the
+//! deadline passes.
+//!
+//! Evaluating Nixpkgs legitimately takes much longer than the rest of the checks, so the time the
+//! checks wait for Nix doesn't count towards their timeout, see `excluding`.
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::{error, fmt, thread};
+
+use relative_path::RelativePathBuf;
+
+use crate::config::Config;
+use crate::problem::Problem;
+use crate::validation;
+
+thread_local! {
+    /// When the check running on this thread times out, if it has a timeout.
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// The error of a check that timed out, converted into a problem by `run`.
+#[derive(Debug)]
+pub struct TimedOut {
+    /// The file that was processed when the deadline passed.
+    path: PathBuf,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Timed out on {}", self.path.display())
+    }
+}
+
+impl error::Error for TimedOut {}
+
+/// Runs a check of the Nixpkgs with the configured timeout, turning it into a problem if it times
+/// out.
+pub fn run<T>(
+    check: &str,
+    nixpkgs_path: &Path,
+    config: &Config,
+    f: impl FnOnce() -> validation::Result<T>,
+) -> validation::Result<T> {
+    let Some(timeout) = config.check_timeout else {
+        return f();
+    };
+    let previous = DEADLINE.replace(Some(Instant::now() + Duration::from_secs(timeout)));
+    let result = f();
+    DEADLINE.set(previous);
+    match result {
+        Err(error) => match error.downcast_ref::<TimedOut>() {
+            Some(TimedOut { path }) => {
+                tracing::warn!(check, path = %path.display(), "Check timed out");
+                let relative_path = path.strip_prefix(nixpkgs_path).unwrap_or(path);
+                Ok(
+                    Problem::at(RelativePathBuf::from_path(relative_path).unwrap_or_default())
+                        .code("NPV-150")
+                        .message(format!(
+                            "The {check} check timed out on this file after {timeout} seconds."
+                        ))
+                        .suggestion("Please make sure it's not unusually large or deeply nested.")
+                        .into(),
+                )
+            }
+            None => Err(error),
+        },
+        result => result,
+    }
+}
+
+/// Whether the error is because a check timed out, which needs to be kept as is so that `run` can
+/// turn it into a problem.
+pub fn is_timed_out(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<TimedOut>().is_some()
+}
+
+/// Fails if the deadline of the check running on this thread passed, while processing the file.
+pub fn check(path: &Path) -> anyhow::Result<()> {
+    match DEADLINE.get() {
+        Some(deadline) if Instant::now() >= deadline => Err(TimedOut {
+            path: path.to_owned(),
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// The deadline of the check running on this thread, to pass on to the threads it runs parts of
+/// the check on with `with_deadline`.
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.get()
+}
+
+/// Runs the function with the deadline of a check from `deadline`, like on a thread that runs a
+/// part of the check.
+pub fn with_deadline<T>(deadline: Option<Instant>, f: impl FnOnce() -> T) -> T {
+    let previous = DEADLINE.replace(deadline);
+    let result = f();
+    DEADLINE.set(previous);
+    result
+}
+
+/// Runs the function without counting the time it takes towards the timeout of the check running
+/// on this thread, like for waiting for a Nix evaluation.
+pub fn excluding<T>(f: impl FnOnce() -> T) -> T {
+    let Some(deadline) = DEADLINE.get() else {
+        return f();
+    };
+    let start = Instant::now();
+    let result = f();
+    DEADLINE.set(Some(deadline + start.elapsed()));
+    result
+}
+
+/// Parses the contents of the Nix file, failing if the deadline of the check running on this
+/// thread passes before it's done.
+pub fn parse(path: &Path, contents: String) -> anyhow::Result<rnix::Parse<rnix::Root>> {
+    let Some(deadline) = DEADLINE.get() else {
+        return Ok(rnix::Root::parse(&contents));
+    };
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(rnix::Root::parse(&contents)));
+    receiver
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|_| {
+            TimedOut {
+                path: path.to_owned(),
+            }
+            .into()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::validation::Validation::{Failure, Success};
+
+    #[test]
+    fn times_out_on_the_current_file() -> anyhow::Result<()> {
+        let config = Config {
+            check_timeout: Some(0),
+            ..Config::default()
+        };
+        let path = Path::new("/nixpkgs/pkgs/by-name/fo/foo/package.nix");
+        let result = run("structure", Path::new("/nixpkgs"), &config, || {
+            check(path)?;
+            Ok(Success(()))
+        })?;
+        let Failure(problems) = result else {
+            panic!("expected the check to time out");
+        };
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].code(), "NPV-150");
+        assert_eq!(
+            problems[0].to_string(),
+            "- pkgs/by-name/fo/foo/package.nix: The structure check timed out on this file after 0 seconds. Please make sure it's not unusually large or deeply nested."
+        );
+
+        // Waiting for Nix doesn't count
+        let config = Config {
+            check_timeout: Some(1),
+            ..Config::default()
+        };
+        let result = run("eval", Path::new("/nixpkgs"), &config, || {
+            excluding(|| thread::sleep(Duration::from_millis(1100)));
+            check(path)?;
+            Ok(Success(()))
+        })?;
+        testing::assert_success(result);
+
+        // Without a timeout, nothing times out, neither after the check
+        assert!(check(path).is_ok());
+        let result = run(
+            "structure",
+            Path::new("/nixpkgs"),
+            &Config::default(),
+            || {
+                check(path)?;
+                parse(path, "{ }".to_owned())?;
+                Ok(Success(()))
+            },
+        )?;
+        testing::assert_success(result);
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+//! Stepping through problems one by one to deal with them, see `--interactive`, or through their
+//! fixes, see `--fix --interactive`.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead as _, IsTerminal as _, Write as _};
+use std::path::Path;
+use std::process;
+
+use anyhow::Context;
+use colored::Colorize as _;
+
+use crate::delta;
+use crate::fix::{self, FileChange};
+use crate::git;
+use crate::output::snippet;
+use crate::problem::Problem;
+use crate::status::{Fatal, Status};
+use crate::suppressions::Suppressions;
+
+/// What the user wants to do with a problem.
+enum Action {
+    Open,
+    Fix,
+    Suppress,
+    Skip,
+    Quit,
+}
+
+/// What the user wants to do with a fix.
+enum FixAction {
+    Accept,
+    Edit,
+    Skip,
+    Quit,
+}
+
+/// Asks the user what to do with each problem of the status, returning the status without the
+/// problems they suppressed or fixed.
+pub fn triage(
+    status: Status,
+    nixpkgs_path: &Path,
+    mut suppressions: Option<&mut Suppressions>,
+    nixfmt: Option<&Path>,
+) -> anyhow::Result<Status> {
+    require_terminal()?;
+    let Some(problems) = status.errors() else {
+        return Ok(status);
+    };
+
+    // Problems that were suppressed or fixed
+    let mut resolved = BTreeSet::new();
+    'problems: for (index, problem) in problems.iter().enumerate() {
+        eprintln!(
+            "\n{} {problem}",
+            format!("[{}/{}]", index + 1, problems.len()).bold()
+        );
+        if let Some(snippet) = problem
+            .location()
+            .and_then(|location| snippet::render(nixpkgs_path, &location))
+        {
+            eprint!("{snippet}");
+        }
+
+        loop {
+            match prompt(problem, suppressions.is_some())? {
+                Action::Open => open(nixpkgs_path, problem)?,
+                Action::Fix => {
+                    let fix = problem.fix().expect("only offered with a fix");
+                    match fix.apply(nixpkgs_path, nixfmt) {
+                        Ok(()) => {
+                            eprintln!("{}", format!("Applied: {fix}").green());
+                            resolved.insert(delta::key(problem));
+                            break;
+                        }
+                        // The user may be able to resolve this, e.g. by removing the target
+                        Err(error) => eprintln!("{}", format!("Could not fix: {error:#}").red()),
+                    }
+                }
+                Action::Suppress => {
+                    let suppressions = suppressions.as_deref_mut().expect("only offered if set");
+                    suppressions.add(problem)?;
+                    resolved.insert(delta::key(problem));
+                    break;
+                }
+                Action::Skip => break,
+                Action::Quit => break 'problems,
+            }
+        }
+    }
+
+    Ok(status.retain(|problem| !resolved.contains(&delta::key(problem))))
+}
+
+/// Shows the diff of the fix of each problem that has one, asking the user whether to apply it,
+/// to skip it, or to edit the diff before applying it. Fixes are applied right away, such that
+/// quitting keeps the ones applied so far. Returns the status without the fixed problems.
+pub fn review_fixes(
+    status: Status,
+    nixpkgs_path: &Path,
+    nixfmt: Option<&Path>,
+) -> anyhow::Result<Status> {
+    require_terminal()?;
+    let Some(problems) = status.errors() else {
+        return Ok(status);
+    };
+    let fixes = fix::in_apply_order(problems);
+
+    let mut fixed = BTreeSet::new();
+    for (number, (index, fix)) in fixes.iter().enumerate() {
+        eprintln!(
+            "\n{} {fix}",
+            format!("[{}/{}]", number + 1, fixes.len()).bold()
+        );
+        let diff = match fix.changes(nixpkgs_path, nixfmt) {
+            Ok(changes) => changes.iter().map(FileChange::diff).collect::<String>(),
+            Err(error) => {
+                eprintln!("{}", format!("Could not fix: {error:#}").red());
+                continue;
+            }
+        };
+        eprint!("{}", colored_diff(&diff));
+
+        let result = match prompt_fix()? {
+            FixAction::Accept => fix.apply(nixpkgs_path, nixfmt),
+            FixAction::Edit => apply_edited(nixpkgs_path, &diff),
+            FixAction::Skip => continue,
+            FixAction::Quit => break,
+        };
+        match result {
+            Ok(()) => {
+                eprintln!("{}", format!("Applied: {fix}").green());
+                fixed.insert(delta::key(&problems[*index]));
+            }
+            Err(error) => eprintln!("{}", format!("Could not fix: {error:#}").red()),
+        }
+    }
+
+    Ok(status.retain(|problem| !fixed.contains(&delta::key(problem))))
+}
+
+/// Lets the user edit the diff of a fix in the editor, applying what's left of it with
+/// `git apply`, which also works outside of git repositories. Nothing is applied if the user
+/// removes all of it.
+fn apply_edited(nixpkgs_path: &Path, diff: &str) -> anyhow::Result<()> {
+    let file = tempfile::Builder::new()
+        .prefix("nixpkgs-vet-fix")
+        .suffix(".diff")
+        .tempfile()
+        .context("Could not create a file for the diff")?;
+    fs::write(file.path(), diff).context("Could not write the diff")?;
+    edit(file.path(), None)?;
+    let edited = fs::read_to_string(file.path()).context("Could not read the edited diff")?;
+    if edited.trim().is_empty() {
+        anyhow::bail!("The diff is empty");
+    }
+    // The user may have changed the number of lines of the hunks without updating their headers
+    let path = file.path().to_string_lossy();
+    git::run(
+        nixpkgs_path,
+        &["apply", "--recount", "--whitespace=nowarn", "--", &path],
+    )?;
+    Ok(())
+}
+
+/// The diff with added lines in green, removed ones in red, and hunk headers in cyan.
+fn colored_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let colored = if line.starts_with("+++") || line.starts_with("---") {
+                line.bold()
+            } else if line.starts_with('+') {
+                line.green()
+            } else if line.starts_with('-') {
+                line.red()
+            } else if line.starts_with("@@") {
+                line.cyan()
+            } else {
+                line.normal()
+            };
+            format!("{colored}\n")
+        })
+        .collect()
+}
+
+/// Asks what to do with a fix until a valid answer is given.
+fn prompt_fix() -> anyhow::Result<FixAction> {
+    loop {
+        eprint!("{} ", "[a]ccept, [e]dit, [s]kip, [q]uit?".cyan());
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            // End of input
+            return Ok(FixAction::Quit);
+        }
+        match answer.trim() {
+            "a" => return Ok(FixAction::Accept),
+            "e" => return Ok(FixAction::Edit),
+            "s" | "" => return Ok(FixAction::Skip),
+            "q" => return Ok(FixAction::Quit),
+            _ => eprintln!("Unknown choice {:?}", answer.trim()),
+        }
+    }
+}
+
+/// Both ways of stepping through problems need to ask the user.
+fn require_terminal() -> anyhow::Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!(Fatal::Usage(
+            "--interactive requires standard input to be a terminal".to_owned()
+        )));
+    }
+    Ok(())
+}
+
+/// Asks what to do with the problem until a valid answer is given.
+fn prompt(problem: &Problem, can_suppress: bool) -> anyhow::Result<Action> {
+    let can_open = problem.location().is_some();
+    let can_fix = problem.fix().is_some();
+    let mut choices = vec![];
+    if can_open {
+        choices.push("[o]pen");
+    }
+    if can_fix {
+        choices.push("[f]ix");
+    }
+    if can_suppress {
+        choices.push("[s]uppress");
+    }
+    choices.extend(["[n]ext", "[q]uit"]);
+
+    loop {
+        eprint!("{} ", format!("{}?", choices.join(", ")).cyan());
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            // End of input
+            return Ok(Action::Quit);
+        }
+        match answer.trim() {
+            "o" if can_open => return Ok(Action::Open),
+            "f" if can_fix => return Ok(Action::Fix),
+            "s" if can_suppress => return Ok(Action::Suppress),
+            "n" | "" => return Ok(Action::Skip),
+            "q" => return Ok(Action::Quit),
+            _ => eprintln!("Unknown choice {:?}", answer.trim()),
+        }
+    }
+}
+
+/// Opens the location of the problem in the editor, see `edit`.
+fn open(nixpkgs_path: &Path, problem: &Problem) -> anyhow::Result<()> {
+    let location = problem.location().expect("only offered with a location");
+    edit(&location.path.to_path(nixpkgs_path), location.line)
+}
+
+/// Opens the file in the editor from `$VISUAL` or `$EDITOR`, at the line if there is one.
+fn edit(path: &Path, line: Option<usize>) -> anyhow::Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or("vi".to_owned());
+    // The editor may come with arguments, like `code --wait`
+    let mut words = editor.split_whitespace();
+    let mut command = process::Command::new(words.next().unwrap_or("vi"));
+    command.args(words);
+    if let Some(line) = line {
+        // Understood by most terminal editors, like vi, emacs and nano
+        command.arg(format!("+{line}"));
+    }
+    command.arg(path);
+
+    let status = command
+        .status()
+        .with_context(|| Fatal::Misconfiguration(format!("Failed to run editor {editor:?}")))?;
+    if !status.success() {
+        eprintln!("The editor exited with {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+# Takes a path to nixpkgs and a path to the json-encoded list of attributes whose
+# `passthru.updateScript` should be checked.
+#
+# Returns for each attribute what's wrong with its update script in the way
+# `maintainers/scripts/update.nix` runs them, or null if it has none. See ./update_scripts.rs for
+# how this is used.
+{
+  attrsPath,
+  nixpkgsPath,
+  system ? "x86_64-linux",
+}:
+let
+  attrs = builtins.fromJSON (builtins.readFile attrsPath);
+
+  pkgs = import nixpkgsPath {
+    # Don't let the user's home directory influence this result.
+    config = { };
+    overlays = [ ];
+    inherit system;
+  };
+  inherit (pkgs) lib;
+
+  # Like `maintainers/scripts/update.nix`, which uses `command` of attribute sets and turns single
+  # commands into lists.
+  isCommandPart =
+    value: builtins.isString value || builtins.isPath value || lib.isDerivation value;
+
+  check =
+    updateScript:
+    let
+      isSet = builtins.isAttrs updateScript && !lib.isDerivation updateScript;
+      command = if isSet then updateScript.command or null else updateScript;
+      parts = if builtins.isList command then command else [ command ];
+      defect =
+        if isSet && !updateScript ? command then
+          "no-command"
+        else if parts == [ ] then
+          "empty-command"
+        else if !builtins.all isCommandPart parts then
+          "invalid-command-part"
+        else if isSet && updateScript ? attrPath && !builtins.isString updateScript.attrPath then
+          "invalid-attr-path"
+        else if
+          isSet
+          && updateScript ? supportedFeatures
+          && !(
+            builtins.isList updateScript.supportedFeatures
+            && builtins.all builtins.isString updateScript.supportedFeatures
+          )
+        then
+          "invalid-supported-features"
+        else
+          null;
+    in
+    {
+      inherit defect;
+      missing_files = map toString (
+        builtins.filter (part: builtins.isPath part && !builtins.pathExists part) parts
+      );
+    };
+
+  checkAttr =
+    name:
+    let
+      value = pkgs.${name};
+      result = builtins.tryEval (
+        let
+          checked =
+            if value ? updateScript then
+              check value.updateScript
+            else if value ? passthru.updateScript then
+              check value.passthru.updateScript
+            else
+              null;
+        in
+        builtins.deepSeq checked checked
+      );
+    in
+    if result.success then
+      result.value
+    else
+      {
+        defect = "eval-failure";
+        missing_files = [ ];
+      };
+in
+builtins.listToAttrs (
+  map (name: {
+    inherit name;
+    value = checkAttr name;
+  }) attrs
+)
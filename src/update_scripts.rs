@@ -0,0 +1,81 @@
+//! Evaluates the `passthru.updateScript` of the changed packages in `pkgs/by-name`, to find the
+//! ones that can't be run, see `--check update-scripts`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::eval;
+use crate::problem::npv_156::{self, UpdateScriptDefect};
+use crate::scope::Scope;
+use crate::validation::{self, Validation::Success};
+
+const UPDATE_SCRIPTS_NIX: &[u8] = include_bytes!("update_scripts.nix");
+
+/// What's wrong with the update script of a package, as returned by `./update_scripts.nix`.
+#[derive(Deserialize)]
+struct UpdateScriptResult {
+    defect: Option<UpdateScriptDefect>,
+    /// The absolute paths of the files the command refers to that don't exist.
+    missing_files: Vec<PathBuf>,
+}
+
+/// Checks that the update scripts of the packages in `pkgs/by-name` that are in scope can be run
+/// by `maintainers/scripts/update.nix`, and that the files they refer to exist, see
+/// `eval::evaluate_json`. The packages are evaluated for each of the configured systems, see
+/// `eval::for_each_system`.
+pub fn check_update_scripts(
+    nixpkgs_path: &Path,
+    package_names: &[String],
+    scope: &Scope,
+    config: &Config,
+) -> validation::Result<()> {
+    let _span = tracing::info_span!("update-scripts").entered();
+    if scope.is_everything() {
+        return Ok(Success(()));
+    }
+    let attributes: Vec<&str> = package_names
+        .iter()
+        .filter(|name| scope.contains_package(name))
+        .map(String::as_str)
+        .collect();
+    if attributes.is_empty() {
+        return Ok(Success(()));
+    }
+
+    eval::for_each_system(config, |system| {
+        let results: BTreeMap<String, Option<UpdateScriptResult>> = match eval::evaluate_json(
+            "update-scripts",
+            UPDATE_SCRIPTS_NIX,
+            nixpkgs_path,
+            &[("attrsPath", json!(attributes))],
+            Some(system),
+            config,
+        )? {
+            Ok(results) => results,
+            // Failures that can't be caught, like of files that are copied to the store
+            Err(stderr) => return Ok(eval::eval_error(&stderr).into()),
+        };
+        Ok(results
+            .into_iter()
+            .filter_map(|(name, result)| Some((name, result?)))
+            .flat_map(|(name, result)| {
+                let missing_files = result.missing_files.into_iter().map(|path| {
+                    let path = path.strip_prefix(nixpkgs_path).unwrap_or(&path);
+                    UpdateScriptDefect::MissingFile(path.display().to_string())
+                });
+                result
+                    .defect
+                    .into_iter()
+                    .chain(missing_files)
+                    .map(move |defect| {
+                        npv_156::UpdateScriptInvalid::new(name.clone(), defect).into()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    })
+}
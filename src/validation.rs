@@ -1,24 +1,35 @@
+use std::panic;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::jobs;
 use crate::problem::Problem;
-use Validation::{Failure, Success};
+use crate::timeout;
+use Validation::{Failure, Success, SuccessWithWarnings};
+use itertools::Itertools;
 use itertools::concat;
-use itertools::{
-    Either::{Left, Right},
-    Itertools,
-};
 
 /// The validation result of a check.  Instead of exiting at the first failure, this type can
-/// accumulate multiple failures.  This can be achieved using the functions `and`, `sequence` and
-/// `sequence_`.
+/// accumulate multiple failures.  This can be achieved using the functions `and`, `zip3` and
+/// `zip4`, or by collecting an iterator of validations, see the `FromIterator` implementations.
+///
+/// The problems are the ones of Nixpkgs by default, but can be of any type, so that the checks
+/// can be reused with other problems.
 ///
 /// This leans on <https://hackage.haskell.org/package/validation>.
-pub enum Validation<A> {
-    Failure(Vec<Problem>),
+pub enum Validation<A, P = Problem> {
+    Failure(Vec<P>),
     Success(A),
+    /// A success with non-fatal problems, which don't prevent the checks depending on the value
+    /// from running. Combined with a `Failure`, the warnings become problems of the failure, in
+    /// the order of the checks, such that they're still reported.
+    SuccessWithWarnings(A, Vec<P>),
 }
 
-impl<A, P: Into<Problem>> From<P> for Validation<A> {
+impl<A, T: Into<Problem>> From<T> for Validation<A> {
     /// Create a `Validation<A>` from a single check problem
-    fn from(value: P) -> Self {
+    fn from(value: T) -> Self {
         Failure(vec![value.into()])
     }
 }
@@ -36,7 +47,10 @@ impl<A, P: Into<Problem>> From<P> for Validation<A> {
 /// - Ok(Success(A)): A successful (potentially intermediate) result with an arbitrary value.
 ///   No fatal errors have occurred and no validation problems have been found with Nixpkgs.
 ///
-pub type Result<A> = anyhow::Result<Validation<A>>;
+/// - Ok(SuccessWithWarnings(A, Vec<Problem>)): Like `Success`, but with problems that are only
+///   discouraged, which are reported without failing the check.
+///
+pub type Result<A, P = Problem> = anyhow::Result<Validation<A, P>>;
 
 pub trait ResultIteratorExt<A, E>: Sized + Iterator<Item = std::result::Result<A, E>> {
     fn collect_vec(self) -> std::result::Result<Vec<A>, E>;
@@ -52,74 +66,535 @@ where
     }
 }
 
-impl<A> Validation<A> {
-    /// Map a `Validation<A>` to a `Validation<B>` by applying a function to the potentially
-    /// contained value in case of success.
-    pub fn map<B>(self, f: impl FnOnce(A) -> B) -> Validation<B> {
+impl<A, P> Validation<A, P> {
+    /// A successful validation with the given warnings, which is a `Success` without any.
+    pub fn with_warnings(value: A, warnings: Vec<P>) -> Validation<A, P> {
+        if warnings.is_empty() {
+            Success(value)
+        } else {
+            SuccessWithWarnings(value, warnings)
+        }
+    }
+
+    /// Split a `Validation<A>` into either the value with its warnings in case of success, or
+    /// the problems in case of failure.
+    pub fn into_result(self) -> std::result::Result<(A, Vec<P>), Vec<P>> {
         match self {
-            Failure(err) => Failure(err),
-            Success(value) => Success(f(value)),
+            Failure(errors) => Err(errors),
+            Success(value) => Ok((value, vec![])),
+            SuccessWithWarnings(value, warnings) => Ok((value, warnings)),
         }
     }
 
+    /// Add warnings in front of the ones of a `Validation<A>`, or of its problems if it's a
+    /// failure.
+    fn prepend_warnings(self, warnings: Vec<P>) -> Validation<A, P> {
+        match self.into_result() {
+            Err(errors) => Failure(concat([warnings, errors])),
+            Ok((value, more_warnings)) => {
+                Self::with_warnings(value, concat([warnings, more_warnings]))
+            }
+        }
+    }
+
+    /// Map a `Validation<A>` to a `Validation<B>` by applying a function to the potentially
+    /// contained value in case of success.
+    pub fn map<B>(self, f: impl FnOnce(A) -> B) -> Validation<B, P> {
+        self.and_then(|value| Success(f(value)))
+    }
+
     /// Map a `Validation<A>` to a `Result<B>` by applying a function `A -> Result<B>` only if
     /// there is a `Success` value.
-    pub fn result_map<B>(self, f: impl FnOnce(A) -> Result<B>) -> Result<B> {
+    pub fn result_map<B>(self, f: impl FnOnce(A) -> Result<B, P>) -> Result<B, P> {
+        match self.into_result() {
+            Err(errors) => Ok(Failure(errors)),
+            Ok((value, warnings)) => Ok(f(value)?.prepend_warnings(warnings)),
+        }
+    }
+
+    /// Map a `Validation<A>` to a `Validation<B>` by applying a function `A -> Validation<B>`
+    /// only if there is a `Success` value, for checks that can only run if others succeeded.
+    pub fn and_then<B>(self, f: impl FnOnce(A) -> Validation<B, P>) -> Validation<B, P> {
+        match self.into_result() {
+            Err(errors) => Failure(errors),
+            Ok((value, warnings)) => f(value).prepend_warnings(warnings),
+        }
+    }
+
+    /// Map both sides of a `Validation<A>`, applying `f` to the contained value in case of
+    /// success, and `g` to each `Problem` in case of failure and to each warning.
+    pub fn bimap<B, Q>(self, f: impl FnOnce(A) -> B, g: impl FnMut(P) -> Q) -> Validation<B, Q> {
         match self {
-            Failure(err) => Ok(Failure(err)),
-            Success(value) => f(value),
+            Failure(err) => Failure(err.into_iter().map(g).collect()),
+            Success(value) => Success(f(value)),
+            SuccessWithWarnings(value, warnings) => {
+                SuccessWithWarnings(f(value), warnings.into_iter().map(g).collect())
+            }
         }
     }
+
+    /// Map each `Problem` of a `Validation<A>` in case of failure and each warning, keeping the
+    /// value in case of success, e.g. to convert them to another type of problem.
+    pub fn map_problems<Q>(self, g: impl FnMut(P) -> Q) -> Validation<A, Q> {
+        self.bimap(|value| value, g)
+    }
 }
 
-impl Validation<()> {
+impl<A> Validation<A> {
+    /// Prefix the context to the message of each `Problem` and warning, like the package set they
+    /// were found in, see `Problem::with_context`.
+    pub fn annotate(self, context: &str) -> Validation<A> {
+        self.map_problems(|problem| problem.with_context(context))
+    }
+}
+
+impl<P> Validation<(), P> {
     /// Combine two validations, both of which need to be successful for the return value to be
     /// successful. The `Problem`s of both sides are returned concatenated.
-    pub fn and_<B>(self, other: Validation<B>) -> Validation<B> {
+    pub fn and_<B>(self, other: Validation<B, P>) -> Validation<B, P> {
         self.and(other, |(), b| b)
     }
 }
 
-impl<A> Validation<A> {
+impl<A, P> Validation<A, P> {
     /// Combine two validations, both of which need to be successful for the return value to be
-    /// successful. The `Problem`s of both sides are returned concatenated.
-    pub fn and<B, C, F: FnOnce(A, B) -> C>(self, other: Validation<B>, f: F) -> Validation<C> {
-        match (self, other) {
-            (Success(a), Success(b)) => Success(f(a, b)),
-            (Failure(errors_l), Failure(errors_r)) => Failure(concat([errors_l, errors_r])),
-            (Failure(errors), Success(_)) | (Success(_), Failure(errors)) => Failure(errors),
+    /// successful. The `Problem`s of both sides are returned concatenated, and so are the
+    /// warnings.
+    pub fn and<B, C, F: FnOnce(A, B) -> C>(
+        self,
+        other: Validation<B, P>,
+        f: F,
+    ) -> Validation<C, P> {
+        match (self.into_result(), other.into_result()) {
+            (Ok((a, warnings_l)), Ok((b, warnings_r))) => {
+                Validation::with_warnings(f(a, b), concat([warnings_l, warnings_r]))
+            }
+            (Err(errors_l), Err(errors_r)) => Failure(concat([errors_l, errors_r])),
+            (Err(errors), Ok((_, warnings))) => Failure(concat([errors, warnings])),
+            (Ok((_, warnings)), Err(errors)) => Failure(concat([warnings, errors])),
         }
     }
 }
 
-/// Combine many validations into a single one.
+/// Combine three validations of different types into one of a tuple, like nesting `and`. All of
+/// them need to be successful for the returned validation to be successful, otherwise the
+/// `Problem`s of all of them are returned concatenated.
+pub fn zip3<A, B, C, P>(
+    a: Validation<A, P>,
+    b: Validation<B, P>,
+    c: Validation<C, P>,
+) -> Validation<(A, B, C), P> {
+    a.and(b, |a, b| (a, b)).and(c, |(a, b), c| (a, b, c))
+}
+
+/// Like `zip3`, but for four validations.
+pub fn zip4<A, B, C, D, P>(
+    a: Validation<A, P>,
+    b: Validation<B, P>,
+    c: Validation<C, P>,
+    d: Validation<D, P>,
+) -> Validation<(A, B, C, D), P> {
+    zip3(a, b, c).and(d, |(a, b, c), d| (a, b, c, d))
+}
+
+/// Combine many validations into a single one, with `collect`.
 ///
 /// All given validations need to be successful in order for the returned validation to be
 /// successful, in which case the returned validation value contains a `Vec` of each individual
 /// value.
 ///
-/// Otherwise, the `Problem`s of all validations are returned concatenated.
-pub fn sequence<A>(check_results: impl IntoIterator<Item = Validation<A>>) -> Validation<Vec<A>> {
-    let (errors, values): (Vec<Vec<Problem>>, Vec<A>) =
-        check_results
+/// Otherwise, the `Problem`s of all validations are returned concatenated, along with the warnings
+/// in the order of the validations. The warnings are concatenated either way.
+impl<A, P> FromIterator<Validation<A, P>> for Validation<Vec<A>, P> {
+    fn from_iter<I: IntoIterator<Item = Validation<A, P>>>(check_results: I) -> Self {
+        let mut values = vec![];
+        let mut problems = vec![];
+        let mut failed = false;
+        for validation in check_results {
+            match validation {
+                Failure(errors) => {
+                    failed = true;
+                    problems.extend(errors);
+                }
+                Success(value) => values.push(value),
+                SuccessWithWarnings(value, warnings) => {
+                    values.push(value);
+                    problems.extend(warnings);
+                }
+            }
+        }
+        if failed {
+            Failure(problems)
+        } else {
+            Validation::with_warnings(values, problems)
+        }
+    }
+}
+
+/// Like collecting into `Validation<Vec<()>>`, but without any containing value, for convenience
+impl<P> FromIterator<Validation<(), P>> for Validation<(), P> {
+    fn from_iter<I: IntoIterator<Item = Validation<(), P>>>(validations: I) -> Self {
+        validations
             .into_iter()
-            .partition_map(|validation| match validation {
-                Failure(err) => Left(err),
-                Success(value) => Right(value),
-            });
+            .collect::<Validation<Vec<()>, P>>()
+            .map(|_| ())
+    }
+}
 
-    // To combine the errors from the results we flatten all the error Vec's into a new Vec
-    // This is not very efficient, but doesn't matter because generally we should have no errors
-    let flattened_errors = errors.into_iter().concat();
+/// Adds problems to a validation, making it a failure if there are any. The value is dropped
+/// then, and the warnings come before the problems, like when combining it with a failure using
+/// `and`.
+impl<A, P> Extend<P> for Validation<A, P> {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, problems: I) {
+        let mut problems = problems.into_iter().peekable();
+        if problems.peek().is_none() {
+            return;
+        }
+        match self {
+            Failure(errors) => errors.extend(problems),
+            Success(_) => *self = Failure(problems.collect()),
+            SuccessWithWarnings(_, warnings) => {
+                *self = Failure(
+                    std::mem::take(warnings)
+                        .into_iter()
+                        .chain(problems)
+                        .collect(),
+                );
+            }
+        }
+    }
+}
+
+/// Problems that can be fatal, meaning that the checks after them can't run, see
+/// `sequence_until_fatal`.
+pub trait IsFatal {
+    fn is_fatal(&self) -> bool;
+}
 
-    if flattened_errors.is_empty() {
-        Success(values)
-    } else {
-        Failure(flattened_errors)
+/// Like collecting the validations, but the checks are only run until one of them has a fatal
+/// problem, such that the ones after it aren't run. The problems of the checks that were run are
+/// returned either way.
+pub fn sequence_until_fatal<A, P: IsFatal>(
+    checks: impl IntoIterator<Item = Result<A, P>>,
+) -> Result<Vec<A>, P> {
+    let mut results = vec![];
+    for check in checks {
+        let result = check?;
+        let is_fatal = matches!(&result, Failure(problems) if problems.iter().any(P::is_fatal));
+        results.push(result);
+        if is_fatal {
+            tracing::debug!("Not running the remaining checks after a fatal problem");
+            break;
+        }
     }
+    Ok(results.into_iter().collect())
+}
+
+/// Like collecting the validations, but running the checks in parallel, with as many at the same
+/// time as there are jobs, see `jobs::count`. The values and problems are in the order of the
+/// checks. Once a check fails with a fatal error, no further checks start, and the error is
+/// returned.
+///
+/// The checks run on other threads, so they can't rely on thread-local state, except for the
+/// deadline of `timeout::run`, which they get from the calling thread.
+pub fn par_sequence<A: Send, P: Send>(
+    checks: impl IntoIterator<Item = impl FnOnce() -> Result<A, P> + Send>,
+) -> Result<Vec<A>, P> {
+    let checks = checks.into_iter().collect_vec();
+    let thread_count = jobs::count().get().min(checks.len());
+    if thread_count <= 1 {
+        return Ok(checks
+            .into_iter()
+            .map(|check| check())
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .collect());
+    }
+
+    let queue = Mutex::new(checks.into_iter().enumerate());
+    let failed = AtomicBool::new(false);
+    // The checks are logged within the span of the caller, like the one of the Nixpkgs checked
+    let span = tracing::Span::current();
+    let deadline = timeout::deadline();
+    let mut results = thread::scope(|threads| {
+        let handles = (0..thread_count)
+            .map(|_| {
+                threads.spawn(|| {
+                    let _span = span.enter();
+                    timeout::with_deadline(deadline, || {
+                        let mut results = vec![];
+                        while !failed.load(Ordering::Relaxed) {
+                            let Some((index, check)) = queue.lock().expect("not poisoned").next()
+                            else {
+                                break;
+                            };
+                            let result = check();
+                            failed.fetch_or(result.is_err(), Ordering::Relaxed);
+                            results.push((index, result));
+                        }
+                        results
+                    })
+                })
+            })
+            .collect_vec();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect_vec()
+    });
+
+    // Checks are started in order, so the ones that didn't start come after the first error
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .collect())
+}
+
+/// Like `par_sequence`, but without any containing value, for convenience
+pub fn par_sequence_<P: Send>(
+    checks: impl IntoIterator<Item = impl FnOnce() -> Result<(), P> + Send>,
+) -> Result<(), P> {
+    Ok(par_sequence(checks)?.map(|_| ()))
 }
 
-/// Like `sequence`, but without any containing value, for convenience
-pub fn sequence_(validations: impl IntoIterator<Item = Validation<()>>) -> Validation<()> {
-    sequence(validations).map(|_| ())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{npv_109, npv_120, npv_143};
+    use crate::testing;
+
+    #[test]
+    fn combinators() {
+        let shard: Validation<u32> =
+            npv_109::ByNameShardIsNotDirectory::new("pkgs/by-name/fo").into();
+        assert!(
+            matches!(shard.and_then(|value| Success(value + 1)), Failure(problems) if problems.len() == 1)
+        );
+        let one: Validation<u32> = Success(1);
+        assert!(matches!(
+            one.and_then(|value| Success(value + 1)),
+            Success(2)
+        ));
+        let one: Validation<u32> = Success(1);
+        assert!(matches!(
+            one.and_then(|_| -> Validation<u32> {
+                npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into()
+            }),
+            Failure(problems) if problems.len() == 1
+        ));
+        let one: Validation<u32> = Success(1);
+        assert!(matches!(
+            one.bimap(|value| value * 2, |problem| problem),
+            Success(2)
+        ));
+
+        // Other types of problems work just the same
+        let shard =
+            Validation::<u32>::from(npv_109::ByNameShardIsNotDirectory::new("pkgs/by-name/fo"))
+                .map_problems(|problem| problem.code().to_owned());
+        let missing: Validation<u32, String> = Failure(vec!["missing".to_owned()]);
+        assert!(matches!(
+            shard.and(missing, |a, b| a + b),
+            Failure(problems) if problems == ["NPV-109", "missing"]
+        ));
+    }
+
+    #[test]
+    fn annotates_problems() {
+        let missing: Validation<()> = npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into();
+        let Failure(problems) = missing.annotate("In package set vimPlugins") else {
+            panic!("expected a failure");
+        };
+        let [problem] = &problems[..] else {
+            panic!("expected a single problem");
+        };
+        assert_eq!(problem.code(), "NPV-143");
+        assert_eq!(
+            problem.to_string(),
+            "- In package set vimPlugins: pkgs/by-name/fo/foo: Missing required \"package.nix\" file."
+        );
+        assert_eq!(
+            problem
+                .location()
+                .map(|location| location.path.into_string()),
+            Some("pkgs/by-name/fo/foo".to_owned())
+        );
+        assert!(matches!(
+            Success(()).annotate("In package set vimPlugins"),
+            Success(())
+        ));
+    }
+
+    #[test]
+    fn concatenates_warnings() {
+        let warning = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let left = SuccessWithWarnings(1, vec![warning("pkgs/by-name/fo/foo")]);
+        let right = SuccessWithWarnings(2, vec![warning("pkgs/by-name/ba/bar")]);
+        let SuccessWithWarnings(3, warnings) = left.and(right, |a, b| a + b) else {
+            panic!("expected a success with warnings");
+        };
+        assert_eq!(warnings.len(), 2);
+
+        let values: Validation<Vec<_>> = [
+            Success(1),
+            SuccessWithWarnings(2, vec![warning("pkgs/by-name/fo/foo")]),
+            SuccessWithWarnings(3, vec![warning("pkgs/by-name/ba/bar")]),
+        ]
+        .into_iter()
+        .collect();
+        let SuccessWithWarnings(values, warnings) = values else {
+            panic!("expected a success with warnings");
+        };
+        assert_eq!(values, [1, 2, 3]);
+        assert_eq!(
+            warnings.iter().map(Problem::to_string).collect_vec(),
+            [
+                "- pkgs/by-name/fo/foo: Missing required \"package.nix\" file.",
+                "- pkgs/by-name/ba/bar: Missing required \"package.nix\" file."
+            ]
+        );
+
+        // Dependent checks still run, and failures keep the warnings
+        let dependent = SuccessWithWarnings(1, vec![warning("pkgs/by-name/fo/foo")])
+            .and_then(|value| Success(value + 1));
+        assert!(matches!(dependent, SuccessWithWarnings(2, warnings) if warnings.len() == 1));
+        let failure =
+            || Validation::<()>::from(npv_109::ByNameShardIsNotDirectory::new("pkgs/by-name/fo"));
+        let failed = SuccessWithWarnings((), vec![warning("pkgs/by-name/fo/foo")]).and_(failure());
+        testing::assert_problems_matching(
+            failed,
+            &[
+                "- pkgs/by-name/fo/foo: Missing required \"package.nix\" file.",
+                "- pkgs/by-name/fo: This is a file, but it should be a directory.",
+            ],
+        );
+        let failed = failure().and_(SuccessWithWarnings(
+            (),
+            vec![warning("pkgs/by-name/fo/foo")],
+        ));
+        assert!(matches!(failed, Failure(problems) if problems.len() == 2));
+        let failed =
+            SuccessWithWarnings(1, vec![warning("pkgs/by-name/fo/foo")]).and_then(|_| failure());
+        assert!(matches!(failed, Failure(problems) if problems.len() == 2));
+        let collected: Validation<()> = [
+            SuccessWithWarnings((), vec![warning("pkgs/by-name/fo/foo")]),
+            failure(),
+        ]
+        .into_iter()
+        .collect();
+        assert!(matches!(collected, Failure(problems) if problems.len() == 2));
+        assert!(matches!(
+            Validation::<()>::with_warnings((), vec![]),
+            Success(())
+        ));
+    }
+
+    #[test]
+    fn zips_validations() {
+        let problem = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let zipped = zip4(
+            Success(1),
+            Validation::<u32>::from(problem("pkgs/by-name/fo/foo")),
+            Success("three"),
+            Validation::<()>::from(problem("pkgs/by-name/ba/bar")),
+        );
+        assert!(matches!(zipped, Failure(problems) if problems.len() == 2));
+        assert!(matches!(
+            zip3(Success(1), Success("two"), Validation::<_>::with_warnings(3, vec![problem("pkgs/by-name/fo/foo")])),
+            SuccessWithWarnings((1, "two", 3), warnings) if warnings.len() == 1
+        ));
+    }
+
+    #[test]
+    fn extends_with_problems() {
+        let problem = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let mut validation =
+            Validation::<u32>::with_warnings(1, vec![problem("pkgs/by-name/fo/foo")]);
+        validation.extend([]);
+        assert!(matches!(validation, SuccessWithWarnings(1, _)));
+        validation.extend([problem("pkgs/by-name/ba/bar")]);
+        validation.extend([problem("pkgs/by-name/ba/baz")]);
+        // The warning is kept
+        testing::assert_problems_matching(
+            validation,
+            &[
+                "- pkgs/by-name/fo/foo: Missing required \"package.nix\" file.",
+                "- pkgs/by-name/ba/bar: Missing required \"package.nix\" file.",
+                "- pkgs/by-name/ba/baz: Missing required \"package.nix\" file.",
+            ],
+        );
+    }
+
+    #[test]
+    fn sequences_in_parallel() -> anyhow::Result<()> {
+        let problem = |path: &str| -> Problem { npv_143::PackageNixMissing::new(path).into() };
+        let paths = [
+            "pkgs/by-name/fo/foo",
+            "pkgs/by-name/ba/bar",
+            "pkgs/by-name/ba/baz",
+        ];
+        let Failure(problems) =
+            par_sequence::<(), _>(paths.map(|path| move || Ok(problem(path).into())))?
+        else {
+            panic!("expected a failure");
+        };
+        let Failure(sequenced) = paths
+            .map(|path| Validation::<()>::from(problem(path)))
+            .into_iter()
+            .collect::<Validation<()>>()
+        else {
+            panic!("expected a failure");
+        };
+        assert_eq!(
+            problems.iter().map(Problem::to_string).collect_vec(),
+            sequenced.iter().map(Problem::to_string).collect_vec()
+        );
+
+        let values = par_sequence::<_, Problem>((0..10).map(|value| move || Ok(Success(value))))?;
+        assert_eq!(testing::assert_success(values), (0..10).collect_vec());
+        assert!(
+            par_sequence_::<Problem>((0..10).map(|value| move || {
+                anyhow::ensure!(value != 5, "Check {value} failed");
+                Ok(Success(()))
+            }))
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sequences_until_fatal() -> anyhow::Result<()> {
+        let mut checked = vec![];
+        let mut check = |index: u32, validation: Validation<u32>| {
+            checked.push(index);
+            Ok(validation)
+        };
+        let sequenced = sequence_until_fatal(
+            [
+                (
+                    0,
+                    npv_143::PackageNixMissing::new("pkgs/by-name/fo/foo").into(),
+                ),
+                (1, Success(1)),
+                (
+                    2,
+                    npv_120::NixEvalError::new(
+                        npv_120::EvalFailureKind::Other,
+                        None,
+                        "error: syntax error",
+                    )
+                    .into(),
+                ),
+                (3, Success(3)),
+            ]
+            .into_iter()
+            .map(|(index, validation)| check(index, validation)),
+        )?;
+        assert!(matches!(sequenced, Failure(problems) if problems.len() == 2));
+        assert_eq!(checked, [0, 1, 2]);
+        Ok(())
+    }
 }
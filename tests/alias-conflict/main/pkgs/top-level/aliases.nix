@@ -0,0 +1,4 @@
+self: super: {
+  # The package was renamed back, but the alias was kept
+  foo = self.bar;
+}
@@ -0,0 +1,4 @@
+self: super: {
+  # Existing aliases without a date are fine
+  old = self.bar;
+}
@@ -0,0 +1,6 @@
+self: super: {
+  # Existing aliases without a date are fine
+  old = self.bar;
+  dated = self.bar; # Added 2024-01-31
+  foo = self.bar;
+}
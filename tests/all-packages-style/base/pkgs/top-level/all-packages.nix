@@ -0,0 +1,15 @@
+{ lib }:
+self: super:
+with self;
+{
+  # Sorted
+  alpha = callPackage ./alpha { };
+  charlie = callPackage ./charlie { };
+
+  delta = callPackage ./delta { };
+  zulu = callPackage ./zulu { };
+
+  # Not sorted, but not changed either
+  yankee = callPackage ./yankee { };
+  xray = callPackage ./xray { };
+}
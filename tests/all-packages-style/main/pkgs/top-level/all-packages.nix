@@ -0,0 +1,17 @@
+{ lib }:
+self: super:
+with self;
+{
+  # Sorted
+  alpha = callPackage ./alpha { };
+  charlie = callPackage ./charlie { };
+  bravo = callPackage ./bravo { };
+
+  delta = callPackage ./delta { };
+  echo = callPackage ./echo { }; foxtrot = callPackage ./foxtrot { };
+  zulu = callPackage ./zulu { };
+
+  # Not sorted, but not changed either
+  yankee = callPackage ./yankee { };
+  xray = callPackage ./xray { };
+}
@@ -0,0 +1,6 @@
+{ buildPythonPackage }:
+buildPythonPackage {
+  pname = "changed";
+  version = "1.0";
+  pyproject = true;
+}
@@ -0,0 +1,5 @@
+{ buildPythonPackage }:
+buildPythonPackage {
+  pname = "changed";
+  version = "1.1";
+}
@@ -0,0 +1,7 @@
+{ buildPythonPackage, setuptools }:
+buildPythonPackage (finalAttrs: {
+  pname = "good";
+  version = "1.0";
+  pyproject = true;
+  build-system = [ setuptools ];
+})
@@ -0,0 +1,7 @@
+{ buildPythonPackage }:
+buildPythonPackage {
+  pname = "new";
+  version = "1.0";
+  pyproject = true;
+  format = "other";
+}
@@ -0,0 +1,6 @@
+{ buildPythonPackage }:
+buildPythonPackage {
+  pname = "old";
+  version = "1.0";
+  format = "setuptools";
+}
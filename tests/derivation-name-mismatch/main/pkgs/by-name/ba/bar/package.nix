@@ -0,0 +1,7 @@
+{ someDrv }:
+someDrv
+// {
+  pname = "bar";
+  version = "1.0";
+  name = "bar-1.0";
+}
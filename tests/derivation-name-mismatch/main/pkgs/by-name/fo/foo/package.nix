@@ -0,0 +1,7 @@
+{ someDrv }:
+someDrv
+// {
+  pname = "foo";
+  version = "1.0";
+  name = "foo-2.0";
+}
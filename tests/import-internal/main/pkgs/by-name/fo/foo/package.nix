@@ -0,0 +1,5 @@
+{ someDrv }:
+let
+  helpers = import ../../../top-level/helpers.nix { };
+in
+someDrv
@@ -0,0 +1,8 @@
+{ lib }:
+let
+  helper = x: x;
+in
+{
+  untested = x: helper x;
+  tested = x: x;
+}
@@ -0,0 +1,7 @@
+{ lib }:
+{
+  testTested = {
+    expr = lib.strings.tested 1;
+    expected = 1;
+  };
+}
@@ -0,0 +1,10 @@
+{ lib }:
+let
+  helper = x: x;
+in
+{
+  untested = x: helper x;
+  tested = x: x;
+  newTested = x: x;
+  newUntested = x: x;
+}
@@ -0,0 +1,8 @@
+{ lib }:
+with lib;
+{
+  testNewTested = {
+    expr = newTested 1;
+    expected = 1;
+  };
+}
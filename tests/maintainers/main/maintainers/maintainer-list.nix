@@ -0,0 +1,26 @@
+/*
+  List of maintainers, sorted alphabetically by handle.
+*/
+{
+  alice = {
+    email = "alice@example.com";
+    github = "alice";
+    githubId = 1;
+    name = "Alice";
+  };
+  Bob = {
+    email = "bob@example";
+    github = "bob";
+    githubId = 2;
+    name = "Bob";
+  };
+  dave = {
+    github = "dave";
+    githubId = 2;
+    name = "Dave";
+  };
+  carol = {
+    github = "carol";
+    name = "Carol";
+  };
+}
@@ -0,0 +1,17 @@
+{ lib }:
+with lib.maintainers;
+{
+  foo = {
+    members = [
+      alice
+      eve
+    ];
+    scope = "Maintain foo.";
+    shortName = "Foo";
+  };
+  bar = {
+    members = [ lib.maintainers.Bob ];
+    scope = "Maintain bar.";
+    shortName = "Foo";
+  };
+}
@@ -0,0 +1,6 @@
+[
+  ./programs/a.nix
+  ./programs/missing.nix
+  ./programs/broken.nix
+  ./services/b
+]
@@ -0,0 +1 @@
+{ ... }: { options = { 
@@ -0,0 +1 @@
+{ imports = [ ../c.nix ]; }
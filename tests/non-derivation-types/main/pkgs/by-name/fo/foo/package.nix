@@ -0,0 +1,4 @@
+{ }:
+{
+  type = "nixos-test";
+}
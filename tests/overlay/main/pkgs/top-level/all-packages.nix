@@ -0,0 +1,5 @@
+# Stands in for the attributes of the Nixpkgs the overlay is applied on, which would be new
+# packages not using pkgs/by-name otherwise
+self: super: {
+  upstream = self.callPackage ({ someDrv }: someDrv) { };
+}
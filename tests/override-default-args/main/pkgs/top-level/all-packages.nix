@@ -0,0 +1 @@
+self: super: with self; { nonDerivation = self.callPackage ./../by-name/no/nonDerivation/package.nix { inherit someDrv; }; }
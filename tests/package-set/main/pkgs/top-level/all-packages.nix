@@ -0,0 +1,5 @@
+self: super: {
+  testPackages = {
+    foo = self.callPackage ../test-packages/fo/foo/package.nix { };
+  };
+}
@@ -0,0 +1,8 @@
+{ someDrv }:
+someDrv
+// {
+  patches = [
+    ./used.patch
+    ./missing.patch
+  ];
+}
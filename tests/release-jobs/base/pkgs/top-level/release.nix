@@ -0,0 +1,11 @@
+# A mock of the Hydra jobs, where jobs that throw fail to evaluate
+{ supportedSystems }:
+let
+  pkgs = import ../.. { };
+  job = value: { x86_64-linux = value // { drvPath = "/nix/store/mock.drv"; }; };
+  failingJob = { x86_64-linux = throw "failing job"; };
+in
+{
+  foo = job pkgs.foo;
+  broken = failingJob;
+}
@@ -0,0 +1,2 @@
+{ someDrv }:
+someDrv // { version = "2"; }
@@ -0,0 +1,12 @@
+# A mock of the Hydra jobs, where jobs that throw fail to evaluate
+{ supportedSystems }:
+let
+  pkgs = import ../.. { };
+  job = value: { x86_64-linux = value // { drvPath = "/nix/store/mock.drv"; }; };
+  failingJob = { x86_64-linux = throw "failing job"; };
+in
+{
+  foo = failingJob;
+  broken = failingJob;
+  new = failingJob;
+}
@@ -0,0 +1,6 @@
+self: super: {
+  foo = self.callPackage ({ someDrv }: someDrv) { };
+  bar = self.callPackage ({ someDrv }: someDrv) { };
+  baz = self.callPackage ({ someDrv }: someDrv) { };
+  qux = self.callPackage ({ someDrv }: someDrv) { };
+}
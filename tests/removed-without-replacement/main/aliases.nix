@@ -0,0 +1 @@
+self: super: { qux = throw "'qux' has been removed"; }
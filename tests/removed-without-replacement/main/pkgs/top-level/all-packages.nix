@@ -0,0 +1,3 @@
+self: super: {
+  baz = self.callPackage ({ someDrv }: someDrv) { };
+}
@@ -0,0 +1,2 @@
+# qux
+{ someDrv }: someDrv
@@ -0,0 +1 @@
+self: super: { qux = self.callPackage ./../by-name/qu/quux/package.nix { }; }
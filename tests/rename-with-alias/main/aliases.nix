@@ -0,0 +1 @@
+self: super: { foo = self.bar; }
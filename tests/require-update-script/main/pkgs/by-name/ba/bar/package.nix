@@ -0,0 +1 @@
+{ someDrv }: someDrv // { passthru.updateScript = ./update.sh; }
@@ -0,0 +1,2 @@
+# Would fail to evaluate, but evaluation is skipped
+{ someDrv }: throw "not evaluated"
@@ -0,0 +1,8 @@
+{ someDrv }:
+someDrv
+// {
+  passthru.tests = {
+    existing = someDrv.type;
+    missing = someDrv.tests.missing;
+  };
+}
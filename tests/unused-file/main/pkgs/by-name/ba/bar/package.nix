@@ -0,0 +1,2 @@
+{ someDrv }:
+someDrv // { src = ./.; }
@@ -0,0 +1 @@
+used
@@ -0,0 +1,2 @@
+{ someDrv }:
+someDrv // import ./helper.nix
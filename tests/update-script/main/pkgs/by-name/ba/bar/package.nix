@@ -0,0 +1,8 @@
+{ someDrv }:
+someDrv
+// {
+  passthru.updateScript = [
+    ./update.py
+    "--commit"
+  ];
+}
@@ -0,0 +1,5 @@
+{ someDrv }:
+someDrv
+// {
+  passthru.updateScript = ./update.sh;
+}